@@ -32,6 +32,11 @@ slint_init(slint_wasm_data).then((_) => {
     }
 
     connection.onInitialize((params: InitializeParams): InitializeResult => {
+        // Only the full text of the changed documents is passed on to the server
+        params.capabilities.experimental = {
+            ...(params.capabilities.experimental ?? {}),
+            fullTextDocumentSync: true,
+        };
         the_lsp = slint_lsp.create(
             params,
             send_notification,
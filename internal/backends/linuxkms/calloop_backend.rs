@@ -67,6 +67,70 @@ fn invoke_from_event_loop(
     }
 }
 
+/// Reads back what the given window shows on the screen, for example for visual testing on the
+/// device. The buffer has the size and orientation of the window, even if the output is rotated
+/// with `SLINT_KMS_ROTATION`. Call this after the window was rendered, for example from a timer.
+///
+/// This is not supported with the Skia Vulkan renderer and returns an error then: once presented,
+/// the swapchain images belong to the Vulkan presentation engine, which doesn't allow reading them.
+/// Use the Skia OpenGL, FemtoVG, or software renderer for visual testing on the device.
+pub fn grab_screen(
+    window: &i_slint_core::api::Window,
+) -> Result<
+    i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+    PlatformError,
+> {
+    with_fullscreen_window_adapter(window, |adapter| adapter.grab_screen())
+}
+
+/// Returns the outputs, and their modes, that the given window is shown on. This is more than one
+/// output when the window is mirrored on several outputs or spans a tiled display.
+pub fn output_info(
+    window: &i_slint_core::api::Window,
+) -> Result<Vec<crate::OutputInfo>, PlatformError> {
+    with_fullscreen_window_adapter(window, |adapter| Ok(adapter.output_info()))
+}
+
+/// Returns the connectors of all the DRM devices, and the modes of the connected displays,
+/// without showing anything on them. Use this to find the values for `SLINT_DRM_OUTPUT`
+/// and `SLINT_DRM_MODE`.
+pub fn list_connectors() -> Result<Vec<crate::ConnectorInfo>, PlatformError> {
+    // Reading the connectors doesn't need a session, unlike rendering
+    #[cfg(any(
+        feature = "renderer-skia-opengl",
+        feature = "renderer-femtovg",
+        feature = "renderer-software"
+    ))]
+    {
+        let device_opener = |device: &std::path::Path| -> Result<Rc<OwnedFd>, PlatformError> {
+            std::fs::File::open(device)
+                .map(|file| Rc::new(file.into()))
+                .map_err(|e| format!("Error opening device: {e}").into())
+        };
+        return crate::display::drmoutput::list_connectors(&device_opener);
+    }
+
+    #[cfg(not(any(
+        feature = "renderer-skia-opengl",
+        feature = "renderer-femtovg",
+        feature = "renderer-software"
+    )))]
+    Err("Listing the connectors requires the OpenGL based renderers or the software renderer"
+        .into())
+}
+
+fn with_fullscreen_window_adapter<R>(
+    window: &i_slint_core::api::Window,
+    callback: impl FnOnce(&FullscreenWindowAdapter) -> Result<R, PlatformError>,
+) -> Result<R, PlatformError> {
+    let window_adapter = i_slint_core::window::WindowInner::from_pub(window).window_adapter();
+    let adapter = window_adapter
+        .internal(i_slint_core::InternalToken)
+        .and_then(|internal| internal.as_any().downcast_ref::<FullscreenWindowAdapter>())
+        .ok_or("The window is not shown by the linuxkms backend")?;
+    callback(adapter)
+}
+
 pub struct Backend {
     #[cfg(feature = "libseat")]
     seat: Rc<RefCell<libseat::Seat>>,
@@ -97,13 +161,15 @@ pub fn new_with_renderer_by_name(renderer_name: Option<&str>) -> Result<Self, Pl
             Some("skia-opengl") => crate::renderer::skia::SkiaRendererAdapter::new_opengl,
             #[cfg(feature = "renderer-femtovg")]
             Some("femtovg") => crate::renderer::femtovg::FemtoVGRendererAdapter::new,
-            None => crate::renderer::try_skia_then_femtovg,
+            #[cfg(feature = "renderer-software")]
+            Some("software") => crate::renderer::sw::SoftwareRendererAdapter::new,
+            None => crate::renderer::try_skia_then_femtovg_then_software,
             Some(renderer_name) => {
                 eprintln!(
                     "slint linuxkms backend: unrecognized renderer {}, falling back default",
                     renderer_name
                 );
-                crate::renderer::try_skia_then_femtovg
+                crate::renderer::try_skia_then_femtovg_then_software
             }
         };
 
@@ -195,8 +261,18 @@ fn create_window_adapter(
                     .map_err(|e| format!("Failed to parse SLINT_KMS_ROTATION: {e}"))
             })?;
 
+        let flip = std::env::var("SLINT_KMS_FLIP").map_or(Ok(Default::default()), |flip_str| {
+            flip_str.as_str().try_into().map_err(|e| format!("Failed to parse SLINT_KMS_FLIP: {e}"))
+        })?;
+
+        let vsync = i_slint_core::bool_from_env("SLINT_KMS_VSYNC")?.unwrap_or(true);
+        let warm_up = i_slint_core::bool_from_env("SLINT_KMS_WARM_UP")?.unwrap_or(false);
+        let hardware_cursor = i_slint_core::bool_from_env("SLINT_DRM_HW_CURSOR")?.unwrap_or(false);
+
         let renderer = (self.renderer_factory)(&device_accessor)?;
-        let adapter = FullscreenWindowAdapter::new(renderer, rotation)?;
+        renderer.set_vsync(vsync);
+        let adapter =
+            FullscreenWindowAdapter::new(renderer, rotation, flip, warm_up, hardware_cursor)?;
 
         *self.window.borrow_mut() = Some(adapter.clone());
 
@@ -175,7 +175,8 @@ fn process_events<F>(
             return Ok(calloop::PostAction::Continue);
         };
         let window = adapter.window();
-        let screen_size = window.size().to_logical(window.scale_factor());
+        let window_size = window.size().to_logical(window.scale_factor());
+        let screen_size = adapter.logical_screen_size();
 
         for event in &mut self.libinput {
             match event {
@@ -184,25 +185,29 @@ fn process_events<F>(
                         input::event::PointerEvent::Motion(motion_event) => {
                             let mut mouse_pos =
                                 self.mouse_pos.as_ref().get().unwrap_or(LogicalPosition {
-                                    x: screen_size.width / 2.,
-                                    y: screen_size.height / 2.,
+                                    x: window_size.width / 2.,
+                                    y: window_size.height / 2.,
                                 });
-                            mouse_pos.x = (mouse_pos.x + motion_event.dx() as f32)
-                                .clamp(0., screen_size.width);
-                            mouse_pos.y = (mouse_pos.y + motion_event.dy() as f32)
-                                .clamp(0., screen_size.height);
+                            let (dx, dy) = adapter.screen_motion_to_window_motion((
+                                motion_event.dx() as f32,
+                                motion_event.dy() as f32,
+                            ));
+                            mouse_pos.x = (mouse_pos.x + dx).clamp(0., window_size.width);
+                            mouse_pos.y = (mouse_pos.y + dy).clamp(0., window_size.height);
                             self.mouse_pos.set(Some(mouse_pos));
                             let event = WindowEvent::PointerMoved { position: mouse_pos };
                             window.dispatch_event(event);
                         }
                         input::event::PointerEvent::MotionAbsolute(abs_motion_event) => {
-                            let mouse_pos = LogicalPosition {
-                                x: abs_motion_event.absolute_x_transformed(screen_size.width as u32)
-                                    as _,
-                                y: abs_motion_event
-                                    .absolute_y_transformed(screen_size.height as u32)
-                                    as _,
-                            };
+                            let mouse_pos =
+                                adapter.screen_position_to_window_position(LogicalPosition {
+                                    x: abs_motion_event
+                                        .absolute_x_transformed(screen_size.width as u32)
+                                        as _,
+                                    y: abs_motion_event
+                                        .absolute_y_transformed(screen_size.height as u32)
+                                        as _,
+                                });
                             self.mouse_pos.set(Some(mouse_pos));
                             let event = WindowEvent::PointerMoved { position: mouse_pos };
                             window.dispatch_event(event);
@@ -235,10 +240,11 @@ fn process_events<F>(
                 input::Event::Touch(touch_event) => {
                     if let Some(event) = match touch_event {
                         input::event::TouchEvent::Down(touch_down_event) => {
-                            self.last_touch_pos = LogicalPosition::new(
-                                touch_down_event.x_transformed(screen_size.width as u32) as _,
-                                touch_down_event.y_transformed(screen_size.height as u32) as _,
-                            );
+                            self.last_touch_pos =
+                                adapter.screen_position_to_window_position(LogicalPosition::new(
+                                    touch_down_event.x_transformed(screen_size.width as u32) as _,
+                                    touch_down_event.y_transformed(screen_size.height as u32) as _,
+                                ));
                             Some(WindowEvent::PointerPressed {
                                 position: self.last_touch_pos,
                                 button: PointerEventButton::Left,
@@ -249,10 +255,12 @@ fn process_events<F>(
                             button: PointerEventButton::Left,
                         }),
                         input::event::TouchEvent::Motion(touch_motion_event) => {
-                            self.last_touch_pos = LogicalPosition::new(
-                                touch_motion_event.x_transformed(screen_size.width as u32) as _,
-                                touch_motion_event.y_transformed(screen_size.height as u32) as _,
-                            );
+                            self.last_touch_pos =
+                                adapter.screen_position_to_window_position(LogicalPosition::new(
+                                    touch_motion_event.x_transformed(screen_size.width as u32) as _,
+                                    touch_motion_event.y_transformed(screen_size.height as u32)
+                                        as _,
+                                ));
                             Some(WindowEvent::PointerMoved { position: self.last_touch_pos })
                         }
                         _ => None,
@@ -4,6 +4,7 @@
 use std::rc::Rc;
 
 use i_slint_core::api::PhysicalSize;
+use i_slint_core::graphics::{Rgba8Pixel, SharedPixelBuffer};
 use i_slint_core::platform::PlatformError;
 
 pub trait Presenter {
@@ -17,10 +18,76 @@ fn present_with_next_frame_callback(
         &self,
         ready_for_next_animation_frame: Box<dyn FnOnce()>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// When vsync is disabled, frames are presented as soon as they are rendered instead of
+    /// being synchronized with the display refresh.
+    fn set_vsync(&self, vsync: bool);
+    /// Drops the next presented frame instead of showing it. Presenters that can't drop frames
+    /// show it as usual.
+    fn discard_next_frame(&self);
+    /// Returns an estimate of how many frames ago the buffer that the next frame is rendered into
+    /// was rendered to, or 0 if its content is unknown. Displays can't tell which of their buffers
+    /// the renderer picks next, so this is only a hint: an exact age reported by the rendering
+    /// surface takes precedence.
+    fn buffer_age(&self) -> u8;
+    /// Reads back the content of the last presented frame, as it is shown on the screen. Returns
+    /// an error if the presenter has no access to the presented buffer, as with Vulkan.
+    fn read_front_buffer(&self) -> Result<SharedPixelBuffer<Rgba8Pixel>, PlatformError>;
+    /// Shows `image`, with premultiplied alpha, as mouse cursor on the cursor planes of the
+    /// display. The top-left corner of the image is the hot spot. Returns false if the display
+    /// has no cursor plane that can show the image.
+    fn set_hardware_cursor(&self, _image: &SharedPixelBuffer<Rgba8Pixel>) -> bool {
+        false
+    }
+    /// Moves the cursor set with `set_hardware_cursor` to `position` on the screen, in pixels,
+    /// or hides it.
+    fn move_hardware_cursor(&self, _position: Option<(i32, i32)>) -> Result<(), PlatformError> {
+        Ok(())
+    }
 }
 
+/// The DPI assumed for displays that don't report their physical size
+const DEFAULT_DPI: f32 = 96.;
+
+/// Computes the default scale factor of a display from its DPI, relative to 96 DPI. The DPI is
+/// read from the `SLINT_DRM_DPI` environment variable, or otherwise derived from the size of the
+/// display in pixels and in millimeters. Displays with bogus dimensions, such as 0x0 mm, are
+/// assumed to have 96 DPI.
+pub fn scale_factor_from_dpi(
+    size: PhysicalSize,
+    physical_size_mm: (u32, u32),
+) -> Result<f32, PlatformError> {
+    let dpi = match std::env::var("SLINT_DRM_DPI") {
+        Ok(dpi_str) => match dpi_str.parse::<f32>() {
+            Ok(dpi) if dpi > 0. => dpi,
+            _ => {
+                return Err(format!(
+                    "Failed to parse SLINT_DRM_DPI: Must be a positive number, found {dpi_str}"
+                )
+                .into())
+            }
+        },
+        Err(_) => {
+            let (width_mm, height_mm) = physical_size_mm;
+            if width_mm == 0 || height_mm == 0 {
+                DEFAULT_DPI
+            } else {
+                size.width as f32 * 25.4 / width_mm as f32
+            }
+        }
+    };
+    Ok(dpi / DEFAULT_DPI)
+}
+
+#[cfg(any(
+    feature = "renderer-skia-opengl",
+    feature = "renderer-femtovg",
+    feature = "renderer-software"
+))]
+pub mod drmoutput;
 #[cfg(any(feature = "renderer-skia-opengl", feature = "renderer-femtovg"))]
 pub mod egldisplay;
+#[cfg(feature = "renderer-software")]
+pub mod swdisplay;
 #[cfg(feature = "renderer-skia-vulkan")]
 pub mod vulkandisplay;
 
@@ -80,6 +147,59 @@ pub fn degrees(&self) -> f32 {
         }
     }
 
+    /// Reverts the rotation of a frame read back from the screen, so that the returned buffer is
+    /// oriented like the window.
+    pub fn screen_pixels_to_window_pixels(
+        &self,
+        screen: SharedPixelBuffer<Rgba8Pixel>,
+    ) -> SharedPixelBuffer<Rgba8Pixel> {
+        if *self == RenderingRotation::NoRotation {
+            return screen;
+        }
+        let (screen_width, screen_height) = (screen.width() as usize, screen.height() as usize);
+        let window_size = self
+            .screen_size_to_rotated_window_size(PhysicalSize::new(screen.width(), screen.height()));
+        let mut window = SharedPixelBuffer::new(window_size.width, window_size.height);
+        let window_width = window_size.width as usize;
+        let screen_pixels = screen.as_slice();
+        for (index, pixel) in window.make_mut_slice().iter_mut().enumerate() {
+            let (x, y) = (index % window_width, index / window_width);
+            let (screen_x, screen_y) = match self {
+                RenderingRotation::NoRotation => (x, y),
+                RenderingRotation::Rotate90 => (screen_width - 1 - y, x),
+                RenderingRotation::Rotate180 => (screen_width - 1 - x, screen_height - 1 - y),
+                RenderingRotation::Rotate270 => (y, screen_height - 1 - x),
+            };
+            *pixel = screen_pixels[screen_y * screen_width + screen_x];
+        }
+        window
+    }
+
+    /// Maps a position on the screen to the position in the window that is drawn there.
+    pub fn screen_position_to_window_position(
+        &self,
+        (x, y): (f32, f32),
+        (screen_width, screen_height): (f32, f32),
+    ) -> (f32, f32) {
+        match self {
+            RenderingRotation::NoRotation => (x, y),
+            RenderingRotation::Rotate90 => (y, screen_width - x),
+            RenderingRotation::Rotate180 => (screen_width - x, screen_height - y),
+            RenderingRotation::Rotate270 => (screen_height - y, x),
+        }
+    }
+
+    /// Maps a movement on the screen, such as a relative mouse motion, to the movement in the
+    /// window.
+    pub fn screen_motion_to_window_motion(&self, (dx, dy): (f32, f32)) -> (f32, f32) {
+        match self {
+            RenderingRotation::NoRotation => (dx, dy),
+            RenderingRotation::Rotate90 => (dy, -dx),
+            RenderingRotation::Rotate180 => (-dx, -dy),
+            RenderingRotation::Rotate270 => (-dy, dx),
+        }
+    }
+
     pub fn translation_after_rotation(&self, screen_size: PhysicalSize) -> (f32, f32) {
         match self {
             RenderingRotation::NoRotation => (0., 0.),
@@ -91,3 +211,253 @@ pub fn translation_after_rotation(&self, screen_size: PhysicalSize) -> (f32, f32
         }
     }
 }
+
+/// This enum describes whether the output is supposed to be mirrored, for example for displays
+/// that are viewed through a mirror. The mirroring is applied on the screen, after the rotation,
+/// and is also implemented inside the actual renderer.
+#[non_exhaustive]
+#[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum RenderingFlip {
+    /// No mirroring
+    #[default]
+    NoFlip,
+    /// Mirror left and right
+    Horizontal,
+    /// Mirror top and bottom
+    Vertical,
+    /// Mirror both left and right and top and bottom
+    Both,
+}
+
+impl TryFrom<&str> for RenderingFlip {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            "" | "none" => Self::NoFlip,
+            "h" => Self::Horizontal,
+            "v" => Self::Vertical,
+            "hv" | "vh" => Self::Both,
+            _ => {
+                return Err(format!(
+                    "Invalid value for flip. Must be one of none, h, v, or hv, found {value}"
+                ))
+            }
+        })
+    }
+}
+
+impl RenderingFlip {
+    /// Returns whether the output is mirrored horizontally and vertically, in that order.
+    pub fn axes(&self) -> (bool, bool) {
+        match self {
+            RenderingFlip::NoFlip => (false, false),
+            RenderingFlip::Horizontal => (true, false),
+            RenderingFlip::Vertical => (false, true),
+            RenderingFlip::Both => (true, true),
+        }
+    }
+
+    /// Mirrors a position on the screen. As mirroring twice is a no-op, this maps between
+    /// positions before and after the flip in both directions.
+    pub fn flip_position(
+        &self,
+        (x, y): (f32, f32),
+        (screen_width, screen_height): (f32, f32),
+    ) -> (f32, f32) {
+        let (horizontal, vertical) = self.axes();
+        (
+            if horizontal { screen_width - x } else { x },
+            if vertical { screen_height - y } else { y },
+        )
+    }
+
+    /// Mirrors a movement on the screen, such as a relative mouse motion.
+    pub fn flip_motion(&self, (dx, dy): (f32, f32)) -> (f32, f32) {
+        let (horizontal, vertical) = self.axes();
+        (if horizontal { -dx } else { dx }, if vertical { -dy } else { dy })
+    }
+
+    /// Reverts the mirroring of a frame read back from the screen.
+    pub fn flip_pixels(
+        &self,
+        screen: SharedPixelBuffer<Rgba8Pixel>,
+    ) -> SharedPixelBuffer<Rgba8Pixel> {
+        let (horizontal, vertical) = self.axes();
+        if !horizontal && !vertical {
+            return screen;
+        }
+        let (width, height) = (screen.width() as usize, screen.height() as usize);
+        let mut flipped = SharedPixelBuffer::new(screen.width(), screen.height());
+        let screen_pixels = screen.as_slice();
+        for (index, pixel) in flipped.make_mut_slice().iter_mut().enumerate() {
+            let (x, y) = (index % width, index / width);
+            let screen_x = if horizontal { width - 1 - x } else { x };
+            let screen_y = if vertical { height - 1 - y } else { y };
+            *pixel = screen_pixels[screen_y * width + screen_x];
+        }
+        flipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renders a window of the given size into a screen buffer, by applying the same transform
+    /// as the renderers: the rotation and translation, followed by the flip.
+    fn render(
+        rotation: RenderingRotation,
+        flip: RenderingFlip,
+        window: &SharedPixelBuffer<Rgba8Pixel>,
+    ) -> SharedPixelBuffer<Rgba8Pixel> {
+        let window_size = PhysicalSize::new(window.width(), window.height());
+        let screen_size = rotation.screen_size_to_rotated_window_size(window_size);
+        let mut screen = SharedPixelBuffer::new(screen_size.width, screen_size.height);
+        let (translate_x, translate_y) = rotation.translation_after_rotation(screen_size);
+        let (sin, cos) = rotation.degrees().to_radians().sin_cos();
+        let screen_width = screen_size.width as usize;
+        let screen_pixels = screen.make_mut_slice();
+        for (index, pixel) in window.as_slice().iter().enumerate() {
+            // Transform the center of the pixel, to not depend on rounding
+            let x = (index % window.width() as usize) as f32 + 0.5 + translate_x;
+            let y = (index / window.width() as usize) as f32 + 0.5 + translate_y;
+            let position = (x * cos - y * sin, x * sin + y * cos);
+            let (x, y) =
+                flip.flip_position(position, (screen_size.width as f32, screen_size.height as f32));
+            screen_pixels[y.floor() as usize * screen_width + x.floor() as usize] = *pixel;
+        }
+        screen
+    }
+
+    fn test_window() -> SharedPixelBuffer<Rgba8Pixel> {
+        let mut window = SharedPixelBuffer::new(3, 2);
+        for (index, pixel) in window.make_mut_slice().iter_mut().enumerate() {
+            *pixel = Rgba8Pixel::new(index as u8, 0, 0, 255);
+        }
+        window
+    }
+
+    const ROTATIONS: [RenderingRotation; 4] = [
+        RenderingRotation::NoRotation,
+        RenderingRotation::Rotate90,
+        RenderingRotation::Rotate180,
+        RenderingRotation::Rotate270,
+    ];
+    const FLIPS: [RenderingFlip; 4] = [
+        RenderingFlip::NoFlip,
+        RenderingFlip::Horizontal,
+        RenderingFlip::Vertical,
+        RenderingFlip::Both,
+    ];
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(RenderingRotation::try_from("180"), Ok(RenderingRotation::Rotate180));
+        assert!(RenderingRotation::try_from("45").is_err());
+        assert_eq!(RenderingFlip::try_from("h"), Ok(RenderingFlip::Horizontal));
+        assert_eq!(RenderingFlip::try_from("v"), Ok(RenderingFlip::Vertical));
+        assert_eq!(RenderingFlip::try_from("vh"), Ok(RenderingFlip::Both));
+        assert!(RenderingFlip::try_from("x").is_err());
+    }
+
+    #[test]
+    fn test_rotate_180() {
+        let window = test_window();
+        let screen = render(RenderingRotation::Rotate180, RenderingFlip::NoFlip, &window);
+        assert_eq!((screen.width(), screen.height()), (3, 2));
+        let reversed = window.as_slice().iter().rev().copied().collect::<Vec<_>>();
+        assert_eq!(screen.as_slice(), reversed.as_slice());
+        // A flip in both directions looks like a rotation by 180°
+        let flipped = render(RenderingRotation::NoRotation, RenderingFlip::Both, &window);
+        assert_eq!(flipped.as_slice(), screen.as_slice());
+    }
+
+    #[test]
+    fn test_screen_position_to_window_position() {
+        let screen_size = PhysicalSize::new(4, 2);
+        let table = [
+            (RenderingRotation::NoRotation, (0., 0.), (0., 0.)),
+            (RenderingRotation::NoRotation, (1., 0.5), (1., 0.5)),
+            (RenderingRotation::NoRotation, (4., 2.), (4., 2.)),
+            (RenderingRotation::Rotate90, (0., 0.), (0., 4.)),
+            (RenderingRotation::Rotate90, (1., 0.5), (0.5, 3.)),
+            (RenderingRotation::Rotate90, (4., 2.), (2., 0.)),
+            (RenderingRotation::Rotate180, (0., 0.), (4., 2.)),
+            (RenderingRotation::Rotate180, (1., 0.5), (3., 1.5)),
+            (RenderingRotation::Rotate180, (4., 2.), (0., 0.)),
+            (RenderingRotation::Rotate270, (0., 0.), (2., 0.)),
+            (RenderingRotation::Rotate270, (1., 0.5), (1.5, 1.)),
+            (RenderingRotation::Rotate270, (4., 2.), (0., 4.)),
+        ];
+        for (rotation, screen_position, window_position) in table {
+            assert_eq!(
+                rotation.screen_position_to_window_position(
+                    screen_position,
+                    (screen_size.width as f32, screen_size.height as f32)
+                ),
+                window_position,
+                "{rotation:?} {screen_position:?}"
+            );
+
+            // The renderers draw the window position at the screen position
+            let (translate_x, translate_y) = rotation.translation_after_rotation(screen_size);
+            let (sin, cos) = rotation.degrees().to_radians().sin_cos();
+            let (x, y) = (window_position.0 + translate_x, window_position.1 + translate_y);
+            let (x, y) = (x * cos - y * sin, x * sin + y * cos);
+            assert!(
+                (x - screen_position.0).abs() < 0.001 && (y - screen_position.1).abs() < 0.001,
+                "{rotation:?} {screen_position:?} is drawn at {:?}",
+                (x, y)
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_back() {
+        let window = test_window();
+        for rotation in ROTATIONS {
+            for flip in FLIPS {
+                let screen = render(rotation, flip, &window);
+                let read_back = rotation.screen_pixels_to_window_pixels(flip.flip_pixels(screen));
+                assert_eq!(read_back.width(), window.width(), "{rotation:?} {flip:?}");
+                assert_eq!(read_back.as_slice(), window.as_slice(), "{rotation:?} {flip:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_input_mapping() {
+        let window = test_window();
+        for rotation in ROTATIONS {
+            for flip in FLIPS {
+                let screen = render(rotation, flip, &window);
+                let screen_size = (screen.width() as f32, screen.height() as f32);
+                for (index, pixel) in screen.as_slice().iter().enumerate() {
+                    // A click in the center of a screen pixel must land on the window pixel
+                    // that was drawn there.
+                    let x = (index % screen.width() as usize) as f32 + 0.5;
+                    let y = (index / screen.width() as usize) as f32 + 0.5;
+                    let (x, y) = rotation.screen_position_to_window_position(
+                        flip.flip_position((x, y), screen_size),
+                        screen_size,
+                    );
+                    let window_index = y as usize * window.width() as usize + x as usize;
+                    assert_eq!(window.as_slice()[window_index], *pixel, "{rotation:?} {flip:?}");
+                }
+
+                // Moving to the next pixel on the screen moves by one pixel in the window too
+                let (x, y) = rotation.screen_position_to_window_position(
+                    flip.flip_position((0.5, 0.5), screen_size),
+                    screen_size,
+                );
+                let (dx, dy) = rotation.screen_motion_to_window_motion(flip.flip_motion((1., 0.)));
+                let (next_x, next_y) = rotation.screen_position_to_window_position(
+                    flip.flip_position((1.5, 0.5), screen_size),
+                    screen_size,
+                );
+                assert_eq!((x + dx, y + dy), (next_x, next_y), "{rotation:?} {flip:?}");
+            }
+        }
+    }
+}
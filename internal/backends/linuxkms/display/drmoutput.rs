@@ -0,0 +1,178 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+//! Helpers to pick the connector, mode and CRTC of a DRM device, shared by the displays.
+
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+use std::rc::Rc;
+
+use crate::DeviceOpener;
+use drm::control::Device;
+use i_slint_core::api::PhysicalSize as PhysicalWindowSize;
+use i_slint_core::platform::PlatformError;
+
+// Wrapped needed because gbm::Device<T> wants T to be sized.
+#[derive(Clone)]
+pub struct SharedFd(pub Rc<OwnedFd>);
+impl AsFd for SharedFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl drm::Device for SharedFd {}
+
+impl drm::control::Device for SharedFd {}
+
+/// Returns the mode of the connector requested with `SLINT_DRM_MODE`, in the form `1920x1080@60`
+/// or `1920x1080`. Without refresh rate, the mode with the highest refresh rate at that size is used.
+pub fn select_mode(
+    connector: &drm::control::connector::Info,
+    requested_mode: &str,
+) -> Result<drm::control::Mode, PlatformError> {
+    if requested_mode.eq_ignore_ascii_case("list") {
+        let modes = connector
+            .modes()
+            .iter()
+            .map(|mode| {
+                format!(
+                    "{}x{}@{} (preferred: {})",
+                    mode.size().0,
+                    mode.size().1,
+                    mode.vrefresh(),
+                    mode.mode_type().contains(drm::control::ModeTypeFlags::PREFERRED)
+                )
+            })
+            .collect::<Vec<_>>();
+        // Can't return error here because newlines are escaped.
+        panic!("\nDRM Mode List Requested:\n{}\n", modes.join("\n"));
+    }
+
+    let parse_error = || {
+        format!("Failed to parse SLINT_DRM_MODE: Must be of the form WIDTHxHEIGHT or WIDTHxHEIGHT@REFRESH, found {requested_mode}")
+    };
+    let (size, refresh) = match requested_mode.split_once('@') {
+        Some((size, refresh)) => (size, Some(refresh.parse::<u32>().map_err(|_| parse_error())?)),
+        None => (requested_mode, None),
+    };
+    let (width, height) = size
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse::<u16>().ok()?, height.parse::<u16>().ok()?)))
+        .ok_or_else(parse_error)?;
+
+    let mode = connector
+        .modes()
+        .iter()
+        .filter(|mode| mode.size() == (width, height))
+        .filter(|mode| refresh.map_or(true, |refresh| mode.vrefresh() == refresh))
+        .max_by_key(|mode| mode.vrefresh());
+
+    mode.copied().ok_or_else(|| {
+        let modes = connector
+            .modes()
+            .iter()
+            .map(|mode| format!("{}x{}@{}", mode.size().0, mode.size().1, mode.vrefresh()))
+            .collect::<Vec<_>>();
+        format!(
+            "No display mode matching SLINT_DRM_MODE={requested_mode} found. Available modes: {}",
+            modes.join(", ")
+        )
+        .into()
+    })
+}
+
+/// Returns the mode the display prefers, or otherwise the largest one.
+pub fn preferred_mode(
+    connector: &drm::control::connector::Info,
+) -> Result<drm::control::Mode, PlatformError> {
+    connector
+        .modes()
+        .iter()
+        .max_by(|current_mode, next_mode| {
+            let current = (
+                current_mode.mode_type().contains(drm::control::ModeTypeFlags::PREFERRED),
+                current_mode.size().0 as u32 * current_mode.size().1 as u32,
+            );
+            let next = (
+                next_mode.mode_type().contains(drm::control::ModeTypeFlags::PREFERRED),
+                next_mode.size().0 as u32 * next_mode.size().1 as u32,
+            );
+
+            current.cmp(&next)
+        })
+        .copied()
+        .ok_or_else(|| format!("No preferred or non-zero size display mode found").into())
+}
+
+pub fn list_connectors(
+    device_opener: &DeviceOpener,
+) -> Result<Vec<crate::ConnectorInfo>, PlatformError> {
+    let drm_devices =
+        std::fs::read_dir("/dev/dri/").map_err(|e| format!("Error reading /dev/dri: {e}"))?;
+    let mut result = Vec::new();
+    for device in drm_devices {
+        let device = device.map_err(|e| format!("Error opening DRM device: {e}"))?;
+        // Render nodes have no connectors
+        if !device.file_name().to_string_lossy().starts_with("card") {
+            continue;
+        }
+        let drm_device = SharedFd(device_opener(&device.path())?);
+        let Ok(resources) = drm_device.resource_handles() else { continue };
+        result.extend(resources.connectors().iter().filter_map(|handle| {
+            let connector = drm_device.get_connector(*handle, false).ok()?;
+            Some(crate::ConnectorInfo {
+                name: format!("{}-{}", connector.interface().as_str(), connector.interface_id()),
+                connected: connector.state() == drm::control::connector::State::Connected,
+                modes: connector
+                    .modes()
+                    .iter()
+                    .map(|mode| crate::ModeInfo {
+                        size: PhysicalWindowSize::new(mode.size().0 as u32, mode.size().1 as u32),
+                        refresh_rate: mode.vrefresh(),
+                        preferred: mode
+                            .mode_type()
+                            .contains(drm::control::ModeTypeFlags::PREFERRED),
+                    })
+                    .collect(),
+            })
+        }));
+    }
+    Ok(result)
+}
+
+/// Returns a CRTC that can drive the connector and isn't one of the `used` CRTCs.
+pub fn find_crtc(
+    drm_device: &SharedFd,
+    resources: &drm::control::ResourceHandles,
+    connector: &drm::control::connector::Info,
+    used: &[drm::control::crtc::Handle],
+) -> Result<drm::control::crtc::Handle, PlatformError> {
+    let current_crtc = connector
+        .current_encoder()
+        .filter(|current| connector.encoders().iter().any(|h| *h == *current))
+        .and_then(|current| drm_device.get_encoder(current).ok())
+        .and_then(|encoder| encoder.crtc())
+        .filter(|crtc| !used.contains(crtc));
+
+    if let Some(crtc) = current_crtc {
+        return Ok(crtc);
+    }
+
+    // No crtc found for current encoder? Pick the first possible crtc
+    // as described in https://manpages.debian.org/testing/libdrm-dev/drm-kms.7.en.html#CRTC/Encoder_Selection
+    connector
+        .encoders()
+        .iter()
+        .filter_map(|handle| drm_device.get_encoder(*handle).ok())
+        .flat_map(|encoder| resources.filter_crtcs(encoder.possible_crtcs()))
+        .filter(|crtc_handle| !used.contains(crtc_handle))
+        .find(|crtc_handle| drm_device.get_crtc(*crtc_handle).is_ok())
+        .ok_or_else(|| {
+            format!(
+                "Could not find any crtc for any encoder connected to output {}-{}",
+                connector.interface().as_str(),
+                connector.interface_id()
+            )
+            .into()
+        })
+}
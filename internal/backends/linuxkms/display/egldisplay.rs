@@ -2,31 +2,21 @@
 // SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
 
 use std::cell::{Cell, RefCell};
-use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+use std::collections::HashMap;
 use std::rc::Rc;
 
+use super::drmoutput::{find_crtc, preferred_mode, select_mode, SharedFd};
 use crate::DeviceOpener;
 use drm::control::Device;
 use gbm::AsRaw;
 use i_slint_core::api::PhysicalSize as PhysicalWindowSize;
 use i_slint_core::platform::PlatformError;
 
-// Wrapped needed because gbm::Device<T> wants T to be sized.
-#[derive(Clone)]
-pub struct SharedFd(Rc<OwnedFd>);
-impl AsFd for SharedFd {
-    fn as_fd(&self) -> BorrowedFd<'_> {
-        self.0.as_fd()
-    }
-}
-
-impl drm::Device for SharedFd {}
-
-impl drm::control::Device for SharedFd {}
-
 struct OwnedFramebufferHandle {
     handle: drm::control::framebuffer::Handle,
     device: SharedFd,
+    /// The number of the frame that was rendered into the buffer
+    frame: u64,
 }
 
 impl Drop for OwnedFramebufferHandle {
@@ -35,29 +25,367 @@ fn drop(&mut self) {
     }
 }
 
-#[derive(Default)]
-enum PageFlipState {
-    #[default]
+/// The range of RGB values the connector sends to the display.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ColorRange {
+    /// Full range, 0-255
+    Full,
+    /// Limited range, 16-235
+    Limited,
+}
+
+impl TryFrom<&str> for ColorRange {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "full" => Ok(Self::Full),
+            "limited" => Ok(Self::Limited),
+            _ => Err(format!(
+                "Invalid value for color range. Must be one of full or limited, found {value}"
+            )),
+        }
+    }
+}
+
+/// The pixel format of the framebuffers.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DrmFormat {
+    /// 8 bits per color channel
+    Xrgb8888,
+    /// 10 bits per color channel, for displays with a high color depth
+    Xrgb2101010,
+}
+
+impl TryFrom<&str> for DrmFormat {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "xrgb8888" => Ok(Self::Xrgb8888),
+            "xrgb2101010" => Ok(Self::Xrgb2101010),
+            _ => Err(format!(
+                "Invalid value for the framebuffer format. Must be one of xrgb8888 or xrgb2101010, found {value}"
+            )),
+        }
+    }
+}
+
+impl DrmFormat {
+    fn gbm_format(self) -> gbm::Format {
+        match self {
+            Self::Xrgb8888 => gbm::Format::Xrgb8888,
+            Self::Xrgb2101010 => gbm::Format::Xrgb2101010,
+        }
+    }
+
+    pub fn bits_per_channel(self) -> u8 {
+        match self {
+            Self::Xrgb8888 => 8,
+            Self::Xrgb2101010 => 10,
+        }
+    }
+}
+
+/// The power state of the displays, set with the connectors' `DPMS` property.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DpmsState {
+    On,
+    Off,
+}
+
+/// The position of a connector in a display that is made of several tiles, as described by the
+/// connector's `TILE` property.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct TileInfo {
+    group_id: u32,
+    num_h_tiles: u32,
+    num_v_tiles: u32,
+    h_loc: u32,
+    v_loc: u32,
+    h_size: u32,
+    v_size: u32,
+}
+
+impl TryFrom<&str> for TileInfo {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        // The kernel formats the property as
+        // group_id:is_single_monitor:num_h_tile:num_v_tile:tile_h_loc:tile_v_loc:tile_h_size:tile_v_size
+        let invalid = || format!("Invalid tile description {value}");
+        let fields = value
+            .trim_end_matches('\0')
+            .split(':')
+            .map(|field| field.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        let [group_id, _, num_h_tiles, num_v_tiles, h_loc, v_loc, h_size, v_size] = fields[..]
+        else {
+            return Err(invalid());
+        };
+        Ok(Self { group_id, num_h_tiles, num_v_tiles, h_loc, v_loc, h_size, v_size })
+    }
+}
+
+/// Returns the offset of each tile within the framebuffer spanning all tiles.
+fn tile_offsets(tiles: &[TileInfo]) -> Vec<(u32, u32)> {
+    tiles
+        .iter()
+        .map(|tile| {
+            let x = tiles
+                .iter()
+                .filter(|t| t.v_loc == tile.v_loc && t.h_loc < tile.h_loc)
+                .map(|t| t.h_size)
+                .sum();
+            let y = tiles
+                .iter()
+                .filter(|t| t.h_loc == tile.h_loc && t.v_loc < tile.v_loc)
+                .map(|t| t.v_size)
+                .sum();
+            (x, y)
+        })
+        .collect()
+}
+
+/// A CRTC driving a connector, scanning out the part of the framebuffer at `offset`. A display
+/// made of several tiles is driven by one output per tile.
+struct Output {
+    crtc: drm::control::crtc::Handle,
+    connector: drm::control::connector::Info,
+    mode: drm::control::Mode,
+    offset: (u32, u32),
+    /// Set when the output is driven with atomic modesetting
+    atomic: Option<AtomicProperties>,
+}
+
+/// The value of a plane's `type` property for primary planes
+const DRM_PLANE_TYPE_PRIMARY: u64 = 1;
+
+/// The flag of `drmModeAddFB2WithModifiers` telling that the modifiers are set
+const DRM_MODE_FB_MODIFIERS: u32 = 1 << 1;
+
+/// The primary plane scanning out an output, and the handles of the properties set by atomic
+/// commits.
+struct AtomicProperties {
+    plane: drm::control::plane::Handle,
+    connector_properties: HashMap<String, drm::control::property::Handle>,
+    crtc_properties: HashMap<String, drm::control::property::Handle>,
+    plane_properties: HashMap<String, drm::control::property::Handle>,
+}
+
+impl AtomicProperties {
+    fn new(
+        drm_device: &SharedFd,
+        resources: &drm::control::ResourceHandles,
+        output: &Output,
+        used_planes: &[drm::control::plane::Handle],
+    ) -> Result<Self, PlatformError> {
+        let planes = drm_device
+            .plane_handles()
+            .map_err(|e| format!("Error reading DRM plane handles: {e}"))?;
+        let plane = planes
+            .planes()
+            .iter()
+            .copied()
+            .filter(|plane| !used_planes.contains(plane))
+            .find(|plane| {
+                let Ok(info) = drm_device.get_plane(*plane) else { return false };
+                resources.filter_crtcs(info.possible_crtcs()).contains(&output.crtc)
+                    && properties(drm_device, *plane).map_or(false, |properties| {
+                        properties.iter().any(|(name, _, value)| {
+                            name == "type" && *value == DRM_PLANE_TYPE_PRIMARY
+                        })
+                    })
+            })
+            .ok_or_else(|| format!("No primary plane found for CRTC {:?}", output.crtc))?;
+
+        let handles = |properties: Vec<(String, _, _)>| {
+            properties.into_iter().map(|(name, handle, _)| (name, handle)).collect()
+        };
+        Ok(Self {
+            plane,
+            connector_properties: handles(properties(drm_device, output.connector.handle())?),
+            crtc_properties: handles(properties(drm_device, output.crtc)?),
+            plane_properties: handles(properties(drm_device, plane)?),
+        })
+    }
+}
+
+/// Returns the name, handle and value of each property of a DRM object.
+fn properties(
+    drm_device: &SharedFd,
+    handle: impl drm::control::ResourceHandle,
+) -> Result<
+    Vec<(String, drm::control::property::Handle, drm::control::property::RawValue)>,
+    PlatformError,
+> {
+    let properties = drm_device
+        .get_properties(handle)
+        .map_err(|e| format!("Error reading DRM object properties: {e}"))?;
+    let (handles, values) = properties.as_props_and_values();
+    Ok(handles
+        .iter()
+        .zip(values)
+        .filter_map(|(handle, value)| {
+            let info = drm_device.get_property(*handle).ok()?;
+            Some((info.name().to_str().ok()?.to_string(), *handle, *value))
+        })
+        .collect())
+}
+
+fn property(
+    properties: &HashMap<String, drm::control::property::Handle>,
+    name: &str,
+) -> Result<drm::control::property::Handle, String> {
+    properties.get(name).copied().ok_or_else(|| format!("Missing DRM property {name}"))
+}
+
+type Buffer<B = gbm::BufferObject<OwnedFramebufferHandle>> = (B, drm::control::framebuffer::Handle);
+
+/// How often a page flip that the driver rejected as busy is retried before giving up
+const MAX_PAGE_FLIP_RETRIES: u32 = 50;
+/// The delay between retries of a page flip that the driver rejected as busy
+const PAGE_FLIP_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(2);
+
+/// A page flip that the driver rejected temporarily, for example right after a modeset
+#[derive(Debug)]
+struct PageFlipBusy(String);
+
+impl std::fmt::Display for PageFlipBusy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for PageFlipBusy {}
+
+/// Converts an error of a page flip or atomic commit, marking EBUSY and EAGAIN as temporary.
+fn page_flip_error(
+    message: &str,
+    error: std::io::Error,
+) -> Box<dyn std::error::Error + Send + Sync> {
+    match error.raw_os_error().map(nix::errno::Errno::from_i32) {
+        Some(nix::errno::Errno::EBUSY | nix::errno::Errno::EAGAIN) => {
+            Box::new(PageFlipBusy(format!("{message}: {error}")))
+        }
+        _ => format!("{message}: {error}").into(),
+    }
+}
+
+/// The state of the page flips. `B` is the type of the buffers, which is only generic for testing.
+enum PageFlipState<B = gbm::BufferObject<OwnedFramebufferHandle>> {
     NoFrameBufferPosted,
     InitialBufferPosted,
+    /// A page flip to the last buffer was requested. Until it completed, the previous buffer
+    /// is still scanned out and must not be released back to the GBM surface.
     WaitingForPageFlip {
-        _buffer_to_keep_alive_until_flip: gbm::BufferObject<OwnedFramebufferHandle>,
+        _buffer_to_keep_alive_until_flip: B,
+        /// A buffer presented while the flip was pending, flipped to once it completed
+        queued_buffer: Option<Buffer<B>>,
     },
     ReadyForNextBuffer,
+    /// The driver rejected the page flip to this buffer as busy. It's retried with a timer.
+    RetryingPageFlip {
+        buffer: Buffer<B>,
+        /// The CRTCs that accepted the page flip to the buffer already. Only the others are
+        /// flipped when retrying.
+        flipped_crtcs: Vec<drm::control::crtc::Handle>,
+    },
+}
+
+impl<B> Default for PageFlipState<B> {
+    fn default() -> Self {
+        Self::NoFrameBufferPosted
+    }
+}
+
+impl<B> PageFlipState<B> {
+    /// Resets the state, so that the next frame sets up the outputs again, as after powering
+    /// them on. Returns the buffers that may still be scanned out until then, which must be
+    /// kept until the next page flip completed.
+    fn reset(&mut self) -> Vec<B> {
+        match std::mem::take(self) {
+            Self::WaitingForPageFlip { _buffer_to_keep_alive_until_flip: buffer, .. } => {
+                vec![buffer]
+            }
+            Self::RetryingPageFlip { buffer: (buffer, _), flipped_crtcs }
+                if !flipped_crtcs.is_empty() =>
+            {
+                vec![buffer]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Updates the state after all pending page flips completed, which releases the buffer that
+    /// was scanned out before. Returns the buffer presented while waiting, to flip to next, or
+    /// None if no page flip was waited for, as for flips requested before the state was reset.
+    fn page_flips_completed(&mut self) -> Option<Option<Buffer<B>>> {
+        match std::mem::take(self) {
+            Self::WaitingForPageFlip { queued_buffer, .. } => {
+                *self = Self::ReadyForNextBuffer;
+                Some(queued_buffer)
+            }
+            state => {
+                *self = state;
+                None
+            }
+        }
+    }
 }
 
 pub struct EglDisplay {
     last_buffer: Cell<Option<gbm::BufferObject<OwnedFramebufferHandle>>>,
     page_flip_state: RefCell<PageFlipState>,
-    crtc: drm::control::crtc::Handle,
-    connector: drm::control::connector::Info,
-    mode: drm::control::Mode,
-    gbm_surface: gbm::Surface<OwnedFramebufferHandle>,
+    /// Buffers that may still be scanned out after the outputs were set up again, for example
+    /// when powering them on. They're released once the next page flip completed.
+    buffers_kept_until_flip: RefCell<Vec<gbm::BufferObject<OwnedFramebufferHandle>>>,
+    pending_page_flips: Cell<usize>,
+    /// The outputs the display is shown on. Empty while no display is connected.
+    outputs: RefCell<Vec<Output>>,
+    gbm_surface: RefCell<gbm::Surface<OwnedFramebufferHandle>>,
+    /// The surface replaced after the size of the screen changed. The EGL surface of the
+    /// renderer refers to it until it's recreated, see `take_outputs_changed`.
+    retired_gbm_surface: Cell<Option<gbm::Surface<OwnedFramebufferHandle>>>,
     gbm_device: gbm::Device<SharedFd>,
     drm_device: SharedFd,
-    pub size: PhysicalWindowSize,
+    size: Cell<PhysicalWindowSize>,
+    pub format: DrmFormat,
+    /// The default scale factor, derived from the DPI of the display
+    pub scale_factor: f32,
+    output_info: RefCell<Vec<crate::OutputInfo>>,
+    /// Set when the outputs changed after a display was plugged in or unplugged
+    outputs_changed: Cell<bool>,
     page_flip_event_source_registered: Cell<bool>,
     next_animation_frame_callback: Cell<Option<Box<dyn FnOnce()>>>,
+    vsync: Cell<bool>,
+    /// Set when the outputs are driven with atomic modesetting
+    atomic_modesetting: bool,
+    /// Set when the outputs are configured for a variable refresh rate
+    vrr: Cell<bool>,
+    /// Applied again to the outputs selected after a hotplug
+    color_range: Option<ColorRange>,
+    gamma_ramp: Option<(Vec<u16>, Vec<u16>, Vec<u16>)>,
+    discard_next_frame: Cell<bool>,
+    dpms_state: Cell<DpmsState>,
+    /// The number of frames presented so far
+    frame_count: Cell<u64>,
+    /// The age the buffer of the last presented frame had when it was rendered into
+    buffer_age: Cell<u8>,
+    /// Powers the displays off when no frame was presented for `idle_timeout`
+    idle_timer: i_slint_core::timers::Timer,
+    idle_timeout: Option<std::time::Duration>,
+    /// Retries a page flip that was rejected as busy
+    page_flip_retry_timer: i_slint_core::timers::Timer,
+    /// The number of consecutive page flips rejected as busy
+    page_flip_retries: Cell<u32>,
+    /// Set when running headless, see `SLINT_DRM_HEADLESS`. The presented frames are written as
+    /// PNG files to this directory instead of being shown.
+    headless_directory: Option<std::path::PathBuf>,
+    /// The buffer shown on the cursor planes, see `SLINT_DRM_HW_CURSOR`
+    cursor: RefCell<Option<gbm::BufferObject<()>>>,
+    cursor_visible: Cell<bool>,
 }
 
 impl EglDisplay {
@@ -69,289 +397,2136 @@ pub fn set_next_animation_frame_callback(
     }
 
     pub fn present(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let mut front_buffer = unsafe {
-            self.gbm_surface
-                .lock_front_buffer()
-                .map_err(|e| format!("Error locking gmb surface front buffer: {e}"))?
-        };
+        // The renderer recreated its surface before rendering this frame
+        self.retired_gbm_surface.take();
 
-        // TODO: support modifiers
-        // TODO: consider falling back to the old non-planar API
-        let fb = self
-            .gbm_device
-            .add_planar_framebuffer(&front_buffer, &[None, None, None, None], 0)
-            .map_err(|e| format!("Error adding gbm buffer as framebuffer: {e}"))?;
+        if self.discard_next_frame.take() {
+            // Nothing scans the buffer out, so it can go right back to the surface
+            drop(unsafe {
+                self.gbm_surface
+                    .borrow()
+                    .lock_front_buffer()
+                    .map_err(|e| format!("Error locking gmb surface front buffer: {e}"))?
+            });
+            self.next_animation_frame_callback.take();
+            return Ok(());
+        }
 
-        front_buffer
-            .set_userdata(OwnedFramebufferHandle { handle: fb, device: self.drm_device.clone() })
-            .map_err(|e| format!("Error setting userdata on gbm surface front buffer: {e}"))?;
+        if let Some(directory) = &self.headless_directory {
+            return self.write_frame(directory);
+        }
 
-        if let Some(last_buffer) = self.last_buffer.replace(Some(front_buffer)) {
-            self.gbm_device
-                .page_flip(self.crtc, fb, drm::control::PageFlipFlags::EVENT, None)
-                .map_err(|e| format!("Error presenting fb: {e}"))?;
+        if self.outputs.borrow().is_empty() {
+            // No display is connected. The next frame is rendered once one is plugged in.
+            drop(unsafe {
+                self.gbm_surface
+                    .borrow()
+                    .lock_front_buffer()
+                    .map_err(|e| format!("Error locking gmb surface front buffer: {e}"))?
+            });
+            self.next_animation_frame_callback.take();
+            return Ok(());
+        }
 
-            *self.page_flip_state.borrow_mut() =
-                PageFlipState::WaitingForPageFlip { _buffer_to_keep_alive_until_flip: last_buffer };
-        } else {
-            self.gbm_device
-                .set_crtc(self.crtc, Some(fb), (0, 0), &[self.connector.handle()], Some(self.mode))
-                .map_err(|e| format!("Error presenting fb: {e}"))?;
-            *self.page_flip_state.borrow_mut() = PageFlipState::InitialBufferPosted;
-
-            if let Some(next_animation_frame_callback) = self.next_animation_frame_callback.take() {
-                // We can render the next frame right away, if needed, since we have at least two buffers. The callback
-                // will decide (will check if animation is running). However invoke the callback through the event loop
-                // instead of directly, so that if it decides to set `needs_redraw` to true, the event loop will process it.
-                i_slint_core::timers::Timer::single_shot(
-                    std::time::Duration::default(),
-                    move || {
-                        next_animation_frame_callback();
-                    },
-                )
+        if self.dpms_state.get() == DpmsState::Off {
+            self.set_dpms(DpmsState::On)?;
+        }
+        self.idle_timer.restart();
+
+        let (front_buffer, fb) = self.lock_front_buffer()?;
+
+        let mut page_flip_state = self.page_flip_state.borrow_mut();
+        match *page_flip_state {
+            PageFlipState::WaitingForPageFlip { ref mut queued_buffer, .. } => {
+                // Replacing a queued buffer is fine, it was never scanned out
+                *queued_buffer = Some((front_buffer, fb));
+            }
+            PageFlipState::RetryingPageFlip { ref mut buffer, ref flipped_crtcs } => {
+                // The buffer to retry with was never scanned out if no CRTC flipped to it yet.
+                // Otherwise, the new frame is dropped so that all outputs show the same one.
+                if flipped_crtcs.is_empty() {
+                    *buffer = (front_buffer, fb);
+                }
+            }
+            PageFlipState::InitialBufferPosted | PageFlipState::ReadyForNextBuffer => {
+                *page_flip_state = self.flip_to(front_buffer, fb, Vec::new())?;
+            }
+            PageFlipState::NoFrameBufferPosted if self.uses_atomic_modesetting() => {
+                self.atomic_commit(fb, drm::control::AtomicCommitFlags::ALLOW_MODESET, true)?;
+                self.initial_buffer_posted(&mut page_flip_state, front_buffer);
+            }
+            PageFlipState::NoFrameBufferPosted => {
+                for output in self.outputs.borrow().iter() {
+                    self.gbm_device
+                        .set_crtc(
+                            output.crtc,
+                            Some(fb),
+                            output.offset,
+                            &[output.connector.handle()],
+                            Some(output.mode),
+                        )
+                        .map_err(|e| format!("Error presenting fb: {e}"))?;
+                }
+                self.initial_buffer_posted(&mut page_flip_state, front_buffer);
             }
         }
 
         Ok(())
     }
-}
 
-impl super::Presenter for EglDisplay {
-    fn register_page_flip_handler(
-        self: Rc<Self>,
-        event_loop_handle: crate::calloop_backend::EventLoopHandle,
-    ) -> Result<(), PlatformError> {
-        if self.page_flip_event_source_registered.replace(true) {
+    /// The size of the framebuffer spanning all outputs.
+    pub fn size(&self) -> PhysicalWindowSize {
+        self.size.get()
+    }
+
+    /// Describes the outputs the display is shown on.
+    pub fn output_info(&self) -> Vec<crate::OutputInfo> {
+        self.output_info.borrow().clone()
+    }
+
+    /// Returns true once after the outputs changed because a display was plugged in or unplugged.
+    /// The renderer must then recreate its surface if the size changed.
+    pub fn take_outputs_changed(&self) -> bool {
+        self.outputs_changed.take()
+    }
+
+    /// Selects the outputs again after a display was plugged in or unplugged. When the size of
+    /// the screen changes, the gbm surface is replaced with one of the new size.
+    fn handle_hotplug(&self) -> Result<(), PlatformError> {
+        let resources = self
+            .drm_device
+            .resource_handles()
+            .map_err(|e| format!("Error reading DRM resource handles: {e}"))?;
+        let previous = self.outputs.borrow().first().map(|output| output.connector.handle());
+        let mut outputs = match select_outputs(&self.drm_device, &resources, previous, true) {
+            Ok(outputs) => outputs,
+            Err(e) => {
+                eprintln!("No display available, waiting for a display to be connected: {e}");
+                Vec::new()
+            }
+        };
+
+        let describe = |outputs: &[Output]| {
+            outputs
+                .iter()
+                .map(|output| {
+                    (output.connector.handle(), output.crtc, output.mode.size(), output.offset)
+                })
+                .collect::<Vec<_>>()
+        };
+        if describe(&outputs) == describe(&self.outputs.borrow()) {
             return Ok(());
         }
 
-        let self_weak = Rc::downgrade(&self);
+        if self.atomic_modesetting {
+            if let Err(e) = set_up_atomic_modesetting(&self.drm_device, &resources, &mut outputs) {
+                eprintln!("Warning: Falling back to legacy modesetting: {e}");
+            }
+        }
+        if self.vrr.get() {
+            if let Err(e) = vrr_supported(&self.drm_device, &outputs) {
+                eprintln!("Warning: Variable refresh rate is not available: {e}");
+                self.vrr.set(false);
+            }
+        }
 
-        let source = calloop::generic::Generic::new_with_error::<drm::SystemError>(
-            self.gbm_device.0.clone(),
-            calloop::Interest::READ,
-            calloop::Mode::Level,
-        );
+        let (width, height) = outputs_size(&outputs);
+        let size = self.size.get();
+        if !outputs.is_empty() && (width, height) != (size.width, size.height) {
+            let gbm_surface = self
+                .gbm_device
+                .create_surface::<OwnedFramebufferHandle>(
+                    width,
+                    height,
+                    self.format.gbm_format(),
+                    gbm::BufferObjectFlags::SCANOUT | gbm::BufferObjectFlags::RENDERING,
+                )
+                .map_err(|e| format!("Error creating gbm surface: {e}"))?;
+            self.retired_gbm_surface.set(Some(self.gbm_surface.replace(gbm_surface)));
+            self.size.set(PhysicalWindowSize::new(width, height));
+        }
+
+        if let Err(e) = self.disable_outputs() {
+            eprintln!("Warning: Error disabling the previous outputs: {e}");
+        }
+        // The next frame sets up the new outputs, like the first frame. Page flips that are still
+        // pending are ignored by the page flip handler.
+        *self.page_flip_state.borrow_mut() = PageFlipState::NoFrameBufferPosted;
+        self.pending_page_flips.set(0);
+        // Nothing is scanned out after disabling the outputs
+        self.last_buffer.set(None);
+        self.buffers_kept_until_flip.borrow_mut().clear();
+        // The cursor is set on the new CRTCs when it's shown next
+        self.cursor_visible.set(false);
+
+        *self.output_info.borrow_mut() = output_info(&outputs);
+        *self.outputs.borrow_mut() = outputs;
+        self.apply_output_settings()?;
+        self.outputs_changed.set(true);
+        Ok(())
+    }
+
+    /// Turns off the CRTCs of the outputs, before they are replaced.
+    fn disable_outputs(&self) -> Result<(), PlatformError> {
+        let outputs = self.outputs.borrow();
+        if outputs.is_empty() {
+            return Ok(());
+        }
+        if !self.uses_atomic_modesetting() {
+            for output in outputs.iter() {
+                self.drm_device
+                    .set_crtc(output.crtc, None, (0, 0), &[], None)
+                    .map_err(|e| format!("Error disabling CRTC: {e}"))?;
+            }
+            return Ok(());
+        }
+
+        use drm::control::property::Value;
+        let mut request = drm::control::atomic::AtomicModeReq::new();
+        for output in outputs.iter() {
+            let Some(atomic) = &output.atomic else { continue };
+            request.add_property(
+                output.connector.handle(),
+                property(&atomic.connector_properties, "CRTC_ID")?,
+                Value::CRTC(None),
+            );
+            request.add_property(
+                output.crtc,
+                property(&atomic.crtc_properties, "ACTIVE")?,
+                Value::Boolean(false),
+            );
+            request.add_property(
+                output.crtc,
+                property(&atomic.crtc_properties, "MODE_ID")?,
+                Value::Blob(0),
+            );
+            request.add_property(
+                atomic.plane,
+                property(&atomic.plane_properties, "FB_ID")?,
+                Value::Framebuffer(None),
+            );
+            request.add_property(
+                atomic.plane,
+                property(&atomic.plane_properties, "CRTC_ID")?,
+                Value::CRTC(None),
+            );
+        }
+        self.drm_device
+            .atomic_commit(drm::control::AtomicCommitFlags::ALLOW_MODESET, request)
+            .map_err(|e| format!("Error disabling outputs: {e}").into())
+    }
+
+    /// Applies the color range and gamma ramp configured with `SLINT_DRM_COLOR_RANGE` and
+    /// `SLINT_DRM_GAMMA` to the outputs.
+    fn apply_output_settings(&self) -> Result<(), PlatformError> {
+        if let Some(color_range) = self.color_range {
+            self.set_color_range(color_range)?;
+        }
+        if let Some((red, green, blue)) = &self.gamma_ramp {
+            self.set_gamma(red, green, blue)?;
+        }
+        Ok(())
+    }
 
+    /// Listens to the kernel's uevents to follow displays being plugged in and unplugged.
+    fn register_hotplug_handler(
+        self: &Rc<Self>,
+        event_loop_handle: &crate::calloop_backend::EventLoopHandle,
+    ) -> Result<(), PlatformError> {
+        use nix::sys::socket::{
+            bind, recv, socket, AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol,
+            SockType,
+        };
+        use std::os::fd::AsRawFd;
+
+        let socket = socket(
+            AddressFamily::Netlink,
+            SockType::Datagram,
+            SockFlag::SOCK_CLOEXEC | SockFlag::SOCK_NONBLOCK,
+            SockProtocol::NetlinkKObjectUEvent,
+        )
+        .map_err(|e| format!("Error creating uevent socket: {e}"))?;
+        // The kernel broadcasts the uevents to the first multicast group
+        bind(socket.as_raw_fd(), &NetlinkAddr::new(0, 1))
+            .map_err(|e| format!("Error binding uevent socket: {e}"))?;
+
+        let fd = socket.as_raw_fd();
+        let self_weak = Rc::downgrade(self);
+        let source =
+            calloop::generic::Generic::new(socket, calloop::Interest::READ, calloop::Mode::Level);
         event_loop_handle
             .insert_source(source, move |_, _, _| {
-                let Some(this) = self_weak.upgrade() else {
-                    return Ok(calloop::PostAction::Continue);
-                };
-                if this
-                    .gbm_device
-                    .receive_events()?
-                    .any(|event| matches!(event, drm::control::Event::PageFlip(..)))
-                {
-                    *this.page_flip_state.borrow_mut() = PageFlipState::ReadyForNextBuffer;
-
-                    if let Some(next_animation_frame_callback) =
-                        this.next_animation_frame_callback.take()
-                    {
-                        next_animation_frame_callback();
+                let mut hotplug = false;
+                let mut message = [0u8; 4096];
+                loop {
+                    match recv(fd, &mut message, MsgFlags::empty()) {
+                        Ok(len) => hotplug |= is_drm_hotplug_event(&message[..len]),
+                        Err(nix::errno::Errno::EAGAIN) => break,
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                if let Some(this) = self_weak.upgrade().filter(|_| hotplug) {
+                    if let Err(e) = this.handle_hotplug() {
+                        eprintln!("Error updating the outputs after a hotplug: {e}");
                     }
                 }
                 Ok(calloop::PostAction::Continue)
             })
-            .map_err(|e| {
-                PlatformError::Other(format!("Error registering page flip handler: {e}"))
-            })?;
+            .map_err(|e| format!("Error registering hotplug handler: {e}"))?;
         Ok(())
     }
 
-    fn present_with_next_frame_callback(
+    /// Writes the rendered frame as PNG file to `directory`, in place of showing it.
+    fn write_frame(
         &self,
-        ready_for_next_animation_frame: Box<dyn FnOnce()>,
+        directory: &std::path::Path,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.set_next_animation_frame_callback(ready_for_next_animation_frame);
-        self.present()
-    }
+        let buffer = unsafe {
+            self.gbm_surface
+                .borrow()
+                .lock_front_buffer()
+                .map_err(|e| format!("Error locking gmb surface front buffer: {e}"))?
+        };
+        let frame = self.frame_count.get() + 1;
+        self.frame_count.set(frame);
 
-    fn is_ready_to_present(&self) -> bool {
-        matches!(
-            *self.page_flip_state.borrow(),
-            PageFlipState::NoFrameBufferPosted
-                | PageFlipState::InitialBufferPosted
-                | PageFlipState::ReadyForNextBuffer
+        let pixels = self.read_buffer(&buffer)?;
+        let path = directory.join(format!("frame-{frame:05}.png"));
+        image::save_buffer(
+            &path,
+            pixels.as_bytes(),
+            pixels.width(),
+            pixels.height(),
+            image::ColorType::Rgba8,
         )
-    }
-}
+        .map_err(|e| format!("Error writing frame to {}: {e}", path.display()))?;
 
-impl raw_window_handle::HasWindowHandle for EglDisplay {
-    fn window_handle(
-        &self,
-    ) -> Result<raw_window_handle::WindowHandle<'_>, raw_window_handle::HandleError> {
-        let mut gbm_surface_handle = raw_window_handle::GbmWindowHandle::empty();
-        gbm_surface_handle.gbm_surface = self.gbm_surface.as_raw() as _;
+        // Keep the buffer for `read_front_buffer`, the previous one goes back to the surface
+        self.last_buffer.set(Some(buffer));
 
-        // Safety: This is safe because the handle remains valid; the next rwh release provides `new()` without unsafe.
-        let active_handle = unsafe { raw_window_handle::ActiveHandle::new_unchecked() };
+        if let Some(next_animation_frame_callback) = self.next_animation_frame_callback.take() {
+            // There is no page flip to wait for. Invoke the callback through the event loop, like
+            // after posting the initial buffer.
+            i_slint_core::timers::Timer::single_shot(std::time::Duration::default(), move || {
+                next_animation_frame_callback();
+            })
+        }
 
-        Ok(unsafe {
-            raw_window_handle::WindowHandle::borrow_raw(
-                raw_window_handle::RawWindowHandle::Gbm(gbm_surface_handle),
-                active_handle,
-            )
-        })
+        Ok(())
     }
-}
 
-impl raw_window_handle::HasDisplayHandle for EglDisplay {
-    fn display_handle(
+    fn initial_buffer_posted(
         &self,
-    ) -> Result<raw_window_handle::DisplayHandle<'_>, raw_window_handle::HandleError> {
-        let mut gbm_display_handle = raw_window_handle::GbmDisplayHandle::empty();
-        gbm_display_handle.gbm_device = self.gbm_device.as_raw() as _;
+        page_flip_state: &mut PageFlipState,
+        buffer: gbm::BufferObject<OwnedFramebufferHandle>,
+    ) {
+        if let Some(previous_buffer) = self.last_buffer.replace(Some(buffer)) {
+            self.buffers_kept_until_flip.borrow_mut().push(previous_buffer);
+        }
+        *page_flip_state = PageFlipState::InitialBufferPosted;
 
-        Ok(unsafe {
-            raw_window_handle::DisplayHandle::borrow_raw(raw_window_handle::RawDisplayHandle::Gbm(
-                gbm_display_handle,
-            ))
-        })
+        if let Some(next_animation_frame_callback) = self.next_animation_frame_callback.take() {
+            // We can render the next frame right away, if needed, since we have at least two buffers. The callback
+            // will decide (will check if animation is running). However invoke the callback through the event loop
+            // instead of directly, so that if it decides to set `needs_redraw` to true, the event loop will process it.
+            i_slint_core::timers::Timer::single_shot(std::time::Duration::default(), move || {
+                next_animation_frame_callback();
+            })
+        }
     }
-}
 
-pub fn create_egl_display(device_opener: &DeviceOpener) -> Result<EglDisplay, PlatformError> {
-    let mut last_err = None;
-    if let Ok(drm_devices) = std::fs::read_dir("/dev/dri/") {
-        for device in drm_devices {
-            if let Ok(device) = device.map_err(|e| format!("Error opening DRM device: {e}")) {
-                match try_create_egl_display(device_opener, &device.path()) {
-                    Ok(dsp) => return Ok(dsp),
-                    Err(e) => last_err = Some(e),
-                }
-            }
+    /// Reads back the last presented buffer and converts it to RGBA.
+    pub fn read_front_buffer(
+        &self,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        let page_flip_state = self.page_flip_state.borrow();
+        // A buffer presented while a page flip is pending is shown right after the flip
+        if let PageFlipState::WaitingForPageFlip { queued_buffer: Some((buffer, _)), .. } =
+            &*page_flip_state
+        {
+            return self.read_buffer(buffer);
         }
+        let buffer = self.last_buffer.take().ok_or("No frame was presented yet")?;
+        let result = self.read_buffer(&buffer);
+        self.last_buffer.set(Some(buffer));
+        result
     }
-    Err(last_err.unwrap_or_else(|| "Could not create an egl display".into()))
-}
-
-pub fn try_create_egl_display(
-    device_opener: &DeviceOpener,
-    device: &std::path::Path,
-) -> Result<EglDisplay, PlatformError> {
-    let drm_device = SharedFd(device_opener(device)?);
 
-    let resources = drm_device
+    fn read_buffer(
+        &self,
+        buffer: &gbm::BufferObject<OwnedFramebufferHandle>,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        let size = self.size.get();
+        let (width, height) = (size.width, size.height);
+        let format = self.format;
+        buffer
+            .map(&self.gbm_device, 0, 0, width, height, |mapped| {
+                let mut pixels = i_slint_core::graphics::SharedPixelBuffer::<
+                    i_slint_core::graphics::Rgba8Pixel,
+                >::new(width, height);
+                let stride = mapped.stride() as usize;
+                let data = mapped.buffer();
+                for (y, row) in pixels.make_mut_slice().chunks_mut(width as usize).enumerate() {
+                    let source = &data[y * stride..][..width as usize * 4];
+                    for (pixel, source) in row.iter_mut().zip(source.chunks_exact(4)) {
+                        let value =
+                            u32::from_le_bytes([source[0], source[1], source[2], source[3]]);
+                        let (r, g, b) = match format {
+                            DrmFormat::Xrgb8888 => (value >> 16, value >> 8, value),
+                            // Keep the 8 most significant of the 10 bits of each channel
+                            DrmFormat::Xrgb2101010 => (value >> 22, value >> 12, value >> 2),
+                        };
+                        *pixel =
+                            i_slint_core::graphics::Rgba8Pixel::new(r as u8, g as u8, b as u8, 255);
+                    }
+                }
+                pixels
+            })
+            .map_err(|e| format!("Error mapping the front buffer: {e}"))?
+            .map_err(|e| format!("Error mapping the front buffer: {e}").into())
+    }
+
+    fn uses_atomic_modesetting(&self) -> bool {
+        self.outputs.borrow().iter().all(|output| output.atomic.is_some())
+    }
+
+    /// Commits `fb` to the primary planes of all outputs in one atomic request. With `modeset`,
+    /// the outputs are also configured and enabled, like with `set_crtc`.
+    fn atomic_commit(
+        &self,
+        fb: drm::control::framebuffer::Handle,
+        flags: drm::control::AtomicCommitFlags,
+        modeset: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        use drm::control::property::Value;
+
+        let mut request = drm::control::atomic::AtomicModeReq::new();
+        for output in self.outputs.borrow().iter() {
+            let atomic = output.atomic.as_ref().ok_or("Atomic modesetting is not available")?;
+            let plane_property = |name| property(&atomic.plane_properties, name);
+            request.add_property(
+                atomic.plane,
+                plane_property("FB_ID")?,
+                Value::Framebuffer(Some(fb)),
+            );
+            if !modeset {
+                continue;
+            }
+
+            let mode = self
+                .drm_device
+                .create_property_blob(&output.mode)
+                .map_err(|e| format!("Error creating mode property blob: {e}"))?;
+            request.add_property(
+                output.connector.handle(),
+                property(&atomic.connector_properties, "CRTC_ID")?,
+                Value::CRTC(Some(output.crtc)),
+            );
+            request.add_property(output.crtc, property(&atomic.crtc_properties, "MODE_ID")?, mode);
+            request.add_property(
+                output.crtc,
+                property(&atomic.crtc_properties, "ACTIVE")?,
+                Value::Boolean(true),
+            );
+            if self.vrr.get() {
+                // The display then refreshes when a page flip arrives, within the range of
+                // refresh rates it supports, instead of at the fixed rate of the mode.
+                request.add_property(
+                    output.crtc,
+                    property(&atomic.crtc_properties, "VRR_ENABLED")?,
+                    Value::Boolean(true),
+                );
+            }
+            request.add_property(
+                atomic.plane,
+                plane_property("CRTC_ID")?,
+                Value::CRTC(Some(output.crtc)),
+            );
+
+            // The source rectangle is in 16.16 fixed point
+            let (width, height) = output.mode.size();
+            let (x, y) = output.offset;
+            for (name, value) in [
+                ("SRC_X", (x as u64) << 16),
+                ("SRC_Y", (y as u64) << 16),
+                ("SRC_W", (width as u64) << 16),
+                ("SRC_H", (height as u64) << 16),
+                ("CRTC_W", width as u64),
+                ("CRTC_H", height as u64),
+            ] {
+                request.add_property(
+                    atomic.plane,
+                    plane_property(name)?,
+                    Value::UnsignedRange(value),
+                );
+            }
+            request.add_property(atomic.plane, plane_property("CRTC_X")?, Value::SignedRange(0));
+            request.add_property(atomic.plane, plane_property("CRTC_Y")?, Value::SignedRange(0));
+        }
+
+        self.drm_device
+            .atomic_commit(flags, request)
+            .map_err(|e| page_flip_error("Error committing atomic request", e))
+    }
+
+    fn lock_front_buffer(&self) -> Result<Buffer, Box<dyn std::error::Error + Send + Sync>> {
+        let mut front_buffer = unsafe {
+            self.gbm_surface
+                .borrow()
+                .lock_front_buffer()
+                .map_err(|e| format!("Error locking gmb surface front buffer: {e}"))?
+        };
+
+        // Tiled and compressed buffers are only scanned out correctly when their modifier is
+        // passed along, otherwise drivers assume the implicit layout or reject the buffer.
+        let modifier = front_buffer.modifier().ok().filter(|m| *m != gbm::Modifier::Invalid);
+        let plane_count = front_buffer.plane_count().unwrap_or(1) as usize;
+        let add_planar_framebuffer = |modifier: Option<gbm::Modifier>| {
+            let modifiers = std::array::from_fn(|plane| modifier.filter(|_| plane < plane_count));
+            let flags = if modifier.is_some() { DRM_MODE_FB_MODIFIERS } else { 0 };
+            self.gbm_device.add_planar_framebuffer(&front_buffer, &modifiers, flags)
+        };
+        let fb = match add_planar_framebuffer(modifier) {
+            // Drivers without modifier support reject the modifiers with EINVAL
+            Err(e)
+                if modifier.is_some()
+                    && e.raw_os_error().map(nix::errno::Errno::from_i32)
+                        == Some(nix::errno::Errno::EINVAL) =>
+            {
+                add_planar_framebuffer(None)
+            }
+            result => result,
+        };
+        let fb = match fb {
+            Ok(fb) => fb,
+            // Some older drivers and virtual GPUs don't implement the planar API, fall back to
+            // the single plane one. Both formats use 32 bits per pixel, with 8 unused bits for
+            // XRGB8888 and 2 unused bits for XRGB2101010.
+            Err(e)
+                if matches!(
+                    e.raw_os_error().map(nix::errno::Errno::from_i32),
+                    Some(nix::errno::Errno::ENOSYS | nix::errno::Errno::EINVAL)
+                ) =>
+            {
+                self.gbm_device
+                    .add_framebuffer(&front_buffer, self.format.bits_per_channel() as u32 * 3, 32)
+                    .map_err(|e| format!("Error adding gbm buffer as framebuffer: {e}"))?
+            }
+            Err(e) => return Err(format!("Error adding gbm buffer as framebuffer: {e}").into()),
+        };
+
+        let frame = self.frame_count.get() + 1;
+        self.frame_count.set(frame);
+        let previous_userdata = front_buffer
+            .set_userdata(OwnedFramebufferHandle {
+                handle: fb,
+                device: self.drm_device.clone(),
+                frame,
+            })
+            .map_err(|e| format!("Error setting userdata on gbm surface front buffer: {e}"))?;
+        // The userdata stays with the buffer when it is released back to the surface, so a buffer
+        // without userdata is a new one, with unknown content.
+        self.buffer_age.set(
+            previous_userdata
+                .map_or(0, |previous| (frame - previous.frame).try_into().unwrap_or_default()),
+        );
+
+        Ok((front_buffer, fb))
+    }
+
+    /// Requests a page flip to `buffer` on the CRTCs that aren't in `flipped_crtcs` yet, and
+    /// returns the state to wait for its completion.
+    fn flip_to(
+        &self,
+        buffer: gbm::BufferObject<OwnedFramebufferHandle>,
+        fb: drm::control::framebuffer::Handle,
+        mut flipped_crtcs: Vec<drm::control::crtc::Handle>,
+    ) -> Result<PageFlipState, Box<dyn std::error::Error + Send + Sync>> {
+        let scanned_out_buffer = self.last_buffer.take().ok_or("No buffer is scanned out")?;
+        if let Err(e) = self.page_flip(fb, &mut flipped_crtcs) {
+            // The CRTCs that didn't flip still scan out the previous buffer
+            self.last_buffer.set(Some(scanned_out_buffer));
+            let retries = self.page_flip_retries.get() + 1;
+            if e.is::<PageFlipBusy>() && retries <= MAX_PAGE_FLIP_RETRIES {
+                // Keep the buffer and try again shortly, the driver may be busy with a modeset
+                self.page_flip_retries.set(retries);
+                self.page_flip_retry_timer.restart();
+                return Ok(PageFlipState::RetryingPageFlip { buffer: (buffer, fb), flipped_crtcs });
+            }
+            self.page_flip_retries.set(0);
+            if flipped_crtcs.is_empty() {
+                // `buffer` was never scanned out, so it can be released right away
+                return Err(e);
+            }
+            // Some CRTCs flip to `buffer`, so it must be kept like after a successful flip. The
+            // other CRTCs are flipped with the next frame.
+            eprintln!("Error presenting frame on some outputs: {e}");
+            let scanned_out_buffer = self.last_buffer.replace(Some(buffer)).unwrap();
+            return Ok(PageFlipState::WaitingForPageFlip {
+                _buffer_to_keep_alive_until_flip: scanned_out_buffer,
+                queued_buffer: None,
+            });
+        }
+        self.page_flip_retries.set(0);
+        self.last_buffer.set(Some(buffer));
+        Ok(PageFlipState::WaitingForPageFlip {
+            _buffer_to_keep_alive_until_flip: scanned_out_buffer,
+            queued_buffer: None,
+        })
+    }
+
+    /// Requests a page flip to `fb` on the CRTCs of the outputs that aren't in `flipped_crtcs`,
+    /// and adds them to it. If a CRTC rejects the flip, the CRTCs flipped before it stay in
+    /// `flipped_crtcs`, so that a retry only flips the remaining ones.
+    fn page_flip(
+        &self,
+        fb: drm::control::framebuffer::Handle,
+        flipped_crtcs: &mut Vec<drm::control::crtc::Handle>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let outputs = self.outputs.borrow();
+        // With tiled displays, the next buffer can only be rendered once all tiles flipped, so
+        // every flip is counted until its event arrives.
+        if self.uses_atomic_modesetting() {
+            // A single commit flips all CRTCs or none
+            self.atomic_page_flip(fb)?;
+            self.pending_page_flips.set(self.pending_page_flips.get() + outputs.len());
+            flipped_crtcs.extend(outputs.iter().map(|output| output.crtc));
+            return Ok(());
+        }
+        for output in outputs.iter().filter(|output| !flipped_crtcs.contains(&output.crtc)) {
+            self.page_flip_crtc(output.crtc, fb)?;
+            self.pending_page_flips.set(self.pending_page_flips.get() + 1);
+            flipped_crtcs.push(output.crtc);
+        }
+        Ok(())
+    }
+
+    fn atomic_page_flip(
+        &self,
+        fb: drm::control::framebuffer::Handle,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // The kernel sends one page flip event per CRTC of the request
+        let flags = drm::control::AtomicCommitFlags::PAGE_FLIP_EVENT
+            | drm::control::AtomicCommitFlags::NONBLOCK;
+        if !self.vsync.get() {
+            match self.atomic_commit(
+                fb,
+                flags | drm::control::AtomicCommitFlags::PAGE_FLIP_ASYNC,
+                false,
+            ) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    eprintln!(
+                        "Warning: Asynchronous page flips are not supported, enabling vsync: {e}"
+                    );
+                    self.vsync.set(true);
+                }
+            }
+        }
+        self.atomic_commit(fb, flags, false)
+    }
+
+    fn page_flip_crtc(
+        &self,
+        crtc: drm::control::crtc::Handle,
+        fb: drm::control::framebuffer::Handle,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !self.vsync.get() {
+            // Flip right away, at the risk of tearing. Not all drivers support this.
+            match self.gbm_device.page_flip(
+                crtc,
+                fb,
+                drm::control::PageFlipFlags::EVENT | drm::control::PageFlipFlags::ASYNC,
+                None,
+            ) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    eprintln!(
+                        "Warning: Asynchronous page flips are not supported, enabling vsync: {e}"
+                    );
+                    self.vsync.set(true);
+                }
+            }
+        }
+        self.gbm_device
+            .page_flip(crtc, fb, drm::control::PageFlipFlags::EVENT, None)
+            .map_err(|e| page_flip_error("Error presenting fb", e))
+    }
+
+    /// Configures the connectors' `Broadcast RGB` property to send colors in the given range.
+    /// If a connector doesn't support it, a warning is printed and the display is left unchanged.
+    pub fn set_color_range(&self, range: ColorRange) -> Result<(), PlatformError> {
+        for output in self.outputs.borrow().iter() {
+            set_connector_color_range(&self.drm_device, output.connector.handle(), range)?;
+        }
+        Ok(())
+    }
+
+    /// Powers the displays on or off. After powering on, the next presented frame sets up the
+    /// outputs again, like the first frame.
+    pub fn set_dpms(&self, state: DpmsState) -> Result<(), PlatformError> {
+        for output in self.outputs.borrow().iter() {
+            self.set_connector_dpms(output.connector.handle(), state)?;
+        }
+        self.dpms_state.set(state);
+        if state == DpmsState::On {
+            // Page flips that were pending when powering off are ignored by the page flip handler.
+            // The buffers they flip from may still be scanned out until the next flip completed.
+            let scanned_out_buffers = self.page_flip_state.borrow_mut().reset();
+            self.buffers_kept_until_flip.borrow_mut().extend(scanned_out_buffers);
+            self.pending_page_flips.set(0);
+        }
+        Ok(())
+    }
+
+    fn set_connector_dpms(
+        &self,
+        connector: drm::control::connector::Handle,
+        state: DpmsState,
+    ) -> Result<(), PlatformError> {
+        let properties = self
+            .drm_device
+            .get_properties(connector)
+            .map_err(|e| format!("Error reading connector properties: {e}"))?;
+        let (property_handles, _) = properties.as_props_and_values();
+
+        let dpms = property_handles
+            .iter()
+            .filter_map(|handle| self.drm_device.get_property(*handle).ok())
+            .find(|info| info.name().to_str() == Ok("DPMS"))
+            .ok_or("Output does not support DPMS")?;
+
+        let drm::control::property::ValueType::Enum(enum_values) = dpms.value_type() else {
+            return Err("Unexpected type of the DPMS connector property".into());
+        };
+
+        let value_name = match state {
+            DpmsState::On => "On",
+            DpmsState::Off => "Off",
+        };
+        let value = enum_values
+            .values()
+            .1
+            .iter()
+            .find(|value| value.name().to_str() == Ok(value_name))
+            .ok_or_else(|| format!("Output does not support the DPMS state {value_name}"))?;
+
+        self.drm_device
+            .set_property(connector, dpms.handle(), value.value())
+            .map_err(|e| format!("Error setting DPMS state: {e}").into())
+    }
+
+    /// Loads the given gamma ramp into the color lookup table of the outputs' CRTCs. The three
+    /// slices must have the same length, and are resampled to the size of the lookup tables.
+    pub fn set_gamma(&self, red: &[u16], green: &[u16], blue: &[u16]) -> Result<(), PlatformError> {
+        if red.len() != green.len() || red.len() != blue.len() {
+            return Err(format!(
+                "Gamma ramp channels must have the same length, found {}, {}, and {}",
+                red.len(),
+                green.len(),
+                blue.len()
+            )
+            .into());
+        }
+        if red.len() < 2 {
+            return Err("Gamma ramp must have at least two entries".into());
+        }
+        for output in self.outputs.borrow().iter() {
+            self.set_crtc_gamma(output, red, green, blue)?;
+        }
+        Ok(())
+    }
+
+    fn set_crtc_gamma(
+        &self,
+        output: &Output,
+        red: &[u16],
+        green: &[u16],
+        blue: &[u16],
+    ) -> Result<(), PlatformError> {
+        if output.atomic.is_some() {
+            let crtc_properties = properties(&self.drm_device, output.crtc)?;
+            let find = |name| crtc_properties.iter().find(|(n, _, _)| n == name);
+            if let (Some((_, gamma_lut, _)), Some((_, _, gamma_lut_size))) =
+                (find("GAMMA_LUT"), find("GAMMA_LUT_SIZE"))
+            {
+                let size = *gamma_lut_size as usize;
+                let lut: Vec<DrmColorLut> = (0..size)
+                    .map(|i| DrmColorLut {
+                        red: resample_gamma(red, i, size),
+                        green: resample_gamma(green, i, size),
+                        blue: resample_gamma(blue, i, size),
+                        reserved: 0,
+                    })
+                    .collect();
+                if let Some(blob) = create_gamma_lut_blob(&self.drm_device, &lut)? {
+                    return self
+                        .drm_device
+                        .set_property(output.crtc, *gamma_lut, blob)
+                        .map_err(|e| format!("Error setting the GAMMA_LUT property: {e}").into());
+                }
+            }
+        }
+
+        let size = self
+            .drm_device
+            .get_crtc(output.crtc)
+            .map_err(|e| format!("Error reading CRTC info: {e}"))?
+            .gamma_length() as usize;
+        if size == 0 {
+            return Err("Output does not support a gamma lookup table".into());
+        }
+        let resample = |ramp: &[u16]| -> Vec<u16> {
+            (0..size).map(|i| resample_gamma(ramp, i, size)).collect()
+        };
+        self.drm_device
+            .set_gamma(output.crtc, &resample(red), &resample(green), &resample(blue))
+            .map_err(|e| format!("Error setting gamma: {e}").into())
+    }
+
+    /// Uploads `image` into a buffer for the cursor planes, and shows it on all outputs.
+    #[allow(deprecated)] // The legacy cursor API, which works with and without atomic modesetting
+    fn create_cursor(
+        &self,
+        image: &i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+    ) -> Result<gbm::BufferObject<()>, PlatformError> {
+        if self.headless_directory.is_some() {
+            return Err("There are no outputs when running headless".into());
+        }
+
+        // Cursor buffers must have the size the driver asks for, which is 64x64 by default
+        let cursor_size = |capability| {
+            self.drm_device.get_driver_capability(capability).map_or(64, |size| size as u32)
+        };
+        let width = cursor_size(drm::DriverCapability::CursorWidth);
+        let height = cursor_size(drm::DriverCapability::CursorHeight);
+        if image.width() > width || image.height() > height {
+            return Err(format!(
+                "The cursor image is {}x{} pixels, but the cursor planes are {width}x{height}",
+                image.width(),
+                image.height()
+            )
+            .into());
+        }
+
+        let mut buffer = self
+            .gbm_device
+            .create_buffer_object::<()>(
+                width,
+                height,
+                gbm::Format::Argb8888,
+                gbm::BufferObjectFlags::CURSOR | gbm::BufferObjectFlags::WRITE,
+            )
+            .map_err(|e| format!("Error creating cursor buffer: {e}"))?;
+        // ARGB8888 is stored as blue, green, red, and alpha bytes, with premultiplied alpha
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        for (y, row) in image.as_slice().chunks(image.width() as usize).enumerate() {
+            for (x, pixel) in row.iter().enumerate() {
+                let offset = (y * width as usize + x) * 4;
+                pixels[offset..offset + 4].copy_from_slice(&[pixel.b, pixel.g, pixel.r, pixel.a]);
+            }
+        }
+        buffer
+            .write(&pixels)
+            .map_err(|e| format!("Error writing cursor buffer: {e}"))?
+            .map_err(|e| format!("Error writing cursor buffer: {e}"))?;
+
+        // With atomic modesetting, the kernel maps the legacy cursor calls to the cursor plane
+        for output in self.outputs.borrow().iter() {
+            self.drm_device
+                .set_cursor2(output.crtc, Some(&buffer), (0, 0))
+                .map_err(|e| format!("Error setting the cursor: {e}"))?;
+        }
+        Ok(buffer)
+    }
+}
+
+impl super::Presenter for EglDisplay {
+    fn register_page_flip_handler(
+        self: Rc<Self>,
+        event_loop_handle: crate::calloop_backend::EventLoopHandle,
+    ) -> Result<(), PlatformError> {
+        if self.page_flip_event_source_registered.replace(true) {
+            return Ok(());
+        }
+        // Without outputs there are no page flips to wait for, and no displays to power off
+        if self.headless_directory.is_some() {
+            return Ok(());
+        }
+
+        if let Err(e) = self.register_hotplug_handler(&event_loop_handle) {
+            eprintln!("Warning: Displays that are plugged in or unplugged are not detected: {e}");
+        }
+
+        let self_weak = Rc::downgrade(&self);
+
+        if let Some(idle_timeout) = self.idle_timeout {
+            let self_weak = self_weak.clone();
+            self.idle_timer.start(
+                i_slint_core::timers::TimerMode::SingleShot,
+                idle_timeout,
+                move || {
+                    let Some(this) = self_weak.upgrade() else { return };
+                    if let Err(e) = this.set_dpms(DpmsState::Off) {
+                        eprintln!("Error powering off the display: {e}");
+                    }
+                },
+            );
+        }
+
+        {
+            let self_weak = self_weak.clone();
+            self.page_flip_retry_timer.start(
+                i_slint_core::timers::TimerMode::SingleShot,
+                PAGE_FLIP_RETRY_INTERVAL,
+                move || {
+                    let Some(this) = self_weak.upgrade() else { return };
+                    let previous_state = std::mem::take(&mut *this.page_flip_state.borrow_mut());
+                    let next_state = match previous_state {
+                        PageFlipState::RetryingPageFlip { buffer: (buffer, fb), flipped_crtcs } => {
+                            this.flip_to(buffer, fb, flipped_crtcs).unwrap_or_else(|e| {
+                                eprintln!("Error presenting frame: {e}");
+                                PageFlipState::ReadyForNextBuffer
+                            })
+                        }
+                        state => state,
+                    };
+                    let failed = matches!(next_state, PageFlipState::ReadyForNextBuffer);
+                    *this.page_flip_state.borrow_mut() = next_state;
+                    if failed {
+                        // The frame is lost, but let the next one be rendered
+                        if let Some(next_animation_frame_callback) =
+                            this.next_animation_frame_callback.take()
+                        {
+                            next_animation_frame_callback();
+                        }
+                    }
+                },
+            );
+            // Activate it only when a page flip is rejected as busy
+            self.page_flip_retry_timer.stop();
+        }
+
+        let source = calloop::generic::Generic::new_with_error::<drm::SystemError>(
+            self.gbm_device.0.clone(),
+            calloop::Interest::READ,
+            calloop::Mode::Level,
+        );
+
+        event_loop_handle
+            .insert_source(source, move |_, _, _| {
+                let Some(this) = self_weak.upgrade() else {
+                    return Ok(calloop::PostAction::Continue);
+                };
+                let page_flips = this
+                    .gbm_device
+                    .receive_events()?
+                    .filter(|event| matches!(event, drm::control::Event::PageFlip(..)))
+                    .count();
+                let pending_page_flips = this.pending_page_flips.get().saturating_sub(page_flips);
+                this.pending_page_flips.set(pending_page_flips);
+                if page_flips > 0 && pending_page_flips == 0 {
+                    // Only now the buffer scanned out before the flip is released
+                    let completed = this.page_flip_state.borrow_mut().page_flips_completed();
+                    // The flip was requested before the displays were powered off and on
+                    let Some(queued_buffer) = completed else {
+                        return Ok(calloop::PostAction::Continue);
+                    };
+                    this.buffers_kept_until_flip.borrow_mut().clear();
+
+                    if let Some((buffer, fb)) = queued_buffer {
+                        let next_state = this.flip_to(buffer, fb, Vec::new()).unwrap_or_else(|e| {
+                            eprintln!("Error presenting queued frame: {e}");
+                            PageFlipState::ReadyForNextBuffer
+                        });
+                        let ready = matches!(next_state, PageFlipState::ReadyForNextBuffer);
+                        *this.page_flip_state.borrow_mut() = next_state;
+                        if !ready {
+                            return Ok(calloop::PostAction::Continue);
+                        }
+                    }
+                    if let Some(next_animation_frame_callback) =
+                        this.next_animation_frame_callback.take()
+                    {
+                        next_animation_frame_callback();
+                    }
+                }
+                Ok(calloop::PostAction::Continue)
+            })
+            .map_err(|e| {
+                PlatformError::Other(format!("Error registering page flip handler: {e}"))
+            })?;
+        Ok(())
+    }
+
+    fn present_with_next_frame_callback(
+        &self,
+        ready_for_next_animation_frame: Box<dyn FnOnce()>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.set_next_animation_frame_callback(ready_for_next_animation_frame);
+        self.present()
+    }
+
+    fn set_vsync(&self, vsync: bool) {
+        self.vsync.set(vsync);
+    }
+
+    fn discard_next_frame(&self) {
+        self.discard_next_frame.set(true);
+    }
+
+    fn read_front_buffer(
+        &self,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        EglDisplay::read_front_buffer(self)
+    }
+
+    fn set_hardware_cursor(
+        &self,
+        image: &i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+    ) -> bool {
+        match self.create_cursor(image) {
+            Ok(buffer) => {
+                *self.cursor.borrow_mut() = Some(buffer);
+                self.cursor_visible.set(true);
+                true
+            }
+            Err(e) => {
+                eprintln!("Hardware cursor not available, drawing the cursor with the scene: {e}");
+                false
+            }
+        }
+    }
+
+    #[allow(deprecated)]
+    fn move_hardware_cursor(&self, position: Option<(i32, i32)>) -> Result<(), PlatformError> {
+        let cursor = self.cursor.borrow();
+        let Some(buffer) = cursor.as_ref() else { return Ok(()) };
+        let visible = position.is_some();
+        for output in self.outputs.borrow().iter() {
+            if visible != self.cursor_visible.get() {
+                self.drm_device
+                    .set_cursor2(output.crtc, position.map(|_| buffer), (0, 0))
+                    .map_err(|e| format!("Error setting the cursor: {e}"))?;
+            }
+            // Tiles show the part of the screen at their offset
+            if let Some((x, y)) = position {
+                self.drm_device
+                    .move_cursor(
+                        output.crtc,
+                        (x - output.offset.0 as i32, y - output.offset.1 as i32),
+                    )
+                    .map_err(|e| format!("Error moving the cursor: {e}"))?;
+            }
+        }
+        self.cursor_visible.set(visible);
+        Ok(())
+    }
+
+    fn buffer_age(&self) -> u8 {
+        // Only an estimate: the buffers of the surface are used in turn, so the next buffer is
+        // expected to be as old as the last one was. EGL reports the exact age, if it can.
+        self.buffer_age.get()
+    }
+
+    fn is_ready_to_present(&self) -> bool {
+        matches!(
+            *self.page_flip_state.borrow(),
+            PageFlipState::NoFrameBufferPosted
+                | PageFlipState::InitialBufferPosted
+                | PageFlipState::ReadyForNextBuffer
+        )
+    }
+}
+
+impl raw_window_handle::HasWindowHandle for EglDisplay {
+    fn window_handle(
+        &self,
+    ) -> Result<raw_window_handle::WindowHandle<'_>, raw_window_handle::HandleError> {
+        let mut gbm_surface_handle = raw_window_handle::GbmWindowHandle::empty();
+        gbm_surface_handle.gbm_surface = self.gbm_surface.borrow().as_raw() as _;
+
+        // Safety: This is safe because the handle remains valid; the next rwh release provides `new()` without unsafe.
+        let active_handle = unsafe { raw_window_handle::ActiveHandle::new_unchecked() };
+
+        Ok(unsafe {
+            raw_window_handle::WindowHandle::borrow_raw(
+                raw_window_handle::RawWindowHandle::Gbm(gbm_surface_handle),
+                active_handle,
+            )
+        })
+    }
+}
+
+impl raw_window_handle::HasDisplayHandle for EglDisplay {
+    fn display_handle(
+        &self,
+    ) -> Result<raw_window_handle::DisplayHandle<'_>, raw_window_handle::HandleError> {
+        let mut gbm_display_handle = raw_window_handle::GbmDisplayHandle::empty();
+        gbm_display_handle.gbm_device = self.gbm_device.as_raw() as _;
+
+        Ok(unsafe {
+            raw_window_handle::DisplayHandle::borrow_raw(raw_window_handle::RawDisplayHandle::Gbm(
+                gbm_display_handle,
+            ))
+        })
+    }
+}
+
+/// Returns the EGL swap interval configured with `SLINT_KMS_SWAP_INTERVAL`, 1 (vsync) by default.
+pub fn swap_interval_from_env() -> Result<u32, PlatformError> {
+    std::env::var("SLINT_KMS_SWAP_INTERVAL").map_or(Ok(1), |interval_str| {
+        interval_str.parse().map_err(|_| {
+            format!("Failed to parse SLINT_KMS_SWAP_INTERVAL: Must be unsigned integral, found {interval_str}")
+                .into()
+        })
+    })
+}
+
+pub fn create_egl_display(device_opener: &DeviceOpener) -> Result<EglDisplay, PlatformError> {
+    let headless = i_slint_core::bool_from_env("SLINT_DRM_HEADLESS")?.unwrap_or(false);
+
+    let mut last_err = None;
+    if let Ok(drm_devices) = std::fs::read_dir("/dev/dri/") {
+        for device in drm_devices {
+            if let Ok(device) = device.map_err(|e| format!("Error opening DRM device: {e}")) {
+                let result = if headless {
+                    try_create_headless_egl_display(device_opener, &device.path())
+                } else {
+                    try_create_egl_display(device_opener, &device.path())
+                };
+                match result {
+                    Ok(dsp) => return Ok(dsp),
+                    Err(e) => last_err = Some(e),
+                }
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| "Could not create an egl display".into()))
+}
+
+pub fn try_create_egl_display(
+    device_opener: &DeviceOpener,
+    device: &std::path::Path,
+) -> Result<EglDisplay, PlatformError> {
+    let drm_device = SharedFd(device_opener(device)?);
+
+    let resources = drm_device
         .resource_handles()
         .map_err(|e| format!("Error reading DRM resource handles: {e}"))?;
 
-    let connector = if let Ok(requested_connector_name) = std::env::var("SLINT_DRM_OUTPUT") {
-        let mut connectors = resources.connectors().iter().filter_map(|handle| {
-            let connector = drm_device.get_connector(*handle, false).ok()?;
-            let name = format!("{}-{}", connector.interface().as_str(), connector.interface_id());
-            let connected = connector.state() == drm::control::connector::State::Connected;
-            Some((name, connector, connected))
-        });
+    let mut outputs = select_outputs(&drm_device, &resources, None, false)?;
+
+    // Mixing legacy and atomic modesetting fails on some drivers, so use atomic modesetting
+    // exclusively when the driver supports it.
+    let atomic_modesetting =
+        drm_device.set_client_capability(drm::ClientCapability::UniversalPlanes, true).is_ok()
+            && drm_device.set_client_capability(drm::ClientCapability::Atomic, true).is_ok()
+            && match set_up_atomic_modesetting(&drm_device, &resources, &mut outputs) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("Warning: Falling back to legacy modesetting: {e}");
+                    false
+                }
+            };
+
+    let vrr = i_slint_core::bool_from_env("SLINT_DRM_VRR")?.unwrap_or(false)
+        && match vrr_supported(&drm_device, &outputs) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Warning: Variable refresh rate is not available: {e}");
+                false
+            }
+        };
+
+    let (width, height) = outputs_size(&outputs);
+    let width = std::num::NonZeroU32::new(width as _)
+        .ok_or_else(|| format!("Invalid mode screen width {width}"))?;
+    let height = std::num::NonZeroU32::new(height as _)
+        .ok_or_else(|| format!("Invalid mode screen height {height}"))?;
+
+    //eprintln!("mode {}/{}", width, height);
+
+    let gbm_device = gbm::Device::new(drm_device.clone())
+        .map_err(|e| format!("Error creating gbm device: {e}"))?;
+
+    let requested_format = std::env::var("SLINT_DRM_FORMAT")
+        .ok()
+        .map(|format_str| {
+            DrmFormat::try_from(format_str.as_str())
+                .map_err(|e| format!("Failed to parse SLINT_DRM_FORMAT: {e}"))
+        })
+        .transpose()?
+        .unwrap_or(DrmFormat::Xrgb8888);
+    let format = if requested_format == DrmFormat::Xrgb8888
+        || format_supported(&drm_device, &resources, &outputs, requested_format)
+    {
+        requested_format
+    } else {
+        eprintln!("Warning: The display does not support the {requested_format:?} format, falling back to Xrgb8888");
+        DrmFormat::Xrgb8888
+    };
+
+    let create_surface = |format: DrmFormat| {
+        gbm_device.create_surface::<OwnedFramebufferHandle>(
+            width.get(),
+            height.get(),
+            format.gbm_format(),
+            gbm::BufferObjectFlags::SCANOUT | gbm::BufferObjectFlags::RENDERING,
+        )
+    };
+    let (gbm_surface, format) = match create_surface(format) {
+        Ok(surface) => (surface, format),
+        Err(e) if format != DrmFormat::Xrgb8888 => {
+            eprintln!("Warning: Error creating gbm surface with the {format:?} format, falling back to Xrgb8888: {e}");
+            let surface = create_surface(DrmFormat::Xrgb8888)
+                .map_err(|e| format!("Error creating gbm surface: {e}"))?;
+            (surface, DrmFormat::Xrgb8888)
+        }
+        Err(e) => return Err(format!("Error creating gbm surface: {e}").into()),
+    };
+
+    let window_size = PhysicalWindowSize::new(width.get(), height.get());
+
+    let idle_timeout = std::env::var("SLINT_KMS_IDLE_TIMEOUT")
+        .ok()
+        .map(|timeout_str| {
+            timeout_str.parse().map(std::time::Duration::from_secs).map_err(|_| {
+                format!("Failed to parse SLINT_KMS_IDLE_TIMEOUT: Must be unsigned integral, found {timeout_str}")
+            })
+        })
+        .transpose()?;
+
+    let color_range = std::env::var("SLINT_DRM_COLOR_RANGE")
+        .ok()
+        .map(|range_str| {
+            ColorRange::try_from(range_str.as_str())
+                .map_err(|e| format!("Failed to parse SLINT_DRM_COLOR_RANGE: {e}"))
+        })
+        .transpose()?;
+
+    let gamma_ramp =
+        std::env::var("SLINT_DRM_GAMMA").ok().map(|path| load_gamma_ramp(&path)).transpose()?;
+
+    let scale_factor = super::scale_factor_from_dpi(
+        {
+            let (width, height) = outputs[0].mode.size();
+            PhysicalWindowSize::new(width as u32, height as u32)
+        },
+        outputs[0].connector.size().unwrap_or_default(),
+    )?;
+
+    let display = EglDisplay {
+        last_buffer: Cell::default(),
+        page_flip_state: Default::default(),
+        buffers_kept_until_flip: Default::default(),
+        pending_page_flips: Cell::new(0),
+        output_info: RefCell::new(output_info(&outputs)),
+        outputs: RefCell::new(outputs),
+        gbm_surface: RefCell::new(gbm_surface),
+        retired_gbm_surface: Cell::new(None),
+        gbm_device,
+        drm_device,
+        size: Cell::new(window_size),
+        scale_factor,
+        outputs_changed: Cell::new(false),
+        format,
+        page_flip_event_source_registered: Cell::new(false),
+        next_animation_frame_callback: Default::default(),
+        vsync: Cell::new(true),
+        atomic_modesetting,
+        vrr: Cell::new(vrr),
+        color_range,
+        gamma_ramp,
+        discard_next_frame: Cell::new(false),
+        dpms_state: Cell::new(DpmsState::On),
+        frame_count: Cell::new(0),
+        buffer_age: Cell::new(0),
+        idle_timer: Default::default(),
+        page_flip_retry_timer: Default::default(),
+        page_flip_retries: Cell::new(0),
+        idle_timeout,
+        headless_directory: None,
+        cursor: Default::default(),
+        cursor_visible: Cell::new(false),
+    };
+
+    display.apply_output_settings()?;
+
+    Ok(display)
+}
+
+/// Selects the connectors to show the screen on, as requested with `SLINT_DRM_OUTPUT`, and the
+/// mode and CRTC for each. Without a requested output, the `previous` connector is preferred
+/// while it's connected. After a `hotplug`, requested mirrors that are not connected are left
+/// out instead of failing.
+fn select_outputs(
+    drm_device: &SharedFd,
+    resources: &drm::control::ResourceHandles,
+    previous: Option<drm::control::connector::Handle>,
+    hotplug: bool,
+) -> Result<Vec<Output>, PlatformError> {
+    // Additional outputs showing the same content as `connector`
+    let mut mirrored_connectors = Vec::new();
+    let connector = if let Ok(requested_connector_names) = std::env::var("SLINT_DRM_OUTPUT") {
+        let connectors = resources
+            .connectors()
+            .iter()
+            .filter_map(|handle| {
+                let connector = drm_device.get_connector(*handle, false).ok()?;
+                let name =
+                    format!("{}-{}", connector.interface().as_str(), connector.interface_id());
+                let connected = connector.state() == drm::control::connector::State::Connected;
+                Some((name, connector, connected))
+            })
+            .collect::<Vec<_>>();
 
-        if requested_connector_name.eq_ignore_ascii_case("list") {
+        if hotplug && requested_connector_names.eq_ignore_ascii_case("list") {
+            // The list was shown when starting, so don't stop the application after a hotplug.
+            return Err("SLINT_DRM_OUTPUT=list doesn't select an output".into());
+        } else if requested_connector_names.eq_ignore_ascii_case("list") {
             let names_and_status = connectors
+                .iter()
                 .map(|(name, _, connected)| format!("{} (connected: {})", name, connected))
                 .collect::<Vec<_>>();
             // Can't return error here because newlines are escaped.
             panic!("\nDRM Output List Requested:\n{}\n", names_and_status.join("\n"));
         } else {
-            let (_, connector, connected) =
-                connectors.find(|(name, _, _)| name == &requested_connector_name).ok_or_else(
-                    || format!("No output with the name '{}' found", requested_connector_name),
-                )?;
-
-            if !connected {
-                return Err(format!(
-                    "Requested output '{}' is not connected",
-                    requested_connector_name
-                )
-                .into());
-            };
+            // A comma separated list of outputs mirrors the first one on the others
+            let mut requested_connectors = Vec::new();
+            for (index, requested_connector_name) in
+                requested_connector_names.split(',').enumerate()
+            {
+                let (_, connector, connected) = connectors
+                    .iter()
+                    .find(|(name, _, _)| name == requested_connector_name)
+                    .ok_or_else(|| {
+                        format!("No output with the name '{}' found", requested_connector_name)
+                    })?;
+
+                if !connected {
+                    if hotplug && index > 0 {
+                        continue;
+                    }
+                    return Err(format!(
+                        "Requested output '{}' is not connected",
+                        requested_connector_name
+                    )
+                    .into());
+                };
+
+                requested_connectors.push(connector.clone());
+            }
 
-            connector
+            mirrored_connectors = requested_connectors.split_off(1);
+            requested_connectors.remove(0)
         }
     } else {
-        resources
-            .connectors()
-            .iter()
-            .find_map(|handle| {
-                let connector = drm_device.get_connector(*handle, false).ok()?;
-                (connector.state() == drm::control::connector::State::Connected).then(|| connector)
-            })
+        let connected = |handle: &drm::control::connector::Handle| {
+            let connector = drm_device.get_connector(*handle, false).ok()?;
+            (connector.state() == drm::control::connector::State::Connected).then(|| connector)
+        };
+        previous
+            .as_ref()
+            .and_then(connected)
+            .or_else(|| resources.connectors().iter().find_map(connected))
             .ok_or_else(|| format!("No connected display connector found"))?
     };
 
-    let mode = *connector
-        .modes()
+    let mode = match std::env::var("SLINT_DRM_MODE") {
+        // After a hotplug, a mode list request or a mode the new display doesn't support falls
+        // back to the preferred mode instead of stopping the application.
+        Ok(requested_mode) if hotplug && requested_mode.eq_ignore_ascii_case("list") => {
+            preferred_mode(&connector)?
+        }
+        Ok(requested_mode) if hotplug => select_mode(&connector, &requested_mode).or_else(|e| {
+            eprintln!("Warning: {e}. Using the preferred mode of the display instead.");
+            preferred_mode(&connector)
+        })?,
+        Ok(requested_mode) => select_mode(&connector, &requested_mode)?,
+        Err(_) => preferred_mode(&connector)?,
+    };
+
+    let crtc = find_crtc(drm_device, resources, &connector, &[])?;
+    let mut outputs = vec![Output { crtc, connector, mode, offset: (0, 0), atomic: None }];
+    if !mirrored_connectors.is_empty() {
+        outputs.extend(mirrored_outputs(drm_device, resources, &outputs[0], mirrored_connectors)?);
+    } else if let Some(tiled_outputs) = tiled_outputs(drm_device, resources, &outputs[0].connector)?
+    {
+        outputs = tiled_outputs;
+    }
+    Ok(outputs)
+}
+
+/// Looks up the primary plane and the properties for driving each output with atomic
+/// modesetting.
+fn set_up_atomic_modesetting(
+    drm_device: &SharedFd,
+    resources: &drm::control::ResourceHandles,
+    outputs: &mut [Output],
+) -> Result<(), PlatformError> {
+    let mut used_planes = Vec::new();
+    let atomic_properties = outputs
         .iter()
-        .max_by(|current_mode, next_mode| {
-            let current = (
-                current_mode.mode_type().contains(drm::control::ModeTypeFlags::PREFERRED),
-                current_mode.size().0 as u32 * current_mode.size().1 as u32,
-            );
-            let next = (
-                next_mode.mode_type().contains(drm::control::ModeTypeFlags::PREFERRED),
-                next_mode.size().0 as u32 * next_mode.size().1 as u32,
-            );
+        .map(|output| {
+            let properties = AtomicProperties::new(drm_device, resources, output, &used_planes)?;
+            used_planes.push(properties.plane);
+            Ok(properties)
+        })
+        .collect::<Result<Vec<_>, PlatformError>>()?;
+    for (output, properties) in outputs.iter_mut().zip(atomic_properties) {
+        output.atomic = Some(properties);
+    }
+    Ok(())
+}
+
+/// Returns the size of the framebuffer spanning all outputs.
+fn outputs_size(outputs: &[Output]) -> (u32, u32) {
+    outputs.iter().fold((0, 0), |(width, height), output| {
+        let (mode_width, mode_height) = output.mode.size();
+        (
+            width.max(output.offset.0 + mode_width as u32),
+            height.max(output.offset.1 + mode_height as u32),
+        )
+    })
+}
 
-            current.cmp(&next)
+fn output_info(outputs: &[Output]) -> Vec<crate::OutputInfo> {
+    outputs
+        .iter()
+        .map(|output| {
+            let (width, height) = output.mode.size();
+            crate::OutputInfo {
+                name: format!(
+                    "{}-{}",
+                    output.connector.interface().as_str(),
+                    output.connector.interface_id()
+                ),
+                size: PhysicalWindowSize::new(width as u32, height as u32),
+                refresh_rate: output.mode.vrefresh(),
+                physical_size_mm: output.connector.size().unwrap_or_default(),
+            }
         })
-        .ok_or_else(|| format!("No preferred or non-zero size display mode found"))?;
+        .collect()
+}
 
-    let encoder = connector
-        .current_encoder()
-        .filter(|current| connector.encoders().iter().any(|h| *h == *current))
-        .and_then(|current| drm_device.get_encoder(current).ok());
+/// Creates a display without outputs, which renders with the GPU of `device` into a surface of
+/// the size requested with `SLINT_DRM_MODE`, and writes the presented frames as PNG files to the
+/// directory set with `SLINT_DRM_HEADLESS_DIR`.
+fn try_create_headless_egl_display(
+    device_opener: &DeviceOpener,
+    device: &std::path::Path,
+) -> Result<EglDisplay, PlatformError> {
+    let drm_device = SharedFd(device_opener(device)?);
 
-    let crtc = if let Some(encoder) = encoder {
-        encoder.crtc().ok_or_else(|| format!("no crtc for encoder"))?
-    } else {
-        // No crtc found for current encoder? Pick the first possible crtc
-        // as described in https://manpages.debian.org/testing/libdrm-dev/drm-kms.7.en.html#CRTC/Encoder_Selection
-        connector
-            .encoders()
-            .iter()
-            .filter_map(|handle| drm_device.get_encoder(*handle).ok())
-            .flat_map(|encoder| resources.filter_crtcs(encoder.possible_crtcs()))
-            .find(|crtc_handle| drm_device.get_crtc(*crtc_handle).is_ok())
+    let (width, height) = match std::env::var("SLINT_DRM_MODE") {
+        Ok(mode_str) => mode_str
+            .split('@')
+            .next()
+            .and_then(|size| size.split_once('x'))
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+            .filter(|(width, height)| *width > 0 && *height > 0)
             .ok_or_else(|| {
-                format!(
-                    "Could not find any crtc for any encoder connected to output {}-{}",
-                    connector.interface().as_str(),
-                    connector.interface_id()
-                )
-            })?
+                format!("Failed to parse SLINT_DRM_MODE: Must be of the form WIDTHxHEIGHT when running headless, found {mode_str}")
+            })?,
+        Err(_) => (1024, 768),
     };
 
-    let (width, height) = mode.size();
-    let width = std::num::NonZeroU32::new(width as _)
-        .ok_or_else(|| format!("Invalid mode screen width {width}"))?;
-    let height = std::num::NonZeroU32::new(height as _)
-        .ok_or_else(|| format!("Invalid mode screen height {height}"))?;
-
-    //eprintln!("mode {}/{}", width, height);
+    let directory = std::path::PathBuf::from(
+        std::env::var("SLINT_DRM_HEADLESS_DIR").unwrap_or_else(|_| ".".into()),
+    );
+    std::fs::create_dir_all(&directory)
+        .map_err(|e| format!("Error creating directory {}: {e}", directory.display()))?;
 
     let gbm_device = gbm::Device::new(drm_device.clone())
         .map_err(|e| format!("Error creating gbm device: {e}"))?;
 
+    let format = std::env::var("SLINT_DRM_FORMAT")
+        .ok()
+        .map(|format_str| {
+            DrmFormat::try_from(format_str.as_str())
+                .map_err(|e| format!("Failed to parse SLINT_DRM_FORMAT: {e}"))
+        })
+        .transpose()?
+        .unwrap_or(DrmFormat::Xrgb8888);
+
+    // The buffers are only read back, never scanned out
     let gbm_surface = gbm_device
         .create_surface::<OwnedFramebufferHandle>(
-            width.get(),
-            height.get(),
-            gbm::Format::Xrgb8888,
-            gbm::BufferObjectFlags::SCANOUT | gbm::BufferObjectFlags::RENDERING,
+            width,
+            height,
+            format.gbm_format(),
+            gbm::BufferObjectFlags::RENDERING,
         )
         .map_err(|e| format!("Error creating gbm surface: {e}"))?;
 
-    let window_size = PhysicalWindowSize::new(width.get(), height.get());
+    let size = PhysicalWindowSize::new(width, height);
+    let scale_factor = super::scale_factor_from_dpi(size, (0, 0))?;
 
     Ok(EglDisplay {
         last_buffer: Cell::default(),
         page_flip_state: Default::default(),
-        crtc,
-        connector,
-        mode,
-        gbm_surface,
+        buffers_kept_until_flip: Default::default(),
+        pending_page_flips: Cell::new(0),
+        outputs: Default::default(),
+        gbm_surface: RefCell::new(gbm_surface),
+        retired_gbm_surface: Cell::new(None),
         gbm_device,
         drm_device,
-        size: window_size,
+        size: Cell::new(size),
+        scale_factor,
+        output_info: RefCell::new(vec![crate::OutputInfo {
+            name: "headless".into(),
+            size,
+            refresh_rate: 0,
+            physical_size_mm: (0, 0),
+        }]),
+        outputs_changed: Cell::new(false),
+        format,
         page_flip_event_source_registered: Cell::new(false),
         next_animation_frame_callback: Default::default(),
+        vsync: Cell::new(true),
+        atomic_modesetting: false,
+        vrr: Cell::new(false),
+        color_range: None,
+        gamma_ramp: None,
+        discard_next_frame: Cell::new(false),
+        dpms_state: Cell::new(DpmsState::On),
+        frame_count: Cell::new(0),
+        buffer_age: Cell::new(0),
+        idle_timer: Default::default(),
+        page_flip_retry_timer: Default::default(),
+        page_flip_retries: Cell::new(0),
+        idle_timeout: None,
+        headless_directory: Some(directory),
+        cursor: Default::default(),
+        cursor_visible: Cell::new(false),
+    })
+}
+
+/// An entry of the lookup table of the `GAMMA_LUT` CRTC property, matching `struct drm_color_lut`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct DrmColorLut {
+    red: u16,
+    green: u16,
+    blue: u16,
+    reserved: u16,
+}
+
+/// Creates a property blob for the `GAMMA_LUT` property. Blobs are created from sized types, so
+/// this is limited to the common lookup table sizes and returns None for others.
+fn create_gamma_lut_blob(
+    drm_device: &SharedFd,
+    lut: &[DrmColorLut],
+) -> Result<Option<drm::control::property::RawValue>, PlatformError> {
+    fn create<const N: usize>(
+        drm_device: &SharedFd,
+        lut: &[DrmColorLut],
+    ) -> Result<drm::control::property::Value<'static>, PlatformError> {
+        let lut: &[DrmColorLut; N] = lut.try_into().map_err(|_| "Invalid gamma lookup table")?;
+        drm_device
+            .create_property_blob(lut)
+            .map_err(|e| format!("Error creating gamma lookup table property blob: {e}").into())
+    }
+    let blob = match lut.len() {
+        256 => create::<256>(drm_device, lut)?,
+        1024 => create::<1024>(drm_device, lut)?,
+        4096 => create::<4096>(drm_device, lut)?,
+        _ => return Ok(None),
+    };
+    match blob {
+        drm::control::property::Value::Blob(id) => Ok(Some(id)),
+        _ => Err("Unexpected value type of the gamma lookup table property blob".into()),
+    }
+}
+
+/// Returns the entry `index` of a lookup table with `size` entries, linearly interpolated from
+/// the given ramp.
+fn resample_gamma(ramp: &[u16], index: usize, size: usize) -> u16 {
+    if size < 2 {
+        return ramp[0];
+    }
+    let position = index as f64 * (ramp.len() - 1) as f64 / (size - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = (lower + 1).min(ramp.len() - 1);
+    let fraction = position - lower as f64;
+    (ramp[lower] as f64 * (1. - fraction) + ramp[upper] as f64 * fraction).round() as u16
+}
+
+/// Reads a gamma ramp from a text file with one entry per line, each consisting of the red,
+/// green, and blue values in the range 0 to 65535, separated by whitespace.
+fn load_gamma_ramp(path: &str) -> Result<(Vec<u16>, Vec<u16>, Vec<u16>), PlatformError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Error reading gamma ramp from {path}: {e}"))?;
+    let (mut red, mut green, mut blue) = (Vec::new(), Vec::new(), Vec::new());
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let values = line
+            .split_whitespace()
+            .map(|value| value.parse::<u16>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .filter(|values| values.len() == 3)
+            .ok_or_else(|| {
+                format!(
+                    "Invalid gamma ramp entry in {path} at line {}: Expected three values between 0 and 65535, found {line}",
+                    line_number + 1
+                )
+            })?;
+        red.push(values[0]);
+        green.push(values[1]);
+        blue.push(values[2]);
+    }
+    Ok((red, green, blue))
+}
+
+/// Checks that all outputs can be driven with a variable refresh rate. This requires atomic
+/// modesetting and connectors that report to be capable of it.
+fn vrr_supported(drm_device: &SharedFd, outputs: &[Output]) -> Result<(), PlatformError> {
+    for output in outputs {
+        let atomic = output.atomic.as_ref().ok_or("Atomic modesetting is not available")?;
+        property(&atomic.crtc_properties, "VRR_ENABLED")?;
+        let capable = properties(drm_device, output.connector.handle())?
+            .iter()
+            .any(|(name, _, value)| name == "vrr_capable" && *value == 1);
+        if !capable {
+            return Err(format!(
+                "Output {}-{} is not capable of a variable refresh rate",
+                output.connector.interface().as_str(),
+                output.connector.interface_id()
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if a primary plane of each output can scan out framebuffers of the given format.
+/// Primary planes are only known if the client capability for universal planes is set, so the
+/// format is assumed to be supported otherwise.
+fn format_supported(
+    drm_device: &SharedFd,
+    resources: &drm::control::ResourceHandles,
+    outputs: &[Output],
+    format: DrmFormat,
+) -> bool {
+    let Ok(planes) = drm_device.plane_handles() else { return true };
+    let primary_planes = planes
+        .planes()
+        .iter()
+        .copied()
+        .filter(|plane| {
+            properties(drm_device, *plane).map_or(false, |properties| {
+                properties
+                    .iter()
+                    .any(|(name, _, value)| name == "type" && *value == DRM_PLANE_TYPE_PRIMARY)
+            })
+        })
+        .filter_map(|plane| drm_device.get_plane(plane).ok())
+        .collect::<Vec<_>>();
+    if primary_planes.is_empty() {
+        return true;
+    }
+
+    let fourcc = format.gbm_format() as u32;
+    outputs.iter().all(|output| {
+        primary_planes.iter().any(|plane| {
+            resources.filter_crtcs(plane.possible_crtcs()).contains(&output.crtc)
+                && plane.formats().contains(&fourcc)
+        })
     })
 }
+
+/// Returns the tile information of the connector, if it is part of a tiled display.
+fn connector_tile(
+    drm_device: &SharedFd,
+    connector: drm::control::connector::Handle,
+) -> Option<TileInfo> {
+    let properties = drm_device.get_properties(connector).ok()?;
+    let (property_handles, values) = properties.as_props_and_values();
+    let (_, blob) = property_handles.iter().zip(values).find(|(handle, _)| {
+        drm_device.get_property(**handle).map_or(false, |info| info.name().to_str() == Ok("TILE"))
+    })?;
+    // Connectors that are not tiled have an empty blob
+    if *blob == 0 {
+        return None;
+    }
+    let data = drm_device.get_property_blob(*blob).ok()?;
+    let description = std::str::from_utf8(&data).ok()?;
+    TileInfo::try_from(description)
+        .map_err(|e| eprintln!("Warning: Ignoring the TILE property of the output: {e}"))
+        .ok()
+}
+
+/// A connector property with enumerated values, with the name and value of each variant
+struct EnumProperty {
+    handle: drm::control::property::Handle,
+    name: String,
+    values: Vec<(String, drm::control::property::RawValue)>,
+}
+
+/// Access to the properties of connectors, as needed to configure them.
+trait ConnectorProperties {
+    /// Returns the properties of the connector that have enumerated values
+    fn enum_properties(
+        &self,
+        connector: drm::control::connector::Handle,
+    ) -> Result<Vec<EnumProperty>, PlatformError>;
+    fn set_property(
+        &self,
+        connector: drm::control::connector::Handle,
+        property: drm::control::property::Handle,
+        value: drm::control::property::RawValue,
+    ) -> Result<(), PlatformError>;
+}
+
+impl ConnectorProperties for SharedFd {
+    fn enum_properties(
+        &self,
+        connector: drm::control::connector::Handle,
+    ) -> Result<Vec<EnumProperty>, PlatformError> {
+        let properties = self
+            .get_properties(connector)
+            .map_err(|e| format!("Error reading connector properties: {e}"))?;
+        let (property_handles, _) = properties.as_props_and_values();
+        Ok(property_handles
+            .iter()
+            .filter_map(|handle| self.get_property(*handle).ok())
+            .filter_map(|info| {
+                let drm::control::property::ValueType::Enum(enum_values) = info.value_type() else {
+                    return None;
+                };
+                Some(EnumProperty {
+                    handle: info.handle(),
+                    name: info.name().to_string_lossy().into_owned(),
+                    values: enum_values
+                        .values()
+                        .1
+                        .iter()
+                        .map(|value| (value.name().to_string_lossy().into_owned(), value.value()))
+                        .collect(),
+                })
+            })
+            .collect())
+    }
+
+    fn set_property(
+        &self,
+        connector: drm::control::connector::Handle,
+        property: drm::control::property::Handle,
+        value: drm::control::property::RawValue,
+    ) -> Result<(), PlatformError> {
+        drm::control::Device::set_property(self, connector, property, value)
+            .map_err(|e| format!("Error setting connector property: {e}").into())
+    }
+}
+
+/// Sets the connector's `Broadcast RGB` property to send colors in the given range. If the
+/// connector doesn't support it, a warning is printed and the connector is left unchanged.
+fn set_connector_color_range(
+    drm_device: &impl ConnectorProperties,
+    connector: drm::control::connector::Handle,
+    range: ColorRange,
+) -> Result<(), PlatformError> {
+    let Some(broadcast_rgb) = drm_device
+        .enum_properties(connector)?
+        .into_iter()
+        .find(|property| property.name == "Broadcast RGB")
+    else {
+        eprintln!("Warning: Output does not support configuring the color range");
+        return Ok(());
+    };
+
+    let value_name = match range {
+        ColorRange::Full => "Full",
+        ColorRange::Limited => "Limited 16:235",
+    };
+    let Some((_, value)) = broadcast_rgb.values.iter().find(|(name, _)| name == value_name) else {
+        eprintln!("Warning: Output does not support the {value_name} color range");
+        return Ok(());
+    };
+
+    drm_device.set_property(connector, broadcast_rgb.handle, *value)
+}
+
+/// Returns the outputs mirroring `output` on the `connectors`, one for each connector. Each
+/// connector must support a mode of the same size as the mode of `output`.
+fn mirrored_outputs(
+    drm_device: &SharedFd,
+    resources: &drm::control::ResourceHandles,
+    output: &Output,
+    connectors: Vec<drm::control::connector::Info>,
+) -> Result<Vec<Output>, PlatformError> {
+    let mode = output.mode;
+    let mut used_crtcs = vec![output.crtc];
+    let mut outputs = Vec::new();
+    for connector in connectors {
+        // Prefer the same refresh rate, so that all page flips complete together
+        let mirrored_mode = *connector
+            .modes()
+            .iter()
+            .filter(|m| m.size() == mode.size())
+            .max_by_key(|m| (m.vrefresh() == mode.vrefresh(), m.vrefresh()))
+            .ok_or_else(|| {
+                format!(
+                    "Output {}-{} cannot be mirrored: It has no display mode of the size {}x{}",
+                    connector.interface().as_str(),
+                    connector.interface_id(),
+                    mode.size().0,
+                    mode.size().1
+                )
+            })?;
+        let crtc = find_crtc(drm_device, resources, &connector, &used_crtcs)?;
+        used_crtcs.push(crtc);
+        outputs.push(Output { crtc, connector, mode: mirrored_mode, offset: (0, 0), atomic: None });
+    }
+    Ok(outputs)
+}
+
+/// If the connector is one tile of a display made of several tiles, returns the outputs driving
+/// all the tiles together, each showing its part of the framebuffer.
+fn tiled_outputs(
+    drm_device: &SharedFd,
+    resources: &drm::control::ResourceHandles,
+    connector: &drm::control::connector::Info,
+) -> Result<Option<Vec<Output>>, PlatformError> {
+    let Some(tile) = connector_tile(drm_device, connector.handle()) else {
+        return Ok(None);
+    };
+
+    let tiles = resources
+        .connectors()
+        .iter()
+        .filter_map(|handle| {
+            let connector = drm_device.get_connector(*handle, false).ok()?;
+            if connector.state() != drm::control::connector::State::Connected {
+                return None;
+            }
+            let tile = connector_tile(drm_device, *handle)
+                .filter(|other| other.group_id == tile.group_id)?;
+            Some((connector, tile))
+        })
+        .collect::<Vec<_>>();
+
+    let tile_count = (tile.num_h_tiles * tile.num_v_tiles) as usize;
+    if tiles.len() != tile_count {
+        eprintln!(
+            "Warning: Only {} of the {tile_count} tiles of the display are connected, using a single tile",
+            tiles.len()
+        );
+        return Ok(None);
+    }
+
+    let offsets = tile_offsets(&tiles.iter().map(|(_, tile)| *tile).collect::<Vec<_>>());
+
+    let mut outputs: Vec<Output> = Vec::with_capacity(tiles.len());
+    for ((connector, tile), offset) in tiles.into_iter().zip(offsets) {
+        let mode = *connector
+            .modes()
+            .iter()
+            .filter(|mode| mode.size() == (tile.h_size as u16, tile.v_size as u16))
+            .max_by_key(|mode| mode.mode_type().contains(drm::control::ModeTypeFlags::PREFERRED))
+            .ok_or_else(|| {
+                format!(
+                    "No display mode matching the tile size {}x{} found",
+                    tile.h_size, tile.v_size
+                )
+            })?;
+        let used_crtcs = outputs.iter().map(|output| output.crtc).collect::<Vec<_>>();
+        let crtc = find_crtc(drm_device, resources, &connector, &used_crtcs)?;
+        outputs.push(Output { crtc, connector, mode, offset, atomic: None });
+    }
+
+    Ok(Some(outputs))
+}
+
+/// Returns true if `message`, a kernel uevent, reports that displays were plugged into or
+/// unplugged from a DRM device. The message consists of a summary and `KEY=value` fields, all
+/// terminated by NUL.
+fn is_drm_hotplug_event(message: &[u8]) -> bool {
+    let (mut drm, mut hotplug) = (false, false);
+    for field in message.split(|byte| *byte == 0) {
+        match field {
+            b"SUBSYSTEM=drm" => drm = true,
+            b"HOTPLUG=1" => hotplug = true,
+            _ => {}
+        }
+    }
+    drm && hotplug
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_drm_hotplug_event() {
+        assert!(is_drm_hotplug_event(
+            b"change@/devices/pci0000:00/0000:00:02.0/drm/card0\0ACTION=change\0DEVPATH=/devices/pci0000:00/0000:00:02.0/drm/card0\0SUBSYSTEM=drm\0HOTPLUG=1\0DEVNAME=dri/card0\0SEQNUM=4242\0"
+        ));
+        // Other events of the DRM device
+        assert!(!is_drm_hotplug_event(
+            b"add@/devices/pci0000:00/0000:00:02.0/drm/card0\0ACTION=add\0SUBSYSTEM=drm\0DEVNAME=dri/card0\0"
+        ));
+        // A HOTPLUG field of another subsystem
+        assert!(!is_drm_hotplug_event(
+            b"change@/devices/platform/dock.0\0ACTION=change\0SUBSYSTEM=platform\0HOTPLUG=1\0"
+        ));
+    }
+
+    #[test]
+    fn test_tile_info_from_property() {
+        assert_eq!(
+            TileInfo::try_from("3:1:2:1:1:0:1920:2160\0"),
+            Ok(TileInfo {
+                group_id: 3,
+                num_h_tiles: 2,
+                num_v_tiles: 1,
+                h_loc: 1,
+                v_loc: 0,
+                h_size: 1920,
+                v_size: 2160,
+            })
+        );
+        // Without the terminating nul
+        assert_eq!(TileInfo::try_from("3:1:2:1:1:0:1920:2160").map(|tile| tile.h_size), Ok(1920));
+
+        assert!(TileInfo::try_from("").is_err());
+        assert!(TileInfo::try_from("3:1:2:1:1:0:1920").is_err());
+        assert!(TileInfo::try_from("3:1:2:1:1:0:1920:2160:0").is_err());
+        assert!(TileInfo::try_from("3:1:2:1:1:0:1920:-2160").is_err());
+        assert!(TileInfo::try_from("3:1:2:1:1:0:1920:2160\0\0garbage").is_err());
+    }
+
+    #[test]
+    fn test_tile_offsets() {
+        let tile = |h_loc, v_loc, h_size, v_size| TileInfo {
+            group_id: 1,
+            num_h_tiles: 2,
+            num_v_tiles: 2,
+            h_loc,
+            v_loc,
+            h_size,
+            v_size,
+        };
+
+        assert!(tile_offsets(&[]).is_empty());
+        assert_eq!(tile_offsets(&[tile(0, 0, 1920, 2160)]), vec![(0, 0)]);
+
+        // Side by side, in any order
+        assert_eq!(
+            tile_offsets(&[tile(1, 0, 1920, 2160), tile(0, 0, 1920, 2160)]),
+            vec![(1920, 0), (0, 0)]
+        );
+
+        // A grid of tiles of different sizes
+        assert_eq!(
+            tile_offsets(&[
+                tile(0, 0, 1000, 600),
+                tile(1, 0, 800, 600),
+                tile(0, 1, 1000, 400),
+                tile(1, 1, 800, 400),
+            ]),
+            vec![(0, 0), (1000, 0), (0, 600), (1000, 600)]
+        );
+
+        // A row of three tiles
+        assert_eq!(
+            tile_offsets(&[tile(2, 0, 500, 100), tile(0, 0, 700, 100), tile(1, 0, 600, 100)]),
+            vec![(1300, 0), (0, 0), (700, 0)]
+        );
+    }
+
+    /// A connector with the given enum properties, recording the properties that are set
+    struct MockConnector {
+        properties: Vec<(&'static str, Vec<&'static str>)>,
+        set_properties: RefCell<Vec<(drm::control::property::Handle, u64)>>,
+    }
+
+    impl MockConnector {
+        fn new(properties: Vec<(&'static str, Vec<&'static str>)>) -> Self {
+            Self { properties, set_properties: Default::default() }
+        }
+    }
+
+    impl ConnectorProperties for MockConnector {
+        fn enum_properties(
+            &self,
+            _connector: drm::control::connector::Handle,
+        ) -> Result<Vec<EnumProperty>, PlatformError> {
+            Ok(self
+                .properties
+                .iter()
+                .enumerate()
+                .map(|(index, (name, values))| EnumProperty {
+                    handle: drm::control::from_u32(index as u32 + 1).unwrap(),
+                    name: name.to_string(),
+                    values: values
+                        .iter()
+                        .enumerate()
+                        .map(|(value, name)| (name.to_string(), value as u64))
+                        .collect(),
+                })
+                .collect())
+        }
+
+        fn set_property(
+            &self,
+            _connector: drm::control::connector::Handle,
+            property: drm::control::property::Handle,
+            value: u64,
+        ) -> Result<(), PlatformError> {
+            self.set_properties.borrow_mut().push((property, value));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_set_connector_color_range() {
+        let connector = drm::control::from_u32(1).unwrap();
+        let broadcast_rgb = drm::control::from_u32(2).unwrap();
+        let supporting_connector = || {
+            MockConnector::new(vec![
+                ("DPMS", vec!["On", "Standby", "Suspend", "Off"]),
+                ("Broadcast RGB", vec!["Automatic", "Full", "Limited 16:235"]),
+            ])
+        };
+
+        let mock = supporting_connector();
+        set_connector_color_range(&mock, connector, ColorRange::Full).unwrap();
+        assert_eq!(*mock.set_properties.borrow(), vec![(broadcast_rgb, 1)]);
+
+        let mock = supporting_connector();
+        set_connector_color_range(&mock, connector, ColorRange::Limited).unwrap();
+        assert_eq!(*mock.set_properties.borrow(), vec![(broadcast_rgb, 2)]);
+
+        // Connectors without the property or the range are left unchanged
+        let mock = MockConnector::new(vec![("DPMS", vec!["On", "Standby", "Suspend", "Off"])]);
+        set_connector_color_range(&mock, connector, ColorRange::Full).unwrap();
+        assert!(mock.set_properties.borrow().is_empty());
+
+        let mock = MockConnector::new(vec![("Broadcast RGB", vec!["Automatic", "Full"])]);
+        set_connector_color_range(&mock, connector, ColorRange::Limited).unwrap();
+        assert!(mock.set_properties.borrow().is_empty());
+    }
+
+    /// A buffer that fails the test when it's released while the mock display scans it out
+    struct TrackedBuffer {
+        id: usize,
+        scanned_out: Rc<RefCell<std::collections::HashSet<usize>>>,
+    }
+
+    impl Drop for TrackedBuffer {
+        fn drop(&mut self) {
+            assert!(
+                !self.scanned_out.borrow().contains(&self.id),
+                "Buffer {} was released while it's scanned out",
+                self.id
+            );
+        }
+    }
+
+    /// Presents buffers like `EglDisplay`, to a display that scans out the buffer it was set up
+    /// with, and both buffers while a page flip is pending.
+    #[derive(Default)]
+    struct MockDisplay {
+        scanned_out: Rc<RefCell<std::collections::HashSet<usize>>>,
+        pending_flip: Option<usize>,
+        state: PageFlipState<TrackedBuffer>,
+        last_buffer: Option<TrackedBuffer>,
+        buffers_kept_until_flip: Vec<TrackedBuffer>,
+    }
+
+    impl MockDisplay {
+        fn new_buffer(&self, id: usize) -> TrackedBuffer {
+            TrackedBuffer { id, scanned_out: self.scanned_out.clone() }
+        }
+
+        fn flip_to(&mut self, buffer: TrackedBuffer) {
+            assert_eq!(self.pending_flip, None, "The driver rejects a second page flip");
+            self.scanned_out.borrow_mut().insert(buffer.id);
+            self.pending_flip = Some(buffer.id);
+            let scanned_out_buffer = self.last_buffer.replace(buffer).unwrap();
+            self.state = PageFlipState::WaitingForPageFlip {
+                _buffer_to_keep_alive_until_flip: scanned_out_buffer,
+                queued_buffer: None,
+            };
+        }
+
+        fn present(&mut self, buffer: TrackedBuffer) {
+            if let PageFlipState::NoFrameBufferPosted = self.state {
+                // Setting up the CRTC waits for a pending page flip
+                self.complete_page_flip();
+                *self.scanned_out.borrow_mut() = [buffer.id].into();
+                if let Some(previous_buffer) = self.last_buffer.replace(buffer) {
+                    self.buffers_kept_until_flip.push(previous_buffer);
+                }
+                self.state = PageFlipState::InitialBufferPosted;
+                return;
+            }
+            match &mut self.state {
+                PageFlipState::WaitingForPageFlip { queued_buffer, .. } => {
+                    *queued_buffer = Some((buffer, drm::control::from_u32(1).unwrap()));
+                }
+                PageFlipState::InitialBufferPosted | PageFlipState::ReadyForNextBuffer => {
+                    self.flip_to(buffer);
+                }
+                PageFlipState::NoFrameBufferPosted | PageFlipState::RetryingPageFlip { .. } => {
+                    unreachable!()
+                }
+            }
+        }
+
+        fn complete_page_flip(&mut self) {
+            let Some(id) = self.pending_flip.take() else { return };
+            *self.scanned_out.borrow_mut() = [id].into();
+            // Like the page flip handler of `EglDisplay`
+            let Some(queued_buffer) = self.state.page_flips_completed() else { return };
+            self.buffers_kept_until_flip.clear();
+            if let Some((buffer, _)) = queued_buffer {
+                self.flip_to(buffer);
+            }
+        }
+
+        fn power_on(&mut self) {
+            // Like `EglDisplay::set_dpms`
+            let scanned_out_buffers = self.state.reset();
+            self.buffers_kept_until_flip.extend(scanned_out_buffers);
+        }
+    }
+
+    #[test]
+    fn test_buffers_kept_while_scanned_out() {
+        let mut display = MockDisplay::default();
+        let mut random = 42u32;
+        for id in 0..10000 {
+            random = random.wrapping_mul(1664525).wrapping_add(1013904223);
+            match random >> 28 {
+                0..=8 => {
+                    let buffer = display.new_buffer(id);
+                    display.present(buffer);
+                }
+                9..=14 => display.complete_page_flip(),
+                _ => display.power_on(),
+            }
+            // The buffer that was set or flipped to last is kept
+            if let Some(last_buffer) = &display.last_buffer {
+                assert!(display.scanned_out.borrow().contains(&last_buffer.id));
+            }
+        }
+        display.scanned_out.borrow_mut().clear();
+    }
+}
@@ -0,0 +1,661 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+//! Shows frames rendered on the CPU in DRM dumb buffers, for systems without usable GPU
+//! acceleration.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use super::drmoutput::{find_crtc, preferred_mode, select_mode, SharedFd};
+use crate::DeviceOpener;
+use drm::buffer::Buffer as _;
+use drm::control::Device;
+use i_slint_core::api::PhysicalSize as PhysicalWindowSize;
+use i_slint_core::platform::PlatformError;
+
+/// A dumb buffer in the XRGB8888 format, added as framebuffer to scan it out
+struct DumbFramebuffer {
+    buffer: Option<drm::control::dumbbuffer::DumbBuffer>,
+    handle: drm::control::framebuffer::Handle,
+    device: SharedFd,
+}
+
+impl Drop for DumbFramebuffer {
+    fn drop(&mut self) {
+        self.device.destroy_framebuffer(self.handle).ok();
+        if let Some(buffer) = self.buffer.take() {
+            self.device.destroy_dumb_buffer(buffer).ok();
+        }
+    }
+}
+
+/// What needs to be done to show the buffer a frame was just rendered into
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum ScanoutAction {
+    /// Configure the CRTC to scan out the buffer. Only needed for the first frame.
+    SetCrtc(usize),
+    /// Flip to the buffer at the next vblank
+    PageFlip(usize),
+    /// The buffer is already scanned out, as there is only one
+    WaitForVblank,
+}
+
+/// Tracks which buffer is scanned out and which one the next frame is rendered into.
+struct BufferQueue {
+    /// The number of the frame that was last rendered into each buffer, 0 if none was
+    frames: Vec<u64>,
+    frame_count: u64,
+    /// The buffer that is scanned out
+    front: Option<usize>,
+    /// The buffer that is flipped to at the next vblank
+    pending_flip: Option<usize>,
+}
+
+impl BufferQueue {
+    fn new(buffer_count: usize) -> Self {
+        Self { frames: vec![0; buffer_count], frame_count: 0, front: None, pending_flip: None }
+    }
+
+    /// The buffer to render the next frame into
+    fn back_buffer(&self) -> usize {
+        self.front.map_or(0, |front| (front + 1) % self.frames.len())
+    }
+
+    /// How many frames ago the back buffer was rendered into, or 0 if its content is unknown
+    fn buffer_age(&self) -> u8 {
+        match self.frames[self.back_buffer()] {
+            0 => 0,
+            frame => (self.frame_count + 1 - frame).try_into().unwrap_or_default(),
+        }
+    }
+
+    /// Records that a frame was rendered into the back buffer and returns how to show it.
+    fn present(&mut self) -> ScanoutAction {
+        let buffer = self.back_buffer();
+        self.frame_count += 1;
+        self.frames[buffer] = self.frame_count;
+        match self.front {
+            None => {
+                self.front = Some(buffer);
+                ScanoutAction::SetCrtc(buffer)
+            }
+            Some(front) if front == buffer => ScanoutAction::WaitForVblank,
+            Some(_) => {
+                self.pending_flip = Some(buffer);
+                ScanoutAction::PageFlip(buffer)
+            }
+        }
+    }
+
+    /// Records that the frame rendered into the back buffer is not shown. Its content is then
+    /// unknown to the renderer.
+    fn discard(&mut self) {
+        let buffer = self.back_buffer();
+        self.frames[buffer] = 0;
+    }
+
+    /// Records that the flip requested by `present` completed, or failed with `success` false.
+    fn page_flipped(&mut self, success: bool) {
+        if let Some(buffer) = self.pending_flip.take() {
+            if success {
+                self.front = Some(buffer);
+            }
+        }
+    }
+}
+
+pub struct SoftwareBufferDisplay {
+    buffers: RefCell<Vec<DumbFramebuffer>>,
+    queue: RefCell<BufferQueue>,
+    /// Set while a page flip or vblank event is pending, the next frame is rendered after it
+    waiting_for_event: Cell<bool>,
+    drm_device: SharedFd,
+    crtc: drm::control::crtc::Handle,
+    /// The index of the CRTC in the resources of the device, to request vblank events for it
+    crtc_index: u32,
+    connector: drm::control::connector::Handle,
+    mode: drm::control::Mode,
+    pub size: PhysicalWindowSize,
+    /// The number of pixels from the start of one row of the buffers to the next
+    pub stride: usize,
+    /// The default scale factor, derived from the DPI of the display
+    pub scale_factor: f32,
+    pub output_info: crate::OutputInfo,
+    page_flip_event_source_registered: Cell<bool>,
+    next_animation_frame_callback: Cell<Option<Box<dyn FnOnce()>>>,
+    vsync: Cell<bool>,
+    discard_next_frame: Cell<bool>,
+}
+
+impl SoftwareBufferDisplay {
+    /// Maps the buffer the next frame is rendered into and passes its bytes to `callback`.
+    pub fn map_back_buffer<R>(
+        &self,
+        callback: impl FnOnce(&mut [u8]) -> R,
+    ) -> Result<R, PlatformError> {
+        let back_buffer = self.queue.borrow().back_buffer();
+        self.map_buffer(back_buffer, callback)
+    }
+
+    fn map_buffer<R>(
+        &self,
+        index: usize,
+        callback: impl FnOnce(&mut [u8]) -> R,
+    ) -> Result<R, PlatformError> {
+        let mut buffers = self.buffers.borrow_mut();
+        let buffer = buffers[index].buffer.as_mut().ok_or("Dumb buffer was destroyed")?;
+        let mut mapping = self
+            .drm_device
+            .map_dumb_buffer(buffer)
+            .map_err(|e| format!("Error mapping dumb buffer: {e}"))?;
+        Ok(callback(mapping.as_mut()))
+    }
+
+    pub fn present(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let single_buffer = self.buffers.borrow().len() == 1;
+        // A single buffer is scanned out while it's rendered into, so the frame was shown anyway
+        if self.discard_next_frame.take() && !single_buffer {
+            self.queue.borrow_mut().discard();
+            self.next_animation_frame_callback.take();
+            return Ok(());
+        }
+
+        let framebuffers =
+            self.buffers.borrow().iter().map(|buffer| buffer.handle).collect::<Vec<_>>();
+        if scan_out(self, &mut self.queue.borrow_mut(), &framebuffers, &self.vsync)? {
+            self.waiting_for_event.set(true);
+        } else {
+            self.frame_done_soon();
+        }
+        Ok(())
+    }
+
+    /// Lets the next frame be rendered without waiting for a page flip or vblank event.
+    fn frame_done_soon(&self) {
+        if let Some(next_animation_frame_callback) = self.next_animation_frame_callback.take() {
+            // Invoke the callback through the event loop instead of directly, so that if it
+            // decides to set `needs_redraw` to true, the event loop will process it.
+            i_slint_core::timers::Timer::single_shot(std::time::Duration::default(), move || {
+                next_animation_frame_callback();
+            })
+        }
+    }
+
+    /// Reads back the buffer that is scanned out and converts it to RGBA.
+    pub fn read_front_buffer(
+        &self,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        let front = self.queue.borrow().front.ok_or("No frame was presented yet")?;
+        let (width, height) = (self.size.width, self.size.height);
+        let stride = self.stride * 4;
+        self.map_buffer(front, |data| {
+            let mut pixels = i_slint_core::graphics::SharedPixelBuffer::<
+                i_slint_core::graphics::Rgba8Pixel,
+            >::new(width, height);
+            for (y, row) in pixels.make_mut_slice().chunks_mut(width as usize).enumerate() {
+                let source = &data[y * stride..][..width as usize * 4];
+                for (pixel, source) in row.iter_mut().zip(source.chunks_exact(4)) {
+                    let value = u32::from_le_bytes([source[0], source[1], source[2], source[3]]);
+                    *pixel = i_slint_core::graphics::Rgba8Pixel::new(
+                        (value >> 16) as u8,
+                        (value >> 8) as u8,
+                        value as u8,
+                        255,
+                    );
+                }
+            }
+            pixels
+        })
+    }
+}
+
+/// The DRM calls that show the buffers, separate from the display so that presenting can be
+/// tested without a device.
+trait ScanoutDevice {
+    /// Configures the CRTC to scan out `fb`
+    fn set_crtc(
+        &self,
+        fb: drm::control::framebuffer::Handle,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Requests a flip to `fb`, which is reported with a page flip event
+    fn page_flip(
+        &self,
+        fb: drm::control::framebuffer::Handle,
+        flags: drm::control::PageFlipFlags,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Requests a vblank event for the next vblank
+    fn wait_vblank(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
+
+impl ScanoutDevice for SoftwareBufferDisplay {
+    fn set_crtc(
+        &self,
+        fb: drm::control::framebuffer::Handle,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.drm_device
+            .set_crtc(self.crtc, Some(fb), (0, 0), &[self.connector], Some(self.mode))
+            .map_err(|e| format!("Error presenting fb: {e}").into())
+    }
+
+    fn page_flip(
+        &self,
+        fb: drm::control::framebuffer::Handle,
+        flags: drm::control::PageFlipFlags,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.drm_device
+            .page_flip(self.crtc, fb, flags, None)
+            .map_err(|e| format!("Error presenting fb: {e}").into())
+    }
+
+    fn wait_vblank(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.drm_device
+            .wait_vblank(
+                drm::VblankWaitTarget::Relative(1),
+                drm::VblankWaitFlags::EVENT,
+                self.crtc_index,
+                0,
+            )
+            .map(|_| ())
+            .map_err(|e| format!("Error requesting vblank event: {e}").into())
+    }
+}
+
+/// Shows the frame that was just rendered into the back buffer of `queue`, whose buffers were
+/// added as `framebuffers`. Returns true if the next frame has to wait for a page flip or vblank
+/// event, or false if it can be rendered right away.
+fn scan_out(
+    device: &impl ScanoutDevice,
+    queue: &mut BufferQueue,
+    framebuffers: &[drm::control::framebuffer::Handle],
+    vsync: &Cell<bool>,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    match queue.present() {
+        ScanoutAction::SetCrtc(index) => {
+            device.set_crtc(framebuffers[index])?;
+            Ok(false)
+        }
+        ScanoutAction::PageFlip(index) => {
+            if let Err(e) = page_flip(device, framebuffers[index], vsync) {
+                queue.page_flipped(false);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        ScanoutAction::WaitForVblank if !vsync.get() => Ok(false),
+        ScanoutAction::WaitForVblank => {
+            // Render the next frame right after the next vblank, so that the buffer is
+            // changed while it's not scanned out.
+            device.wait_vblank()?;
+            Ok(true)
+        }
+    }
+}
+
+fn page_flip(
+    device: &impl ScanoutDevice,
+    fb: drm::control::framebuffer::Handle,
+    vsync: &Cell<bool>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !vsync.get() {
+        // Flip right away, at the risk of tearing. Not all drivers support this.
+        match device
+            .page_flip(fb, drm::control::PageFlipFlags::EVENT | drm::control::PageFlipFlags::ASYNC)
+        {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                eprintln!(
+                    "Warning: Asynchronous page flips are not supported, enabling vsync: {e}"
+                );
+                vsync.set(true);
+            }
+        }
+    }
+    device.page_flip(fb, drm::control::PageFlipFlags::EVENT)
+}
+
+impl super::Presenter for SoftwareBufferDisplay {
+    fn register_page_flip_handler(
+        self: Rc<Self>,
+        event_loop_handle: crate::calloop_backend::EventLoopHandle,
+    ) -> Result<(), PlatformError> {
+        if self.page_flip_event_source_registered.replace(true) {
+            return Ok(());
+        }
+
+        let self_weak = Rc::downgrade(&self);
+
+        let source = calloop::generic::Generic::new_with_error::<drm::SystemError>(
+            self.drm_device.0.clone(),
+            calloop::Interest::READ,
+            calloop::Mode::Level,
+        );
+
+        event_loop_handle
+            .insert_source(source, move |_, _, _| {
+                let Some(this) = self_weak.upgrade() else {
+                    return Ok(calloop::PostAction::Continue);
+                };
+                let (mut page_flipped, mut vblank) = (false, false);
+                for event in this.drm_device.receive_events()? {
+                    match event {
+                        drm::control::Event::PageFlip(..) => page_flipped = true,
+                        drm::control::Event::Vblank(..) => vblank = true,
+                        _ => {}
+                    }
+                }
+                if !(page_flipped || vblank) || !this.waiting_for_event.replace(false) {
+                    return Ok(calloop::PostAction::Continue);
+                }
+                if page_flipped {
+                    this.queue.borrow_mut().page_flipped(true);
+                }
+                if let Some(next_animation_frame_callback) =
+                    this.next_animation_frame_callback.take()
+                {
+                    next_animation_frame_callback();
+                }
+                Ok(calloop::PostAction::Continue)
+            })
+            .map_err(|e| {
+                PlatformError::Other(format!("Error registering page flip handler: {e}"))
+            })?;
+        Ok(())
+    }
+
+    fn present_with_next_frame_callback(
+        &self,
+        ready_for_next_animation_frame: Box<dyn FnOnce()>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.next_animation_frame_callback.set(Some(ready_for_next_animation_frame));
+        self.present()
+    }
+
+    fn set_vsync(&self, vsync: bool) {
+        self.vsync.set(vsync);
+    }
+
+    fn discard_next_frame(&self) {
+        self.discard_next_frame.set(true);
+    }
+
+    fn buffer_age(&self) -> u8 {
+        self.queue.borrow().buffer_age()
+    }
+
+    fn read_front_buffer(
+        &self,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        SoftwareBufferDisplay::read_front_buffer(self)
+    }
+
+    fn is_ready_to_present(&self) -> bool {
+        !self.waiting_for_event.get()
+    }
+}
+
+pub fn create_software_display(
+    device_opener: &DeviceOpener,
+) -> Result<SoftwareBufferDisplay, PlatformError> {
+    let mut last_err = None;
+    if let Ok(drm_devices) = std::fs::read_dir("/dev/dri/") {
+        for device in drm_devices {
+            if let Ok(device) = device.map_err(|e| format!("Error opening DRM device: {e}")) {
+                // Render nodes have no connectors
+                if !device.file_name().to_string_lossy().starts_with("card") {
+                    continue;
+                }
+                match try_create_software_display(device_opener, &device.path()) {
+                    Ok(dsp) => return Ok(dsp),
+                    Err(e) => last_err = Some(e),
+                }
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| "Could not create a software display".into()))
+}
+
+fn try_create_software_display(
+    device_opener: &DeviceOpener,
+    device: &std::path::Path,
+) -> Result<SoftwareBufferDisplay, PlatformError> {
+    let drm_device = SharedFd(device_opener(device)?);
+
+    let resources = drm_device
+        .resource_handles()
+        .map_err(|e| format!("Error reading DRM resource handles: {e}"))?;
+
+    let connector_name = |connector: &drm::control::connector::Info| {
+        format!("{}-{}", connector.interface().as_str(), connector.interface_id())
+    };
+    let connected = |connector: &drm::control::connector::Info| {
+        connector.state() == drm::control::connector::State::Connected
+    };
+    let mut connectors = resources
+        .connectors()
+        .iter()
+        .filter_map(|handle| drm_device.get_connector(*handle, false).ok());
+
+    let connector = if let Ok(requested_connector_names) = std::env::var("SLINT_DRM_OUTPUT") {
+        let mut requested_connector_names = requested_connector_names.split(',');
+        let requested_connector_name = requested_connector_names.next().unwrap_or_default();
+        if requested_connector_names.next().is_some() {
+            eprintln!("Warning: The software renderer shows the user interface only on the first output of SLINT_DRM_OUTPUT, {requested_connector_name}");
+        }
+        let connector = connectors
+            .find(|connector| connector_name(connector) == requested_connector_name)
+            .ok_or_else(|| format!("No output with the name '{requested_connector_name}' found"))?;
+        if !connected(&connector) {
+            return Err(
+                format!("Requested output '{requested_connector_name}' is not connected").into()
+            );
+        }
+        connector
+    } else {
+        connectors
+            .find(|connector| connected(connector))
+            .ok_or_else(|| format!("No connected display connector found"))?
+    };
+
+    let mode = if let Ok(requested_mode) = std::env::var("SLINT_DRM_MODE") {
+        select_mode(&connector, &requested_mode)?
+    } else {
+        preferred_mode(&connector)?
+    };
+
+    let crtc = find_crtc(&drm_device, &resources, &connector, &[])?;
+    let crtc_index =
+        resources.crtcs().iter().position(|handle| *handle == crtc).unwrap_or_default();
+
+    let single_buffer = i_slint_core::bool_from_env("SLINT_KMS_SINGLE_BUFFER")?.unwrap_or(false);
+
+    let (width, height) = mode.size();
+    let buffers = (0..if single_buffer { 1 } else { 2 })
+        .map(|_| {
+            let buffer = drm_device
+                .create_dumb_buffer(
+                    (width as u32, height as u32),
+                    drm::buffer::DrmFourcc::Xrgb8888,
+                    32,
+                )
+                .map_err(|e| format!("Error creating dumb buffer: {e}"))?;
+            let handle = drm_device
+                .add_framebuffer(&buffer, 24, 32)
+                .map_err(|e| format!("Error adding dumb buffer as framebuffer: {e}"))?;
+            Ok(DumbFramebuffer { buffer: Some(buffer), handle, device: drm_device.clone() })
+        })
+        .collect::<Result<Vec<_>, PlatformError>>()?;
+    let stride = buffers[0].buffer.as_ref().map_or(0, |buffer| buffer.pitch() as usize / 4);
+
+    let size = PhysicalWindowSize::new(width as u32, height as u32);
+    let scale_factor = super::scale_factor_from_dpi(size, connector.size().unwrap_or_default())?;
+
+    let output_info = crate::OutputInfo {
+        name: connector_name(&connector),
+        size,
+        refresh_rate: mode.vrefresh(),
+        physical_size_mm: connector.size().unwrap_or_default(),
+    };
+
+    Ok(SoftwareBufferDisplay {
+        queue: RefCell::new(BufferQueue::new(buffers.len())),
+        buffers: RefCell::new(buffers),
+        waiting_for_event: Cell::new(false),
+        drm_device,
+        crtc,
+        crtc_index: crtc_index as u32,
+        connector: connector.handle(),
+        mode,
+        size,
+        stride,
+        scale_factor,
+        output_info,
+        page_flip_event_source_registered: Cell::new(false),
+        next_animation_frame_callback: Default::default(),
+        vsync: Cell::new(true),
+        discard_next_frame: Cell::new(false),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_buffer() {
+        let mut queue = BufferQueue::new(1);
+        assert_eq!(queue.buffer_age(), 0);
+        let actions = (0..5)
+            .map(|_| {
+                assert_eq!(queue.back_buffer(), 0);
+                queue.present()
+            })
+            .collect::<Vec<_>>();
+        // The buffer is set up for scanout once, and never flipped
+        assert_eq!(
+            actions.iter().filter(|action| matches!(action, ScanoutAction::SetCrtc(_))).count(),
+            1
+        );
+        assert!(!actions.iter().any(|action| matches!(action, ScanoutAction::PageFlip(_))));
+        assert_eq!(actions[0], ScanoutAction::SetCrtc(0));
+        assert_eq!(queue.buffer_age(), 1);
+    }
+
+    #[test]
+    fn test_double_buffer() {
+        let mut queue = BufferQueue::new(2);
+        assert_eq!(queue.present(), ScanoutAction::SetCrtc(0));
+        assert_eq!(queue.buffer_age(), 0);
+        assert_eq!(queue.present(), ScanoutAction::PageFlip(1));
+        queue.page_flipped(true);
+        assert_eq!(queue.back_buffer(), 0);
+        assert_eq!(queue.buffer_age(), 2);
+        assert_eq!(queue.present(), ScanoutAction::PageFlip(0));
+
+        // A failed flip leaves the frame in the back buffer
+        queue.page_flipped(false);
+        assert_eq!(queue.back_buffer(), 0);
+        assert_eq!(queue.buffer_age(), 1);
+
+        // A discarded frame leaves unknown content behind
+        queue.discard();
+        assert_eq!(queue.buffer_age(), 0);
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Call {
+        SetCrtc(drm::control::framebuffer::Handle),
+        PageFlip(drm::control::framebuffer::Handle, drm::control::PageFlipFlags),
+        WaitVblank,
+    }
+
+    /// Records the calls to show the buffers
+    #[derive(Default)]
+    struct MockDevice {
+        calls: RefCell<Vec<Call>>,
+        reject_async_flips: bool,
+    }
+
+    impl ScanoutDevice for MockDevice {
+        fn set_crtc(
+            &self,
+            fb: drm::control::framebuffer::Handle,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.calls.borrow_mut().push(Call::SetCrtc(fb));
+            Ok(())
+        }
+
+        fn page_flip(
+            &self,
+            fb: drm::control::framebuffer::Handle,
+            flags: drm::control::PageFlipFlags,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            if self.reject_async_flips && flags.contains(drm::control::PageFlipFlags::ASYNC) {
+                return Err("Asynchronous flips are not supported".into());
+            }
+            self.calls.borrow_mut().push(Call::PageFlip(fb, flags));
+            Ok(())
+        }
+
+        fn wait_vblank(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.calls.borrow_mut().push(Call::WaitVblank);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_single_buffer_scan_out() {
+        let device = MockDevice::default();
+        let fb = drm::control::from_u32(1).unwrap();
+        let mut queue = BufferQueue::new(1);
+        let vsync = Cell::new(true);
+
+        let waits = (0..5)
+            .map(|_| scan_out(&device, &mut queue, &[fb], &vsync).unwrap())
+            .collect::<Vec<_>>();
+        // The first frame is shown right away, the others are rendered after the next vblank
+        assert_eq!(waits, [false, true, true, true, true]);
+        let calls = device.calls.take();
+        assert_eq!(calls.iter().filter(|call| matches!(call, Call::SetCrtc(_))).count(), 1);
+        assert!(!calls.iter().any(|call| matches!(call, Call::PageFlip(..))));
+        assert_eq!(calls[0], Call::SetCrtc(fb));
+
+        // Without vsync, frames are rendered without waiting for the vblank
+        vsync.set(false);
+        for _ in 0..3 {
+            assert!(!scan_out(&device, &mut queue, &[fb], &vsync).unwrap());
+        }
+        assert!(device.calls.take().is_empty());
+    }
+
+    #[test]
+    fn test_double_buffer_scan_out() {
+        let device = MockDevice { reject_async_flips: true, ..Default::default() };
+        let fbs = [drm::control::from_u32(1).unwrap(), drm::control::from_u32(2).unwrap()];
+        let mut queue = BufferQueue::new(2);
+        let vsync = Cell::new(true);
+
+        assert!(!scan_out(&device, &mut queue, &fbs, &vsync).unwrap());
+        assert!(scan_out(&device, &mut queue, &fbs, &vsync).unwrap());
+        queue.page_flipped(true);
+        // Rejected asynchronous flips fall back to flipping at the vblank
+        vsync.set(false);
+        assert!(scan_out(&device, &mut queue, &fbs, &vsync).unwrap());
+        assert!(vsync.get());
+
+        assert_eq!(
+            device.calls.take(),
+            [
+                Call::SetCrtc(fbs[0]),
+                Call::PageFlip(fbs[1], drm::control::PageFlipFlags::EVENT),
+                Call::PageFlip(fbs[0], drm::control::PageFlipFlags::EVENT),
+            ]
+        );
+    }
+}
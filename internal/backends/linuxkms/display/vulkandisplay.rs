@@ -5,20 +5,95 @@
 use i_slint_core::platform::PlatformError;
 use vulkano::device::physical::{PhysicalDevice, PhysicalDeviceType};
 use vulkano::device::{DeviceExtensions, QueueFlags};
-use vulkano::instance::{Instance, InstanceCreateFlags, InstanceCreateInfo, InstanceExtensions};
+use vulkano::instance::InstanceExtensions;
 use vulkano::swapchain::Surface;
 use vulkano::VulkanLibrary;
 
+use std::os::fd::AsRawFd;
 use std::sync::Arc;
 
+use crate::DeviceOpener;
+
 pub struct VulkanDisplay {
     pub physical_device: Arc<PhysicalDevice>,
     pub queue_family_index: u32,
     pub surface: Arc<Surface>,
     pub size: PhysicalWindowSize,
+    /// The default scale factor, derived from the DPI of the display
+    pub scale_factor: f32,
+    /// The duration of a refresh cycle of the selected mode
+    pub refresh_interval: std::time::Duration,
+    /// The output the display is shown on
+    pub output_info: crate::OutputInfo,
+}
+
+/// A primary DRM device node, with its device number
+struct DrmDevice {
+    path: std::path::PathBuf,
+    major: u64,
+    minor: u64,
+}
+
+/// Lists the primary DRM device nodes (`/dev/dri/card*`), in the same order the OpenGL path tries
+/// them. The nodes are not opened, so that no DRM master is taken from the Vulkan driver.
+fn drm_devices() -> Vec<DrmDevice> {
+    let Ok(entries) = std::fs::read_dir("/dev/dri/") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_str().map_or(false, |name| name.starts_with("card")))
+        .filter_map(|entry| {
+            let stat = nix::sys::stat::stat(&entry.path()).ok()?;
+            Some(DrmDevice {
+                path: entry.path(),
+                major: nix::sys::stat::major(stat.st_rdev),
+                minor: nix::sys::stat::minor(stat.st_rdev),
+            })
+        })
+        .collect()
+}
+
+/// Checks that the device opener gives access to the DRM device that scans out for the Vulkan
+/// device. The device is closed again right away, as the Vulkan driver opens its own file
+/// descriptor and needs to become DRM master on it.
+fn check_drm_device_access(
+    device_opener: &DeviceOpener,
+    drm_device: &DrmDevice,
+) -> Result<(), PlatformError> {
+    let fd = device_opener(&drm_device.path)?;
+    let stat = nix::sys::stat::fstat(fd.as_raw_fd()).map_err(|e| {
+        format!("Error reading the device number of {}: {e}", drm_device.path.display())
+    })?;
+    if (nix::sys::stat::major(stat.st_rdev), nix::sys::stat::minor(stat.st_rdev))
+        != (drm_device.major, drm_device.minor)
+    {
+        return Err(format!(
+            "The device opener returned a different device for {}",
+            drm_device.path.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Returns the index of the DRM device that scans out for the given physical device, as reported
+/// by `VK_EXT_physical_device_drm`.
+fn drm_device_index(physical_device: &PhysicalDevice, drm_devices: &[DrmDevice]) -> Option<usize> {
+    if !physical_device.supported_extensions().ext_physical_device_drm {
+        return None;
+    }
+    let properties = physical_device.properties();
+    if properties.has_primary != Some(true) {
+        return None;
+    }
+    let (major, minor) = (properties.primary_major?, properties.primary_minor?);
+    drm_devices
+        .iter()
+        .position(|device| device.major as i64 == major && device.minor as i64 == minor)
 }
 
-pub fn create_vulkan_display() -> Result<VulkanDisplay, PlatformError> {
+pub fn create_vulkan_display(device_opener: &DeviceOpener) -> Result<VulkanDisplay, PlatformError> {
     let library = VulkanLibrary::new()
         .map_err(|load_err| format!("Error loading vulkan library: {load_err}"))?;
 
@@ -28,18 +103,12 @@ pub fn create_vulkan_display() -> Result<VulkanDisplay, PlatformError> {
         khr_get_surface_capabilities2: true,
         khr_get_physical_device_properties2: true,
         ..InstanceExtensions::empty()
-    }
-    .intersection(library.supported_extensions());
+    };
 
-    let instance = Instance::new(
-        library.clone(),
-        InstanceCreateInfo {
-            flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
-            enabled_extensions: required_extensions,
-            ..Default::default()
-        },
-    )
-    .map_err(|instance_err| format!("Error creating Vulkan instance: {instance_err}"))?;
+    let instance =
+        i_slint_renderer_skia::vulkan_surface::create_instance(library, required_extensions)?;
+
+    let drm_devices = drm_devices();
 
     let device_extensions = DeviceExtensions { khr_swapchain: true, ..DeviceExtensions::empty() };
     let (physical_device, queue_family_index) = instance
@@ -55,16 +124,32 @@ pub fn create_vulkan_display() -> Result<VulkanDisplay, PlatformError> {
                 })
                 .map(|i| (p, i as u32))
         })
-        .min_by_key(|(p, _)| match p.properties().device_type {
-            PhysicalDeviceType::DiscreteGpu => 0,
-            PhysicalDeviceType::IntegratedGpu => 1,
-            PhysicalDeviceType::VirtualGpu => 2,
-            PhysicalDeviceType::Cpu => 3,
-            PhysicalDeviceType::Other => 4,
-            _ => 5,
+        // Prefer the GPU that owns an accessible DRM device, so that rendering happens on the GPU
+        // that scans out. This matters on systems with several GPUs.
+        .min_by_key(|(p, _)| {
+            let device_type_rank = match p.properties().device_type {
+                PhysicalDeviceType::DiscreteGpu => 0,
+                PhysicalDeviceType::IntegratedGpu => 1,
+                PhysicalDeviceType::VirtualGpu => 2,
+                PhysicalDeviceType::Cpu => 3,
+                PhysicalDeviceType::Other => 4,
+                _ => 5,
+            };
+            (drm_device_index(p, &drm_devices).unwrap_or(usize::MAX), device_type_rank)
         })
         .ok_or_else(|| format!("Vulkan: Failed to find suitable physical device"))?;
 
+    if let Some(drm_device) =
+        drm_device_index(&physical_device, &drm_devices).map(|index| &drm_devices[index])
+    {
+        if let Err(e) = check_drm_device_access(device_opener, drm_device) {
+            eprintln!(
+                "Warning: Cannot access the DRM device {} of the Vulkan device: {e}",
+                drm_device.path.display()
+            );
+        }
+    }
+
     let displays =
         physical_device.display_properties().map_err(|e| format!("Error reading displays: {e}"))?;
 
@@ -162,5 +247,29 @@ pub fn create_vulkan_display() -> Result<VulkanDisplay, PlatformError> {
 
     let size = PhysicalWindowSize::new(mode.visible_region()[0], mode.visible_region()[1]);
 
-    Ok(VulkanDisplay { physical_device, queue_family_index, surface: vulkan_surface, size })
+    let [width_mm, height_mm] = display.physical_dimensions();
+    let scale_factor = super::scale_factor_from_dpi(size, (width_mm, height_mm))?;
+
+    // The refresh rate is in millihertz
+    let refresh_interval = match mode.refresh_rate() {
+        0 => std::time::Duration::from_millis(16),
+        refresh_rate => std::time::Duration::from_secs_f64(1000. / refresh_rate as f64),
+    };
+
+    let output_info = crate::OutputInfo {
+        name: display.name().unwrap_or("unknown").to_string(),
+        size,
+        refresh_rate: mode.refresh_rate() / 1000,
+        physical_size_mm: (width_mm, height_mm),
+    };
+
+    Ok(VulkanDisplay {
+        physical_device,
+        queue_family_index,
+        surface: vulkan_surface,
+        size,
+        scale_factor,
+        refresh_interval,
+        output_info,
+    })
 }
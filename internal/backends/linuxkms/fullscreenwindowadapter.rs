@@ -7,15 +7,17 @@
 use std::pin::Pin;
 use std::rc::Rc;
 
-use i_slint_core::api::{LogicalPosition, PhysicalSize as PhysicalWindowSize};
+use i_slint_core::api::{LogicalPosition, LogicalSize, PhysicalSize as PhysicalWindowSize};
 use i_slint_core::graphics::Image;
 use i_slint_core::item_rendering::ItemRenderer;
 use i_slint_core::platform::WindowEvent;
+use i_slint_core::renderer::RendererSealed;
 use i_slint_core::slice::Slice;
+use i_slint_core::window::WindowAdapterInternal;
 use i_slint_core::Property;
 use i_slint_core::{platform::PlatformError, window::WindowAdapter};
 
-use crate::display::RenderingRotation;
+use crate::display::{RenderingFlip, RenderingRotation};
 
 pub trait FullscreenRenderer {
     fn as_core_renderer(&self) -> &dyn i_slint_core::renderer::Renderer;
@@ -23,14 +25,52 @@ pub trait FullscreenRenderer {
     fn render_and_present(
         &self,
         rotation: RenderingRotation,
+        flip: RenderingFlip,
         draw_mouse_cursor_callback: &dyn Fn(&mut dyn ItemRenderer),
         ready_for_next_animation_frame: Box<dyn FnOnce()>,
     ) -> Result<(), PlatformError>;
     fn size(&self) -> PhysicalWindowSize;
+    /// The default scale factor of the window, derived from the DPI of the display.
+    fn scale_factor(&self) -> f32;
+    /// Describes the outputs the renderer shows the window on.
+    fn output_info(&self) -> Vec<crate::OutputInfo>;
+    /// Takes over the outputs selected after a display was plugged in or unplugged, and returns
+    /// the new size of the screen if the outputs changed.
+    fn update_outputs(&self) -> Result<Option<PhysicalWindowSize>, PlatformError> {
+        Ok(None)
+    }
+    /// Reads back the last presented frame, oriented like the window. Returns an error if the
+    /// renderer can't read back, as the Skia Vulkan renderer.
+    fn read_back(
+        &self,
+        rotation: RenderingRotation,
+        flip: RenderingFlip,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    >;
     fn register_page_flip_handler(
         &self,
         event_loop_handle: crate::calloop_backend::EventLoopHandle,
     ) -> Result<(), PlatformError>;
+    /// Enables (the default) or disables synchronizing the presentation with the display refresh.
+    /// Disabling is useful for benchmarking, as frames are then rendered as fast as possible.
+    fn set_vsync(&self, vsync: bool);
+    /// Renders the next frame without showing it on the screen, if supported.
+    fn discard_next_frame(&self);
+    /// Shows `image` as mouse cursor on a hardware cursor plane, see
+    /// [`crate::display::Presenter::set_hardware_cursor`]. Returns false if the cursor must be
+    /// drawn with the scene.
+    fn set_hardware_cursor(
+        &self,
+        _image: &i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+    ) -> bool {
+        false
+    }
+    /// Moves the hardware cursor to `position` on the screen, in pixels, or hides it.
+    fn move_hardware_cursor(&self, _position: Option<(i32, i32)>) -> Result<(), PlatformError> {
+        Ok(())
+    }
 }
 
 pub struct FullscreenWindowAdapter {
@@ -38,6 +78,13 @@ pub struct FullscreenWindowAdapter {
     renderer: Box<dyn FullscreenRenderer>,
     needs_redraw: Cell<bool>,
     rotation: RenderingRotation,
+    flip: RenderingFlip,
+    warm_up: Cell<bool>,
+    /// Set when the mouse cursor is shown on a hardware cursor plane instead of being drawn with
+    /// the scene, see `SLINT_DRM_HW_CURSOR`
+    hardware_cursor: bool,
+    /// Where the mouse cursor was drawn in the last frame, or moved to on the cursor plane
+    drawn_mouse_position: Cell<Option<LogicalPosition>>,
 }
 
 impl WindowAdapter for FullscreenWindowAdapter {
@@ -59,11 +106,11 @@ fn request_redraw(&self) {
 
     fn set_visible(&self, visible: bool) -> Result<(), PlatformError> {
         if visible {
-            if let Some(scale_factor) =
-                std::env::var("SLINT_SCALE_FACTOR").ok().and_then(|sf| sf.parse().ok())
-            {
-                self.window.dispatch_event(WindowEvent::ScaleFactorChanged { scale_factor });
-            }
+            let scale_factor = std::env::var("SLINT_SCALE_FACTOR")
+                .ok()
+                .and_then(|sf| sf.parse().ok())
+                .unwrap_or_else(|| self.renderer.scale_factor());
+            self.window.dispatch_event(WindowEvent::ScaleFactorChanged { scale_factor });
         } else if crate::calloop_backend::QUIT_ON_LAST_WINDOW_CLOSED
             .load(std::sync::atomic::Ordering::Relaxed)
         {
@@ -71,18 +118,50 @@ fn set_visible(&self, visible: bool) -> Result<(), PlatformError> {
         }
         Ok(())
     }
+
+    fn internal(&self, _: i_slint_core::InternalToken) -> Option<&dyn WindowAdapterInternal> {
+        Some(self)
+    }
+}
+
+impl WindowAdapterInternal for FullscreenWindowAdapter {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
 }
 
 impl FullscreenWindowAdapter {
     pub fn new(
         renderer: Box<dyn FullscreenRenderer>,
         rotation: RenderingRotation,
+        flip: RenderingFlip,
+        warm_up: bool,
+        hardware_cursor: bool,
     ) -> Result<Rc<Self>, PlatformError> {
+        // The cursor plane shows the image as it is, without the rotation and mirroring of the
+        // scene.
+        let transformed =
+            rotation != RenderingRotation::NoRotation || flip != RenderingFlip::NoFlip;
+        if hardware_cursor && transformed {
+            eprintln!("The hardware cursor is not supported with rotation or flip, drawing the cursor with the scene");
+        }
+        let hardware_cursor = hardware_cursor
+            && !transformed
+            && mouse_cursor_pixels().map_or(false, |image| renderer.set_hardware_cursor(&image));
+        if hardware_cursor {
+            // Until the mouse moves
+            renderer.move_hardware_cursor(None)?;
+        }
+
         Ok(Rc::<FullscreenWindowAdapter>::new_cyclic(|self_weak| FullscreenWindowAdapter {
             window: i_slint_core::api::Window::new(self_weak.clone()),
             renderer,
             needs_redraw: Cell::new(true),
             rotation,
+            flip,
+            warm_up: Cell::new(warm_up),
+            hardware_cursor,
+            drawn_mouse_position: Default::default(),
         }))
     }
 
@@ -90,13 +169,42 @@ pub fn render_if_needed(
         self: Rc<Self>,
         mouse_position: Pin<&Property<Option<LogicalPosition>>>,
     ) -> Result<(), PlatformError> {
+        if let Some(size) = self.renderer.update_outputs()? {
+            let size = self.rotation.screen_size_to_rotated_window_size(size);
+            self.window.dispatch_event(WindowEvent::Resized {
+                size: size.to_logical(self.window.scale_factor()),
+            });
+            self.request_redraw();
+        }
+        if self.hardware_cursor {
+            self.move_hardware_cursor(mouse_position.get())?;
+        }
         if !self.renderer.is_ready_to_present() {
             return Ok(());
         }
         if self.needs_redraw.replace(false) {
+            if !self.hardware_cursor {
+                self.mark_mouse_cursor_dirty(mouse_position.get());
+            }
+            if self.warm_up.take() {
+                // Render the first frame once without showing it, so that shaders, glyph atlases
+                // and other GPU resources exist before the first frame that makes it to the screen.
+                self.renderer.discard_next_frame();
+                self.renderer.render_and_present(
+                    self.rotation,
+                    self.flip,
+                    &|_| {},
+                    Box::new(|| {}),
+                )?;
+            }
             self.renderer.render_and_present(
                 self.rotation,
+                self.flip,
                 &|item_renderer| {
+                    // Reading the position here would also redraw the scene when the mouse moves
+                    if self.hardware_cursor {
+                        return;
+                    }
                     if let Some(mouse_position) = mouse_position.get() {
                         item_renderer.save_state();
                         item_renderer.translate(
@@ -123,6 +231,80 @@ pub fn render_if_needed(
         Ok(())
     }
 
+    /// Moves the mouse cursor on the hardware cursor plane, which doesn't need a redraw
+    fn move_hardware_cursor(
+        &self,
+        mouse_position: Option<LogicalPosition>,
+    ) -> Result<(), PlatformError> {
+        if self.drawn_mouse_position.replace(mouse_position) == mouse_position {
+            return Ok(());
+        }
+        let position = mouse_position.map(|position| {
+            let position = position.to_physical(self.window.scale_factor());
+            (position.x, position.y)
+        });
+        self.renderer.move_hardware_cursor(position)
+    }
+
+    /// With partial rendering, the renderer only redraws what changed in the scene. Tell it about
+    /// the mouse cursor moving, which is drawn on top of the scene.
+    fn mark_mouse_cursor_dirty(&self, mouse_position: Option<LogicalPosition>) {
+        let previous_position = self.drawn_mouse_position.replace(mouse_position);
+        if previous_position == mouse_position {
+            return;
+        }
+        let cursor_size = mouse_cursor_image().size();
+        let cursor_size = i_slint_core::lengths::LogicalSize::new(
+            cursor_size.width as _,
+            cursor_size.height as _,
+        );
+        for position in [previous_position, mouse_position].into_iter().flatten() {
+            let origin = i_slint_core::lengths::logical_point_from_api(position);
+            self.renderer.as_core_renderer().mark_dirty_region(
+                i_slint_core::graphics::euclid::Box2D::from_origin_and_size(origin, cursor_size),
+            );
+        }
+    }
+
+    /// Returns what is shown on the screen, for example for visual testing on the device. The
+    /// rotation and flip are reverted, so the buffer has the size and orientation of the window.
+    pub fn grab_screen(
+        &self,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        self.renderer.read_back(self.rotation, self.flip)
+    }
+
+    /// The size of the screen in logical pixels, before the rotation is applied. Absolute
+    /// positions reported by input devices are relative to this size.
+    pub fn logical_screen_size(&self) -> LogicalSize {
+        self.renderer.size().to_logical(self.window.scale_factor())
+    }
+
+    /// Maps a position on the screen, such as reported by a touch screen, to the position in the
+    /// window that is drawn there.
+    pub fn screen_position_to_window_position(&self, position: LogicalPosition) -> LogicalPosition {
+        let screen_size = self.logical_screen_size();
+        let screen_size = (screen_size.width, screen_size.height);
+        let (x, y) = self.rotation.screen_position_to_window_position(
+            self.flip.flip_position((position.x, position.y), screen_size),
+            screen_size,
+        );
+        LogicalPosition::new(x, y)
+    }
+
+    /// Maps a relative motion on the screen, such as reported by a mouse, to the motion in the
+    /// window, so that the mouse cursor moves in the same direction as the mouse.
+    pub fn screen_motion_to_window_motion(&self, motion: (f32, f32)) -> (f32, f32) {
+        self.rotation.screen_motion_to_window_motion(self.flip.flip_motion(motion))
+    }
+
+    pub fn output_info(&self) -> Vec<crate::OutputInfo> {
+        self.renderer.output_info()
+    }
+
     pub fn register_event_loop(
         &self,
         event_loop_handle: crate::calloop_backend::EventLoopHandle,
@@ -131,6 +313,20 @@ pub fn register_event_loop(
     }
 }
 
+/// The pixels of the mouse cursor image, for the hardware cursor plane
+fn mouse_cursor_pixels(
+) -> Option<i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>> {
+    let image = mouse_cursor_image();
+    let inner: &i_slint_core::graphics::ImageInner = (&image).into();
+    match inner {
+        i_slint_core::ImageInner::EmbeddedImage {
+            buffer: i_slint_core::graphics::SharedImageBuffer::RGBA8Premultiplied(pixels),
+            ..
+        } => Some(pixels.clone()),
+        _ => None,
+    }
+}
+
 fn mouse_cursor_image() -> Image {
     let mouse_pointer_svg = i_slint_core::graphics::load_image_from_embedded_data(
         Slice::from_slice(include_bytes!("mouse-pointer.svg")),
@@ -155,3 +351,140 @@ fn mouse_cursor_image() -> Image {
         cached_image @ _ => cached_image.clone().into(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use i_slint_core::graphics::FontRequest;
+    use i_slint_core::lengths::{LogicalLength, LogicalPoint, LogicalRect, ScaleFactor};
+    use std::cell::RefCell;
+
+    /// A renderer that records the frames it renders
+    #[derive(Default)]
+    struct MockRenderer {
+        frames: Rc<RefCell<Vec<&'static str>>>,
+        discard_next_frame: Cell<bool>,
+    }
+
+    impl RendererSealed for MockRenderer {
+        fn text_size(
+            &self,
+            _font_request: FontRequest,
+            _text: &str,
+            _max_width: Option<LogicalLength>,
+            _scale_factor: ScaleFactor,
+        ) -> i_slint_core::lengths::LogicalSize {
+            Default::default()
+        }
+
+        fn text_input_byte_offset_for_position(
+            &self,
+            _text_input: Pin<&i_slint_core::items::TextInput>,
+            _pos: LogicalPoint,
+            _font_request: FontRequest,
+            _scale_factor: ScaleFactor,
+        ) -> usize {
+            0
+        }
+
+        fn text_input_cursor_rect_for_byte_offset(
+            &self,
+            _text_input: Pin<&i_slint_core::items::TextInput>,
+            _byte_offset: usize,
+            _font_request: FontRequest,
+            _scale_factor: ScaleFactor,
+        ) -> LogicalRect {
+            Default::default()
+        }
+
+        fn default_font_size(&self) -> LogicalLength {
+            LogicalLength::new(12.)
+        }
+
+        fn set_window_adapter(&self, _window_adapter: &Rc<dyn WindowAdapter>) {}
+    }
+
+    impl FullscreenRenderer for MockRenderer {
+        fn as_core_renderer(&self) -> &dyn i_slint_core::renderer::Renderer {
+            self
+        }
+
+        fn is_ready_to_present(&self) -> bool {
+            true
+        }
+
+        fn render_and_present(
+            &self,
+            _rotation: RenderingRotation,
+            _flip: RenderingFlip,
+            _draw_mouse_cursor_callback: &dyn Fn(&mut dyn ItemRenderer),
+            _ready_for_next_animation_frame: Box<dyn FnOnce()>,
+        ) -> Result<(), PlatformError> {
+            let frame = if self.discard_next_frame.take() { "discarded" } else { "presented" };
+            self.frames.borrow_mut().push(frame);
+            Ok(())
+        }
+
+        fn size(&self) -> PhysicalWindowSize {
+            PhysicalWindowSize::new(800, 600)
+        }
+
+        fn scale_factor(&self) -> f32 {
+            1.
+        }
+
+        fn output_info(&self) -> Vec<crate::OutputInfo> {
+            Vec::new()
+        }
+
+        fn read_back(
+            &self,
+            _rotation: RenderingRotation,
+            _flip: RenderingFlip,
+        ) -> Result<
+            i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+            PlatformError,
+        > {
+            Err("Reading back is not supported".into())
+        }
+
+        fn register_page_flip_handler(
+            &self,
+            _event_loop_handle: crate::calloop_backend::EventLoopHandle,
+        ) -> Result<(), PlatformError> {
+            Ok(())
+        }
+
+        fn set_vsync(&self, _vsync: bool) {}
+
+        fn discard_next_frame(&self) {
+            self.discard_next_frame.set(true);
+        }
+    }
+
+    /// Renders two frames with the adapter and returns what the renderer did
+    fn render_two_frames(warm_up: bool) -> Vec<&'static str> {
+        let renderer = MockRenderer::default();
+        let frames = renderer.frames.clone();
+        let adapter = FullscreenWindowAdapter::new(
+            Box::new(renderer),
+            RenderingRotation::NoRotation,
+            RenderingFlip::NoFlip,
+            warm_up,
+            false,
+        )
+        .unwrap();
+        let mouse_position = Box::pin(Property::new(None));
+        adapter.clone().render_if_needed(mouse_position.as_ref()).unwrap();
+        adapter.request_redraw();
+        adapter.clone().render_if_needed(mouse_position.as_ref()).unwrap();
+        let frames = frames.borrow().clone();
+        frames
+    }
+
+    #[test]
+    fn test_warm_up_renders_before_first_present() {
+        assert_eq!(render_two_frames(true), vec!["discarded", "presented", "presented"]);
+        assert_eq!(render_two_frames(false), vec!["presented", "presented"]);
+    }
+}
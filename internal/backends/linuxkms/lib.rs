@@ -7,6 +7,45 @@
 #[cfg(target_os = "linux")]
 mod fullscreenwindowadapter;
 
+/// Describes a display output that the backend renders to, for diagnostics.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct OutputInfo {
+    /// The name of the output, such as `HDMI-A-1`
+    pub name: String,
+    /// The resolution of the display mode, in pixels
+    pub size: i_slint_core::api::PhysicalSize,
+    /// The refresh rate of the display mode, in Hz
+    pub refresh_rate: u32,
+    /// The physical size of the display in millimeters, as reported by the display. Many
+    /// displays report 0x0.
+    pub physical_size_mm: (u32, u32),
+}
+
+/// Describes a display connector of a DRM device, as returned by [`list_connectors`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ConnectorInfo {
+    /// The name of the connector, as used in `SLINT_DRM_OUTPUT`, such as `HDMI-A-1`
+    pub name: String,
+    /// Whether a display is connected
+    pub connected: bool,
+    /// The modes supported by the connected display
+    pub modes: Vec<ModeInfo>,
+}
+
+/// Describes a display mode of a connector, as returned by [`list_connectors`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ModeInfo {
+    /// The resolution of the display mode, in pixels
+    pub size: i_slint_core::api::PhysicalSize,
+    /// The refresh rate of the display mode, in Hz
+    pub refresh_rate: u32,
+    /// Whether the display reports this mode as its preferred one
+    pub preferred: bool,
+}
+
 #[cfg(target_os = "linux")]
 use std::os::fd::OwnedFd;
 
@@ -25,7 +64,12 @@ mod renderer {
     #[cfg(feature = "renderer-femtovg")]
     pub mod femtovg;
 
-    pub fn try_skia_then_femtovg(
+    #[cfg(feature = "renderer-software")]
+    pub mod sw;
+
+    /// Tries the GPU accelerated renderers first. Only if none of them can be created, for example
+    /// because there is no GPU, the software renderer is used.
+    pub fn try_skia_then_femtovg_then_software(
         _device_opener: &crate::DeviceOpener,
     ) -> Result<
         Box<dyn crate::fullscreenwindowadapter::FullscreenRenderer>,
@@ -44,6 +88,20 @@ pub fn try_skia_then_femtovg(
             result = femtovg::FemtoVGRendererAdapter::new(_device_opener);
         }
 
+        #[cfg(feature = "renderer-software")]
+        if let Err(accelerated_error) = &result {
+            if cfg!(any(
+                feature = "renderer-skia-opengl",
+                feature = "renderer-skia-vulkan",
+                feature = "renderer-femtovg"
+            )) {
+                eprintln!(
+                    "Warning: No GPU accelerated renderer available, falling back to the software renderer: {accelerated_error}"
+                );
+            }
+            result = sw::SoftwareRendererAdapter::new(_device_opener);
+        }
+
         result
     }
 }
@@ -27,3 +27,22 @@ fn run_event_loop(&self) -> Result<(), PlatformError> {
         unimplemented!()
     }
 }
+
+pub fn grab_screen(
+    _window: &i_slint_core::api::Window,
+) -> Result<
+    i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+    PlatformError,
+> {
+    Err("The linuxkms backend is only supported on Linux".into())
+}
+
+pub fn output_info(
+    _window: &i_slint_core::api::Window,
+) -> Result<Vec<crate::OutputInfo>, PlatformError> {
+    Err("The linuxkms backend is only supported on Linux".into())
+}
+
+pub fn list_connectors() -> Result<Vec<crate::ConnectorInfo>, PlatformError> {
+    Err("The linuxkms backend is only supported on Linux".into())
+}
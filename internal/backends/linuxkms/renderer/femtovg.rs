@@ -1,10 +1,13 @@
 // Copyright © SixtyFPS GmbH <info@slint.dev>
 // SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
 
+use std::cell::{Cell, RefCell};
 use std::{num::NonZeroU32, rc::Rc};
 
+use i_slint_core::api::PhysicalSize as PhysicalWindowSize;
 use i_slint_core::item_rendering::ItemRenderer;
 use i_slint_core::platform::PlatformError;
+use i_slint_core::renderer::RendererSealed;
 use i_slint_renderer_femtovg::FemtoVGRendererExt;
 use raw_window_handle::{
     HasDisplayHandle, HasRawDisplayHandle, HasRawWindowHandle, HasWindowHandle,
@@ -17,7 +20,7 @@
     surface::{SurfaceAttributesBuilder, WindowSurface},
 };
 
-use crate::display::{egldisplay::EglDisplay, Presenter, RenderingRotation};
+use crate::display::{egldisplay::EglDisplay, Presenter, RenderingFlip, RenderingRotation};
 
 pub struct FemtoVGRendererAdapter {
     renderer: i_slint_renderer_femtovg::FemtoVGRenderer,
@@ -26,25 +29,57 @@ pub struct FemtoVGRendererAdapter {
 
 struct GlContextWrapper {
     glutin_context: glutin::context::PossiblyCurrentContext,
-    glutin_surface: glutin::surface::Surface<glutin::surface::WindowSurface>,
+    glutin_surface: RefCell<glutin::surface::Surface<glutin::surface::WindowSurface>>,
+    config: glutin::config::Config,
+    swap_interval: u32,
+    /// The size of the display when the surface was created
+    size: Cell<PhysicalWindowSize>,
     egl_display: Rc<EglDisplay>,
 }
 
-impl GlContextWrapper {
-    fn new(egl_display: Rc<EglDisplay>) -> Result<Self, PlatformError> {
-        let width: std::num::NonZeroU32 = egl_display.size.width.try_into().map_err(|_| {
-            format!(
-                "Attempting to create window surface with an invalid width: {}",
-                egl_display.size.width
-            )
-        })?;
-        let height: std::num::NonZeroU32 = egl_display.size.height.try_into().map_err(|_| {
-            format!(
-                "Attempting to create window surface with an invalid height: {}",
-                egl_display.size.height
-            )
-        })?;
+/// Creates a window surface for the gbm surface of `egl_display`, with its current size.
+fn create_window_surface(
+    egl_display: &EglDisplay,
+    config: &glutin::config::Config,
+) -> Result<glutin::surface::Surface<WindowSurface>, PlatformError> {
+    let size = egl_display.size();
+    let width: std::num::NonZeroU32 = size.width.try_into().map_err(|_| {
+        format!("Attempting to create window surface with an invalid width: {}", size.width)
+    })?;
+    let height: std::num::NonZeroU32 = size.height.try_into().map_err(|_| {
+        format!("Attempting to create window surface with an invalid height: {}", size.height)
+    })?;
+
+    let window_handle = egl_display.window_handle().unwrap();
+    let attrs = SurfaceAttributesBuilder::<WindowSurface>::new().build(
+        window_handle.raw_window_handle(),
+        width,
+        height,
+    );
+
+    unsafe {
+        config
+            .display()
+            .create_window_surface(config, &attrs)
+            .map_err(|e| format!("Error creating EGL window surface: {e}").into())
+    }
+}
 
+fn set_swap_interval(
+    surface: &glutin::surface::Surface<WindowSurface>,
+    context: &glutin::context::PossiblyCurrentContext,
+    swap_interval: u32,
+) {
+    // Ignore if the driver doesn't support it.
+    let swap_interval = match NonZeroU32::new(swap_interval) {
+        Some(interval) => glutin::surface::SwapInterval::Wait(interval),
+        None => glutin::surface::SwapInterval::DontWait,
+    };
+    surface.set_swap_interval(context, swap_interval).ok();
+}
+
+impl GlContextWrapper {
+    fn new(egl_display: Rc<EglDisplay>, swap_interval: u32) -> Result<Self, PlatformError> {
         let display_handle = egl_display.display_handle().unwrap();
         let window_handle = egl_display.window_handle().unwrap();
 
@@ -56,7 +91,18 @@ fn new(egl_display: Rc<EglDisplay>) -> Result<Self, PlatformError> {
             .map_err(|e| format!("Error creating EGL display: {e}"))?
         };
 
-        let config_template = glutin::config::ConfigTemplateBuilder::new().build();
+        let mut config_template_builder = glutin::config::ConfigTemplateBuilder::new();
+        // The EGL configuration must match the format of the GBM surface
+        let bits_per_channel = egl_display.format.bits_per_channel();
+        if bits_per_channel != 8 {
+            config_template_builder =
+                config_template_builder.with_buffer_type(glutin::config::ColorBufferType::Rgb {
+                    r_size: bits_per_channel,
+                    g_size: bits_per_channel,
+                    b_size: bits_per_channel,
+                });
+        }
+        let config_template = config_template_builder.build();
 
         let config = unsafe {
             gl_display
@@ -92,18 +138,7 @@ fn new(egl_display: Rc<EglDisplay>) -> Result<Self, PlatformError> {
                 .map_err(|e| format!("Error creating EGL context: {e}"))?
         };
 
-        let attrs = SurfaceAttributesBuilder::<WindowSurface>::new().build(
-            window_handle.raw_window_handle(),
-            width,
-            height,
-        );
-
-        let surface = unsafe {
-            config
-                .display()
-                .create_window_surface(&config, &attrs)
-                .map_err(|e| format!("Error creating EGL window surface: {e}"))?
-        };
+        let surface = create_window_surface(&egl_display, &config)?;
 
         let context = not_current_gl_context.make_current(&surface)
         .map_err(|glutin_error: glutin::error::Error| -> PlatformError {
@@ -111,17 +146,26 @@ fn new(egl_display: Rc<EglDisplay>) -> Result<Self, PlatformError> {
             .into()
     })?;
 
+        set_swap_interval(&surface, &context, swap_interval);
+
         drop(window_handle);
         drop(display_handle);
 
-        Ok(Self { glutin_context: context, glutin_surface: surface, egl_display })
+        Ok(Self {
+            glutin_context: context,
+            glutin_surface: RefCell::new(surface),
+            config,
+            swap_interval,
+            size: Cell::new(egl_display.size()),
+            egl_display,
+        })
     }
 }
 
 unsafe impl i_slint_renderer_femtovg::OpenGLInterface for GlContextWrapper {
     fn ensure_current(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if !self.glutin_context.is_current() {
-            self.glutin_context.make_current(&self.glutin_surface).map_err(
+            self.glutin_context.make_current(&self.glutin_surface.borrow()).map_err(
                 |glutin_error| -> PlatformError {
                     format!("FemtoVG: Error making context current: {glutin_error}").into()
                 },
@@ -131,7 +175,7 @@ fn ensure_current(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
     }
 
     fn swap_buffers(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.glutin_surface.swap_buffers(&self.glutin_context).map_err(
+        self.glutin_surface.borrow().swap_buffers(&self.glutin_context).map_err(
             |glutin_error| -> PlatformError {
                 format!("FemtoVG: Error swapping buffers: {glutin_error}").into()
             },
@@ -145,7 +189,19 @@ fn resize(
         _width: NonZeroU32,
         _height: NonZeroU32,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Ignore resize requests
+        // The window always covers the screen. Only when the size of the screen changed after a
+        // hotplug, the surface must be recreated for the new gbm surface.
+        let size = self.egl_display.size();
+        if size == self.size.get() {
+            return Ok(());
+        }
+        let surface = create_window_surface(&self.egl_display, &self.config)?;
+        self.glutin_context.make_current(&surface).map_err(|glutin_error| -> PlatformError {
+            format!("FemtoVG: Error making context current: {glutin_error}").into()
+        })?;
+        set_swap_interval(&surface, &self.glutin_context, self.swap_interval);
+        *self.glutin_surface.borrow_mut() = surface;
+        self.size.set(size);
         Ok(())
     }
 
@@ -158,11 +214,13 @@ impl FemtoVGRendererAdapter {
     pub fn new(
         device_opener: &crate::DeviceOpener,
     ) -> Result<Box<dyn crate::fullscreenwindowadapter::FullscreenRenderer>, PlatformError> {
+        let swap_interval = crate::display::egldisplay::swap_interval_from_env()?;
         let egl_display = Rc::new(crate::display::egldisplay::create_egl_display(device_opener)?);
 
         let renderer = Box::new(Self {
             renderer: i_slint_renderer_femtovg::FemtoVGRenderer::new(GlContextWrapper::new(
                 egl_display.clone(),
+                swap_interval,
             )?)?,
             egl_display,
         });
@@ -185,21 +243,53 @@ fn is_ready_to_present(&self) -> bool {
     fn render_and_present(
         &self,
         rotation: RenderingRotation,
+        flip: RenderingFlip,
         draw_mouse_cursor_callback: &dyn Fn(&mut dyn ItemRenderer),
         ready_for_next_animation_frame: Box<dyn FnOnce()>,
     ) -> Result<(), PlatformError> {
         self.egl_display.set_next_animation_frame_callback(ready_for_next_animation_frame);
         self.renderer.render_transformed_with_post_callback(
             rotation.degrees(),
-            rotation.translation_after_rotation(self.egl_display.size),
-            self.egl_display.size,
+            rotation.translation_after_rotation(self.egl_display.size()),
+            flip.axes(),
+            self.egl_display.size(),
             Some(&|item_renderer| {
                 draw_mouse_cursor_callback(item_renderer);
             }),
         )
     }
     fn size(&self) -> i_slint_core::api::PhysicalSize {
-        self.egl_display.size
+        self.egl_display.size()
+    }
+
+    fn update_outputs(&self) -> Result<Option<PhysicalWindowSize>, PlatformError> {
+        if !self.egl_display.take_outputs_changed() {
+            return Ok(None);
+        }
+        let size = self.egl_display.size();
+        self.renderer.resize(size)?;
+        Ok(Some(size))
+    }
+
+    fn scale_factor(&self) -> f32 {
+        self.egl_display.scale_factor
+    }
+
+    fn output_info(&self) -> Vec<crate::OutputInfo> {
+        self.egl_display.output_info()
+    }
+
+    fn read_back(
+        &self,
+        rotation: RenderingRotation,
+        flip: RenderingFlip,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        Ok(rotation.screen_pixels_to_window_pixels(
+            flip.flip_pixels(self.egl_display.read_front_buffer()?),
+        ))
     }
 
     fn register_page_flip_handler(
@@ -208,4 +298,23 @@ fn register_page_flip_handler(
     ) -> Result<(), PlatformError> {
         self.egl_display.clone().register_page_flip_handler(event_loop_handle)
     }
+
+    fn set_vsync(&self, vsync: bool) {
+        self.egl_display.set_vsync(vsync)
+    }
+
+    fn discard_next_frame(&self) {
+        self.egl_display.discard_next_frame()
+    }
+
+    fn set_hardware_cursor(
+        &self,
+        image: &i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+    ) -> bool {
+        self.egl_display.set_hardware_cursor(image)
+    }
+
+    fn move_hardware_cursor(&self, position: Option<(i32, i32)>) -> Result<(), PlatformError> {
+        self.egl_display.move_hardware_cursor(position)
+    }
 }
@@ -4,7 +4,7 @@
 use std::cell::Cell;
 use std::rc::{Rc, Weak};
 
-use crate::display::{Presenter, RenderingRotation};
+use crate::display::{Presenter, RenderingFlip, RenderingRotation};
 use i_slint_core::api::PhysicalSize as PhysicalWindowSize;
 use i_slint_core::item_rendering::ItemRenderer;
 use i_slint_core::platform::PlatformError;
@@ -13,16 +13,38 @@
 pub struct SkiaRendererAdapter {
     renderer: i_slint_renderer_skia::SkiaRenderer,
     presenter: Rc<dyn crate::display::Presenter>,
-    size: PhysicalWindowSize,
+    size: Cell<PhysicalWindowSize>,
+    scale_factor: f32,
+    output_info: Vec<crate::OutputInfo>,
+    /// The display rendered to with OpenGL and the swap interval of its surface, to recreate the
+    /// surface when the outputs change
+    #[cfg(feature = "renderer-skia-opengl")]
+    egl_display: Option<(Rc<crate::display::egldisplay::EglDisplay>, u32)>,
 }
 
 impl SkiaRendererAdapter {
     #[cfg(feature = "renderer-skia-vulkan")]
     pub fn new_vulkan(
-        _device_opener: &crate::DeviceOpener,
+        device_opener: &crate::DeviceOpener,
     ) -> Result<Box<dyn crate::fullscreenwindowadapter::FullscreenRenderer>, PlatformError> {
-        // TODO: figure out how to associate vulkan with an existing drm fd.
-        let display = crate::display::vulkandisplay::create_vulkan_display()?;
+        let present_mode =
+            std::env::var("SLINT_KMS_PRESENT_MODE").map_or(Ok(Default::default()), |mode_str| {
+                mode_str
+                    .as_str()
+                    .try_into()
+                    .map_err(|e| format!("Failed to parse SLINT_KMS_PRESENT_MODE: {e}"))
+            })?;
+
+        let frame_interval = std::env::var("SLINT_TARGET_FPS").map_or(Ok(None), |fps_str| {
+            match fps_str.parse::<f64>() {
+                Ok(fps) if fps > 0. => Ok(Some(std::time::Duration::from_secs_f64(1. / fps))),
+                _ => Err(format!(
+                    "Failed to parse SLINT_TARGET_FPS: Must be a positive number, found {fps_str}"
+                )),
+            }
+        })?;
+
+        let display = crate::display::vulkandisplay::create_vulkan_display(device_opener)?;
 
         let skia_vk_surface = i_slint_renderer_skia::vulkan_surface::VulkanSurface::from_surface(
             display.physical_device,
@@ -31,13 +53,50 @@ pub fn new_vulkan(
             display.size,
         )?;
 
+        match std::env::var("SLINT_DRM_FORMAT").as_deref() {
+            Ok("xrgb2101010") => {
+                if let Err(e) = skia_vk_surface.reconfigure(
+                    vulkano::format::Format::A2B10G10R10_UNORM_PACK32,
+                    vulkano::swapchain::ColorSpace::SrgbNonLinear,
+                    display.size,
+                ) {
+                    eprintln!("Warning: Error switching the Vulkan surface to 10 bits per color channel, falling back to 8 bits: {e}");
+                }
+            }
+            Ok("xrgb8888") | Err(_) => {}
+            Ok(format_str) => {
+                return Err(format!(
+                    "Failed to parse SLINT_DRM_FORMAT: Must be one of xrgb8888 or xrgb2101010, found {format_str}"
+                )
+                .into())
+            }
+        }
+
+        let timer_driver = TimerBasedAnimationDriver::new(
+            present_mode,
+            frame_interval.unwrap_or(display.refresh_interval),
+        );
+        // Drive animations with the presentation of frames, unless a frame rate is requested or
+        // the device can't tell when frames are shown.
+        let present_wait_driver = frame_interval
+            .is_none()
+            .then(|| PresentWaitAnimationDriver::new(&skia_vk_surface, timer_driver.clone()))
+            .flatten();
+        let presenter: Rc<dyn Presenter> = match present_wait_driver {
+            Some(driver) => driver,
+            None => timer_driver,
+        };
+
         let renderer = Box::new(Self {
             renderer: i_slint_renderer_skia::SkiaRenderer::new_with_surface(Box::new(
                 skia_vk_surface,
             )),
-            // TODO: For vulkan we don't have a page flip event handling mechanism yet, so drive it with a timer.
-            presenter: TimerBasedAnimationDriver::new(),
-            size: display.size,
+            presenter,
+            size: Cell::new(display.size),
+            scale_factor: display.scale_factor,
+            output_info: vec![display.output_info.clone()],
+            #[cfg(feature = "renderer-skia-opengl")]
+            egl_display: None,
         });
 
         eprintln!("Using Skia Vulkan renderer");
@@ -49,31 +108,46 @@ pub fn new_vulkan(
     pub fn new_opengl(
         device_opener: &crate::DeviceOpener,
     ) -> Result<Box<dyn crate::fullscreenwindowadapter::FullscreenRenderer>, PlatformError> {
-        let display = crate::display::egldisplay::create_egl_display(device_opener)?;
+        let swap_interval = crate::display::egldisplay::swap_interval_from_env()?;
+        let display = Rc::new(crate::display::egldisplay::create_egl_display(device_opener)?);
 
-        use i_slint_renderer_skia::Surface;
-        use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
-        let skia_gl_surface = i_slint_renderer_skia::opengl_surface::OpenGLSurface::new(
-            display.window_handle().unwrap(),
-            display.display_handle().unwrap(),
-            display.size,
-        )?;
-
-        let size = display.size;
+        let skia_gl_surface = Self::create_opengl_surface(&display, swap_interval)?;
 
         let renderer = Box::new(Self {
             renderer: i_slint_renderer_skia::SkiaRenderer::new_with_surface(Box::new(
                 skia_gl_surface,
             )),
-            presenter: Rc::new(display),
-            size,
+            presenter: display.clone(),
+            size: Cell::new(display.size()),
+            scale_factor: display.scale_factor,
+            output_info: Vec::new(),
+            egl_display: Some((display, swap_interval)),
         });
 
+        if i_slint_core::bool_from_env("SLINT_KMS_PARTIAL_RENDERING")?.unwrap_or(false) {
+            renderer.renderer.set_partial_rendering(true);
+        }
+
         eprintln!("Using Skia OpenGL renderer");
 
         Ok(renderer)
     }
 
+    #[cfg(feature = "renderer-skia-opengl")]
+    fn create_opengl_surface(
+        display: &crate::display::egldisplay::EglDisplay,
+        swap_interval: u32,
+    ) -> Result<i_slint_renderer_skia::opengl_surface::OpenGLSurface, PlatformError> {
+        use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
+        i_slint_renderer_skia::opengl_surface::OpenGLSurface::new_with_options(
+            display.window_handle().unwrap(),
+            display.display_handle().unwrap(),
+            display.size(),
+            swap_interval,
+            display.format.bits_per_channel(),
+        )
+    }
+
     pub fn new_try_vulkan_then_opengl(
         device_opener: &crate::DeviceOpener,
     ) -> Result<Box<dyn crate::fullscreenwindowadapter::FullscreenRenderer>, PlatformError> {
@@ -106,13 +180,16 @@ fn is_ready_to_present(&self) -> bool {
     fn render_and_present(
         &self,
         rotation: RenderingRotation,
+        flip: RenderingFlip,
         draw_mouse_cursor_callback: &dyn Fn(&mut dyn ItemRenderer),
         ready_for_next_animation_frame: Box<dyn FnOnce()>,
     ) -> Result<(), PlatformError> {
         self.renderer.render_transformed_with_post_callback(
             rotation.degrees(),
-            rotation.translation_after_rotation(self.size),
-            self.size,
+            rotation.translation_after_rotation(self.size.get()),
+            flip.axes(),
+            self.size.get(),
+            self.presenter.buffer_age(),
             Some(&|item_renderer| {
                 draw_mouse_cursor_callback(item_renderer);
             }),
@@ -121,7 +198,49 @@ fn render_and_present(
         Ok(())
     }
     fn size(&self) -> i_slint_core::api::PhysicalSize {
-        self.size
+        self.size.get()
+    }
+
+    fn update_outputs(&self) -> Result<Option<PhysicalWindowSize>, PlatformError> {
+        #[cfg(feature = "renderer-skia-opengl")]
+        if let Some((display, swap_interval)) = &self.egl_display {
+            if !display.take_outputs_changed() {
+                return Ok(None);
+            }
+            let size = display.size();
+            if size != self.size.get() {
+                // The current surface renders into the gbm surface of the previous size
+                let surface = Self::create_opengl_surface(display, *swap_interval)?;
+                self.renderer.set_surface(Box::new(surface));
+                self.size.set(size);
+            }
+            return Ok(Some(size));
+        }
+        Ok(None)
+    }
+
+    fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    fn output_info(&self) -> Vec<crate::OutputInfo> {
+        #[cfg(feature = "renderer-skia-opengl")]
+        if let Some((display, _)) = &self.egl_display {
+            return display.output_info();
+        }
+        self.output_info.clone()
+    }
+
+    fn read_back(
+        &self,
+        rotation: RenderingRotation,
+        flip: RenderingFlip,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        Ok(rotation
+            .screen_pixels_to_window_pixels(flip.flip_pixels(self.presenter.read_front_buffer()?)))
     }
 
     fn register_page_flip_handler(
@@ -130,38 +249,86 @@ fn register_page_flip_handler(
     ) -> Result<(), PlatformError> {
         self.presenter.clone().register_page_flip_handler(event_loop_handle)
     }
+
+    fn set_vsync(&self, vsync: bool) {
+        self.presenter.set_vsync(vsync)
+    }
+
+    fn discard_next_frame(&self) {
+        self.presenter.discard_next_frame()
+    }
+
+    fn set_hardware_cursor(
+        &self,
+        image: &i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+    ) -> bool {
+        self.presenter.set_hardware_cursor(image)
+    }
+
+    fn move_hardware_cursor(&self, position: Option<(i32, i32)>) -> Result<(), PlatformError> {
+        self.presenter.move_hardware_cursor(position)
+    }
+}
+
+/// This enum describes how the timer based animation driver paces frames when
+/// there is no page flip event to synchronize with.
+#[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
+enum PresentMode {
+    /// Wait for the timer interval before requesting the next frame. This saves power.
+    #[default]
+    Vsync,
+    /// Request the next frame right after presenting, for latency-sensitive use-cases.
+    /// The rate is still bounded by the time it takes to present to the panel.
+    LowLatency,
+}
+
+impl TryFrom<&str> for PresentMode {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "vsync" => Ok(Self::Vsync),
+            "low-latency" => Ok(Self::LowLatency),
+            _ => Err(format!(
+                "Invalid value for present mode. Must be one of vsync or low-latency, found {value}"
+            )),
+        }
+    }
 }
 
 struct TimerBasedAnimationDriver {
     timer: i_slint_core::timers::Timer,
     next_animation_frame_callback: Cell<Option<Box<dyn FnOnce()>>>,
+    present_mode: PresentMode,
+    vsync: Cell<bool>,
 }
 
 impl TimerBasedAnimationDriver {
-    fn new() -> Rc<Self> {
+    fn new(present_mode: PresentMode, interval: std::time::Duration) -> Rc<Self> {
         Rc::new_cyclic(|self_weak: &Weak<Self>| {
             let self_weak = self_weak.clone();
             let timer = i_slint_core::timers::Timer::default();
-            timer.start(
-                i_slint_core::timers::TimerMode::Repeated,
-                std::time::Duration::from_millis(16),
-                move || {
-                    let Some(this) = self_weak.upgrade() else { return };
-                    // Stop the timer and let the callback decide if we need to continue. It will set
-                    // `needs_redraw` to true of animations should continue, render() will be called,
-                    // present_with_next_frame_callback() will be called and then the timer restarted.
-                    this.timer.stop();
-                    if let Some(next_animation_frame_callback) =
-                        this.next_animation_frame_callback.take()
-                    {
-                        next_animation_frame_callback();
-                    }
-                },
-            );
+            timer.start(i_slint_core::timers::TimerMode::Repeated, interval, move || {
+                let Some(this) = self_weak.upgrade() else { return };
+                // Stop the timer and let the callback decide if we need to continue. It will set
+                // `needs_redraw` to true of animations should continue, render() will be called,
+                // present_with_next_frame_callback() will be called and then the timer restarted.
+                this.timer.stop();
+                if let Some(next_animation_frame_callback) =
+                    this.next_animation_frame_callback.take()
+                {
+                    next_animation_frame_callback();
+                }
+            });
             // Activate it only when we present a frame.
             timer.stop();
 
-            Self { timer, next_animation_frame_callback: Default::default() }
+            Self {
+                timer,
+                next_animation_frame_callback: Default::default(),
+                present_mode,
+                vsync: Cell::new(true),
+            }
         })
     }
 }
@@ -182,8 +349,244 @@ fn present_with_next_frame_callback(
         &self,
         ready_for_next_animation_frame: Box<dyn FnOnce()>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.next_animation_frame_callback.set(Some(ready_for_next_animation_frame));
-        self.timer.restart();
+        // Without vsync, don't wait for the timer and render as fast as possible
+        let present_mode =
+            if self.vsync.get() { self.present_mode } else { PresentMode::LowLatency };
+        match present_mode {
+            PresentMode::Vsync => {
+                self.next_animation_frame_callback.set(Some(ready_for_next_animation_frame));
+                self.timer.restart();
+            }
+            PresentMode::LowLatency => {
+                // The callback requests a redraw only if animations are still running, so this
+                // doesn't spin when idle.
+                i_slint_core::timers::Timer::single_shot(
+                    std::time::Duration::default(),
+                    move || {
+                        ready_for_next_animation_frame();
+                    },
+                );
+            }
+        }
         Ok(())
     }
+
+    fn set_vsync(&self, vsync: bool) {
+        self.vsync.set(vsync);
+    }
+
+    fn buffer_age(&self) -> u8 {
+        // The swapchain doesn't tell
+        0
+    }
+
+    fn read_front_buffer(
+        &self,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        // Swapchain images belong to the presentation engine once presented, and VK_KHR_display
+        // has no way to read the buffer that is scanned out. See `grab_screen`.
+        Err("Reading back the screen is not supported with Vulkan".into())
+    }
+
+    fn discard_next_frame(&self) {
+        // The frame is presented by the renderer's surface already
+    }
+}
+
+/// Drives animations with the presentation of the frames, for Vulkan devices that support
+/// `VK_KHR_present_wait`. A thread waits until each presented frame is shown on the screen and then
+/// notifies the event loop, so that the next animation frame is rendered in sync with the refresh
+/// of the display. Frames that aren't presented, as well as the low-latency and the no-vsync modes,
+/// are paced by the timer based driver.
+#[cfg(feature = "renderer-skia-vulkan")]
+struct PresentWaitAnimationDriver {
+    timer_driver: Rc<TimerBasedAnimationDriver>,
+    next_animation_frame_callback: Cell<Option<Box<dyn FnOnce()>>>,
+    /// Set when the surface presented a frame that the thread waits for
+    presentation_pending: Rc<Cell<bool>>,
+    /// Receives a message from the thread whenever a frame was shown, until it's inserted into
+    /// the event loop
+    frame_shown_channel: Cell<Option<calloop::channel::Channel<()>>>,
+}
+
+#[cfg(feature = "renderer-skia-vulkan")]
+impl PresentWaitAnimationDriver {
+    /// How long to wait for a frame to be shown before rendering the next one anyway
+    const PRESENT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+    /// Returns None if the device of the surface can't wait for frames to be shown.
+    fn new(
+        surface: &i_slint_renderer_skia::vulkan_surface::VulkanSurface,
+        timer_driver: Rc<TimerBasedAnimationDriver>,
+    ) -> Option<Rc<Self>> {
+        let (presented_sender, presented_receiver) =
+            std::sync::mpsc::channel::<i_slint_renderer_skia::vulkan_surface::PresentedFrame>();
+        let presentation_pending = Rc::new(Cell::new(false));
+
+        let pending = presentation_pending.clone();
+        if !surface.set_present_listener(move |frame| {
+            pending.set(true);
+            presented_sender.send(frame).ok();
+        }) {
+            return None;
+        }
+
+        let (shown_sender, shown_channel) = calloop::channel::channel();
+        // The thread ends when the surface and with it the listener is dropped
+        let spawn_result =
+            std::thread::Builder::new().name("Slint Vulkan present wait".into()).spawn(move || {
+                for frame in presented_receiver {
+                    if let Err(e) = frame.wait(Self::PRESENT_TIMEOUT) {
+                        eprintln!("Warning: {e}");
+                    }
+                    if shown_sender.send(()).is_err() {
+                        break;
+                    }
+                }
+            });
+        if let Err(e) = spawn_result {
+            eprintln!("Warning: Animations are driven by a timer, error starting the thread waiting for presented Vulkan frames: {e}");
+            return None;
+        }
+
+        Some(Rc::new(Self {
+            timer_driver,
+            next_animation_frame_callback: Default::default(),
+            presentation_pending,
+            frame_shown_channel: Cell::new(Some(shown_channel)),
+        }))
+    }
+}
+
+#[cfg(feature = "renderer-skia-vulkan")]
+impl Presenter for PresentWaitAnimationDriver {
+    fn is_ready_to_present(&self) -> bool {
+        true
+    }
+
+    fn register_page_flip_handler(
+        self: Rc<Self>,
+        event_loop_handle: crate::calloop_backend::EventLoopHandle,
+    ) -> Result<(), PlatformError> {
+        let Some(channel) = self.frame_shown_channel.take() else { return Ok(()) };
+        let self_weak = Rc::downgrade(&self);
+        event_loop_handle
+            .insert_source(channel, move |event, _, _| {
+                let calloop::channel::Event::Msg(()) = event else { return };
+                let Some(this) = self_weak.upgrade() else { return };
+                if let Some(next_animation_frame_callback) =
+                    this.next_animation_frame_callback.take()
+                {
+                    next_animation_frame_callback();
+                }
+            })
+            .map_err(|e: calloop::InsertError<calloop::channel::Channel<()>>| {
+                format!("Error registering the handler for presented Vulkan frames: {e}")
+            })?;
+        Ok(())
+    }
+
+    fn present_with_next_frame_callback(
+        &self,
+        ready_for_next_animation_frame: Box<dyn FnOnce()>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let paced_by_display =
+            self.timer_driver.vsync.get() && self.timer_driver.present_mode == PresentMode::Vsync;
+        if self.presentation_pending.take() && paced_by_display {
+            self.next_animation_frame_callback.set(Some(ready_for_next_animation_frame));
+            Ok(())
+        } else {
+            self.timer_driver.present_with_next_frame_callback(ready_for_next_animation_frame)
+        }
+    }
+
+    fn set_vsync(&self, vsync: bool) {
+        self.timer_driver.set_vsync(vsync);
+    }
+
+    fn buffer_age(&self) -> u8 {
+        self.timer_driver.buffer_age()
+    }
+
+    fn read_front_buffer(
+        &self,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        self.timer_driver.read_front_buffer()
+    }
+
+    fn discard_next_frame(&self) {
+        self.timer_driver.discard_next_frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use i_slint_core::animations::Instant;
+    use i_slint_core::timers::TimerList;
+
+    const INTERVAL: std::time::Duration = std::time::Duration::from_millis(16);
+
+    /// Presents a frame and returns a flag that is set once the next animation frame is requested
+    fn present(driver: &TimerBasedAnimationDriver) -> Rc<Cell<bool>> {
+        let requested = Rc::new(Cell::new(false));
+        driver
+            .present_with_next_frame_callback(Box::new({
+                let requested = requested.clone();
+                move || requested.set(true)
+            }))
+            .unwrap();
+        requested
+    }
+
+    #[test]
+    fn test_present_mode_from_str() {
+        assert_eq!(PresentMode::try_from("vsync"), Ok(PresentMode::Vsync));
+        assert_eq!(PresentMode::try_from("low-latency"), Ok(PresentMode::LowLatency));
+        assert!(PresentMode::try_from("immediate").is_err());
+    }
+
+    #[test]
+    fn test_vsync_present_mode_waits_for_interval() {
+        let driver = TimerBasedAnimationDriver::new(PresentMode::Vsync, INTERVAL);
+        let requested = present(&driver);
+        TimerList::maybe_activate_timers(Instant(0));
+        assert!(!requested.get());
+        TimerList::maybe_activate_timers(Instant(INTERVAL.as_millis() as u64));
+        assert!(requested.get());
+    }
+
+    #[test]
+    fn test_low_latency_present_mode_does_not_wait() {
+        let driver = TimerBasedAnimationDriver::new(PresentMode::LowLatency, INTERVAL);
+        let requested = present(&driver);
+        TimerList::maybe_activate_timers(Instant(0));
+        assert!(requested.get());
+        // The interval timer stays idle
+        assert!(!driver.timer.running());
+    }
+
+    #[test]
+    fn test_disabling_vsync_presents_immediately() {
+        let driver = TimerBasedAnimationDriver::new(PresentMode::Vsync, INTERVAL);
+        driver.set_vsync(false);
+        let requested = present(&driver);
+        TimerList::maybe_activate_timers(Instant(0));
+        assert!(requested.get());
+        assert!(!driver.timer.running());
+
+        // Enabling vsync again waits for the interval
+        driver.set_vsync(true);
+        let requested = present(&driver);
+        TimerList::maybe_activate_timers(Instant(0));
+        assert!(!requested.get());
+        TimerList::maybe_activate_timers(Instant(INTERVAL.as_millis() as u64));
+        assert!(requested.get());
+    }
 }
@@ -0,0 +1,307 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+//! Delegate the rendering to the [`i_slint_core::software_renderer::SoftwareRenderer`]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::display::swdisplay::SoftwareBufferDisplay;
+use crate::display::{Presenter, RenderingFlip, RenderingRotation};
+use i_slint_core::api::PhysicalSize as PhysicalWindowSize;
+use i_slint_core::item_rendering::ItemRenderer;
+use i_slint_core::platform::PlatformError;
+use i_slint_core::software_renderer::{
+    LineBufferProvider, PremultipliedRgbaColor, RepaintBufferType, SoftwareRenderer, TargetPixel,
+};
+
+pub struct SoftwareRendererAdapter {
+    renderer: SoftwareRenderer,
+    display: Rc<SoftwareBufferDisplay>,
+    /// Set when rendering line by line, see `SLINT_KMS_LINE_BUFFER`
+    line_buffer: Option<RefCell<Vec<DumbBufferPixel>>>,
+}
+
+/// A pixel of the dumb buffers, in the XRGB8888 format
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct DumbBufferPixel(pub u32);
+
+impl From<DumbBufferPixel> for PremultipliedRgbaColor {
+    #[inline]
+    fn from(pixel: DumbBufferPixel) -> Self {
+        let v = pixel.0;
+        PremultipliedRgbaColor {
+            red: (v >> 16) as u8,
+            green: (v >> 8) as u8,
+            blue: (v >> 0) as u8,
+            alpha: (v >> 24) as u8,
+        }
+    }
+}
+
+impl From<PremultipliedRgbaColor> for DumbBufferPixel {
+    #[inline]
+    fn from(pixel: PremultipliedRgbaColor) -> Self {
+        Self(
+            (pixel.alpha as u32) << 24
+                | ((pixel.red as u32) << 16)
+                | ((pixel.green as u32) << 8)
+                | (pixel.blue as u32),
+        )
+    }
+}
+
+impl TargetPixel for DumbBufferPixel {
+    fn blend(&mut self, color: PremultipliedRgbaColor) {
+        let mut x = PremultipliedRgbaColor::from(*self);
+        x.blend(color);
+        *self = x.into();
+    }
+
+    fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self(0xff000000 | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32))
+    }
+
+    fn background() -> Self {
+        Self(0)
+    }
+}
+
+/// Copies the lines rendered into the line buffer to the mapped dumb buffer, mirrored as
+/// requested by the flip.
+struct DumbBufferLines<'a> {
+    pixels: &'a mut [DumbBufferPixel],
+    stride: usize,
+    size: PhysicalWindowSize,
+    flip: RenderingFlip,
+    line_buffer: &'a mut [DumbBufferPixel],
+}
+
+impl LineBufferProvider for DumbBufferLines<'_> {
+    type TargetPixel = DumbBufferPixel;
+
+    fn process_line(
+        &mut self,
+        line: usize,
+        range: core::ops::Range<usize>,
+        render_fn: impl FnOnce(&mut [Self::TargetPixel]),
+    ) {
+        let line_buffer = &mut self.line_buffer[range.clone()];
+        render_fn(line_buffer);
+
+        let (width, height) = (self.size.width as usize, self.size.height as usize);
+        let (flip_horizontally, flip_vertically) = self.flip.axes();
+        let row = if flip_vertically { height - 1 - line } else { line };
+        let row = &mut self.pixels[row * self.stride..][..width];
+        if flip_horizontally {
+            let target = &mut row[width - range.end..width - range.start];
+            for (target, source) in target.iter_mut().rev().zip(line_buffer.iter()) {
+                *target = *source;
+            }
+        } else {
+            row[range].copy_from_slice(line_buffer);
+        }
+    }
+}
+
+/// Mirrors a frame rendered into `pixels` in place.
+fn flip_pixels(
+    pixels: &mut [DumbBufferPixel],
+    stride: usize,
+    size: PhysicalWindowSize,
+    flip: RenderingFlip,
+) {
+    let (width, height) = (size.width as usize, size.height as usize);
+    let (flip_horizontally, flip_vertically) = flip.axes();
+    if flip_vertically {
+        for y in 0..height / 2 {
+            let (top, bottom) = pixels.split_at_mut((height - 1 - y) * stride);
+            top[y * stride..][..width].swap_with_slice(&mut bottom[..width]);
+        }
+    }
+    if flip_horizontally {
+        for y in 0..height {
+            pixels[y * stride..][..width].reverse();
+        }
+    }
+}
+
+impl SoftwareRendererAdapter {
+    pub fn new(
+        device_opener: &crate::DeviceOpener,
+    ) -> Result<Box<dyn crate::fullscreenwindowadapter::FullscreenRenderer>, PlatformError> {
+        let render_by_line = i_slint_core::bool_from_env("SLINT_KMS_LINE_BUFFER")?.unwrap_or(false);
+
+        let display = crate::display::swdisplay::create_software_display(device_opener)?;
+
+        let renderer = Box::new(Self {
+            renderer: SoftwareRenderer::new(),
+            line_buffer: render_by_line
+                .then(|| RefCell::new(vec![DumbBufferPixel(0); display.size.width as usize])),
+            display: Rc::new(display),
+        });
+
+        eprintln!("Using Software renderer");
+
+        Ok(renderer)
+    }
+}
+
+impl crate::fullscreenwindowadapter::FullscreenRenderer for SoftwareRendererAdapter {
+    fn as_core_renderer(&self) -> &dyn i_slint_core::renderer::Renderer {
+        &self.renderer
+    }
+
+    fn is_ready_to_present(&self) -> bool {
+        self.display.is_ready_to_present()
+    }
+
+    fn render_and_present(
+        &self,
+        rotation: RenderingRotation,
+        flip: RenderingFlip,
+        _draw_mouse_cursor_callback: &dyn Fn(&mut dyn ItemRenderer),
+        ready_for_next_animation_frame: Box<dyn FnOnce()>,
+    ) -> Result<(), PlatformError> {
+        // The software renderer can't draw on top of the scene, so there is no mouse cursor
+        use i_slint_core::software_renderer::RenderingRotation as SoftwareRotation;
+        // The software renderer rotates in the opposite direction
+        self.renderer.set_rendering_rotation(match rotation {
+            RenderingRotation::NoRotation => SoftwareRotation::NoRotation,
+            RenderingRotation::Rotate90 => SoftwareRotation::Rotate270,
+            RenderingRotation::Rotate180 => SoftwareRotation::Rotate180,
+            RenderingRotation::Rotate270 => SoftwareRotation::Rotate90,
+        });
+
+        // Mirroring a full frame in place leaves the buffer with content the renderer can't
+        // reuse, while lines are mirrored when they are copied to the buffer.
+        let flip_in_place = self.line_buffer.is_none() && flip != RenderingFlip::NoFlip;
+        self.renderer.set_repaint_buffer_type(match self.display.buffer_age() {
+            _ if flip_in_place => RepaintBufferType::NewBuffer,
+            1 => RepaintBufferType::ReusedBuffer,
+            2 => RepaintBufferType::SwappedBuffers,
+            _ => RepaintBufferType::NewBuffer,
+        });
+
+        let (size, stride) = (self.display.size, self.display.stride);
+        self.display.map_back_buffer(|bytes| {
+            let pixels: &mut [DumbBufferPixel] = bytemuck::cast_slice_mut(bytes);
+            match &self.line_buffer {
+                Some(line_buffer) => {
+                    self.renderer.render_by_line(DumbBufferLines {
+                        pixels,
+                        stride,
+                        size,
+                        flip,
+                        line_buffer: &mut line_buffer.borrow_mut(),
+                    });
+                }
+                None => {
+                    self.renderer.render(pixels, stride);
+                    flip_pixels(pixels, stride, size, flip);
+                }
+            }
+        })?;
+
+        // Rendering is done on the CPU, so the frame is complete in the buffer at this point
+        self.display.present_with_next_frame_callback(ready_for_next_animation_frame)?;
+        Ok(())
+    }
+
+    fn size(&self) -> i_slint_core::api::PhysicalSize {
+        self.display.size
+    }
+
+    fn scale_factor(&self) -> f32 {
+        self.display.scale_factor
+    }
+
+    fn output_info(&self) -> Vec<crate::OutputInfo> {
+        vec![self.display.output_info.clone()]
+    }
+
+    fn read_back(
+        &self,
+        rotation: RenderingRotation,
+        flip: RenderingFlip,
+    ) -> Result<
+        i_slint_core::graphics::SharedPixelBuffer<i_slint_core::graphics::Rgba8Pixel>,
+        PlatformError,
+    > {
+        Ok(rotation
+            .screen_pixels_to_window_pixels(flip.flip_pixels(self.display.read_front_buffer()?)))
+    }
+
+    fn register_page_flip_handler(
+        &self,
+        event_loop_handle: crate::calloop_backend::EventLoopHandle,
+    ) -> Result<(), PlatformError> {
+        self.display.clone().register_page_flip_handler(event_loop_handle)
+    }
+
+    fn set_vsync(&self, vsync: bool) {
+        self.display.set_vsync(vsync)
+    }
+
+    fn discard_next_frame(&self) {
+        self.display.discard_next_frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer_matches_full_buffer() {
+        let size = PhysicalWindowSize::new(7, 5);
+        let stride = 8;
+        // A gradient with a rectangle on top, rendered for the `range` of pixels of a line
+        let scene =
+            |line: usize, range: core::ops::Range<usize>, pixels: &mut [DumbBufferPixel]| {
+                for (x, pixel) in range.zip(pixels.iter_mut()) {
+                    *pixel = if (2..5).contains(&x) && (1..3).contains(&line) {
+                        DumbBufferPixel::from_rgb(255, 0, 0)
+                    } else {
+                        DumbBufferPixel::from_rgb(x as u8 * 30, line as u8 * 40, 0)
+                    };
+                }
+            };
+
+        for flip in [
+            RenderingFlip::NoFlip,
+            RenderingFlip::Horizontal,
+            RenderingFlip::Vertical,
+            RenderingFlip::Both,
+        ] {
+            let mut full_buffer = vec![DumbBufferPixel(0); stride * size.height as usize];
+            for line in 0..size.height as usize {
+                scene(line, 0..size.width as usize, &mut full_buffer[line * stride..][..7]);
+            }
+            flip_pixels(&mut full_buffer, stride, size, flip);
+
+            let mut line_by_line = vec![DumbBufferPixel(0); stride * size.height as usize];
+            let mut line_buffer = vec![DumbBufferPixel(0); size.width as usize];
+            let mut lines = DumbBufferLines {
+                pixels: &mut line_by_line,
+                stride,
+                size,
+                flip,
+                line_buffer: &mut line_buffer,
+            };
+            for line in 0..size.height as usize {
+                lines.process_line(line, 0..size.width as usize, |pixels| {
+                    scene(line, 0..size.width as usize, pixels)
+                });
+            }
+            assert_eq!(full_buffer.as_slice(), &*lines.pixels, "{flip:?}");
+
+            // Rendering only the rectangle again, as with partial rendering, changes nothing
+            for line in 1..3 {
+                lines.process_line(line, 2..5, |pixels| scene(line, 2..5, pixels));
+            }
+            assert_eq!(full_buffer.as_slice(), &*lines.pixels, "{flip:?}");
+        }
+    }
+}
@@ -95,28 +95,28 @@ pub(crate) fn load_builtins(register: &mut TypeRegister) {
                         let ty = info.ty.clone();
                         info.default_value = Some(compiled(e, register, ty));
                     }
+                    info.documentation = doc_comment(&p);
 
                     (prop_name, info)
                 })
                 .chain(e.CallbackDeclaration().map(|s| {
-                    (
-                        identifier_text(&s.DeclaredIdentifier()).unwrap(),
-                        BuiltinPropertyInfo::new(Type::Callback {
-                            args: s
-                                .Type()
-                                .map(|a| {
-                                    object_tree::type_from_node(a, *diag.borrow_mut(), register)
-                                })
-                                .collect(),
-                            return_type: s.ReturnType().map(|a| {
-                                Box::new(object_tree::type_from_node(
-                                    a.Type(),
-                                    *diag.borrow_mut(),
-                                    register,
-                                ))
-                            }),
+                    let mut info = BuiltinPropertyInfo::new(Type::Callback {
+                        args: s
+                            .Type()
+                            .map(|a| {
+                                object_tree::type_from_node(a, *diag.borrow_mut(), register)
+                            })
+                            .collect(),
+                        return_type: s.ReturnType().map(|a| {
+                            Box::new(object_tree::type_from_node(
+                                a.Type(),
+                                *diag.borrow_mut(),
+                                register,
+                            ))
                         }),
-                    )
+                    });
+                    info.documentation = doc_comment(&s);
+                    (identifier_text(&s.DeclaredIdentifier()).unwrap(), info)
                 }))
         );
         n.deprecated_aliases = e
@@ -252,6 +252,25 @@ fn compiled(
     e
 }
 
+/// Returns the text of the `///` comments directly preceding the declaration in `node`
+fn doc_comment(node: &SyntaxNode) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut token = node.first_token()?.prev_token();
+    while let Some(t) = token {
+        match t.kind() {
+            SyntaxKind::Comment => {
+                let Some(text) = t.text().strip_prefix("///") else { break };
+                lines.push(text.trim().to_owned());
+            }
+            SyntaxKind::Whitespace if t.text().matches('\n').count() <= 1 => {}
+            _ => break,
+        }
+        token = t.prev_token();
+    }
+    lines.reverse();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
 /// Find out if there are comments that starts with `//-key` and returns `None`
 /// if no annotation with this key is found, or `Some(None)` if it is found without a value
 /// or `Some(Some(value))` if there is a `//-key:value`  match
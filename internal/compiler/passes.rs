@@ -1,7 +1,7 @@
 // Copyright © SixtyFPS GmbH <info@slint.dev>
 // SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
 
-mod apply_default_properties_from_style;
+pub mod apply_default_properties_from_style;
 mod binding_analysis;
 mod check_expressions;
 mod check_public_api;
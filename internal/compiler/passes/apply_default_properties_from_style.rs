@@ -11,6 +11,20 @@
 use crate::object_tree::Component;
 use std::rc::Rc;
 
+/// The `(element, property)` pairs of the builtin elements whose default value is set by
+/// [`apply_default_properties_from_style`], instead of the default value of builtins.slint
+pub const PROPERTIES_WITH_STYLE_DEFAULT: &[(&str, &str)] = &[
+    ("TextInput", "text-cursor-width"),
+    ("TextInput", "color"),
+    ("Text", "color"),
+    ("Dialog", "background"),
+    ("Dialog", "default-font-size"),
+    ("Dialog", "default-font-family"),
+    ("Window", "background"),
+    ("Window", "default-font-size"),
+    ("Window", "default-font-family"),
+];
+
 /// Ideally we would be able to write this in builtin.slint, but the StyleMetrics is not available there
 pub fn apply_default_properties_from_style(
     root_component: &Rc<Component>,
@@ -231,6 +231,18 @@ pub struct AnimationDriver {
     /// Indicate whether there are any active animations that require a future call to update_animations.
     active_animations: Cell<bool>,
     global_instant: core::pin::Pin<Box<crate::Property<Instant>>>,
+    /// When set, the time used by [`update_animations`] is simulated instead of the real time.
+    simulated_time: Cell<Option<SimulatedTime>>,
+}
+
+#[derive(Copy, Clone)]
+struct SimulatedTime {
+    /// The real time at which the simulation was started
+    real_start: Instant,
+    /// The simulated time at `real_start`
+    simulated_start: Instant,
+    /// How fast the simulated time passes compared to the real time
+    speed: f32,
 }
 
 impl Default for AnimationDriver {
@@ -241,6 +253,7 @@ fn default() -> Self {
                 Instant::default(),
                 "i_slint_core::AnimationDriver::global_instant",
             )),
+            simulated_time: Cell::default(),
         }
     }
 }
@@ -270,6 +283,32 @@ pub fn set_has_active_animations(&self) {
     pub fn current_tick(&self) -> Instant {
         self.global_instant.as_ref().get()
     }
+
+    /// Makes [`update_animations`] use a simulated time that starts at `start` and advances
+    /// `speed` times as fast as the real time. A speed of zero freezes the time.
+    /// Pass `None` to go back to the real time.
+    pub fn set_simulated_time(&self, simulated_time: Option<(Instant, f32)>) {
+        self.start_simulated_time(simulated_time, real_tick());
+    }
+
+    /// Like [`Self::set_simulated_time`], with the simulation starting at the real time `real_start`
+    fn start_simulated_time(&self, simulated_time: Option<(Instant, f32)>, real_start: Instant) {
+        self.simulated_time.set(simulated_time.map(|(simulated_start, speed)| SimulatedTime {
+            real_start,
+            simulated_start,
+            speed,
+        }));
+    }
+
+    fn simulated_tick(&self, real_tick: Instant) -> Instant {
+        match self.simulated_time.get() {
+            Some(SimulatedTime { real_start, simulated_start, speed }) => {
+                let elapsed = real_tick.0.saturating_sub(real_start.0) as f64 * speed as f64;
+                Instant(simulated_start.0 + elapsed as u64)
+            }
+            None => real_tick,
+        }
+    }
 }
 
 #[cfg(all(not(feature = "std"), feature = "unsafe-single-threaded"))]
@@ -413,14 +452,39 @@ fn test_curve(name: &str, curve: &EasingCurve) {
 
 /// Update the global animation time to the current time
 pub fn update_animations() {
-    CURRENT_ANIMATION_DRIVER.with(|driver| {
-        #[allow(unused_mut)]
-        let mut duration = Instant::duration_since_start().as_millis() as u64;
-        #[cfg(feature = "std")]
-        if let Ok(val) = std::env::var("SLINT_SLOW_ANIMATIONS") {
-            let factor = val.parse().unwrap_or(2);
-            duration /= factor;
-        };
-        driver.update_animations(Instant(duration))
-    });
+    CURRENT_ANIMATION_DRIVER
+        .with(|driver| driver.update_animations(driver.simulated_tick(real_tick())));
+}
+
+/// The time since the start of the backend, slowed down if `SLINT_SLOW_ANIMATIONS` is set
+fn real_tick() -> Instant {
+    #[allow(unused_mut)]
+    let mut duration = Instant::duration_since_start().as_millis() as u64;
+    #[cfg(feature = "std")]
+    if let Ok(val) = std::env::var("SLINT_SLOW_ANIMATIONS") {
+        let factor = val.parse().unwrap_or(2);
+        duration /= factor;
+    };
+    Instant(duration)
+}
+
+#[test]
+fn test_simulated_time() {
+    // A frozen time doesn't depend on when the animations are updated
+    CURRENT_ANIMATION_DRIVER.with(|driver| driver.set_simulated_time(Some((Instant(42_000), 0.))));
+    update_animations();
+    assert_eq!(current_tick(), Instant(42_000));
+
+    let driver = AnimationDriver::default();
+    driver.start_simulated_time(Some((Instant(1000), 2.)), Instant(10_000));
+    assert_eq!(driver.simulated_tick(Instant(10_000)), Instant(1000));
+    assert_eq!(driver.simulated_tick(Instant(10_500)), Instant(2000));
+    // Real times before the start of the simulation don't go back in time
+    assert_eq!(driver.simulated_tick(Instant(9_000)), Instant(1000));
+
+    driver.start_simulated_time(Some((Instant(1000), 0.5)), Instant(10_000));
+    assert_eq!(driver.simulated_tick(Instant(12_000)), Instant(2000));
+
+    driver.start_simulated_time(None, Instant(10_000));
+    assert_eq!(driver.simulated_tick(Instant(12_345)), Instant(12_345));
 }
@@ -88,6 +88,42 @@
 /// parameter cannot be called from the public API without naming it
 pub struct InternalToken;
 
+/// Internal function to read a boolean setting from the environment variable `name`, whose
+/// value must be one of `1`, `true`, `0`, or `false`. Returns None if the variable is not set.
+#[cfg(feature = "std")]
+pub fn bool_from_env(name: &str) -> Result<Option<bool>, PlatformError> {
+    let Ok(value) = std::env::var(name) else {
+        return Ok(None);
+    };
+    match value.as_str() {
+        "1" | "true" => Ok(Some(true)),
+        "0" | "false" => Ok(Some(false)),
+        _ => Err(alloc::format!(
+            "Failed to parse {name}: Must be one of 1, true, 0, or false, found {value}"
+        )
+        .into()),
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_bool_from_env() {
+    assert_eq!(bool_from_env("SLINT_TEST_BOOL_FROM_ENV_UNSET").unwrap(), None);
+    for (value, expected) in [("1", true), ("true", true), ("0", false), ("false", false)] {
+        std::env::set_var("SLINT_TEST_BOOL_FROM_ENV", value);
+        assert_eq!(bool_from_env("SLINT_TEST_BOOL_FROM_ENV").unwrap(), Some(expected));
+    }
+    for value in ["", "yes", "TRUE", "2"] {
+        std::env::set_var("SLINT_TEST_BOOL_FROM_ENV", value);
+        let err = bool_from_env("SLINT_TEST_BOOL_FROM_ENV").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            alloc::format!("Failed to parse SLINT_TEST_BOOL_FROM_ENV: Must be one of 1, true, 0, or false, found {value}")
+        );
+    }
+    std::env::remove_var("SLINT_TEST_BOOL_FROM_ENV");
+}
+
 /// Internal function to access the platform abstraction.
 /// The factory function is called if the platform abstraction is not yet
 /// initialized, and should be given by the platform_selector
@@ -1206,6 +1206,17 @@ pub fn element_position(
     ) -> Option<i_slint_core::lengths::LogicalRect> {
         crate::highlight::element_position(&self.inner, element)
     }
+
+    /// Find the horizontal and vertical layout constraints of the `element`.
+    ///
+    /// WARNING: this is not part of the public API
+    #[cfg(feature = "highlight")]
+    pub fn element_layout_info(
+        &self,
+        element: &i_slint_compiler::object_tree::ElementRc,
+    ) -> Option<(i_slint_core::layout::LayoutInfo, i_slint_core::layout::LayoutInfo)> {
+        crate::highlight::element_layout_info(&self.inner, element)
+    }
 }
 
 impl ComponentHandle for ComponentInstance {
@@ -1308,6 +1319,20 @@ pub enum InvokeError {
     NoSuchCallable,
 }
 
+/// Sets a handler that is called for errors that happen when evaluating the bindings of
+/// a component, like images that cannot be loaded, instead of printing them on stderr.
+///
+/// The handler is set for the current thread only.
+#[doc(hidden)]
+#[cfg(feature = "internal")]
+pub fn set_runtime_error_handler(
+    handler: Option<
+        Box<dyn Fn(&str, Option<&i_slint_compiler::diagnostics::SourceLocation>) + 'static>,
+    >,
+) {
+    crate::eval::RUNTIME_ERROR_HANDLER.with(|h| *h.borrow_mut() = handler);
+}
+
 /// Enters the main event loop. This is necessary in order to receive
 /// events from the windowing system in order to render to the screen
 /// and react to user input.
@@ -10,6 +10,7 @@
 use corelib::model::{Model, ModelRc};
 use corelib::rtti::AnimatedBindingKind;
 use corelib::{Brush, Color, PathData, SharedString, SharedVector};
+use i_slint_compiler::diagnostics::SourceLocation;
 use i_slint_compiler::expression_tree::{
     BuiltinFunction, EasingCurve, Expression, MinMaxOp, Path as ExprPath,
     PathElement as ExprPathElement,
@@ -17,9 +18,25 @@
 use i_slint_compiler::langtype::Type;
 use i_slint_compiler::object_tree::ElementRc;
 use i_slint_core as corelib;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
+pub(crate) type RuntimeErrorHandler = Box<dyn Fn(&str, Option<&SourceLocation>)>;
+
+thread_local! {
+    pub(crate) static RUNTIME_ERROR_HANDLER: RefCell<Option<RuntimeErrorHandler>> = RefCell::new(None);
+}
+
+/// Reports an error that happened while evaluating an expression, like a resource that
+/// cannot be loaded, to the handler set with `set_runtime_error_handler`, or on stderr.
+fn report_runtime_error(message: &str, source_location: Option<&SourceLocation>) {
+    RUNTIME_ERROR_HANDLER.with(|handler| match handler.borrow().as_ref() {
+        Some(handler) => handler(message, source_location),
+        None => eprintln!("{message}"),
+    })
+}
+
 pub trait ErasedPropertyInfo {
     fn get(&self, item: Pin<ItemRef>) -> Value;
     fn set(
@@ -271,7 +288,7 @@ pub fn eval_expression(expression: &Expression, local_context: &mut EvalLocalCon
                 (sub, op) => panic!("unsupported {} {:?}", op, sub),
             }
         }
-        Expression::ImageReference{ resource_ref, .. } => {
+        Expression::ImageReference{ resource_ref, source_location } => {
             Value::Image(match resource_ref {
                 i_slint_compiler::expression_tree::ImageReference::None => {
                     Ok(Default::default())
@@ -306,7 +323,7 @@ pub fn eval_expression(expression: &Expression, local_context: &mut EvalLocalCon
                     todo!()
                 }
             }.unwrap_or_else(|_| {
-                eprintln!("Could not load image {:?}",resource_ref );
+                report_runtime_error(&format!("Could not load image {:?}", resource_ref), source_location.as_ref());
                 Default::default()
             }))
         }
@@ -4,8 +4,10 @@
 //! This module contains the code for the highlight of some elements
 
 use crate::dynamic_item_tree::{DynamicComponentVRc, ItemTreeBox};
+use i_slint_compiler::layout::Orientation;
 use i_slint_compiler::object_tree::{Component, Element, ElementRc};
 use i_slint_core::items::ItemRc;
+use i_slint_core::layout::LayoutInfo;
 use i_slint_core::lengths::LogicalRect;
 use std::cell::RefCell;
 use std::path::PathBuf;
@@ -75,6 +77,42 @@ pub(crate) fn element_position(
     values.geometries.get(0).cloned()
 }
 
+pub(crate) fn element_layout_info(
+    component_instance: &DynamicComponentVRc,
+    element: &ElementRc,
+) -> Option<(LayoutInfo, LayoutInfo)> {
+    generativity::make_guard!(guard);
+    let c = component_instance.unerase(guard);
+
+    // Only elements that are instantiated exactly once, in the root component, have one layout info
+    let enclosing = element.borrow().enclosing_component.upgrade()?;
+    if !Rc::ptr_eq(&enclosing, &c.description().original) {
+        return None;
+    }
+    if element.borrow().layout_info_prop.is_none()
+        && !c.description().items.contains_key(element.borrow().id.as_str())
+    {
+        return None;
+    }
+
+    let instance = c.borrow_instance();
+    let window_adapter = instance.window_adapter();
+    Some((
+        crate::eval_layout::get_layout_info(
+            element,
+            instance,
+            &window_adapter,
+            Orientation::Horizontal,
+        ),
+        crate::eval_layout::get_layout_info(
+            element,
+            instance,
+            &window_adapter,
+            Orientation::Vertical,
+        ),
+    ))
+}
+
 fn fill_highlight_data(
     repeater_path: &[String],
     element: &ElementRc,
@@ -174,6 +174,7 @@ pub fn render(&self) -> Result<(), i_slint_core::platform::PlatformError> {
         self.internal_render_with_post_callback(
             0.,
             (0., 0.),
+            (false, false),
             self.window_adapter()?.window().size(),
             None,
         )
@@ -183,6 +184,7 @@ fn internal_render_with_post_callback(
         &self,
         rotation_angle_degrees: f32,
         translation: (f32, f32),
+        flip: (bool, bool),
         surface_size: i_slint_core::api::PhysicalSize,
         post_render_cb: Option<&dyn Fn(&mut dyn ItemRenderer)>,
     ) -> Result<(), i_slint_core::platform::PlatformError> {
@@ -240,6 +242,14 @@ fn internal_render_with_post_callback(
                 {
                     let mut femtovg_canvas = self.canvas.borrow_mut();
                     femtovg_canvas.reset();
+                    if flip.0 {
+                        femtovg_canvas.translate(surface_size.width as f32, 0.);
+                        femtovg_canvas.scale(-1., 1.);
+                    }
+                    if flip.1 {
+                        femtovg_canvas.translate(0., surface_size.height as f32);
+                        femtovg_canvas.scale(1., -1.);
+                    }
                     femtovg_canvas.rotate(rotation_angle_degrees.to_radians());
                     femtovg_canvas.translate(translation.0, translation.1);
                 }
@@ -564,10 +574,13 @@ fn drop(&mut self) {
 }
 
 pub trait FemtoVGRendererExt {
+    /// Renders the window rotated and translated. `flip` mirrors the output horizontally and
+    /// vertically on the surface, after the rotation and translation are applied.
     fn render_transformed_with_post_callback(
         &self,
         rotation_angle_degrees: f32,
         translation: (f32, f32),
+        flip: (bool, bool),
         surface_size: i_slint_core::api::PhysicalSize,
         post_render_cb: Option<&dyn Fn(&mut dyn ItemRenderer)>,
     ) -> Result<(), i_slint_core::platform::PlatformError>;
@@ -578,12 +591,14 @@ fn render_transformed_with_post_callback(
         &self,
         rotation_angle_degrees: f32,
         translation: (f32, f32),
+        flip: (bool, bool),
         surface_size: i_slint_core::api::PhysicalSize,
         post_render_cb: Option<&dyn Fn(&mut dyn ItemRenderer)>,
     ) -> Result<(), i_slint_core::platform::PlatformError> {
         self.internal_render_with_post_callback(
             rotation_angle_degrees,
             translation,
+            flip,
             surface_size,
             post_render_cb,
         )
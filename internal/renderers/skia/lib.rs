@@ -14,7 +14,7 @@
 use i_slint_core::graphics::euclid::{self, Vector2D};
 use i_slint_core::graphics::rendering_metrics_collector::RenderingMetricsCollector;
 use i_slint_core::graphics::FontRequest;
-use i_slint_core::item_rendering::{ItemCache, ItemRenderer};
+use i_slint_core::item_rendering::{DirtyRegion, ItemCache, ItemRenderer, PartialRenderer};
 use i_slint_core::lengths::{
     LogicalLength, LogicalPoint, LogicalRect, LogicalSize, PhysicalPx, ScaleFactor,
 };
@@ -29,6 +29,7 @@
 
 mod cached_image;
 mod itemrenderer;
+mod partial_rendering;
 mod textlayout;
 
 #[cfg(skia_backend_software)]
@@ -89,6 +90,8 @@ pub struct SkiaRenderer {
     rendering_metrics_collector: RefCell<Option<Rc<RenderingMetricsCollector>>>,
     rendering_first_time: Cell<bool>,
     surface: RefCell<Option<Box<dyn Surface>>>,
+    /// Set when only the parts of the window that changed are redrawn
+    partial_rendering_state: RefCell<Option<partial_rendering::PartialRenderingState>>,
     surface_factory: fn(
         window_handle: raw_window_handle::WindowHandle<'_>,
         display_handle: raw_window_handle::DisplayHandle<'_>,
@@ -106,6 +109,7 @@ fn default() -> Self {
             rendering_metrics_collector: Default::default(),
             rendering_first_time: Default::default(),
             surface: Default::default(),
+            partial_rendering_state: Default::default(),
             surface_factory: create_default_surface,
         }
     }
@@ -123,6 +127,7 @@ pub fn default_software() -> Self {
             rendering_metrics_collector: Default::default(),
             rendering_first_time: Default::default(),
             surface: Default::default(),
+            partial_rendering_state: Default::default(),
             surface_factory: |window_handle, display_handle, size| {
                 software_surface::SoftwareSurface::new(window_handle, display_handle, size)
                     .map(|r| Box::new(r) as Box<dyn Surface>)
@@ -149,6 +154,7 @@ pub fn new_with_surface(surface: Box<dyn Surface + 'static>) -> Self {
             rendering_metrics_collector: Default::default(),
             rendering_first_time: Cell::new(true),
             surface: RefCell::new(Some(surface)),
+            partial_rendering_state: Default::default(),
             surface_factory: |_, _, _| {
                 Err("Skia renderer constructed with surface does not support dynamic surface re-creation".into())
             },
@@ -178,14 +184,16 @@ pub fn set_window_handle(
     pub fn render(&self) -> Result<(), i_slint_core::platform::PlatformError> {
         let window_adapter = self.window_adapter()?;
         let size = window_adapter.window().size();
-        self.internal_render_with_post_callback(0., (0., 0.), size, None)
+        self.internal_render_with_post_callback(0., (0., 0.), (false, false), size, 0, None)
     }
 
     fn internal_render_with_post_callback(
         &self,
         rotation_angle_degrees: f32,
         translation: (f32, f32),
+        flip: (bool, bool),
         surace_size: PhysicalWindowSize,
+        buffer_age: u8,
         post_render_cb: Option<&dyn Fn(&mut dyn ItemRenderer)>,
     ) -> Result<(), i_slint_core::platform::PlatformError> {
         let surface = self.surface.borrow();
@@ -205,11 +213,22 @@ fn internal_render_with_post_callback(
             }
         }
 
+        // The age reported by the surface is exact, while the caller can only estimate it
+        let buffer_age = surface.buffer_age().unwrap_or(buffer_age);
+
         let window_adapter = self.window_adapter()?;
         let window = window_adapter.window();
         let window_inner = WindowInner::from_pub(window);
 
         surface.render(surace_size, &|skia_canvas, mut gr_context| {
+            if flip.0 {
+                skia_canvas.translate((surace_size.width as f32, 0.));
+                skia_canvas.scale((-1., 1.));
+            }
+            if flip.1 {
+                skia_canvas.translate((0., surace_size.height as f32));
+                skia_canvas.scale((1., -1.));
+            }
             skia_canvas.rotate(rotation_angle_degrees, None);
             skia_canvas.translate(translation);
 
@@ -217,6 +236,70 @@ fn internal_render_with_post_callback(
                 let window_background_brush =
                     window_inner.window_item().map(|w| w.as_pin_ref().background());
 
+                let mut box_shadow_cache = Default::default();
+
+                self.image_cache.clear_cache_if_scale_factor_changed(window);
+                self.path_cache.clear_cache_if_scale_factor_changed(window);
+
+                let mut skia_item_renderer = Some(itemrenderer::SkiaItemRenderer::new(
+                    skia_canvas,
+                    window,
+                    &self.image_cache,
+                    &self.path_cache,
+                    &mut box_shadow_cache,
+                ));
+
+                let partial_rendering_state = self.partial_rendering_state.borrow();
+                let mut partial_renderer;
+                // The pixels of the buffer that are redrawn, or `None` for all of them
+                let mut redrawn_rect = None;
+                let item_renderer: &mut dyn ItemRenderer = match partial_rendering_state.as_ref() {
+                    // Rendering notifiers may draw anywhere, so they need the entire window redrawn
+                    Some(state) if self.rendering_notifier.borrow().is_none() => {
+                        partial_renderer = PartialRenderer::new(
+                            &state.partial_cache,
+                            state.force_dirty.take(),
+                            skia_item_renderer.take().unwrap(),
+                        );
+                        for (component, origin) in components {
+                            partial_renderer.compute_dirty_regions(component, *origin);
+                        }
+                        let scale_factor = ScaleFactor::new(window_inner.scale_factor());
+                        let device_rect = |region: DirtyRegion| {
+                            let rect = (region.to_rect() * scale_factor).round_out();
+                            skia_canvas
+                                .local_to_device_as_3x3()
+                                .map_rect(itemrenderer::to_skia_rect(&rect))
+                                .0
+                                .round()
+                        };
+                        let region = state.carry_forward_previous_frame(
+                            skia_canvas,
+                            partial_renderer.dirty_region,
+                            buffer_age,
+                            &device_rect,
+                        );
+                        redrawn_rect = Some(region.map(&device_rect));
+                        if let Some(region) = region {
+                            // Clip to whole pixels, so that the edges of the region are not blended
+                            let region =
+                                (region.to_rect() * scale_factor).round_out() / scale_factor;
+                            partial_renderer.combine_clip(
+                                region,
+                                LogicalLength::default(),
+                                LogicalLength::default(),
+                            );
+                        }
+                        &mut partial_renderer
+                    }
+                    _ => {
+                        if let Some(state) = partial_rendering_state.as_ref() {
+                            state.discard_previous_frame();
+                        }
+                        skia_item_renderer.as_mut().unwrap()
+                    }
+                };
+
                 // Clear with window background if it is a solid color otherwise it will drawn as gradient
                 if let Some(Brush::SolidColor(clear_color)) = window_background_brush {
                     skia_canvas.clear(itemrenderer::to_skia_color(&clear_color));
@@ -235,19 +318,6 @@ fn internal_render_with_post_callback(
                     })
                 }
 
-                let mut box_shadow_cache = Default::default();
-
-                self.image_cache.clear_cache_if_scale_factor_changed(window);
-                self.path_cache.clear_cache_if_scale_factor_changed(window);
-
-                let mut item_renderer = itemrenderer::SkiaItemRenderer::new(
-                    skia_canvas,
-                    window,
-                    &self.image_cache,
-                    &self.path_cache,
-                    &mut box_shadow_cache,
-                );
-
                 // Draws the window background as gradient
                 match window_background_brush {
                     Some(Brush::SolidColor(..)) | None => {}
@@ -264,21 +334,27 @@ fn internal_render_with_post_callback(
                 for (component, origin) in components {
                     i_slint_core::item_rendering::render_component_items(
                         component,
-                        &mut item_renderer,
+                        item_renderer,
                         *origin,
                     );
                 }
 
                 if let Some(collector) = &self.rendering_metrics_collector.borrow_mut().as_ref() {
-                    collector.measure_frame_rendered(&mut item_renderer);
+                    collector.measure_frame_rendered(item_renderer);
                 }
 
                 if let Some(cb) = post_render_cb.as_ref() {
-                    cb(&mut item_renderer)
+                    cb(item_renderer)
                 }
 
                 drop(item_renderer);
 
+                if let Some((state, redrawn_rect)) =
+                    partial_rendering_state.as_ref().zip(redrawn_rect)
+                {
+                    state.save_previous_frame(skia_canvas, redrawn_rect);
+                }
+
                 if let Some(ctx) = gr_context.as_mut() {
                     ctx.flush(None);
                 }
@@ -455,20 +531,35 @@ fn default_font_size(&self) -> LogicalLength {
     fn free_graphics_resources(
         &self,
         component: i_slint_core::item_tree::ItemTreeRef,
-        _items: &mut dyn Iterator<Item = std::pin::Pin<i_slint_core::items::ItemRef<'_>>>,
+        items: &mut dyn Iterator<Item = std::pin::Pin<i_slint_core::items::ItemRef<'_>>>,
     ) -> Result<(), i_slint_core::platform::PlatformError> {
         self.image_cache.component_destroyed(component);
         self.path_cache.component_destroyed(component);
+        if let Some(state) = self.partial_rendering_state.borrow().as_ref() {
+            state.free_graphics_resources(items);
+        }
         Ok(())
     }
 
+    fn mark_dirty_region(&self, region: DirtyRegion) {
+        if let Some(state) = self.partial_rendering_state.borrow().as_ref() {
+            state.force_dirty.set(state.force_dirty.get().union(&region));
+        }
+    }
+
     fn set_window_adapter(&self, window_adapter: &Rc<dyn WindowAdapter>) {
         *self.maybe_window_adapter.borrow_mut() = Some(Rc::downgrade(window_adapter));
         self.image_cache.clear_all();
         self.path_cache.clear_all();
+        if let Some(state) = self.partial_rendering_state.borrow().as_ref() {
+            state.force_screen_refresh.set(true);
+        }
     }
 
     fn resize(&self, size: i_slint_core::api::PhysicalSize) -> Result<(), PlatformError> {
+        if let Some(state) = self.partial_rendering_state.borrow().as_ref() {
+            state.force_screen_refresh.set(true);
+        }
         if let Some(surface) = self.surface.borrow().as_ref() {
             surface.resize_event(size)
         } else {
@@ -543,6 +634,11 @@ fn resize_event(
         size: PhysicalWindowSize,
     ) -> Result<(), i_slint_core::platform::PlatformError>;
     fn bits_per_pixel(&self) -> Result<u8, PlatformError>;
+    /// Returns the number of frames since the buffer the next frame is rendered into was last
+    /// rendered to, if the surface can tell. An age of 0 means the content is unknown.
+    fn buffer_age(&self) -> Option<u8> {
+        None
+    }
 
     /// Implementations should return self to allow upcasting.
     fn as_any(&self) -> &dyn core::any::Any {
@@ -551,13 +647,23 @@ fn as_any(&self) -> &dyn core::any::Any {
 }
 
 pub trait SkiaRendererExt {
+    /// Renders the window. With partial rendering enabled, `buffer_age` is the caller's estimate
+    /// of the number of frames since the buffer rendered into was last rendered to, and only the
+    /// parts of the window that changed since then are redrawn. An age of 0 means the content is
+    /// unknown. The age reported by [`Surface::buffer_age`], if any, is used instead.
+    /// `flip` mirrors the output horizontally and vertically on the surface, after the rotation
+    /// and translation are applied.
     fn render_transformed_with_post_callback(
         &self,
         rotation_angle_degrees: f32,
         translation: (f32, f32),
+        flip: (bool, bool),
         surface_size: PhysicalWindowSize,
+        buffer_age: u8,
         post_render_cb: Option<&dyn Fn(&mut dyn ItemRenderer)>,
     ) -> Result<(), i_slint_core::platform::PlatformError>;
+    /// Enables or disables redrawing only the parts of the window that changed.
+    fn set_partial_rendering(&self, enabled: bool);
 }
 
 impl SkiaRendererExt for SkiaRenderer {
@@ -565,14 +671,22 @@ fn render_transformed_with_post_callback(
         &self,
         rotation_angle_degrees: f32,
         translation: (f32, f32),
+        flip: (bool, bool),
         surface_size: PhysicalWindowSize,
+        buffer_age: u8,
         post_render_cb: Option<&dyn Fn(&mut dyn ItemRenderer)>,
     ) -> Result<(), i_slint_core::platform::PlatformError> {
         self.internal_render_with_post_callback(
             rotation_angle_degrees,
             translation,
+            flip,
             surface_size,
+            buffer_age,
             post_render_cb,
         )
     }
+
+    fn set_partial_rendering(&self, enabled: bool) {
+        *self.partial_rendering_state.borrow_mut() = enabled.then(Default::default);
+    }
 }
@@ -6,7 +6,7 @@
 use glutin::{
     config::GetGlConfig,
     context::{ContextApi, ContextAttributesBuilder},
-    display::GetGlDisplay,
+    display::{GetDisplayExtensions, GetGlDisplay},
     prelude::*,
     surface::{SurfaceAttributesBuilder, WindowSurface},
 };
@@ -29,71 +29,7 @@ fn new(
         display_handle: raw_window_handle::DisplayHandle<'_>,
         size: PhysicalWindowSize,
     ) -> Result<Self, PlatformError> {
-        let width: std::num::NonZeroU32 = size.width.try_into().map_err(|_| {
-            format!("Attempting to create window surface with an invalid width: {}", size.width)
-        })?;
-        let height: std::num::NonZeroU32 = size.height.try_into().map_err(|_| {
-            format!("Attempting to create window surface with an invalid height: {}", size.height)
-        })?;
-
-        let (current_glutin_context, glutin_surface) =
-            Self::init_glutin(window_handle, display_handle, width, height)?;
-
-        glutin_surface.resize(&current_glutin_context, width, height);
-
-        let fb_info = {
-            use glow::HasContext;
-
-            let gl = unsafe {
-                glow::Context::from_loader_function_cstr(|name| {
-                    current_glutin_context.display().get_proc_address(name) as *const _
-                })
-            };
-            let fboid = unsafe { gl.get_parameter_i32(glow::FRAMEBUFFER_BINDING) };
-
-            skia_safe::gpu::gl::FramebufferInfo {
-                fboid: fboid.try_into().map_err(|_| {
-                    format!("Skia Renderer: Internal error, framebuffer binding returned signed id")
-                })?,
-                format: skia_safe::gpu::gl::Format::RGBA8.into(),
-                ..Default::default()
-            }
-        };
-
-        let gl_interface = skia_safe::gpu::gl::Interface::new_load_with_cstr(|name| {
-            current_glutin_context.display().get_proc_address(name) as *const _
-        });
-
-        let mut gr_context =
-            skia_safe::gpu::DirectContext::new_gl(gl_interface, None).ok_or_else(|| {
-                format!("Skia Renderer: Internal Error: Could not create Skia OpenGL interface")
-            })?;
-
-        let width: i32 = size.width.try_into().map_err(|e| {
-                format!("Attempting to create window surface with width that doesn't fit into non-zero i32: {e}")
-            })?;
-        let height: i32 = size.height.try_into().map_err(|e| {
-                format!(
-                    "Attempting to create window surface with height that doesn't fit into non-zero i32: {e}"
-                )
-            })?;
-
-        let surface = Self::create_internal_surface(
-            fb_info,
-            &current_glutin_context,
-            &mut gr_context,
-            width,
-            height,
-        )?
-        .into();
-
-        Ok(Self {
-            fb_info,
-            surface,
-            gr_context: RefCell::new(gr_context),
-            glutin_context: current_glutin_context,
-            glutin_surface,
-        })
+        Self::new_with_options(window_handle, display_handle, size, 1, 8)
     }
 
     fn name(&self) -> &'static str {
@@ -187,14 +123,111 @@ fn bits_per_pixel(&self) -> Result<u8, PlatformError> {
         };
         Ok(rgb_bits + config.alpha_size())
     }
+
+    fn buffer_age(&self) -> Option<u8> {
+        // Without EGL_EXT_buffer_age, glutin reports an age of 0 for every buffer
+        if !self.glutin_context.display().extensions().contains("EGL_EXT_buffer_age") {
+            return None;
+        }
+        self.ensure_context_current().ok()?;
+        Some(self.glutin_surface.buffer_age().try_into().unwrap_or(0))
+    }
 }
 
 impl OpenGLSurface {
+    /// Creates a new surface like [`super::Surface::new`], with the given swap interval instead of
+    /// the default of 1. An interval of 0 presents without waiting for vertical sync, a value `n`
+    /// waits for `n` vertical refreshes. The interval is ignored if the driver doesn't support it.
+    ///
+    /// With `bits_per_channel` other than 8, only configurations with that many bits per color
+    /// channel are used, such as 10 for surfaces with 30 bit colors.
+    pub fn new_with_options(
+        window_handle: raw_window_handle::WindowHandle<'_>,
+        display_handle: raw_window_handle::DisplayHandle<'_>,
+        size: PhysicalWindowSize,
+        swap_interval: u32,
+        bits_per_channel: u8,
+    ) -> Result<Self, PlatformError> {
+        let width: std::num::NonZeroU32 = size.width.try_into().map_err(|_| {
+            format!("Attempting to create window surface with an invalid width: {}", size.width)
+        })?;
+        let height: std::num::NonZeroU32 = size.height.try_into().map_err(|_| {
+            format!("Attempting to create window surface with an invalid height: {}", size.height)
+        })?;
+
+        let (current_glutin_context, glutin_surface) = Self::init_glutin(
+            window_handle,
+            display_handle,
+            width,
+            height,
+            swap_interval,
+            bits_per_channel,
+        )?;
+
+        glutin_surface.resize(&current_glutin_context, width, height);
+
+        let fb_info = {
+            use glow::HasContext;
+
+            let gl = unsafe {
+                glow::Context::from_loader_function_cstr(|name| {
+                    current_glutin_context.display().get_proc_address(name) as *const _
+                })
+            };
+            let fboid = unsafe { gl.get_parameter_i32(glow::FRAMEBUFFER_BINDING) };
+
+            skia_safe::gpu::gl::FramebufferInfo {
+                fboid: fboid.try_into().map_err(|_| {
+                    format!("Skia Renderer: Internal error, framebuffer binding returned signed id")
+                })?,
+                format: Self::color_formats(&current_glutin_context).0.into(),
+                ..Default::default()
+            }
+        };
+
+        let gl_interface = skia_safe::gpu::gl::Interface::new_load_with_cstr(|name| {
+            current_glutin_context.display().get_proc_address(name) as *const _
+        });
+
+        let mut gr_context =
+            skia_safe::gpu::DirectContext::new_gl(gl_interface, None).ok_or_else(|| {
+                format!("Skia Renderer: Internal Error: Could not create Skia OpenGL interface")
+            })?;
+
+        let width: i32 = size.width.try_into().map_err(|e| {
+                format!("Attempting to create window surface with width that doesn't fit into non-zero i32: {e}")
+            })?;
+        let height: i32 = size.height.try_into().map_err(|e| {
+                format!(
+                    "Attempting to create window surface with height that doesn't fit into non-zero i32: {e}"
+                )
+            })?;
+
+        let surface = Self::create_internal_surface(
+            fb_info,
+            &current_glutin_context,
+            &mut gr_context,
+            width,
+            height,
+        )?
+        .into();
+
+        Ok(Self {
+            fb_info,
+            surface,
+            gr_context: RefCell::new(gr_context),
+            glutin_context: current_glutin_context,
+            glutin_surface,
+        })
+    }
+
     fn init_glutin(
         _window_handle: raw_window_handle::WindowHandle<'_>,
         _display_handle: raw_window_handle::DisplayHandle<'_>,
         width: NonZeroU32,
         height: NonZeroU32,
+        swap_interval: u32,
+        bits_per_channel: u8,
     ) -> Result<
         (
             glutin::context::PossiblyCurrentContext,
@@ -243,6 +276,16 @@ fn init_glutin(
         let config_template_builder = config_template_builder
             .compatible_with_native_window(_window_handle.raw_window_handle());
 
+        let config_template_builder = if bits_per_channel != 8 {
+            config_template_builder.with_buffer_type(glutin::config::ColorBufferType::Rgb {
+                r_size: bits_per_channel,
+                g_size: bits_per_channel,
+                b_size: bits_per_channel,
+            })
+        } else {
+            config_template_builder
+        };
+
         let config_template = config_template_builder.build();
 
         let config = unsafe {
@@ -329,13 +372,8 @@ fn init_glutin(
             .into());
         }
 
-        // Try to default to vsync and ignore if the driver doesn't support it.
-        surface
-            .set_swap_interval(
-                &context,
-                glutin::surface::SwapInterval::Wait(NonZeroU32::new(1).unwrap()),
-            )
-            .ok();
+        // Try to apply the swap interval (vsync by default) and ignore if the driver doesn't support it.
+        surface.set_swap_interval(&context, Self::glutin_swap_interval(swap_interval)).ok();
 
         Ok((context, surface))
     }
@@ -359,7 +397,7 @@ fn create_internal_surface(
             gr_context,
             &backend_render_target,
             skia_safe::gpu::SurfaceOrigin::BottomLeft,
-            skia_safe::ColorType::RGBA8888,
+            Self::color_formats(gl_context).1,
             None,
             None,
         ) {
@@ -371,6 +409,32 @@ fn create_internal_surface(
         }
     }
 
+    /// Returns the swap interval waiting for `swap_interval` vertical refreshes, or none for 0
+    fn glutin_swap_interval(swap_interval: u32) -> glutin::surface::SwapInterval {
+        match NonZeroU32::new(swap_interval) {
+            Some(interval) => glutin::surface::SwapInterval::Wait(interval),
+            None => glutin::surface::SwapInterval::DontWait,
+        }
+    }
+
+    /// Returns the format of the framebuffer and the matching Skia color type
+    fn color_formats(
+        gl_context: &glutin::context::PossiblyCurrentContext,
+    ) -> (skia_safe::gpu::gl::Format, skia_safe::ColorType) {
+        Self::color_formats_for(gl_context.config().color_buffer_type())
+    }
+
+    fn color_formats_for(
+        color_buffer_type: Option<glutin::config::ColorBufferType>,
+    ) -> (skia_safe::gpu::gl::Format, skia_safe::ColorType) {
+        match color_buffer_type {
+            Some(glutin::config::ColorBufferType::Rgb { r_size: 10, g_size: 10, b_size: 10 }) => {
+                (skia_safe::gpu::gl::Format::RGB10_A2, skia_safe::ColorType::RGBA1010102)
+            }
+            _ => (skia_safe::gpu::gl::Format::RGBA8, skia_safe::ColorType::RGBA8888),
+        }
+    }
+
     fn ensure_context_current(&self) -> Result<(), PlatformError> {
         if !self.glutin_context.is_current() {
             self.glutin_context.make_current(&self.glutin_surface).map_err(
@@ -394,3 +458,56 @@ fn drop(&mut self) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glutin::config::ColorBufferType;
+    use glutin::surface::SwapInterval;
+
+    #[test]
+    fn test_glutin_swap_interval() {
+        assert!(matches!(OpenGLSurface::glutin_swap_interval(0), SwapInterval::DontWait));
+        assert!(matches!(
+            OpenGLSurface::glutin_swap_interval(1),
+            SwapInterval::Wait(interval) if interval.get() == 1
+        ));
+        assert!(matches!(
+            OpenGLSurface::glutin_swap_interval(2),
+            SwapInterval::Wait(interval) if interval.get() == 2
+        ));
+    }
+
+    #[test]
+    fn test_color_formats() {
+        assert!(matches!(
+            OpenGLSurface::color_formats_for(Some(ColorBufferType::Rgb {
+                r_size: 10,
+                g_size: 10,
+                b_size: 10
+            })),
+            (skia_safe::gpu::gl::Format::RGB10_A2, skia_safe::ColorType::RGBA1010102)
+        ));
+        assert!(matches!(
+            OpenGLSurface::color_formats_for(Some(ColorBufferType::Rgb {
+                r_size: 8,
+                g_size: 8,
+                b_size: 8
+            })),
+            (skia_safe::gpu::gl::Format::RGBA8, skia_safe::ColorType::RGBA8888)
+        ));
+        // Configurations that don't report their color buffer, or have an unexpected one
+        assert!(matches!(
+            OpenGLSurface::color_formats_for(None),
+            (skia_safe::gpu::gl::Format::RGBA8, skia_safe::ColorType::RGBA8888)
+        ));
+        assert!(matches!(
+            OpenGLSurface::color_formats_for(Some(ColorBufferType::Rgb {
+                r_size: 5,
+                g_size: 6,
+                b_size: 5
+            })),
+            (skia_safe::gpu::gl::Format::RGBA8, skia_safe::ColorType::RGBA8888)
+        ));
+    }
+}
@@ -0,0 +1,303 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use i_slint_core::item_rendering::{DirtyRegion, PartialRenderingCache};
+
+/// How many frames the dirty regions are remembered for. Buffers that are older are redrawn
+/// entirely.
+const DIRTY_REGION_HISTORY_LENGTH: usize = 4;
+
+/// The state needed to redraw only the parts of the window that changed.
+#[derive(Default)]
+pub struct PartialRenderingState {
+    pub partial_cache: RefCell<PartialRenderingCache>,
+    /// Regions to redraw in the next frame, regardless of what changed
+    pub force_dirty: Cell<DirtyRegion>,
+    /// Redraw the entire window in the next frame
+    pub force_screen_refresh: Cell<bool>,
+    /// The regions redrawn in the previous frames, most recent first. `None` stands for a frame
+    /// that was redrawn entirely.
+    dirty_region_history: RefCell<VecDeque<Option<DirtyRegion>>>,
+    /// A copy of the previous frame, from which the regions that a reused buffer misses are
+    /// copied. Then only the region that changed since the previous frame needs to be redrawn,
+    /// regardless of how old the buffer is.
+    previous_frame: RefCell<Option<skia_safe::Surface>>,
+}
+
+impl PartialRenderingState {
+    /// Returns the region to redraw in a buffer that is `buffer_age` frames old, given the
+    /// region that changed since the previous frame, or `None` if the buffer must be redrawn
+    /// entirely.
+    pub fn region_to_draw(&self, dirty_region: DirtyRegion, buffer_age: u8) -> Option<DirtyRegion> {
+        let mut history = self.dirty_region_history.borrow_mut();
+        // Everything changed with a forced refresh, as far as older buffers are concerned
+        let changed_region = (!self.force_screen_refresh.take()).then_some(dirty_region);
+        let region = if buffer_age == 0 || buffer_age as usize > history.len() + 1 {
+            // The content of the buffer is unknown or older than the history
+            None
+        } else {
+            // The buffer also misses the changes of the frames rendered since it was rendered into
+            changed_region.and_then(|changed_region| {
+                history
+                    .iter()
+                    .take(buffer_age as usize - 1)
+                    .try_fold(changed_region, |region, previous| {
+                        Some(region.union(previous.as_ref()?))
+                    })
+            })
+        };
+
+        history.push_front(changed_region);
+        history.truncate(DIRTY_REGION_HISTORY_LENGTH);
+        region
+    }
+
+    /// Like [`Self::region_to_draw`], but the regions that the buffer of `canvas` misses are first
+    /// copied from the previous frame, if there is one, so that only the region that changed since
+    /// the previous frame is returned. `device_rect` maps a region to the pixels of the buffer
+    /// that it covers.
+    pub fn carry_forward_previous_frame(
+        &self,
+        canvas: &skia_safe::Canvas,
+        dirty_region: DirtyRegion,
+        buffer_age: u8,
+        device_rect: impl Fn(DirtyRegion) -> skia_safe::IRect,
+    ) -> Option<DirtyRegion> {
+        // After a forced refresh, the previous frame is outdated too
+        let screen_refresh = self.force_screen_refresh.get();
+        let region = self.region_to_draw(dirty_region, buffer_age);
+        if screen_refresh || region == Some(dirty_region) {
+            return region;
+        }
+        let size = canvas.base_layer_size();
+        let mut previous_frame = self.previous_frame.borrow_mut();
+        let Some(previous_frame) = previous_frame
+            .as_mut()
+            .filter(|frame| (frame.width(), frame.height()) == (size.width, size.height))
+        else {
+            return region;
+        };
+
+        canvas.save();
+        canvas.reset_matrix();
+        if let Some(region) = region {
+            canvas.clip_irect(device_rect(region), None);
+        }
+        let mut paint = skia_safe::Paint::default();
+        paint.set_blend_mode(skia_safe::BlendMode::Src);
+        canvas.draw_image(previous_frame.image_snapshot(), (0, 0), Some(&paint));
+        canvas.restore();
+        Some(dirty_region)
+    }
+
+    /// Copies the pixels `redrawn_rect` of the frame rendered into the buffer of `canvas` into the
+    /// copy of the previous frame, or the entire frame if `redrawn_rect` is `None`.
+    pub fn save_previous_frame(
+        &self,
+        canvas: &skia_safe::Canvas,
+        redrawn_rect: Option<skia_safe::IRect>,
+    ) {
+        let size = canvas.base_layer_size();
+        let mut previous_frame = self.previous_frame.borrow_mut();
+        let bounds = match redrawn_rect {
+            Some(rect) if rect.is_empty() => return,
+            Some(rect) => rect,
+            None => {
+                *previous_frame = canvas.new_surface(&canvas.image_info(), None);
+                skia_safe::IRect::from_size(size)
+            }
+        };
+        // A copy of another size can't be updated, and a new copy needs an entirely redrawn frame
+        let frame = previous_frame
+            .take()
+            .filter(|frame| (frame.width(), frame.height()) == (size.width, size.height));
+        // Safety: The surface is only used while the canvas, that it owns, is borrowed
+        let surface = unsafe { canvas.surface() };
+        let Some((mut frame, mut surface)) = frame.zip(surface) else { return };
+        let Some(redrawn) = surface.image_snapshot_with_bounds(bounds) else { return };
+        let mut paint = skia_safe::Paint::default();
+        paint.set_blend_mode(skia_safe::BlendMode::Src);
+        frame.canvas().draw_image(redrawn, (bounds.left, bounds.top), Some(&paint));
+        *previous_frame = Some(frame);
+    }
+
+    /// Forgets the previous frame, when the buffer was drawn into without tracking the regions.
+    pub fn discard_previous_frame(&self) {
+        self.previous_frame.take();
+    }
+
+    /// Releases the cached geometry of the items, which are about to be destroyed.
+    pub fn free_graphics_resources(
+        &self,
+        items: &mut dyn Iterator<Item = std::pin::Pin<i_slint_core::items::ItemRef<'_>>>,
+    ) {
+        for item in items {
+            item.cached_rendering_data_offset().release(&mut self.partial_cache.borrow_mut());
+        }
+        // The area of the destroyed items is unknown, so redraw everything
+        self.force_screen_refresh.set(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use i_slint_core::lengths::LogicalPoint;
+
+    fn region(index: usize) -> DirtyRegion {
+        let x = (index * 10) as i_slint_core::Coord;
+        let size = 5 as i_slint_core::Coord;
+        DirtyRegion::new(LogicalPoint::new(x, x), LogicalPoint::new(x + size, x + size))
+    }
+
+    /// Returns a state that rendered `region(0)` to `region(count - 1)` in that order
+    fn state_with_history(count: usize) -> PartialRenderingState {
+        let state = PartialRenderingState::default();
+        for index in 0..count {
+            state.region_to_draw(region(index), 1);
+        }
+        state
+    }
+
+    #[test]
+    fn test_region_to_draw_unknown_buffer_age() {
+        let state = state_with_history(DIRTY_REGION_HISTORY_LENGTH);
+        assert_eq!(state.region_to_draw(region(10), 0), None);
+        // The frame is remembered even though the buffer was redrawn entirely
+        assert_eq!(state.region_to_draw(region(11), 2), Some(region(11).union(&region(10))));
+    }
+
+    #[test]
+    fn test_region_to_draw_with_buffer_age() {
+        for buffer_age in 1..=DIRTY_REGION_HISTORY_LENGTH as u8 {
+            let state = state_with_history(DIRTY_REGION_HISTORY_LENGTH);
+            // The buffer misses the changes of the frames rendered since, most recent first
+            let expected = (1..buffer_age as usize).fold(region(10), |expected, age| {
+                expected.union(&region(DIRTY_REGION_HISTORY_LENGTH - age))
+            });
+            assert_eq!(
+                state.region_to_draw(region(10), buffer_age),
+                Some(expected),
+                "{buffer_age}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_region_to_draw_buffer_older_than_history() {
+        let state = state_with_history(DIRTY_REGION_HISTORY_LENGTH + 3);
+        let expected = (0..DIRTY_REGION_HISTORY_LENGTH)
+            .fold(region(10), |expected, index| expected.union(&region(index + 3)));
+        assert_eq!(
+            state.region_to_draw(region(10), DIRTY_REGION_HISTORY_LENGTH as u8 + 1),
+            Some(expected)
+        );
+
+        let state = state_with_history(DIRTY_REGION_HISTORY_LENGTH + 3);
+        assert_eq!(state.region_to_draw(region(10), DIRTY_REGION_HISTORY_LENGTH as u8 + 2), None);
+
+        // Before the history is filled, buffers older than the first frame are unknown
+        let state = state_with_history(2);
+        assert_eq!(
+            state.region_to_draw(region(10), 3),
+            Some(region(10).union(&region(1)).union(&region(0)))
+        );
+        let state = state_with_history(2);
+        assert_eq!(state.region_to_draw(region(10), 4), None);
+    }
+
+    #[test]
+    fn test_region_to_draw_after_forced_refresh() {
+        let state = state_with_history(DIRTY_REGION_HISTORY_LENGTH);
+        state.force_screen_refresh.set(true);
+        assert_eq!(state.region_to_draw(region(10), 1), None);
+        // Buffers rendered before the refresh miss all of it
+        assert_eq!(state.region_to_draw(region(11), 1), Some(region(11)));
+        assert_eq!(state.region_to_draw(region(12), 3), None);
+    }
+
+    /// Returns the RGBA values of the pixels of `buffer`, row by row
+    fn pixels(buffer: &mut skia_safe::Surface) -> Vec<[u8; 4]> {
+        let (width, height) = (buffer.width(), buffer.height());
+        let info = skia_safe::ImageInfo::new(
+            (width, height),
+            skia_safe::ColorType::RGBA8888,
+            skia_safe::AlphaType::Premul,
+            None,
+        );
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        assert!(buffer.read_pixels(&info, &mut pixels, width as usize * 4, (0, 0)));
+        pixels.chunks_exact(4).map(|pixel| pixel.try_into().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_carry_forward_previous_frame() {
+        const SIZE: i32 = 40;
+        let device_rect = |region: DirtyRegion| {
+            skia_safe::IRect::new(
+                region.min.x as i32,
+                region.min.y as i32,
+                region.max.x as i32,
+                region.max.y as i32,
+            )
+        };
+        let state = PartialRenderingState::default();
+        // Renders a frame that fills the region that changed since the previous frame with `color`
+        let render =
+            |buffer: &mut skia_safe::Surface, changed, buffer_age, color: skia_safe::Color| {
+                let canvas = buffer.canvas();
+                let region =
+                    state.carry_forward_previous_frame(canvas, changed, buffer_age, &device_rect);
+                canvas.save();
+                if let Some(region) = region {
+                    canvas.clip_irect(device_rect(region), None);
+                }
+                canvas.clear(color);
+                canvas.restore();
+                state.save_previous_frame(canvas, region.map(&device_rect));
+                region
+            };
+        // Checks that only the region that changed differs from the previous frame
+        let check = |buffer: &mut skia_safe::Surface,
+                     previous_frame: &[[u8; 4]],
+                     changed: DirtyRegion,
+                     color: skia_safe::Color| {
+            let color = [color.r(), color.g(), color.b(), color.a()];
+            for (index, (pixel, previous)) in pixels(buffer).iter().zip(previous_frame).enumerate()
+            {
+                let (x, y) = (index as i32 % SIZE, index as i32 / SIZE);
+                let point = LogicalPoint::new(x as i_slint_core::Coord, y as i_slint_core::Coord);
+                let expected = if changed.contains(point) { &color } else { previous };
+                assert_eq!(pixel, expected, "({x}, {y})");
+            }
+        };
+
+        // Two buffers in rotation, whose content is unknown at first
+        let mut buffers =
+            [(); 2].map(|_| skia_safe::surfaces::raster_n32_premul((SIZE, SIZE)).unwrap());
+        buffers[1].canvas().clear(skia_safe::Color::GREEN);
+
+        assert_eq!(render(&mut buffers[0], region(0), 0, skia_safe::Color::RED), None);
+        let first_frame = pixels(&mut buffers[0]);
+        assert!(first_frame.iter().all(|pixel| *pixel == [255, 0, 0, 255]));
+
+        // The rest of the buffer is copied from the previous frame, although its age is unknown
+        assert_eq!(render(&mut buffers[1], region(1), 0, skia_safe::Color::BLUE), Some(region(1)));
+        check(&mut buffers[1], &first_frame, region(1), skia_safe::Color::BLUE);
+        let second_frame = pixels(&mut buffers[1]);
+
+        // The buffer misses the second frame, which is copied as well
+        assert_eq!(
+            render(&mut buffers[0], region(2), 2, skia_safe::Color::YELLOW),
+            Some(region(2))
+        );
+        check(&mut buffers[0], &second_frame, region(2), skia_safe::Color::YELLOW);
+
+        // After a forced refresh, the previous frame is outdated as well
+        state.force_screen_refresh.set(true);
+        assert_eq!(render(&mut buffers[1], region(3), 2, skia_safe::Color::WHITE), None);
+    }
+}
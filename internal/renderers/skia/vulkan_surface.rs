@@ -8,28 +8,99 @@
 
 use vulkano::device::physical::{PhysicalDevice, PhysicalDeviceType};
 use vulkano::device::{
-    Device, DeviceCreateInfo, DeviceExtensions, Queue, QueueCreateInfo, QueueFlags,
+    Device, DeviceCreateInfo, DeviceExtensions, Features, Queue, QueueCreateInfo, QueueFlags,
 };
+use vulkano::format::{Format, FormatFeatures};
 use vulkano::image::view::ImageView;
 use vulkano::image::{Image, ImageUsage};
+use vulkano::instance::debug::{
+    DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessenger,
+    DebugUtilsMessengerCallback, DebugUtilsMessengerCreateInfo,
+};
 use vulkano::instance::{Instance, InstanceCreateFlags, InstanceCreateInfo, InstanceExtensions};
-use vulkano::swapchain::{Surface, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo};
+use vulkano::swapchain::{
+    ColorSpace, Surface, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo,
+};
 use vulkano::sync::GpuFuture;
 use vulkano::{sync, Handle, Validated, VulkanError, VulkanLibrary, VulkanObject};
 
 use raw_window_handle::HasRawDisplayHandle;
 use raw_window_handle::HasRawWindowHandle;
 
+/// The budget and usage of a Vulkan memory heap, in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VulkanHeapBudget {
+    /// The total size of the heap
+    pub size: u64,
+    /// How much memory the process can allocate from the heap without degrading performance
+    pub budget: u64,
+    /// How much memory of the heap the process currently uses
+    pub usage: u64,
+}
+
+/// GPU memory statistics of a [`VulkanSurface`], for debugging leaks and sizing the number of
+/// frames in flight.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VulkanMemoryDiagnostics {
+    /// The budget and usage of each memory heap of the device. Empty if the device doesn't
+    /// support `VK_EXT_memory_budget`.
+    pub heaps: Vec<VulkanHeapBudget>,
+    /// The size of all swapchain images, in bytes
+    pub swapchain_image_bytes: u64,
+    /// The size of the multisample attachment Skia renders into before resolving into the
+    /// swapchain image, in bytes. 0 without multisample anti-aliasing.
+    pub msaa_attachment_bytes: u64,
+}
+
+/// A frame that was queued for presentation, to wait until it's shown on the screen.
+pub struct PresentedFrame {
+    swapchain: Arc<Swapchain>,
+    present_id: std::num::NonZeroU64,
+}
+
+impl PresentedFrame {
+    /// Blocks until the frame is shown on the screen, or returns an error if that takes longer
+    /// than `timeout`.
+    pub fn wait(
+        &self,
+        timeout: std::time::Duration,
+    ) -> Result<(), i_slint_core::platform::PlatformError> {
+        self.swapchain
+            .wait_for_present(self.present_id, Some(timeout))
+            .map(|_| ())
+            .map_err(|e| format!("Error waiting for a Vulkan frame to be presented: {e}").into())
+    }
+}
+
 /// This surface renders into the given window using Vulkan.
 pub struct VulkanSurface {
     gr_context: RefCell<skia_safe::gpu::DirectContext>,
     recreate_swapchain: Cell<bool>,
-    device: Arc<Device>,
+    /// How long to wait for the GPU to finish a frame or release a swapchain image
+    gpu_timeout: std::time::Duration,
+    /// The number of samples per pixel Skia renders with, resolved into the swapchain images
+    sample_count: usize,
+    /// The number of swapchain images requested with `SLINT_VULKAN_FRAMES_IN_FLIGHT`
+    frames_in_flight: Option<u32>,
+    device: RefCell<Arc<Device>>,
+    /// Set when the device was lost, for example because the GPU was reset, until it's re-created
+    device_lost: Cell<bool>,
     previous_frame_end: RefCell<Option<Box<dyn GpuFuture>>>,
-    queue: Arc<Queue>,
-    swapchain: RefCell<Arc<Swapchain>>,
+    queue: RefCell<Arc<Queue>>,
+    /// The queue the frames are presented with. The same as `queue` if its family supports
+    /// presenting to the surface.
+    present_queue: RefCell<Arc<Queue>>,
+    surface: Arc<Surface>,
+    /// None if re-creating the swapchain after a device loss failed
+    swapchain: RefCell<Option<Arc<Swapchain>>>,
     swapchain_images: RefCell<Vec<Arc<Image>>>,
     swapchain_image_views: RefCell<Vec<Arc<ImageView>>>,
+    /// Called with every frame that was queued for presentation, see `set_present_listener`
+    present_listener: RefCell<Option<Box<dyn Fn(PresentedFrame)>>>,
+    /// The id of the last frame presented with `VK_KHR_present_id`
+    present_id: Cell<u64>,
+    /// Reports messages of the validation layer, if enabled with `SLINT_VULKAN_VALIDATION`
+    _debug_messenger: Option<DebugUtilsMessenger>,
 }
 
 impl VulkanSurface {
@@ -40,6 +111,47 @@ pub fn from_surface(
         queue_family_index: u32,
         surface: Arc<Surface>,
         size: PhysicalWindowSize,
+    ) -> Result<Self, i_slint_core::platform::PlatformError> {
+        Self::from_queue_families(
+            physical_device,
+            queue_family_index,
+            queue_family_index,
+            surface,
+            size,
+        )
+    }
+
+    /// Creates a Skia Vulkan rendering surface like [`Self::from_surface`], but renders with a
+    /// queue of `graphics_queue_family_index` and presents with a queue of
+    /// `present_queue_family_index`, for devices where no queue family can do both.
+    pub fn from_queue_families(
+        physical_device: Arc<PhysicalDevice>,
+        graphics_queue_family_index: u32,
+        present_queue_family_index: u32,
+        surface: Arc<Surface>,
+        size: PhysicalWindowSize,
+    ) -> Result<Self, i_slint_core::platform::PlatformError> {
+        let frames_in_flight =
+            frames_in_flight(std::env::var("SLINT_VULKAN_FRAMES_IN_FLIGHT").ok().as_deref())?;
+        Self::with_frames_in_flight(
+            physical_device,
+            graphics_queue_family_index,
+            present_queue_family_index,
+            surface,
+            size,
+            frames_in_flight,
+        )
+    }
+
+    /// Like [`Self::from_queue_families`], with the number of swapchain images to request instead
+    /// of reading it from `SLINT_VULKAN_FRAMES_IN_FLIGHT`.
+    fn with_frames_in_flight(
+        physical_device: Arc<PhysicalDevice>,
+        graphics_queue_family_index: u32,
+        present_queue_family_index: u32,
+        surface: Arc<Surface>,
+        size: PhysicalWindowSize,
+        frames_in_flight: Option<u32>,
     ) -> Result<Self, i_slint_core::platform::PlatformError> {
         /*
         eprintln!(
@@ -48,230 +160,386 @@ pub fn from_surface(
             physical_device.properties().device_type,
         );*/
 
-        let (device, mut queues) = Device::new(
-            physical_device.clone(),
-            DeviceCreateInfo {
-                enabled_extensions: DeviceExtensions {
-                    khr_swapchain: true,
-                    ..DeviceExtensions::empty()
-                },
-                queue_create_infos: vec![QueueCreateInfo {
-                    queue_family_index,
-                    ..Default::default()
-                }],
-                ..Default::default()
-            },
-        )
-        .map_err(|dev_err| format!("Failed to create suitable logical Vulkan device: {dev_err}"))?;
-        let queue = queues.next().ok_or_else(|| format!("Not Vulkan device queue found"))?;
-
-        let (swapchain, swapchain_images) = {
-            let surface_capabilities = device
-                .physical_device()
-                .surface_capabilities(&surface, Default::default())
-                .map_err(|vke| format!("Error macthing Vulkan surface capabilities: {vke}"))?;
-            let image_format = vulkano::format::Format::B8G8R8A8_UNORM.into();
-
-            Swapchain::new(
-                device.clone(),
-                surface.clone(),
-                SwapchainCreateInfo {
-                    min_image_count: surface_capabilities.min_image_count,
-                    image_format,
-                    image_extent: [size.width, size.height],
-                    image_usage: ImageUsage::COLOR_ATTACHMENT,
-                    composite_alpha: surface_capabilities
-                        .supported_composite_alpha
-                        .into_iter()
-                        .next()
-                        .ok_or_else(|| format!("fatal: Vulkan surface capabilities missing composite alpha descriptor"))?,
-                    ..Default::default()
-                },
+        // Report the messages of the validation layer, if it was enabled by `create_instance`
+        let instance = surface.instance();
+        let debug_messenger = if instance.enabled_extensions().ext_debug_utils
+            && instance.enabled_layers().iter().any(|layer| layer == VALIDATION_LAYER)
+        {
+            Some(
+                create_debug_messenger(instance)
+                    .map_err(|vke| format!("Error creating Vulkan debug messenger: {vke}"))?,
             )
-            .map_err(|vke| format!("Error creating Vulkan swapchain: {vke}"))?
+        } else {
+            None
         };
 
-        let mut swapchain_image_views = Vec::with_capacity(swapchain_images.len());
+        let (device, queue, present_queue) = create_device(
+            physical_device,
+            graphics_queue_family_index,
+            present_queue_family_index,
+        )?;
 
-        for image in &swapchain_images {
-            swapchain_image_views.push(ImageView::new_default(image.clone()).map_err(|vke| {
-                format!("fatal: Error creating image view for swap chain image: {vke}")
-            })?);
-        }
+        let surface_capabilities = device
+            .physical_device()
+            .surface_capabilities(&surface, Default::default())
+            .map_err(|vke| format!("Error macthing Vulkan surface capabilities: {vke}"))?;
+        let sample_count = msaa_sample_count(
+            std::env::var("SLINT_VULKAN_MSAA").ok().as_deref(),
+            surface_capabilities.supported_usage_flags,
+            device.physical_device().properties().framebuffer_color_sample_counts,
+        )?;
+        let image_format = swapchain_image_format(&device, &surface, sample_count)?;
 
-        let instance = physical_device.instance();
-        let library = instance.library();
+        let (swapchain, swapchain_images) = create_swapchain(
+            &device,
+            &surface,
+            size,
+            image_format,
+            ColorSpace::SrgbNonLinear,
+            sample_count,
+            frames_in_flight,
+            &[graphics_queue_family_index, present_queue_family_index],
+        )?;
 
-        let get_proc = |of| unsafe {
-            let result = match of {
-                skia_safe::gpu::vk::GetProcOf::Instance(instance, name) => {
-                    library.get_instance_proc_addr(ash::vk::Instance::from_raw(instance as _), name)
-                }
-                skia_safe::gpu::vk::GetProcOf::Device(device, name) => {
-                    (instance.fns().v1_0.get_device_proc_addr)(
-                        ash::vk::Device::from_raw(device as _),
-                        name,
-                    )
-                }
-            };
+        let swapchain_image_views = create_image_views(&swapchain_images)?;
 
-            match result {
-                Some(f) => f as _,
-                None => {
-                    //println!("resolve of {} failed", of.name().to_str().unwrap());
-                    core::ptr::null()
-                }
-            }
-        };
-
-        let backend_context = unsafe {
-            skia_safe::gpu::vk::BackendContext::new(
-                instance.handle().as_raw() as _,
-                physical_device.handle().as_raw() as _,
-                device.handle().as_raw() as _,
-                (queue.handle().as_raw() as _, queue.id_within_family() as _),
-                &get_proc,
-            )
-        };
-
-        let gr_context = skia_safe::gpu::DirectContext::new_vulkan(&backend_context, None)
-            .ok_or_else(|| format!("Error creating Skia Vulkan context"))?;
+        let gr_context = create_gr_context(&device, &queue)?;
 
         let previous_frame_end = RefCell::new(Some(sync::now(device.clone()).boxed()));
 
-        Ok(Self {
+        let gpu_timeout = std::env::var("SLINT_VULKAN_FENCE_TIMEOUT_MS").map_or(
+            Ok(std::time::Duration::from_millis(1000)),
+            |timeout_str| {
+                timeout_str.parse().map(std::time::Duration::from_millis).map_err(|_| {
+                    format!("Failed to parse SLINT_VULKAN_FENCE_TIMEOUT_MS: Must be a number of milliseconds, found {timeout_str}")
+                })
+            },
+        )?;
+
+        let vulkan_surface = Self {
             gr_context: RefCell::new(gr_context),
             recreate_swapchain: Cell::new(false),
-            device,
+            gpu_timeout,
+            sample_count,
+            frames_in_flight,
+            device: RefCell::new(device),
+            device_lost: Cell::new(false),
             previous_frame_end,
-            queue,
-            swapchain: RefCell::new(swapchain),
+            queue: RefCell::new(queue),
+            present_queue: RefCell::new(present_queue),
+            surface,
+            swapchain: RefCell::new(Some(swapchain)),
             swapchain_images: RefCell::new(swapchain_images),
             swapchain_image_views: RefCell::new(swapchain_image_views),
-        })
+            present_listener: Default::default(),
+            present_id: Cell::new(0),
+            _debug_messenger: debug_messenger,
+        };
+
+        if std::env::var_os("SLINT_DEBUG_PERFORMANCE").is_some() {
+            i_slint_core::debug_log!("Vulkan memory: {:?}", vulkan_surface.memory_diagnostics());
+        }
+
+        Ok(vulkan_surface)
     }
 
-    /// Returns a clone of the shared swapchain.
-    pub fn swapchain(&self) -> Arc<Swapchain> {
+    /// Returns a clone of the shared swapchain, or None if the device was lost and couldn't be
+    /// re-created.
+    pub fn swapchain(&self) -> Option<Arc<Swapchain>> {
         self.swapchain.borrow().clone()
     }
-}
 
-impl super::Surface for VulkanSurface {
-    fn new(
-        window_handle: raw_window_handle::WindowHandle<'_>,
-        display_handle: raw_window_handle::DisplayHandle<'_>,
-        size: PhysicalWindowSize,
-    ) -> Result<Self, i_slint_core::platform::PlatformError> {
-        let library = VulkanLibrary::new()
-            .map_err(|load_err| format!("Error loading vulkan library: {load_err}"))?;
+    fn current_swapchain(&self) -> Result<Arc<Swapchain>, i_slint_core::platform::PlatformError> {
+        self.swapchain.borrow().clone().ok_or_else(|| {
+            format!("Vulkan: The swapchain is not available after the device was lost").into()
+        })
+    }
 
-        let required_extensions = InstanceExtensions {
-            khr_surface: true,
-            mvk_macos_surface: true,
-            ext_metal_surface: true,
-            khr_wayland_surface: true,
-            khr_xlib_surface: true,
-            khr_xcb_surface: true,
-            khr_win32_surface: true,
-            khr_get_surface_capabilities2: true,
-            khr_get_physical_device_properties2: true,
-            ..InstanceExtensions::empty()
+    /// Sets a function that is called with every frame that was queued for presentation, for
+    /// example to wait in another thread until it's shown and then render the next animation
+    /// frame. Returns false if the device doesn't support waiting for the presentation of frames
+    /// (`VK_KHR_present_wait`), in which case the function is never called.
+    pub fn set_present_listener(&self, listener: impl Fn(PresentedFrame) + 'static) -> bool {
+        if !self.device.borrow().enabled_features().present_wait {
+            return false;
         }
-        .intersection(library.supported_extensions());
+        *self.present_listener.borrow_mut() = Some(Box::new(listener));
+        true
+    }
 
-        let instance = Instance::new(
-            library.clone(),
-            InstanceCreateInfo {
-                flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
-                enabled_extensions: required_extensions,
-                ..Default::default()
+    /// Returns the budget and usage of the memory heaps of the device, if the device supports
+    /// `VK_EXT_memory_budget`, and the size of the images allocated for rendering.
+    pub fn memory_diagnostics(&self) -> VulkanMemoryDiagnostics {
+        let image_bytes = self.swapchain.borrow().as_ref().map_or(0, |swapchain| {
+            let [width, height] = swapchain.image_extent();
+            let bytes_per_pixel = skia_format_for(swapchain.image_format())
+                .map_or(4, |(_, color_type)| color_type.bytes_per_pixel());
+            width as u64 * height as u64 * bytes_per_pixel as u64
+        });
+
+        VulkanMemoryDiagnostics {
+            heaps: memory_heap_budgets(self.device.borrow().physical_device()),
+            swapchain_image_bytes: image_bytes * self.swapchain_images.borrow().len() as u64,
+            msaa_attachment_bytes: if self.sample_count > 1 {
+                image_bytes * self.sample_count as u64
+            } else {
+                0
             },
-        )
-        .map_err(|instance_err| format!("Error creating Vulkan instance: {instance_err}"))?;
+        }
+    }
 
-        let surface = create_surface(&instance, window_handle, display_handle)
-            .map_err(|surface_err| format!("Error creating Vulkan surface: {surface_err}"))?;
+    /// Renders into an image that is owned by the caller instead of the swapchain, for example to
+    /// compose the Slint UI as a layer of another Vulkan renderer.
+    ///
+    /// The image must have been created on the device of this surface, with the given `format`
+    /// and `size`, and be in the given `layout`. The rendering commands are submitted to the queue
+    /// of this surface, but the caller is responsible for the synchronization before using the
+    /// image.
+    pub fn render_into_image(
+        &self,
+        image: ash::vk::Image,
+        layout: skia_safe::gpu::vk::ImageLayout,
+        format: Format,
+        size: PhysicalWindowSize,
+        callback: &dyn Fn(&skia_safe::Canvas, Option<&mut skia_safe::gpu::DirectContext>),
+    ) -> Result<(), i_slint_core::platform::PlatformError> {
+        let gr_context = &mut self.gr_context.borrow_mut();
 
-        let device_extensions =
-            DeviceExtensions { khr_swapchain: true, ..DeviceExtensions::empty() };
-        let (physical_device, queue_family_index) = instance
-            .enumerate_physical_devices()
-            .map_err(|vke| format!("Error enumerating physical Vulkan devices: {vke}"))?
-            .filter(|p| p.supported_extensions().contains(&device_extensions))
-            .filter_map(|p| {
-                p.queue_family_properties()
-                    .iter()
-                    .enumerate()
-                    .position(|(i, q)| {
-                        q.queue_flags.intersects(QueueFlags::GRAPHICS)
-                            && p.surface_support(i as u32, &surface).unwrap_or(false)
-                    })
-                    .map(|i| (p, i as u32))
-            })
-            .min_by_key(|(p, _)| match p.properties().device_type {
-                PhysicalDeviceType::DiscreteGpu => 0,
-                PhysicalDeviceType::IntegratedGpu => 1,
-                PhysicalDeviceType::VirtualGpu => 2,
-                PhysicalDeviceType::Cpu => 3,
-                PhysicalDeviceType::Other => 4,
-                _ => 5,
+        let width: i32 = size
+            .width
+            .try_into()
+            .map_err(|_| format!("Invalid external image width {}", size.width))?;
+        let height: i32 = size
+            .height
+            .try_into()
+            .map_err(|_| format!("Invalid external image height {}", size.height))?;
+
+        render_into_vk_image(gr_context, image, layout, format, (width, height), 1, callback)?;
+
+        gr_context.flush_and_submit();
+
+        Ok(())
+    }
+
+    /// Re-creates the swapchain and its images with the given format, color space, and size.
+    ///
+    /// The format and color space are checked against the surface before anything is changed, so
+    /// if they're not supported, the surface keeps rendering with its previous configuration.
+    /// Re-creating the swapchain retires the old one, even if that fails. So if re-creating it or
+    /// creating the image views for the new images fails, an error is returned and the swapchain is
+    /// re-created again before the next frame.
+    pub fn reconfigure(
+        &self,
+        format: Format,
+        color_space: ColorSpace,
+        size: PhysicalWindowSize,
+    ) -> Result<(), i_slint_core::platform::PlatformError> {
+        if skia_format_for(format).is_none() {
+            return Err(format!("Skia Vulkan Renderer: Unsupported image format {format:?}").into());
+        }
+
+        let swapchain = self.current_swapchain()?;
+
+        let supported = self
+            .device
+            .borrow()
+            .physical_device()
+            .surface_formats(swapchain.surface(), Default::default())
+            .map_err(|vke| format!("Error querying Vulkan surface formats: {vke}"))?;
+        if !supported.contains(&(format, color_space)) {
+            return Err(format!(
+                "Vulkan surface does not support format {format:?} with color space {color_space:?}"
+            )
+            .into());
+        }
+
+        // Make sure that no frame is in flight that still uses the old images.
+        self.wait_for_previous_frame()?;
+
+        let (new_swapchain, new_images) = swapchain
+            .recreate(SwapchainCreateInfo {
+                image_format: format,
+                image_color_space: color_space,
+                image_extent: [size.width, size.height],
+                ..swapchain.create_info()
             })
-            .ok_or_else(|| format!("Vulkan: Failed to find suitable physical device"))?;
+            .map_err(|vke| {
+                self.recreate_swapchain.set(true);
+                format!("Error re-creating Vulkan swap chain: {vke}")
+            })?;
 
-        Self::from_surface(physical_device, queue_family_index, surface, size)
+        self.replace_swapchain(new_swapchain, new_images)
     }
 
-    fn name(&self) -> &'static str {
-        "vulkan"
+    /// Takes over a re-created swapchain and its images, and creates the image views for them.
+    /// The old swapchain is retired once the new one exists, so the new one is kept even if
+    /// creating the image views fails. They're then created again with the next frame.
+    fn replace_swapchain(
+        &self,
+        swapchain: Arc<Swapchain>,
+        images: Vec<Arc<Image>>,
+    ) -> Result<(), i_slint_core::platform::PlatformError> {
+        let image_views = create_image_views(&images);
+
+        *self.swapchain.borrow_mut() = Some(swapchain);
+        *self.swapchain_images.borrow_mut() = images;
+
+        match image_views {
+            Ok(image_views) => {
+                *self.swapchain_image_views.borrow_mut() = image_views;
+                self.recreate_swapchain.set(false);
+                Ok(())
+            }
+            Err(e) => {
+                self.swapchain_image_views.borrow_mut().clear();
+                self.recreate_swapchain.set(true);
+                Err(e)
+            }
+        }
     }
 
-    fn resize_event(
+    /// Blocks until the GPU finished the previously submitted frame, so that the resources it
+    /// uses can be released. If that takes longer than the configured timeout, an error is
+    /// returned and the frame is still considered in flight, so that the caller can try again
+    /// later.
+    fn wait_for_previous_frame(&self) -> Result<(), i_slint_core::platform::PlatformError> {
+        let Some(previous_frame_end) = self.previous_frame_end.borrow_mut().take() else {
+            return Ok(());
+        };
+        let result = match previous_frame_end.then_signal_fence_and_flush() {
+            Ok(fence) => match fence.wait(Some(self.gpu_timeout)).map_err(Validated::unwrap) {
+                Err(VulkanError::Timeout) => {
+                    *self.previous_frame_end.borrow_mut() = Some(fence.boxed());
+                    return Err(format!(
+                        "Waited on GPU for the previous Vulkan frame for more than {} ms",
+                        self.gpu_timeout.as_millis()
+                    )
+                    .into());
+                }
+                result => result,
+            },
+            Err(e) => Err(e.unwrap()),
+        };
+        *self.previous_frame_end.borrow_mut() =
+            Some(sync::now(self.device.borrow().clone()).boxed());
+        result.map_err(|vke| {
+            self.check_device_lost(&vke);
+            format!("Error waiting for previous Vulkan frame: {vke}").into()
+        })
+    }
+
+    /// Remembers if `error` means that the device was lost, so that it's re-created before the
+    /// next frame is rendered.
+    fn check_device_lost(&self, error: &VulkanError) {
+        if matches!(error, VulkanError::DeviceLost) {
+            self.device_lost.set(true);
+        }
+    }
+
+    /// Replaces the lost device with a new one on the same physical device, and re-creates all
+    /// resources that belong to it: the queue, the swapchain with its images, and the Skia context.
+    ///
+    /// The resources of the lost device are released before the new ones are created, so if this
+    /// function returns an error, the surface has no swapchain and no frame in flight. The device
+    /// stays marked as lost then, so that [`Surface::render`](super::Surface::render) calls this
+    /// function again for the next frame instead of rendering with the released resources.
+    fn recreate_device(
         &self,
-        _size: PhysicalWindowSize,
+        size: PhysicalWindowSize,
     ) -> Result<(), i_slint_core::platform::PlatformError> {
-        self.recreate_swapchain.set(true);
+        // Nothing can be waited for on a lost device, so the resources are released right away.
+        // The old swapchain must be gone before the surface can get a new one.
+        *self.previous_frame_end.borrow_mut() = None;
+        self.gr_context.borrow_mut().abandon();
+        self.swapchain_image_views.borrow_mut().clear();
+        self.swapchain_images.borrow_mut().clear();
+        let (format, color_space) = self
+            .swapchain
+            .borrow_mut()
+            .take()
+            .map_or((Format::B8G8R8A8_UNORM, ColorSpace::SrgbNonLinear), |swapchain| {
+                (swapchain.image_format(), swapchain.image_color_space())
+            });
+
+        let physical_device = self.device.borrow().physical_device().clone();
+        let graphics_queue_family_index = self.queue.borrow().queue_family_index();
+        let present_queue_family_index = self.present_queue.borrow().queue_family_index();
+        let (device, queue, present_queue) = create_device(
+            physical_device,
+            graphics_queue_family_index,
+            present_queue_family_index,
+        )?;
+        let (swapchain, swapchain_images) = create_swapchain(
+            &device,
+            &self.surface,
+            size,
+            format,
+            color_space,
+            self.sample_count,
+            self.frames_in_flight,
+            &[graphics_queue_family_index, present_queue_family_index],
+        )?;
+        let swapchain_image_views = create_image_views(&swapchain_images)?;
+        let gr_context = create_gr_context(&device, &queue)?;
+
+        *self.gr_context.borrow_mut() = gr_context;
+        *self.previous_frame_end.borrow_mut() = Some(sync::now(device.clone()).boxed());
+        *self.device.borrow_mut() = device;
+        *self.queue.borrow_mut() = queue;
+        *self.present_queue.borrow_mut() = present_queue;
+        *self.swapchain.borrow_mut() = Some(swapchain);
+        *self.swapchain_images.borrow_mut() = swapchain_images;
+        *self.swapchain_image_views.borrow_mut() = swapchain_image_views;
+        self.recreate_swapchain.set(false);
+        self.device_lost.set(false);
+
         Ok(())
     }
 
-    fn render(
+    /// Renders a frame into the next swapchain image and queues it for presentation.
+    fn render_frame(
         &self,
         size: PhysicalWindowSize,
         callback: &dyn Fn(&skia_safe::Canvas, Option<&mut skia_safe::gpu::DirectContext>),
     ) -> Result<(), i_slint_core::platform::PlatformError> {
         let gr_context = &mut self.gr_context.borrow_mut();
 
-        let device = self.device.clone();
+        let device = self.device.borrow().clone();
 
         self.previous_frame_end.borrow_mut().as_mut().unwrap().cleanup_finished();
 
         if self.recreate_swapchain.take() {
-            let mut swapchain = self.swapchain.borrow_mut();
+            // The old images are released when replaced, so the previous frame must be done
+            // rendering into them.
+            if let Err(e) = self.wait_for_previous_frame() {
+                self.recreate_swapchain.set(true);
+                return Err(format!("{e} (while re-creating the swapchain for a resize)").into());
+            }
+
+            let swapchain = self.current_swapchain()?;
             let (new_swapchain, new_images) = swapchain
                 .recreate(SwapchainCreateInfo {
                     image_extent: [size.width, size.height],
                     ..swapchain.create_info()
                 })
-                .map_err(|vke| format!("Error re-creating Vulkan swap chain: {vke}"))?;
-
-            *swapchain = new_swapchain;
+                .map_err(|vke| {
+                    self.recreate_swapchain.set(true);
+                    format!("Error re-creating Vulkan swap chain: {vke}")
+                })?;
 
-            let mut new_swapchain_image_views = Vec::with_capacity(new_images.len());
+            self.replace_swapchain(new_swapchain, new_images)?;
+        }
 
-            for image in &new_images {
-                new_swapchain_image_views.push(ImageView::new_default(image.clone()).map_err(
-                    |vke| format!("fatal: Error creating image view for swap chain image: {vke}"),
-                )?);
-            }
+        let swapchain = self.current_swapchain()?;
 
-            *self.swapchain_images.borrow_mut() = new_images;
-            *self.swapchain_image_views.borrow_mut() = new_swapchain_image_views;
+        // With a single image, the previous frame must be done with it before it's rendered into
+        // again, as nothing else holds the rendering back while it's presented
+        if self.swapchain_images.borrow().len() == 1 {
+            self.wait_for_previous_frame()?;
         }
 
-        let swapchain = self.swapchain.borrow().clone();
-
         let (image_index, suboptimal, acquire_future) =
-            match vulkano::swapchain::acquire_next_image(swapchain.clone(), None)
+            match vulkano::swapchain::acquire_next_image(swapchain.clone(), Some(self.gpu_timeout))
                 .map_err(Validated::unwrap)
             {
                 Ok(r) => r,
@@ -279,7 +547,18 @@ fn render(
                     self.recreate_swapchain.set(true);
                     return Ok(()); // Try again next frame
                 }
-                Err(e) => return Err(format!("Vulkan: failed to acquire next image: {e}").into()),
+                Err(VulkanError::Timeout | VulkanError::NotReady) => {
+                    return Err(format!(
+                        "Vulkan: No swapchain image became available within {} ms (swapchain re-creation pending: {})",
+                        self.gpu_timeout.as_millis(),
+                        self.recreate_swapchain.get()
+                    )
+                    .into());
+                }
+                Err(e) => {
+                    self.check_device_lost(&e);
+                    return Err(format!("Vulkan: failed to acquire next image: {e}").into());
+                }
             };
 
         if suboptimal {
@@ -291,53 +570,41 @@ fn render(
             .try_into()
             .map_err(|_| format!("internal error: invalid swapchain image width {width}"))?;
         let height = swapchain.image_extent()[1];
-        let height: i32 = width
+        let height: i32 = height
             .try_into()
             .map_err(|_| format!("internal error: invalid swapchain image height {height}"))?;
 
         let image_view = self.swapchain_image_views.borrow()[image_index as usize].clone();
-        let image_object = image_view.image();
-
-        let format = image_view.format();
-
-        debug_assert_eq!(format, vulkano::format::Format::B8G8R8A8_UNORM);
-        let (vk_format, color_type) =
-            (skia_safe::gpu::vk::Format::B8G8R8A8_UNORM, skia_safe::ColorType::BGRA8888);
-
-        let alloc = skia_safe::gpu::vk::Alloc::default();
-        let image_info = &unsafe {
-            skia_safe::gpu::vk::ImageInfo::new(
-                image_object.handle().as_raw() as _,
-                alloc,
-                skia_safe::gpu::vk::ImageTiling::OPTIMAL,
-                skia_safe::gpu::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-                vk_format,
-                1,
-                None,
-                None,
-                None,
-                None,
-            )
-        };
-
-        let render_target =
-            &skia_safe::gpu::backend_render_targets::make_vk((width, height), image_info);
 
-        let mut skia_surface = skia_safe::gpu::surfaces::wrap_backend_render_target(
+        render_into_vk_image(
             gr_context,
-            render_target,
-            skia_safe::gpu::SurfaceOrigin::TopLeft,
-            color_type,
-            None,
-            None,
-        )
-        .ok_or_else(|| format!("Error creating Skia Vulkan surface"))?;
+            image_view.image().handle(),
+            skia_safe::gpu::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            image_view.format(),
+            (width, height),
+            self.sample_count,
+            callback,
+        )?;
 
-        callback(skia_surface.canvas(), Some(gr_context));
+        // Skia's submission isn't a vulkano future, so nothing orders the rendering before the
+        // presentation on another queue. In that case, wait for the GPU to finish the frame before
+        // presenting it. The swapchain images are shared concurrently by both queue families, see
+        // `create_swapchain`, so their ownership doesn't need to be transferred.
+        let separate_present_queue =
+            !Arc::ptr_eq(&*self.queue.borrow(), &*self.present_queue.borrow());
+        gr_context.submit(separate_present_queue.then_some(skia_safe::gpu::SyncCpu::Yes));
 
-        drop(skia_surface);
+        // Skia abandons its context when it sees the device getting lost
+        if gr_context.abandoned() {
+            self.device_lost.set(true);
+            return Err(format!("Skia Vulkan renderer: The Skia context was abandoned").into());
+        }
 
-        gr_context.submit(None);
+        // Ids are only needed to wait for the presentation of the frame
+        let present_id = self.present_listener.borrow().is_some().then(|| {
+            self.present_id.set(self.present_id.get() + 1);
+            std::num::NonZeroU64::new(self.present_id.get()).unwrap()
+        });
 
         let future = self
             .previous_frame_end
@@ -346,20 +613,29 @@ fn render(
             .unwrap()
             .join(acquire_future)
             .then_swapchain_present(
-                self.queue.clone(),
-                SwapchainPresentInfo::swapchain_image_index(swapchain.clone(), image_index),
+                self.present_queue.borrow().clone(),
+                SwapchainPresentInfo {
+                    present_id,
+                    ..SwapchainPresentInfo::swapchain_image_index(swapchain.clone(), image_index)
+                },
             )
             .then_signal_fence_and_flush();
 
         match future.map_err(Validated::unwrap) {
             Ok(future) => {
                 *self.previous_frame_end.borrow_mut() = Some(future.boxed());
+                if let (Some(present_id), Some(listener)) =
+                    (present_id, self.present_listener.borrow().as_ref())
+                {
+                    listener(PresentedFrame { swapchain: swapchain.clone(), present_id });
+                }
             }
             Err(VulkanError::OutOfDate) => {
                 self.recreate_swapchain.set(true);
                 *self.previous_frame_end.borrow_mut() = Some(sync::now(device.clone()).boxed());
             }
             Err(e) => {
+                self.check_device_lost(&e);
                 *self.previous_frame_end.borrow_mut() = Some(sync::now(device.clone()).boxed());
                 return Err(format!("Skia Vulkan renderer: failed to flush future: {e}").into());
             }
@@ -367,10 +643,165 @@ fn render(
 
         Ok(())
     }
+}
+
+impl super::Surface for VulkanSurface {
+    fn new(
+        window_handle: raw_window_handle::WindowHandle<'_>,
+        display_handle: raw_window_handle::DisplayHandle<'_>,
+        size: PhysicalWindowSize,
+    ) -> Result<Self, i_slint_core::platform::PlatformError> {
+        let library = VulkanLibrary::new()
+            .map_err(|load_err| format!("Error loading vulkan library: {load_err}"))?;
+
+        let required_extensions = InstanceExtensions {
+            khr_surface: true,
+            mvk_macos_surface: true,
+            ext_metal_surface: true,
+            khr_wayland_surface: true,
+            khr_xlib_surface: true,
+            khr_xcb_surface: true,
+            khr_win32_surface: true,
+            khr_get_surface_capabilities2: true,
+            khr_get_physical_device_properties2: true,
+            ..InstanceExtensions::empty()
+        };
+
+        let instance = create_instance(library, required_extensions)?;
+
+        let surface = create_surface(&instance, window_handle, display_handle)
+            .map_err(|surface_err| format!("Error creating Vulkan surface: {surface_err}"))?;
+
+        let device_extensions =
+            DeviceExtensions { khr_swapchain: true, ..DeviceExtensions::empty() };
+        let physical_devices: Vec<_> = instance
+            .enumerate_physical_devices()
+            .map_err(|vke| format!("Error enumerating physical Vulkan devices: {vke}"))?
+            .collect();
+
+        let requested_device = std::env::var("SLINT_VULKAN_DEVICE").ok();
+        if requested_device.as_ref().map_or(false, |name| name.to_lowercase() == "list") {
+            let device_names: Vec<String> = physical_devices
+                .iter()
+                .map(|p| {
+                    let properties = p.properties();
+                    format!(
+                        "{} (type: {:?}, PCI id: {:04x}:{:04x})",
+                        properties.device_name,
+                        properties.device_type,
+                        properties.vendor_id,
+                        properties.device_id
+                    )
+                })
+                .collect();
+
+            // Can't return error here because newlines are escaped.
+            panic!("\nVulkan Device List Requested:\n{}\n", device_names.join("\n"));
+        }
+
+        let suitable_devices: Vec<_> = physical_devices
+            .into_iter()
+            .filter(|p| p.supported_extensions().contains(&device_extensions))
+            .filter_map(|p| {
+                let family_count = p.queue_family_properties().len() as u32;
+                let graphics = |i: &u32| {
+                    p.queue_family_properties()[*i as usize]
+                        .queue_flags
+                        .intersects(QueueFlags::GRAPHICS)
+                };
+                let present = |i: &u32| p.surface_support(*i, &surface).unwrap_or(false);
+                // Prefer a single queue family for rendering and presenting, which needs no
+                // synchronization between queues
+                let (graphics_family, present_family) = (0..family_count)
+                    .find(|i| graphics(i) && present(i))
+                    .map(|i| (i, i))
+                    .or_else(|| {
+                        Some(((0..family_count).find(graphics)?, (0..family_count).find(present)?))
+                    })?;
+                Some((p, graphics_family, present_family))
+            })
+            .collect();
+
+        let requested_device = requested_device.and_then(|name| {
+            let name = name.to_lowercase();
+            let device = suitable_devices.iter().find(|(p, _, _)| {
+                p.properties().device_name.to_lowercase().contains(&name)
+            });
+            if device.is_none() {
+                eprintln!("Warning: No suitable Vulkan device matches SLINT_VULKAN_DEVICE={name}, selecting the default device");
+            }
+            device.cloned()
+        });
+
+        let (physical_device, graphics_queue_family_index, present_queue_family_index) =
+            requested_device
+                .or_else(|| {
+                    suitable_devices.into_iter().min_by_key(|(p, _, _)| {
+                        match p.properties().device_type {
+                            PhysicalDeviceType::DiscreteGpu => 0,
+                            PhysicalDeviceType::IntegratedGpu => 1,
+                            PhysicalDeviceType::VirtualGpu => 2,
+                            PhysicalDeviceType::Cpu => 3,
+                            PhysicalDeviceType::Other => 4,
+                            _ => 5,
+                        }
+                    })
+                })
+                .ok_or_else(|| format!("Vulkan: Failed to find suitable physical device"))?;
+
+        Self::from_queue_families(
+            physical_device,
+            graphics_queue_family_index,
+            present_queue_family_index,
+            surface,
+            size,
+        )
+    }
+
+    fn name(&self) -> &'static str {
+        "vulkan"
+    }
+
+    fn resize_event(
+        &self,
+        _size: PhysicalWindowSize,
+    ) -> Result<(), i_slint_core::platform::PlatformError> {
+        self.recreate_swapchain.set(true);
+        Ok(())
+    }
+
+    fn render(
+        &self,
+        size: PhysicalWindowSize,
+        callback: &dyn Fn(&skia_safe::Canvas, Option<&mut skia_safe::gpu::DirectContext>),
+    ) -> Result<(), i_slint_core::platform::PlatformError> {
+        // A swapchain can't have images of size zero, for example while the window is minimized.
+        // Keep the current images until the window has a size again.
+        if size.width == 0 || size.height == 0 {
+            return Ok(());
+        }
+
+        if !self.device_lost.get() {
+            let result = self.render_frame(size, callback);
+            if !self.device_lost.get() {
+                return result;
+            }
+        }
+
+        // The device was lost, for example because the GPU was reset after a hang or a driver
+        // crash. Re-create it and try the frame once more. If that fails, the device stays marked
+        // as lost and the next frame tries again.
+        self.recreate_device(size)
+            .map_err(|e| format!("Vulkan: The device was lost and re-creating it failed: {e}"))?;
+        self.render_frame(size, callback)
+    }
 
     fn bits_per_pixel(&self) -> Result<u8, i_slint_core::platform::PlatformError> {
-        Ok(match self.swapchain.borrow().image_format() {
-            vulkano::format::Format::B8G8R8A8_UNORM => 32,
+        Ok(match self.current_swapchain()?.image_format() {
+            Format::B8G8R8A8_UNORM
+            | Format::R8G8B8A8_UNORM
+            | Format::R8G8B8A8_SRGB
+            | Format::A2B10G10R10_UNORM_PACK32 => 32,
             fmt @ _ => {
                 return Err(format!(
                     "Skia Vulkan Renderer: Unsupported swapchain image format found {fmt:?}"
@@ -385,6 +816,482 @@ fn as_any(&self) -> &dyn core::any::Any {
     }
 }
 
+/// Creates the logical device with a queue for rendering and one for presenting, which are the
+/// same if both queue family indices are.
+fn create_device(
+    physical_device: Arc<PhysicalDevice>,
+    graphics_queue_family_index: u32,
+    present_queue_family_index: u32,
+) -> Result<(Arc<Device>, Arc<Queue>, Arc<Queue>), i_slint_core::platform::PlatformError> {
+    let mut queue_family_indices = vec![graphics_queue_family_index];
+    if present_queue_family_index != graphics_queue_family_index {
+        queue_family_indices.push(present_queue_family_index);
+    }
+    // Waiting for frames to be shown allows driving animations with the refresh of the display
+    let present_wait_extensions = DeviceExtensions {
+        khr_present_id: true,
+        khr_present_wait: true,
+        ..DeviceExtensions::empty()
+    };
+    let present_wait_features =
+        Features { present_id: true, present_wait: true, ..Features::empty() };
+    let present_wait = physical_device.supported_extensions().contains(&present_wait_extensions)
+        && physical_device.supported_features().contains(&present_wait_features);
+
+    let (device, mut queues) = Device::new(
+        physical_device,
+        DeviceCreateInfo {
+            enabled_extensions: DeviceExtensions {
+                khr_swapchain: true,
+                ..if present_wait { present_wait_extensions } else { DeviceExtensions::empty() }
+            },
+            enabled_features: if present_wait { present_wait_features } else { Features::empty() },
+            queue_create_infos: queue_family_indices
+                .into_iter()
+                .map(|queue_family_index| QueueCreateInfo {
+                    queue_family_index,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        },
+    )
+    .map_err(|dev_err| format!("Failed to create suitable logical Vulkan device: {dev_err}"))?;
+    let queue = queues.next().ok_or_else(|| format!("Not Vulkan device queue found"))?;
+    let present_queue = match queues.next() {
+        Some(present_queue) => present_queue,
+        None => queue.clone(),
+    };
+    Ok((device, queue, present_queue))
+}
+
+/// Creates a swapchain for the surface, with images of the given size and format that are used by
+/// the queues of the given families.
+fn create_swapchain(
+    device: &Arc<Device>,
+    surface: &Arc<Surface>,
+    size: PhysicalWindowSize,
+    image_format: Format,
+    image_color_space: ColorSpace,
+    sample_count: usize,
+    frames_in_flight: Option<u32>,
+    queue_family_indices: &[u32; 2],
+) -> Result<(Arc<Swapchain>, Vec<Arc<Image>>), i_slint_core::platform::PlatformError> {
+    let surface_capabilities = device
+        .physical_device()
+        .surface_capabilities(surface, Default::default())
+        .map_err(|vke| format!("Error macthing Vulkan surface capabilities: {vke}"))?;
+    let min_image_count = swapchain_image_count(
+        frames_in_flight,
+        surface_capabilities.min_image_count,
+        surface_capabilities.max_image_count,
+    );
+
+    Ok(Swapchain::new(
+        device.clone(),
+        surface.clone(),
+        SwapchainCreateInfo {
+            min_image_count,
+            image_format,
+            image_color_space,
+            image_extent: [size.width, size.height],
+            // Skia renders multisampled into its own attachment and resolves into the
+            // swapchain image, which it then wraps as a texture.
+            image_usage: if sample_count > 1 {
+                ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED
+            } else {
+                ImageUsage::COLOR_ATTACHMENT
+            },
+            // With separate queues for rendering and presenting, the images are shared between
+            // them instead of transferring their ownership back and forth for every frame.
+            image_sharing: if queue_family_indices[0] != queue_family_indices[1] {
+                vulkano::sync::Sharing::Concurrent(queue_family_indices.iter().copied().collect())
+            } else {
+                vulkano::sync::Sharing::Exclusive
+            },
+            composite_alpha: surface_capabilities
+                .supported_composite_alpha
+                .into_iter()
+                .next()
+                .ok_or_else(|| {
+                    format!("fatal: Vulkan surface capabilities missing composite alpha descriptor")
+                })?,
+            ..Default::default()
+        },
+    )
+    .map_err(|vke| format!("Error creating Vulkan swapchain: {vke}"))?)
+}
+
+/// Creates the Skia context that renders with the given device and queue.
+fn create_gr_context(
+    device: &Arc<Device>,
+    queue: &Arc<Queue>,
+) -> Result<skia_safe::gpu::DirectContext, i_slint_core::platform::PlatformError> {
+    let physical_device = device.physical_device();
+    let instance = physical_device.instance();
+    let library = instance.library();
+
+    let get_proc = |of| unsafe {
+        let result = match of {
+            skia_safe::gpu::vk::GetProcOf::Instance(instance, name) => {
+                library.get_instance_proc_addr(ash::vk::Instance::from_raw(instance as _), name)
+            }
+            skia_safe::gpu::vk::GetProcOf::Device(device, name) => {
+                (instance.fns().v1_0.get_device_proc_addr)(
+                    ash::vk::Device::from_raw(device as _),
+                    name,
+                )
+            }
+        };
+
+        match result {
+            Some(f) => f as _,
+            None => {
+                //println!("resolve of {} failed", of.name().to_str().unwrap());
+                core::ptr::null()
+            }
+        }
+    };
+
+    let backend_context = unsafe {
+        skia_safe::gpu::vk::BackendContext::new(
+            instance.handle().as_raw() as _,
+            physical_device.handle().as_raw() as _,
+            device.handle().as_raw() as _,
+            (queue.handle().as_raw() as _, queue.id_within_family() as _),
+            &get_proc,
+        )
+    };
+
+    Ok(skia_safe::gpu::DirectContext::new_vulkan(&backend_context, None)
+        .ok_or_else(|| format!("Error creating Skia Vulkan context"))?)
+}
+
+/// Queries the budget and usage of the memory heaps with `VK_EXT_memory_budget`. Returns an empty
+/// list if the extension or the query for extended memory properties isn't supported.
+fn memory_heap_budgets(physical_device: &Arc<PhysicalDevice>) -> Vec<VulkanHeapBudget> {
+    let instance = physical_device.instance();
+    let core_query = instance.api_version() >= vulkano::Version::V1_1
+        && physical_device.api_version() >= vulkano::Version::V1_1;
+    if !physical_device.supported_extensions().ext_memory_budget
+        || !(core_query || instance.enabled_extensions().khr_get_physical_device_properties2)
+    {
+        return Vec::new();
+    }
+
+    let mut budget = ash::vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+    let mut properties = ash::vk::PhysicalDeviceMemoryProperties2 {
+        p_next: &mut budget as *mut _ as *mut _,
+        ..Default::default()
+    };
+    unsafe {
+        if core_query {
+            (instance.fns().v1_1.get_physical_device_memory_properties2)(
+                physical_device.handle(),
+                &mut properties,
+            )
+        } else {
+            (instance
+                .fns()
+                .khr_get_physical_device_properties2
+                .get_physical_device_memory_properties2_khr)(
+                physical_device.handle(),
+                &mut properties,
+            )
+        }
+    }
+
+    let heaps = &properties.memory_properties.memory_heaps
+        [..properties.memory_properties.memory_heap_count as usize];
+    heaps
+        .iter()
+        .enumerate()
+        .map(|(i, heap)| VulkanHeapBudget {
+            size: heap.size,
+            budget: budget.heap_budget[i],
+            usage: budget.heap_usage[i],
+        })
+        .collect()
+}
+
+/// Wraps the given Vulkan image as a Skia render target and invokes the callback to render into it.
+fn render_into_vk_image(
+    gr_context: &mut skia_safe::gpu::DirectContext,
+    image: ash::vk::Image,
+    layout: skia_safe::gpu::vk::ImageLayout,
+    format: Format,
+    (width, height): (i32, i32),
+    sample_count: usize,
+    callback: &dyn Fn(&skia_safe::Canvas, Option<&mut skia_safe::gpu::DirectContext>),
+) -> Result<(), i_slint_core::platform::PlatformError> {
+    let (vk_format, color_type) = skia_format_for(format)
+        .ok_or_else(|| format!("Skia Vulkan Renderer: Unsupported image format {format:?}"))?;
+
+    // The GPU encodes the colors when writing to sRGB images, so Skia must blend in linear space
+    let color_space = (color_type == skia_safe::ColorType::SRGBA8888)
+        .then(skia_safe::ColorSpace::new_srgb_linear);
+
+    let alloc = skia_safe::gpu::vk::Alloc::default();
+    let image_info = &unsafe {
+        skia_safe::gpu::vk::ImageInfo::new(
+            image.as_raw() as _,
+            alloc,
+            skia_safe::gpu::vk::ImageTiling::OPTIMAL,
+            layout,
+            vk_format,
+            1,
+            None,
+            None,
+            None,
+            None,
+        )
+    };
+
+    let mut skia_surface = if sample_count > 1 {
+        let texture = &skia_safe::gpu::backend_textures::make_vk((width, height), image_info);
+        skia_safe::gpu::surfaces::wrap_backend_texture(
+            gr_context,
+            texture,
+            skia_safe::gpu::SurfaceOrigin::TopLeft,
+            sample_count,
+            color_type,
+            color_space,
+            None,
+        )
+    } else {
+        let render_target =
+            &skia_safe::gpu::backend_render_targets::make_vk((width, height), image_info);
+        skia_safe::gpu::surfaces::wrap_backend_render_target(
+            gr_context,
+            render_target,
+            skia_safe::gpu::SurfaceOrigin::TopLeft,
+            color_type,
+            color_space,
+            None,
+        )
+    }
+    .ok_or_else(|| format!("Error creating Skia Vulkan surface"))?;
+
+    callback(skia_surface.canvas(), Some(gr_context));
+
+    drop(skia_surface);
+
+    Ok(())
+}
+
+/// Parses the value of `SLINT_VULKAN_FRAMES_IN_FLIGHT`, the number of swapchain images to request.
+/// Values above 8 are limited to 8.
+fn frames_in_flight(
+    requested: Option<&str>,
+) -> Result<Option<u32>, i_slint_core::platform::PlatformError> {
+    let Some(count_str) = requested else {
+        return Ok(None);
+    };
+    let count: u32 = count_str
+        .parse()
+        .ok()
+        .filter(|count| *count != 0)
+        .ok_or_else(|| {
+            format!("Failed to parse SLINT_VULKAN_FRAMES_IN_FLIGHT: Must be a positive integer, found {count_str}")
+        })?;
+    Ok(Some(count.min(8)))
+}
+
+/// Returns the number of swapchain images to request, which bounds the number of frames in flight.
+/// `requested` is the number of frames in flight set with `SLINT_VULKAN_FRAMES_IN_FLIGHT`, which
+/// defaults to the minimum of the surface. More images allow for smoother throughput at the cost of
+/// memory and latency. With a single image, every frame waits for the previous one to be done.
+fn swapchain_image_count(
+    requested: Option<u32>,
+    min_image_count: u32,
+    max_image_count: Option<u32>,
+) -> u32 {
+    let Some(count) = requested else {
+        return min_image_count;
+    };
+    let max_image_count = max_image_count.unwrap_or(u32::MAX);
+    if count < min_image_count {
+        eprintln!("Warning: The Vulkan surface needs at least {min_image_count} images, using that many instead of SLINT_VULKAN_FRAMES_IN_FLIGHT={count}");
+    } else if count > max_image_count {
+        eprintln!("Warning: The Vulkan surface supports at most {max_image_count} images, using that many instead of SLINT_VULKAN_FRAMES_IN_FLIGHT={count}");
+    }
+    count.clamp(min_image_count, max_image_count.max(min_image_count))
+}
+
+/// Returns the format of the swapchain images. With `SLINT_VULKAN_SRGB`, that's an sRGB format, so that
+/// the GPU converts the colors Skia renders in linear space to sRGB, if the surface and the device
+/// support it.
+fn swapchain_image_format(
+    device: &Arc<Device>,
+    surface: &Arc<Surface>,
+    sample_count: usize,
+) -> Result<Format, i_slint_core::platform::PlatformError> {
+    if !i_slint_core::bool_from_env("SLINT_VULKAN_SRGB")?.unwrap_or(false) {
+        return Ok(Format::B8G8R8A8_UNORM);
+    }
+
+    // Skia supports rendering into R8G8B8A8_SRGB images, but not into B8G8R8A8_SRGB ones
+    let format = Format::R8G8B8A8_SRGB;
+    let required_features = if sample_count > 1 {
+        FormatFeatures::COLOR_ATTACHMENT | FormatFeatures::SAMPLED_IMAGE
+    } else {
+        FormatFeatures::COLOR_ATTACHMENT
+    };
+    let physical_device = device.physical_device();
+    let surface_supported = physical_device
+        .surface_formats(surface, Default::default())
+        .map_or(false, |formats| formats.contains(&(format, ColorSpace::SrgbNonLinear)));
+    let device_supported = physical_device
+        .format_properties(format)
+        .map_or(false, |properties| properties.optimal_tiling_features.contains(required_features));
+    if surface_supported && device_supported {
+        Ok(format)
+    } else {
+        eprintln!(
+            "Warning: The Vulkan surface does not support sRGB images, using {:?}",
+            Format::B8G8R8A8_UNORM
+        );
+        Ok(Format::B8G8R8A8_UNORM)
+    }
+}
+
+/// Returns the number of samples per pixel for multisample anti-aliasing, as `requested` with
+/// `SLINT_VULKAN_MSAA`. Multisampling needs swapchain images that can be sampled, so
+/// `supported_usage` are the image usages the surface supports and `supported` are the sample counts
+/// of the device. If the device doesn't support the requested count, the highest supported count
+/// below it is used.
+fn msaa_sample_count(
+    requested: Option<&str>,
+    supported_usage: ImageUsage,
+    supported: vulkano::image::SampleCounts,
+) -> Result<usize, i_slint_core::platform::PlatformError> {
+    let Some(samples_str) = requested else {
+        return Ok(1);
+    };
+    let requested: u32 = samples_str
+        .parse()
+        .ok()
+        .filter(|samples: &u32| samples.is_power_of_two() && *samples <= 64)
+        .ok_or_else(|| {
+            format!("Failed to parse SLINT_VULKAN_MSAA: Must be one of 1, 2, 4, 8, 16, 32, or 64, found {samples_str}")
+        })?;
+    if requested == 1 {
+        return Ok(1);
+    }
+    if !supported_usage.intersects(ImageUsage::SAMPLED) {
+        eprintln!("Warning: Multisample anti-aliasing is not supported by the Vulkan surface");
+        return Ok(1);
+    }
+    let samples = std::iter::successors(Some(requested), |samples| Some(samples / 2))
+        .take_while(|samples| *samples >= 1)
+        .find(|samples| {
+            vulkano::image::SampleCount::try_from(*samples)
+                .map_or(false, |count| supported.contains_enum(count))
+        })
+        .unwrap_or(1);
+    if samples != requested {
+        eprintln!("Warning: {requested}x multisample anti-aliasing is not supported by the Vulkan device, using {samples}x");
+    }
+    Ok(samples as usize)
+}
+
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// Creates a Vulkan instance with the `required_extensions` that the library supports. When
+/// requested with `SLINT_VULKAN_VALIDATION`, the validation layer is enabled too, and the surfaces
+/// rendering with the instance print its warnings and errors.
+pub fn create_instance(
+    library: Arc<VulkanLibrary>,
+    required_extensions: InstanceExtensions,
+) -> Result<Arc<Instance>, i_slint_core::platform::PlatformError> {
+    let validation_requested =
+        i_slint_core::bool_from_env("SLINT_VULKAN_VALIDATION")?.unwrap_or(false);
+    let validation = validation_requested
+        && library.supported_extensions().ext_debug_utils
+        && library
+            .layer_properties()
+            .map_or(false, |mut layers| layers.any(|layer| layer.name() == VALIDATION_LAYER));
+    if validation_requested && !validation {
+        eprintln!("Warning: The Vulkan validation layer is not available");
+    }
+
+    let enabled_extensions =
+        InstanceExtensions { ext_debug_utils: validation, ..required_extensions }
+            .intersection(library.supported_extensions());
+
+    Instance::new(
+        library,
+        InstanceCreateInfo {
+            flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
+            enabled_extensions,
+            enabled_layers: if validation { vec![VALIDATION_LAYER.into()] } else { vec![] },
+            ..Default::default()
+        },
+    )
+    .map_err(|instance_err| format!("Error creating Vulkan instance: {instance_err}").into())
+}
+
+/// Creates a messenger that prints the warnings and errors reported by the validation layer.
+fn create_debug_messenger(
+    instance: &Arc<Instance>,
+) -> Result<DebugUtilsMessenger, Validated<VulkanError>> {
+    let callback = unsafe {
+        DebugUtilsMessengerCallback::new(|severity, _message_type, data| {
+            let severity = if severity.intersects(DebugUtilsMessageSeverity::ERROR) {
+                "error"
+            } else {
+                "warning"
+            };
+            eprintln!("Vulkan validation {severity}: {}", data.message);
+        })
+    };
+    DebugUtilsMessenger::new(
+        instance.clone(),
+        DebugUtilsMessengerCreateInfo {
+            message_severity: DebugUtilsMessageSeverity::ERROR | DebugUtilsMessageSeverity::WARNING,
+            message_type: DebugUtilsMessageType::GENERAL
+                | DebugUtilsMessageType::VALIDATION
+                | DebugUtilsMessageType::PERFORMANCE,
+            ..DebugUtilsMessengerCreateInfo::user_callback(callback)
+        },
+    )
+}
+
+fn create_image_views(
+    images: &[Arc<Image>],
+) -> Result<Vec<Arc<ImageView>>, i_slint_core::platform::PlatformError> {
+    images
+        .iter()
+        .map(|image| {
+            ImageView::new_default(image.clone()).map_err(|vke| {
+                i_slint_core::platform::PlatformError::from(format!(
+                    "fatal: Error creating image view for swap chain image: {vke}"
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Maps a swapchain image format to the corresponding Skia Vulkan format and color type.
+fn skia_format_for(format: Format) -> Option<(skia_safe::gpu::vk::Format, skia_safe::ColorType)> {
+    Some(match format {
+        Format::B8G8R8A8_UNORM => {
+            (skia_safe::gpu::vk::Format::B8G8R8A8_UNORM, skia_safe::ColorType::BGRA8888)
+        }
+        Format::R8G8B8A8_UNORM => {
+            (skia_safe::gpu::vk::Format::R8G8B8A8_UNORM, skia_safe::ColorType::RGBA8888)
+        }
+        Format::R8G8B8A8_SRGB => {
+            (skia_safe::gpu::vk::Format::R8G8B8A8_SRGB, skia_safe::ColorType::SRGBA8888)
+        }
+        Format::A2B10G10R10_UNORM_PACK32 => (
+            skia_safe::gpu::vk::Format::A2B10G10R10_UNORM_PACK32,
+            skia_safe::ColorType::RGBA1010102,
+        ),
+        _ => return None,
+    })
+}
+
 fn create_surface(
     instance: &Arc<Instance>,
     window_handle: raw_window_handle::WindowHandle<'_>,
@@ -448,3 +1355,349 @@ fn create_surface(
         _ => unimplemented!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a surface that renders into a headless Vulkan surface, or None if there is no
+    /// Vulkan device that supports `VK_EXT_headless_surface`, for example on machines without a
+    /// GPU or a software implementation like lavapipe. `frames_in_flight` is the number of swapchain
+    /// images to request, as with `SLINT_VULKAN_FRAMES_IN_FLIGHT`.
+    fn headless_surface(
+        size: PhysicalWindowSize,
+        frames_in_flight: Option<u32>,
+    ) -> Option<VulkanSurface> {
+        let library = VulkanLibrary::new().ok()?;
+        if !library.supported_extensions().ext_headless_surface {
+            return None;
+        }
+        let instance = Instance::new(
+            library,
+            InstanceCreateInfo {
+                flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
+                enabled_extensions: InstanceExtensions {
+                    khr_surface: true,
+                    ext_headless_surface: true,
+                    ..InstanceExtensions::empty()
+                },
+                ..Default::default()
+            },
+        )
+        .ok()?;
+        let surface = Surface::headless(instance.clone(), None).ok()?;
+        let (physical_device, queue_family_index) =
+            instance.enumerate_physical_devices().ok()?.find_map(|physical_device| {
+                let queue_family_index =
+                    (0..physical_device.queue_family_properties().len() as u32).find(|index| {
+                        physical_device.queue_family_properties()[*index as usize]
+                            .queue_flags
+                            .intersects(QueueFlags::GRAPHICS)
+                            && physical_device.surface_support(*index, &surface).unwrap_or(false)
+                    })?;
+                Some((physical_device, queue_family_index))
+            })?;
+        VulkanSurface::with_frames_in_flight(
+            physical_device,
+            queue_family_index,
+            queue_family_index,
+            surface,
+            size,
+            frames_in_flight,
+        )
+        .ok()
+    }
+
+    #[test]
+    fn test_swapchain_image_count() {
+        assert_eq!(swapchain_image_count(None, 2, Some(4)), 2);
+        assert_eq!(swapchain_image_count(Some(3), 2, Some(4)), 3);
+        assert_eq!(swapchain_image_count(Some(1), 1, Some(4)), 1);
+        // Clamped to what the surface supports
+        assert_eq!(swapchain_image_count(Some(1), 2, Some(4)), 2);
+        assert_eq!(swapchain_image_count(Some(6), 2, Some(4)), 4);
+        assert_eq!(swapchain_image_count(Some(8), 2, None), 8);
+    }
+
+    #[test]
+    fn test_frames_in_flight() {
+        assert_eq!(frames_in_flight(None).unwrap(), None);
+        assert_eq!(frames_in_flight(Some("1")).unwrap(), Some(1));
+        assert_eq!(frames_in_flight(Some("3")).unwrap(), Some(3));
+        // At most 8 images are requested
+        assert_eq!(frames_in_flight(Some("100")).unwrap(), Some(8));
+
+        assert!(frames_in_flight(Some("0")).is_err());
+        assert!(frames_in_flight(Some("-1")).is_err());
+        assert!(frames_in_flight(Some("many")).is_err());
+    }
+
+    #[test]
+    fn test_single_frame_in_flight() {
+        use crate::Surface as _;
+
+        let size = PhysicalWindowSize::new(16, 8);
+        let Some(vulkan_surface) = headless_surface(size, Some(1)) else {
+            eprintln!("Skipping test: No Vulkan device with headless surface support");
+            return;
+        };
+        let min_image_count = vulkan_surface
+            .device
+            .borrow()
+            .physical_device()
+            .surface_capabilities(&vulkan_surface.surface, Default::default())
+            .unwrap()
+            .min_image_count;
+        if min_image_count > 1 {
+            eprintln!(
+                "Skipping test: The headless surface needs at least {min_image_count} images"
+            );
+            return;
+        }
+
+        assert_eq!(vulkan_surface.swapchain_images.borrow().len(), 1);
+        assert_eq!(vulkan_surface.swapchain_image_views.borrow().len(), 1);
+        assert_eq!(
+            vulkan_surface.memory_diagnostics().swapchain_image_bytes,
+            size.width as u64 * size.height as u64 * 4
+        );
+
+        for (i, color) in [
+            skia_safe::Color::RED,
+            skia_safe::Color::GREEN,
+            skia_safe::Color::BLUE,
+            skia_safe::Color::RED,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            vulkan_surface
+                .render(size, &|canvas, _| canvas.clear(color))
+                .unwrap_or_else(|e| panic!("Rendering frame {i}: {e}"));
+            assert_eq!(vulkan_surface.swapchain_images.borrow().len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_msaa_sample_count() {
+        use vulkano::image::SampleCounts;
+
+        let sampled = ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED;
+        let supported = SampleCounts::SAMPLE_1 | SampleCounts::SAMPLE_2 | SampleCounts::SAMPLE_4;
+
+        assert_eq!(msaa_sample_count(None, sampled, supported).unwrap(), 1);
+        assert_eq!(msaa_sample_count(Some("1"), sampled, supported).unwrap(), 1);
+        assert_eq!(msaa_sample_count(Some("4"), sampled, supported).unwrap(), 4);
+        // Falls back to the highest supported count below the requested one
+        assert_eq!(msaa_sample_count(Some("16"), sampled, supported).unwrap(), 4);
+        assert_eq!(msaa_sample_count(Some("8"), sampled, SampleCounts::SAMPLE_1).unwrap(), 1);
+        // The surface images can't be sampled, which is needed to resolve into them
+        assert_eq!(
+            msaa_sample_count(Some("4"), ImageUsage::COLOR_ATTACHMENT, supported).unwrap(),
+            1
+        );
+
+        assert!(msaa_sample_count(Some("3"), sampled, supported).is_err());
+        assert!(msaa_sample_count(Some("128"), sampled, supported).is_err());
+        assert!(msaa_sample_count(Some("x4"), sampled, supported).is_err());
+    }
+
+    #[test]
+    fn test_skia_format_for() {
+        assert_eq!(
+            skia_format_for(Format::B8G8R8A8_UNORM),
+            Some((skia_safe::gpu::vk::Format::B8G8R8A8_UNORM, skia_safe::ColorType::BGRA8888))
+        );
+        assert_eq!(
+            skia_format_for(Format::R8G8B8A8_SRGB),
+            Some((skia_safe::gpu::vk::Format::R8G8B8A8_SRGB, skia_safe::ColorType::SRGBA8888))
+        );
+        assert_eq!(
+            skia_format_for(Format::A2B10G10R10_UNORM_PACK32),
+            Some((
+                skia_safe::gpu::vk::Format::A2B10G10R10_UNORM_PACK32,
+                skia_safe::ColorType::RGBA1010102
+            ))
+        );
+        assert_eq!(skia_format_for(Format::R16G16B16A16_SFLOAT), None);
+        assert_eq!(skia_format_for(Format::B8G8R8A8_SRGB), None);
+    }
+
+    #[test]
+    fn test_render_into_image() {
+        use vulkano::image::{ImageCreateInfo, ImageType};
+        use vulkano::memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator};
+
+        let size = PhysicalWindowSize::new(16, 8);
+        let Some(vulkan_surface) = headless_surface(size, None) else {
+            eprintln!("Skipping test: No Vulkan device with headless surface support");
+            return;
+        };
+
+        // An image that the caller owns, as when composing the UI into another renderer's frame
+        let allocator =
+            Arc::new(StandardMemoryAllocator::new_default(vulkan_surface.device.borrow().clone()));
+        let image = Image::new(
+            allocator,
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::B8G8R8A8_UNORM,
+                extent: [size.width, size.height, 1],
+                usage: ImageUsage::COLOR_ATTACHMENT
+                    | ImageUsage::TRANSFER_SRC
+                    | ImageUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap();
+
+        let pixels = RefCell::new(vec![0u8; size.width as usize * size.height as usize * 4]);
+        vulkan_surface
+            .render_into_image(
+                image.handle(),
+                skia_safe::gpu::vk::ImageLayout::UNDEFINED,
+                Format::B8G8R8A8_UNORM,
+                size,
+                &|canvas, _| {
+                    canvas.clear(skia_safe::Color::RED);
+                    let info = skia_safe::ImageInfo::new(
+                        (size.width as i32, size.height as i32),
+                        skia_safe::ColorType::BGRA8888,
+                        skia_safe::AlphaType::Premul,
+                        None,
+                    );
+                    assert!(canvas.read_pixels(
+                        &info,
+                        &mut pixels.borrow_mut(),
+                        size.width as usize * 4,
+                        (0, 0)
+                    ));
+                },
+            )
+            .unwrap();
+
+        assert!(pixels.borrow().chunks_exact(4).all(|pixel| pixel == [0, 0, 255, 255]));
+
+        // Skia can't render into images of this format
+        assert!(vulkan_surface
+            .render_into_image(
+                image.handle(),
+                skia_safe::gpu::vk::ImageLayout::UNDEFINED,
+                Format::R16G16B16A16_SFLOAT,
+                size,
+                &|_, _| {},
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_present_through_swapchain() {
+        use crate::Surface as _;
+
+        let size = PhysicalWindowSize::new(16, 8);
+        let Some(vulkan_surface) = headless_surface(size, None) else {
+            eprintln!("Skipping test: No Vulkan device with headless surface support");
+            return;
+        };
+        let swapchain = vulkan_surface.swapchain().unwrap();
+        assert_eq!(swapchain.image_extent(), [16, 8]);
+
+        // Acquiring more images than the swapchain has only succeeds if every frame is presented,
+        // which hands its image back to the swapchain
+        let frames = vulkan_surface.swapchain_images.borrow().len() * 3;
+        for i in 0..frames {
+            vulkan_surface
+                .render(size, &|canvas, _| canvas.clear(skia_safe::Color::RED))
+                .unwrap_or_else(|e| panic!("Rendering frame {i}: {e}"));
+        }
+        assert!(Arc::ptr_eq(&vulkan_surface.swapchain().unwrap(), &swapchain));
+
+        // Acquiring or presenting reports an out of date swapchain like this
+        vulkan_surface.recreate_swapchain.set(true);
+        let size = PhysicalWindowSize::new(32, 24);
+        vulkan_surface.render(size, &|canvas, _| canvas.clear(skia_safe::Color::GREEN)).unwrap();
+        let recreated_swapchain = vulkan_surface.swapchain().unwrap();
+        assert!(!Arc::ptr_eq(&recreated_swapchain, &swapchain));
+        assert_eq!(recreated_swapchain.image_extent(), [32, 24]);
+        assert!(vulkan_surface
+            .swapchain_images
+            .borrow()
+            .iter()
+            .all(|image| image.extent() == [32, 24, 1]));
+        assert!(!vulkan_surface.recreate_swapchain.get());
+
+        // A resize re-creates the swapchain with the next frame
+        let size = PhysicalWindowSize::new(8, 8);
+        vulkan_surface.resize_event(size).unwrap();
+        vulkan_surface.render(size, &|canvas, _| canvas.clear(skia_safe::Color::BLUE)).unwrap();
+        assert_eq!(vulkan_surface.swapchain().unwrap().image_extent(), [8, 8]);
+        assert!(!vulkan_surface.recreate_swapchain.get());
+    }
+
+    #[test]
+    fn test_failed_reconfigure_keeps_previous_state() {
+        use crate::Surface as _;
+
+        let size = PhysicalWindowSize::new(16, 8);
+        let Some(vulkan_surface) = headless_surface(size, None) else {
+            eprintln!("Skipping test: No Vulkan device with headless surface support");
+            return;
+        };
+        vulkan_surface.render(size, &|canvas, _| canvas.clear(skia_safe::Color::RED)).unwrap();
+
+        let swapchain = vulkan_surface.swapchain().unwrap();
+        let images = vulkan_surface.swapchain_images.borrow().clone();
+
+        // Skia can't render into this format
+        assert!(vulkan_surface
+            .reconfigure(Format::R16G16B16A16_SFLOAT, ColorSpace::SrgbNonLinear, size)
+            .is_err());
+        // Skia could render into this format, but no surface supports it with this color space
+        assert!(vulkan_surface
+            .reconfigure(Format::R8G8B8A8_SRGB, ColorSpace::DisplayNativeAmd, size)
+            .is_err());
+
+        assert!(Arc::ptr_eq(&vulkan_surface.swapchain().unwrap(), &swapchain));
+        assert_eq!(*vulkan_surface.swapchain_images.borrow(), images);
+        assert_eq!(vulkan_surface.swapchain_image_views.borrow().len(), images.len());
+        assert!(!vulkan_surface.recreate_swapchain.get());
+
+        vulkan_surface.render(size, &|canvas, _| canvas.clear(skia_safe::Color::BLUE)).unwrap();
+        assert!(Arc::ptr_eq(&vulkan_surface.swapchain().unwrap(), &swapchain));
+    }
+
+    #[test]
+    fn test_recreate_lost_device() {
+        use crate::Surface as _;
+        use vulkano::device::DeviceOwned;
+
+        let size = PhysicalWindowSize::new(16, 8);
+        let Some(vulkan_surface) = headless_surface(size, None) else {
+            eprintln!("Skipping test: No Vulkan device with headless surface support");
+            return;
+        };
+
+        for i in 0..20 {
+            let device = vulkan_surface.device.borrow().clone();
+            // Losing the device while a frame is in flight, as after a GPU reset
+            vulkan_surface.render(size, &|canvas, _| canvas.clear(skia_safe::Color::RED)).unwrap();
+            vulkan_surface.check_device_lost(&VulkanError::DeviceLost);
+            assert!(vulkan_surface.device_lost.get());
+
+            vulkan_surface
+                .render(size, &|canvas, _| canvas.clear(skia_safe::Color::BLUE))
+                .unwrap_or_else(|e| panic!("Rendering after losing the device {i} times: {e}"));
+
+            assert!(!vulkan_surface.device_lost.get());
+            assert!(!Arc::ptr_eq(&*vulkan_surface.device.borrow(), &device));
+            let swapchain = vulkan_surface.swapchain().unwrap();
+            assert!(Arc::ptr_eq(swapchain.device(), &*vulkan_surface.device.borrow()));
+            assert_eq!(
+                vulkan_surface.swapchain_image_views.borrow().len(),
+                vulkan_surface.swapchain_images.borrow().len()
+            );
+            assert!(vulkan_surface.previous_frame_end.borrow().is_some());
+        }
+    }
+}
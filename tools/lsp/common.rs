@@ -17,6 +17,8 @@ pub struct PreviewConfig {
     pub style: String,
     pub include_paths: Vec<PathBuf>,
     pub library_paths: HashMap<String, PathBuf>,
+    /// Directories containing additional fonts to make available in the preview
+    pub font_dirs: Vec<PathBuf>,
 }
 
 /// API used by the LSP to talk to the Preview. The other direction uses the
@@ -27,6 +29,26 @@ pub trait PreviewApi {
     fn load_preview(&self, component: PreviewComponent);
     fn config_changed(&self, config: PreviewConfig);
     fn highlight(&self, path: Option<PathBuf>, offset: u32) -> Result<()>;
+    /// Returns the element tree of the previewed component, with the geometry
+    /// and layout constraints computed by the last layout run.
+    fn layout_tree(&self) -> Result<LayoutTreeNode>;
+    /// Overrides the time used by the preview, or goes back to the real time when `None`.
+    fn set_preview_clock(&self, clock: Option<PreviewClock>) -> Result<()>;
+    /// Overrides properties of the global `global` of the previewed component, until the
+    /// preview is reloaded.
+    fn set_palette_overrides(
+        &self,
+        global: String,
+        overrides: Vec<(String, PropertyOverride)>,
+    ) -> Result<()>;
+
+    /// Asks the preview to send the properties of the previewed component that can be
+    /// changed live with a `slint/previewLiveProperties` notification.
+    fn query_live_properties(&self) -> Result<()>;
+    /// Sets a property of the previewed component, without recompiling. The preview answers
+    /// with a `slint/previewLiveProperties` notification, with an error if the value could
+    /// not be set.
+    fn set_live_property(&self, name: String, value: serde_json::Value) -> Result<()>;
 
     /// What is the current component to preview?
     fn current_component(&self) -> Option<PreviewComponent>;
@@ -46,6 +68,74 @@ pub struct PreviewComponent {
     pub style: String,
 }
 
+/// The position and size of an element, in logical pixels
+#[derive(Default, Clone, Copy, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+pub struct LayoutGeometry {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// The layout constraints of an element in one direction
+#[derive(Default, Clone, Copy, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+pub struct LayoutConstraints {
+    pub min: f32,
+    pub max: f32,
+    pub preferred: f32,
+    pub stretch: f32,
+}
+
+/// One element in the layout tree returned by the `slint/layoutTree` command
+#[derive(Default, Clone, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutTreeNode {
+    /// The id of the element, may be empty
+    pub id: String,
+    /// The name of the type of the element
+    pub type_name: String,
+    /// The file the element is defined in
+    pub file: String,
+    /// The location of the element in `file`
+    pub range: lsp_types::Range,
+    /// The geometry of the element, if it is visible
+    pub geometry: Option<LayoutGeometry>,
+    pub horizontal_constraints: Option<LayoutConstraints>,
+    pub vertical_constraints: Option<LayoutConstraints>,
+    pub children: Vec<LayoutTreeNode>,
+}
+
+/// A simulated time for the preview
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct PreviewClock {
+    /// The value of `animation-tick()` in milliseconds when the clock is set
+    pub time: u64,
+    /// How fast the time passes compared to the real time. Zero freezes the time
+    pub speed: f32,
+}
+
+/// A value replacing the value of a property in the preview
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum PropertyOverride {
+    /// A number, in the default unit of the property
+    Number(f64),
+    Bool(bool),
+    String(String),
+    /// A color, encoded as ARGB
+    Color(u32),
+}
+
+/// A property of the previewed component that can be changed in the running preview
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveProperty {
+    pub name: String,
+    /// The name of the type of the property, as in the .slint file
+    pub type_name: String,
+    /// The current value: a number, a boolean, a string, or a `#rrggbbaa` string for colors
+    pub value: serde_json::Value,
+}
+
 #[allow(unused)]
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub enum LspToPreviewMessage {
@@ -53,6 +143,11 @@ pub enum LspToPreviewMessage {
     SetConfiguration { config: PreviewConfig },
     ShowPreview { path: String, component: Option<String>, style: String },
     HighlightFromEditor { path: Option<String>, offset: u32 },
+    SetPreviewClock { clock: Option<PreviewClock> },
+    SetPaletteOverrides { global: String, overrides: Vec<(String, PropertyOverride)> },
+    QueryLiveProperties,
+    Heartbeat, // check that the external previewer is still running
+    SetLiveProperty { name: String, value: serde_json::Value },
 }
 
 #[allow(unused)]
@@ -73,4 +168,8 @@ pub enum PreviewToLspMessage {
     ShowDocument { file: String, selection: lsp_types::Range },
     PreviewTypeChanged { is_external: bool },
     RequestState { unused: bool }, // send all documents!
+    RuntimeError { params: crate::lsp_ext::PreviewRuntimeErrorParams },
+    ElementPath { params: crate::lsp_ext::PreviewElementPathParams },
+    LiveProperties { params: crate::lsp_ext::PreviewLivePropertiesParams },
+    Heartbeat, // answer to LspToPreviewMessage::Heartbeat
 }
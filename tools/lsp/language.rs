@@ -4,45 +4,62 @@
 // cSpell: ignore descr rfind unindented
 
 mod completion;
+mod folding_ranges;
+mod formatting;
 mod goto;
+mod hover;
+mod inlay_hints;
+mod move_component;
+mod palette;
+pub mod progress;
 mod properties;
+mod references;
+mod rename;
+mod scaffold;
 mod semantic_tokens;
+mod signature_help;
 #[cfg(test)]
 mod test;
+mod unused_declarations;
+mod workspace_symbols;
 
-use crate::common::{PreviewApi, PreviewConfig, Result};
+use crate::common::{PreviewApi, PreviewClock, PreviewConfig, PropertyOverride, Result};
 use crate::language::properties::find_element_indent;
-use crate::util::{map_node, map_range, map_token, to_lsp_diag};
+use crate::util::{lookup_qualified_name, map_node, map_range, map_token, to_lsp_diag};
 
 #[cfg(target_arch = "wasm32")]
 use crate::wasm_prelude::*;
 
+use i_slint_compiler::expression_tree::Expression;
+use i_slint_compiler::lookup::LookupResult;
 use i_slint_compiler::object_tree::ElementRc;
 use i_slint_compiler::parser::{syntax_nodes, NodeOrToken, SyntaxKind, SyntaxNode, SyntaxToken};
 use i_slint_compiler::pathutils::clean_path;
 use i_slint_compiler::CompilerConfiguration;
 use i_slint_compiler::{
-    diagnostics::{BuildDiagnostics, SourceFileVersion},
-    langtype::Type,
+    diagnostics::{BuildDiagnostics, DiagnosticLevel, SourceFileVersion},
+    langtype::{ElementType, Type},
 };
 use i_slint_compiler::{typeloader::TypeLoader, typeregister::TypeRegister};
 use lsp_types::request::{
     CodeActionRequest, CodeLensRequest, ColorPresentationRequest, Completion, DocumentColor,
-    DocumentHighlightRequest, DocumentSymbolRequest, ExecuteCommand, GotoDefinition, HoverRequest,
-    PrepareRenameRequest, Rename, SemanticTokensFullRequest,
+    DocumentHighlightRequest, DocumentSymbolRequest, ExecuteCommand, FoldingRangeRequest,
+    Formatting, GotoDefinition, HoverRequest, InlayHintRequest, PrepareRenameRequest,
+    RangeFormatting, References, Rename, ResolveCompletionItem, SemanticTokensFullRequest,
+    SignatureHelpRequest, WorkspaceSymbolRequest,
 };
 use lsp_types::{
     ClientCapabilities, CodeActionOrCommand, CodeActionProviderCapability, CodeLens,
     CodeLensOptions, Color, ColorInformation, ColorPresentation, Command, CompletionOptions,
-    DocumentSymbol, DocumentSymbolResponse, Hover, InitializeParams, InitializeResult, OneOf,
-    Position, PrepareRenameResponse, PublishDiagnosticsParams, RenameOptions,
-    SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions, ServerCapabilities,
-    ServerInfo, TextDocumentSyncCapability, TextEdit, Url, WorkDoneProgressOptions, WorkspaceEdit,
+    DocumentSymbol, DocumentSymbolResponse, InitializeParams, InitializeResult, OneOf, Position,
+    PrepareRenameResponse, PublishDiagnosticsParams, RenameOptions, SemanticTokensFullOptions,
+    SemanticTokensLegend, SemanticTokensOptions, ServerCapabilities, ServerInfo,
+    TextDocumentSyncCapability, TextEdit, Url, WorkDoneProgressOptions, WorkspaceEdit,
 };
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::future::Future;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::rc::Rc;
 
@@ -50,6 +67,16 @@
 const REMOVE_BINDING_COMMAND: &str = "slint/removeBinding";
 const SHOW_PREVIEW_COMMAND: &str = "slint/showPreview";
 const SET_BINDING_COMMAND: &str = "slint/setBinding";
+const LAYOUT_TREE_COMMAND: &str = "slint/layoutTree";
+const FORMAT_WORKSPACE_COMMAND: &str = "slint/formatWorkspace";
+const SET_PREVIEW_CLOCK_COMMAND: &str = "slint/setPreviewClock";
+const SCAFFOLD_COMPONENT_COMMAND: &str = "slint/scaffoldComponent";
+const ELEMENT_AT_OFFSET_COMMAND: &str = "slint/elementAtOffset";
+const SCAFFOLD_REPEATER_COMMAND: &str = "slint/scaffoldRepeater";
+const INITIAL_PROPERTY_VALUES_COMMAND: &str = "slint/initialPropertyValues";
+const PREVIEW_WITH_PALETTE_COMMAND: &str = "slint/previewWithPalette";
+const QUERY_LIVE_PROPERTIES_COMMAND: &str = "slint/queryLiveProperties";
+const SET_LIVE_PROPERTY_COMMAND: &str = "slint/setLiveProperty";
 
 pub fn uri_to_file(uri: &lsp_types::Url) -> Option<PathBuf> {
     let Ok(path) = uri.to_file_path() else { return None };
@@ -64,6 +91,16 @@ fn command_list() -> Vec<String> {
         #[cfg(any(feature = "preview-builtin", feature = "preview-external"))]
         SHOW_PREVIEW_COMMAND.into(),
         SET_BINDING_COMMAND.into(),
+        LAYOUT_TREE_COMMAND.into(),
+        FORMAT_WORKSPACE_COMMAND.into(),
+        SET_PREVIEW_CLOCK_COMMAND.into(),
+        SCAFFOLD_COMPONENT_COMMAND.into(),
+        ELEMENT_AT_OFFSET_COMMAND.into(),
+        SCAFFOLD_REPEATER_COMMAND.into(),
+        INITIAL_PROPERTY_VALUES_COMMAND.into(),
+        PREVIEW_WITH_PALETTE_COMMAND.into(),
+        QUERY_LIVE_PROPERTIES_COMMAND.into(),
+        SET_LIVE_PROPERTY_COMMAND.into(),
     ]
 }
 
@@ -101,13 +138,95 @@ pub fn request_state(ctx: &std::rc::Rc<Context>) {
 pub struct DocumentCache {
     pub(crate) documents: TypeLoader,
     preview_config: PreviewConfig,
+    /// Glob patterns of files and directories the server should not look at
+    pub(crate) exclude_globs: Vec<String>,
+    pub(crate) completion_detail: completion::CompletionDetail,
+    /// The `slint.diagnosticSeverity` overrides: diagnostics whose message contains the text are
+    /// published with the given severity, or not at all if it is `None`
+    pub(crate) severity_overrides: Vec<(String, Option<lsp_types::DiagnosticSeverity>)>,
+    /// The text of the documents opened in the editor, which incremental changes apply to
+    pub(crate) open_document_texts: HashMap<Url, String>,
+    /// The directories of the workspace folders opened in the editor
+    pub(crate) workspace_folders: Vec<PathBuf>,
+    /// The syntax trees of the files of the workspace folders that are not loaded, as parsed
+    /// for `workspace/symbol`, with the modification time of the file when it was parsed
+    pub(crate) parsed_workspace_files:
+        RefCell<HashMap<PathBuf, (std::time::SystemTime, SyntaxNode)>>,
+    /// The include paths as configured, before the relative ones get looked up in the
+    /// workspace folders
+    include_paths: Vec<PathBuf>,
+    /// The files for which diagnostics were published, and that need to be cleared when their
+    /// errors are fixed
+    files_with_diagnostics: std::collections::HashSet<Url>,
 }
 
 impl DocumentCache {
     pub fn new(config: CompilerConfiguration) -> Self {
+        let include_paths = config.include_paths.clone();
         let documents =
             TypeLoader::new(TypeRegister::builtin(), config, &mut BuildDiagnostics::default());
-        Self { documents, preview_config: Default::default() }
+        Self {
+            documents,
+            preview_config: Default::default(),
+            exclude_globs: Vec::new(),
+            completion_detail: Default::default(),
+            severity_overrides: Vec::new(),
+            open_document_texts: HashMap::new(),
+            workspace_folders: Vec::new(),
+            parsed_workspace_files: Default::default(),
+            include_paths,
+            files_with_diagnostics: Default::default(),
+        }
+    }
+
+    /// Drops the empty diagnostics of the files that have none published, so that they are not
+    /// sent again for every file of the import graph, and keeps track of the files with
+    /// diagnostics, so that they get cleared once the errors are fixed.
+    fn track_published_diagnostics(
+        &mut self,
+        lsp_diags: &mut HashMap<Url, Vec<lsp_types::Diagnostic>>,
+    ) {
+        lsp_diags.retain(|uri, diagnostics| {
+            if diagnostics.is_empty() {
+                self.files_with_diagnostics.remove(uri)
+            } else {
+                self.files_with_diagnostics.insert(uri.clone());
+                true
+            }
+        });
+    }
+
+    pub fn set_workspace_folders(&mut self, workspace_folders: Vec<PathBuf>) {
+        self.workspace_folders = workspace_folders;
+        self.update_include_paths();
+    }
+
+    /// Sets the include paths of the compiler configuration. The relative include paths are
+    /// looked up in every workspace folder first, and then, like without workspace folders,
+    /// relative to the importing file.
+    fn update_include_paths(&mut self) {
+        let include_paths = self
+            .include_paths
+            .iter()
+            .flat_map(|path| {
+                self.workspace_folders
+                    .iter()
+                    .filter(|_| path.is_relative())
+                    .map(|folder| folder.join(path))
+                    .chain(std::iter::once(path.clone()))
+            })
+            .collect();
+        self.documents.compiler_config.include_paths = include_paths;
+    }
+
+    /// Returns true if the path or one of its parent directories matches one of the
+    /// `slint.excludeGlobs` patterns
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        !self.exclude_globs.is_empty()
+            && path.ancestors().any(|p| {
+                let p = p.to_string_lossy().replace('\\', "/");
+                self.exclude_globs.iter().any(|pattern| crate::util::glob_match(pattern, &p))
+            })
     }
 
     pub fn document_version(&self, target_uri: &lsp_types::Url) -> SourceFileVersion {
@@ -122,6 +241,8 @@ pub struct Context {
     pub server_notifier: crate::ServerNotifier,
     pub init_param: InitializeParams,
     pub preview: Rc<dyn PreviewApi>,
+    /// Also reports the files loaded by the `open_import_fallback`
+    pub progress: progress::ProgressReporter,
 }
 
 #[derive(Default)]
@@ -160,20 +281,32 @@ pub fn register<
     }
 }
 
+/// Clients that only pass the full text of the documents on, like the browser workers of the
+/// VS Code extension and of SlintPad, announce it with the `fullTextDocumentSync` experimental
+/// capability.
+fn text_document_sync_kind(client_cap: &ClientCapabilities) -> lsp_types::TextDocumentSyncKind {
+    if has_experimental_client_capability(client_cap, "fullTextDocumentSync") {
+        lsp_types::TextDocumentSyncKind::FULL
+    } else {
+        lsp_types::TextDocumentSyncKind::INCREMENTAL
+    }
+}
+
 pub fn server_initialize_result(client_cap: &ClientCapabilities) -> InitializeResult {
     InitializeResult {
         capabilities: ServerCapabilities {
             completion_provider: Some(CompletionOptions {
-                resolve_provider: None,
+                resolve_provider: Some(true),
                 trigger_characters: Some(vec![".".to_owned()]),
                 work_done_progress_options: WorkDoneProgressOptions::default(),
                 all_commit_characters: None,
                 completion_item: None,
             }),
             definition_provider: Some(OneOf::Left(true)),
-            text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                lsp_types::TextDocumentSyncKind::FULL,
-            )),
+            hover_provider: Some(true.into()),
+            text_document_sync: Some(TextDocumentSyncCapability::Kind(text_document_sync_kind(
+                client_cap,
+            ))),
             code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
             execute_command_provider: Some(lsp_types::ExecuteCommandOptions {
                 commands: command_list(),
@@ -194,6 +327,24 @@ pub fn server_initialize_result(client_cap: &ClientCapabilities) -> InitializeRe
                 .into(),
             ),
             document_highlight_provider: Some(OneOf::Left(true)),
+            inlay_hint_provider: Some(OneOf::Left(true)),
+            workspace: Some(lsp_types::WorkspaceServerCapabilities {
+                workspace_folders: Some(lsp_types::WorkspaceFoldersServerCapabilities {
+                    supported: Some(true),
+                    change_notifications: Some(OneOf::Left(true)),
+                }),
+                file_operations: None,
+            }),
+            references_provider: Some(OneOf::Left(true)),
+            workspace_symbol_provider: Some(OneOf::Left(true)),
+            folding_range_provider: Some(lsp_types::FoldingRangeProviderCapability::Simple(true)),
+            signature_help_provider: Some(lsp_types::SignatureHelpOptions {
+                trigger_characters: Some(vec!["(".into(), ",".into()]),
+                retrigger_characters: None,
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            }),
+            document_formatting_provider: Some(OneOf::Left(true)),
+            document_range_formatting_provider: Some(OneOf::Left(true)),
             rename_provider: Some(
                 if client_cap
                     .text_document
@@ -233,6 +384,7 @@ pub fn register_request_handlers(rh: &mut RequestHandler) {
     });
     rh.register::<Completion, _>(|params, ctx| async move {
         let document_cache = &mut ctx.document_cache.borrow_mut();
+        let completion_detail = document_cache.completion_detail;
 
         let result = token_descr(
             document_cache,
@@ -250,22 +402,32 @@ pub fn register_request_handlers(rh: &mut RequestHandler) {
                     .as_ref()
                     .and_then(|t| t.completion.as_ref()),
             )
-            .map(Into::into)
+        })
+        .map(|mut items| {
+            if completion_detail == completion::CompletionDetail::Minimal {
+                completion::defer_details(&mut items, &params.text_document_position);
+            }
+            items.into()
         });
         Ok(result)
     });
-    rh.register::<HoverRequest, _>(|_params, _ctx| async move {
-        /*let result =
-            token_descr(document_cache, params.text_document_position_params).map(|x| Hover {
-                contents: lsp_types::HoverContents::Scalar(MarkedString::from_language_code(
-                    "text".into(),
-                    format!("{:?}", x.token),
-                )),
-                range: None,
-            });
-        let resp = Response::new_ok(id, result);
-        connection.sender.send(Message::Response(resp))?;*/
-        Ok(None::<Hover>)
+    rh.register::<ResolveCompletionItem, _>(|params, ctx| async move {
+        let document_cache = &mut ctx.document_cache.borrow_mut();
+        Ok(completion::resolve_completion_item(
+            document_cache,
+            params,
+            ctx.init_param.capabilities.text_document.as_ref().and_then(|t| t.completion.as_ref()),
+        ))
+    });
+    rh.register::<HoverRequest, _>(|params, ctx| async move {
+        let document_cache = &mut ctx.document_cache.borrow_mut();
+        let result = token_descr(
+            document_cache,
+            &params.text_document_position_params.text_document.uri,
+            &params.text_document_position_params.position,
+        )
+        .and_then(|(token, _)| hover::get_tooltip(document_cache, token));
+        Ok(result)
     });
     rh.register::<CodeActionRequest, _>(|params, ctx| async move {
         let document_cache = &mut ctx.document_cache.borrow_mut();
@@ -291,6 +453,40 @@ pub fn register_request_handlers(rh: &mut RequestHandler) {
         if params.command.as_str() == REMOVE_BINDING_COMMAND {
             return Ok(Some(remove_binding_command(&params.arguments, &ctx).await?));
         }
+        if params.command.as_str() == LAYOUT_TREE_COMMAND {
+            return Ok(Some(serde_json::to_value(ctx.preview.layout_tree()?)?));
+        }
+        if params.command.as_str() == FORMAT_WORKSPACE_COMMAND {
+            return Ok(Some(format_workspace_command(&params.arguments, &ctx).await?));
+        }
+        if params.command.as_str() == SET_PREVIEW_CLOCK_COMMAND {
+            set_preview_clock_command(&params.arguments, &ctx)?;
+            return Ok(None::<serde_json::Value>);
+        }
+        if params.command.as_str() == SCAFFOLD_COMPONENT_COMMAND {
+            return Ok(Some(scaffold_component_command(&params.arguments, &ctx)?));
+        }
+        if params.command.as_str() == ELEMENT_AT_OFFSET_COMMAND {
+            return Ok(Some(element_at_offset_command(&params.arguments, &ctx)?));
+        }
+        if params.command.as_str() == SCAFFOLD_REPEATER_COMMAND {
+            return Ok(Some(scaffold_repeater_command(&params.arguments, &ctx).await?));
+        }
+        if params.command.as_str() == INITIAL_PROPERTY_VALUES_COMMAND {
+            return Ok(Some(initial_property_values_command(&params.arguments, &ctx)?));
+        }
+        if params.command.as_str() == PREVIEW_WITH_PALETTE_COMMAND {
+            preview_with_palette_command(&params.arguments, &ctx)?;
+            return Ok(None::<serde_json::Value>);
+        }
+        if params.command.as_str() == QUERY_LIVE_PROPERTIES_COMMAND {
+            ctx.preview.query_live_properties()?;
+            return Ok(None::<serde_json::Value>);
+        }
+        if params.command.as_str() == SET_LIVE_PROPERTY_COMMAND {
+            set_live_property_command(&params.arguments, &ctx)?;
+            return Ok(None::<serde_json::Value>);
+        }
         Ok(None::<serde_json::Value>)
     });
     rh.register::<DocumentColor, _>(|params, ctx| async move {
@@ -305,17 +501,17 @@ pub fn register_request_handlers(rh: &mut RequestHandler) {
         let color_literal = if requested_color.alpha < 1. {
             format!(
                 "#{:0>2x}{:0>2x}{:0>2x}{:0>2x}",
-                (requested_color.red * 255.) as u8,
-                (requested_color.green * 255.) as u8,
-                (requested_color.blue * 255.) as u8,
-                (requested_color.alpha * 255.) as u8
+                (requested_color.red * 255.).round() as u8,
+                (requested_color.green * 255.).round() as u8,
+                (requested_color.blue * 255.).round() as u8,
+                (requested_color.alpha * 255.).round() as u8
             )
         } else {
             format!(
                 "#{:0>2x}{:0>2x}{:0>2x}",
-                (requested_color.red * 255.) as u8,
-                (requested_color.green * 255.) as u8,
-                (requested_color.blue * 255.) as u8,
+                (requested_color.red * 255.).round() as u8,
+                (requested_color.green * 255.).round() as u8,
+                (requested_color.blue * 255.).round() as u8,
             )
         };
 
@@ -325,6 +521,27 @@ pub fn register_request_handlers(rh: &mut RequestHandler) {
         let document_cache = &mut ctx.document_cache.borrow_mut();
         Ok(get_document_symbols(document_cache, &params.text_document))
     });
+    rh.register::<FoldingRangeRequest, _>(|params, ctx| async move {
+        let document_cache = &mut ctx.document_cache.borrow_mut();
+        Ok(folding_ranges::get_folding_ranges(document_cache, &params.text_document))
+    });
+    rh.register::<SignatureHelpRequest, _>(|params, ctx| async move {
+        let document_cache = &mut ctx.document_cache.borrow_mut();
+        Ok(token_descr(
+            document_cache,
+            &params.text_document_position_params.text_document.uri,
+            &params.text_document_position_params.position,
+        )
+        .and_then(|(token, offset)| {
+            signature_help::get_signature_help(document_cache, token, offset)
+        }))
+    });
+    rh.register::<WorkspaceSymbolRequest, _>(|params, ctx| async move {
+        let document_cache = &mut ctx.document_cache.borrow_mut();
+        Ok(Some(lsp_types::WorkspaceSymbolResponse::Nested(
+            workspace_symbols::get_workspace_symbols(document_cache, &params.query),
+        )))
+    });
     rh.register::<CodeLensRequest, _>(|params, ctx| async move {
         let document_cache = &mut ctx.document_cache.borrow_mut();
         Ok(get_code_lenses(document_cache, &params.text_document))
@@ -333,6 +550,22 @@ pub fn register_request_handlers(rh: &mut RequestHandler) {
         let document_cache = &mut ctx.document_cache.borrow_mut();
         Ok(semantic_tokens::get_semantic_tokens(document_cache, &params.text_document))
     });
+    rh.register::<Formatting, _>(|params, ctx| async move {
+        let document_cache = &mut ctx.document_cache.borrow_mut();
+        Ok(formatting::format_document(document_cache, &params.text_document, None))
+    });
+    rh.register::<RangeFormatting, _>(|params, ctx| async move {
+        let document_cache = &mut ctx.document_cache.borrow_mut();
+        Ok(formatting::format_document(document_cache, &params.text_document, Some(params.range)))
+    });
+    rh.register::<crate::lsp_ext::ComponentListRequest, _>(|params, ctx| async move {
+        let document_cache = &mut ctx.document_cache.borrow_mut();
+        Ok(get_component_list(document_cache, &params).unwrap_or_default())
+    });
+    rh.register::<InlayHintRequest, _>(|params, ctx| async move {
+        let document_cache = &mut ctx.document_cache.borrow_mut();
+        Ok(inlay_hints::get_inlay_hints(document_cache, &params.text_document, &params.range))
+    });
     rh.register::<DocumentHighlightRequest, _>(|_params, ctx| async move {
         let document_cache = &mut ctx.document_cache.borrow_mut();
         let uri = _params.text_document_position_params.text_document.uri;
@@ -365,6 +598,18 @@ pub fn register_request_handlers(rh: &mut RequestHandler) {
         ctx.preview.highlight(None, 0)?;
         Ok(None)
     });
+    rh.register::<References, _>(|params, ctx| async move {
+        let mut document_cache = ctx.document_cache.borrow_mut();
+        let uri = params.text_document_position.text_document.uri;
+        Ok(token_descr(&mut document_cache, &uri, &params.text_document_position.position)
+            .and_then(|(tk, _)| {
+                references::find_all_references(
+                    &mut document_cache,
+                    &tk,
+                    params.context.include_declaration,
+                )
+            }))
+    });
     rh.register::<Rename, _>(|params, ctx| async move {
         let mut document_cache = ctx.document_cache.borrow_mut();
         let uri = params.text_document_position.text_document.uri;
@@ -385,14 +630,19 @@ pub fn register_request_handlers(rh: &mut RequestHandler) {
                     ..Default::default()
                 }));
             }
+            return rename::rename_declaration(&mut document_cache, &tk, &params.new_name)
+                .map(Some)
+                .map_err(Into::into);
         };
-        Err("This symbol cannot be renamed. (Only element id can be renamed at the moment)".into())
+        Err("This symbol cannot be renamed.".into())
     });
     rh.register::<PrepareRenameRequest, _>(|params, ctx| async move {
         let mut document_cache = ctx.document_cache.borrow_mut();
         let uri = params.text_document.uri;
         if let Some((tk, _off)) = token_descr(&mut document_cache, &uri, &params.position) {
-            if find_element_id_for_highlight(&tk, &tk.parent()).is_some() {
+            if find_element_id_for_highlight(&tk, &tk.parent()).is_some()
+                || rename::find_declaration(&mut document_cache, &tk).is_some()
+            {
                 return Ok(map_token(&tk).map(PrepareRenameResponse::Range));
             }
         };
@@ -460,6 +710,254 @@ pub fn query_properties_command(
     }
 }
 
+/// Wraps the element at the position given in the second parameter in a repeater over the model
+/// property named in the third parameter. The edit is only applied if the optional fourth
+/// parameter (`dry_run`) is false; it is returned in any case.
+pub async fn scaffold_repeater_command(
+    params: &[serde_json::Value],
+    ctx: &Rc<Context>,
+) -> Result<serde_json::Value> {
+    let text_document = serde_json::from_value::<lsp_types::TextDocumentIdentifier>(
+        params.first().ok_or("No text document provided")?.clone(),
+    )?;
+    let position = serde_json::from_value::<lsp_types::Position>(
+        params.get(1).ok_or("No position provided")?.clone(),
+    )?;
+    let model_name =
+        serde_json::from_value::<String>(params.get(2).ok_or("No model name provided")?.clone())?;
+    let dry_run = params
+        .get(3)
+        .map(|p| serde_json::from_value::<bool>(p.clone()))
+        .transpose()?
+        .unwrap_or(true);
+
+    let edit = scaffold::scaffold_repeater(
+        &mut ctx.document_cache.borrow_mut(),
+        &text_document.uri,
+        &position,
+        &model_name,
+    )?;
+
+    if !dry_run {
+        apply_workspace_edit(ctx, "repeat element", edit.clone()).await?;
+    }
+
+    Ok(serde_json::to_value(edit)?)
+}
+
+/// Returns the initial value of the public properties of the component named in the second
+/// parameter, declared in the document given in the first parameter.
+pub fn initial_property_values_command(
+    params: &[serde_json::Value],
+    ctx: &Rc<Context>,
+) -> Result<serde_json::Value> {
+    let document_cache = &ctx.document_cache.borrow();
+
+    let text_document_uri = serde_json::from_value::<lsp_types::TextDocumentIdentifier>(
+        params.first().ok_or("No text document provided")?.clone(),
+    )?
+    .uri;
+    let component_name = serde_json::from_value::<String>(
+        params.get(1).ok_or("No component name provided")?.clone(),
+    )?;
+
+    let path = uri_to_file(&text_document_uri).ok_or("Failed to convert the uri to a path")?;
+    let doc = document_cache
+        .documents
+        .get_document(&path)
+        .ok_or_else(|| format!("Document with uri {text_document_uri} not found in cache"))?;
+    let component_name = i_slint_compiler::parser::normalize_identifier(&component_name);
+    let component = doc
+        .inner_components
+        .iter()
+        .find(|c| c.id == component_name)
+        .ok_or_else(|| format!("No component named {component_name} found"))?;
+
+    Ok(serde_json::to_value(properties::initial_property_values(component))?)
+}
+
+/// Returns the type, id and range of the innermost element containing the byte offset given in
+/// the second parameter, in the document given in the first parameter. Returns null if there
+/// is no element at that offset.
+pub fn element_at_offset_command(
+    params: &[serde_json::Value],
+    ctx: &Rc<Context>,
+) -> Result<serde_json::Value> {
+    let document_cache = &mut ctx.document_cache.borrow_mut();
+
+    let text_document_uri = serde_json::from_value::<lsp_types::TextDocumentIdentifier>(
+        params.first().ok_or("No text document provided")?.clone(),
+    )?
+    .uri;
+    let offset = serde_json::from_value::<u32>(params.get(1).ok_or("No offset provided")?.clone())?;
+
+    let element = element_at_offset(document_cache, &text_document_uri, offset);
+    Ok(serde_json::to_value(element.map(|e| properties::get_element_information(&e)))?)
+}
+
+/// Sets the time of the preview to the value of `animation-tick()` given in the first
+/// parameter, advancing at the speed given in the optional second parameter. Resets the
+/// preview to the real time if the first parameter is null or missing.
+pub fn set_preview_clock_command(params: &[serde_json::Value], ctx: &Rc<Context>) -> Result<()> {
+    let time =
+        params.first().map(|p| serde_json::from_value::<Option<u64>>(p.clone())).transpose()?;
+    let speed = params.get(1).map(|p| serde_json::from_value::<f32>(p.clone())).transpose()?;
+    let clock = time.flatten().map(|time| PreviewClock { time, speed: speed.unwrap_or(1.) });
+    ctx.preview.set_preview_clock(clock)
+}
+
+/// Returns the edit creating a new file with a component showing the fields of the struct
+/// named in the second parameter, exported from the document in the first parameter.
+pub fn scaffold_component_command(
+    params: &[serde_json::Value],
+    ctx: &Rc<Context>,
+) -> Result<serde_json::Value> {
+    let text_document = serde_json::from_value::<lsp_types::TextDocumentIdentifier>(
+        params.first().ok_or("No text document provided")?.clone(),
+    )?;
+    let struct_name =
+        serde_json::from_value::<String>(params.get(1).ok_or("No struct name provided")?.clone())?;
+
+    let edit = scaffold::scaffold_component(
+        &ctx.document_cache.borrow(),
+        &text_document.uri,
+        &struct_name,
+    )?;
+    Ok(serde_json::to_value(edit)?)
+}
+
+/// Overrides properties of a global in the preview, to check how the previewed component looks
+/// with another palette. The first parameter is the name of the global, the second an object
+/// mapping property names to their new values. Colors are given as `#rrggbb` strings.
+/// The overrides are dropped when the preview reloads.
+pub fn preview_with_palette_command(params: &[serde_json::Value], ctx: &Rc<Context>) -> Result<()> {
+    let global_name =
+        serde_json::from_value::<String>(params.first().ok_or("No global name provided")?.clone())?;
+    let values = serde_json::from_value::<serde_json::Map<String, serde_json::Value>>(
+        params.get(1).ok_or("No property values provided")?.clone(),
+    )?;
+    let component = ctx.preview.current_component().ok_or("No component is previewed")?;
+    let overrides =
+        palette_overrides(&ctx.document_cache.borrow(), &component.path, &global_name, &values)?;
+    ctx.preview.set_palette_overrides(global_name, overrides)
+}
+
+/// Sets the property named in the first parameter of the previewed component to the value of the
+/// second parameter, without recompiling. Colors are given as `#rrggbb` strings. The preview
+/// answers with a `slint/previewLiveProperties` notification.
+pub fn set_live_property_command(params: &[serde_json::Value], ctx: &Rc<Context>) -> Result<()> {
+    let name = serde_json::from_value::<String>(
+        params.first().ok_or("No property name provided")?.clone(),
+    )?;
+    let value = params.get(1).ok_or("No property value provided")?.clone();
+    ctx.preview.set_live_property(i_slint_compiler::parser::normalize_identifier(&name), value)
+}
+
+/// Checks the `values` against the properties of the global `global_name` as seen from the
+/// document at `path`, and converts them into overrides for the preview.
+fn palette_overrides(
+    document_cache: &DocumentCache,
+    path: &Path,
+    global_name: &str,
+    values: &serde_json::Map<String, serde_json::Value>,
+) -> Result<Vec<(String, PropertyOverride)>> {
+    let doc = document_cache
+        .documents
+        .get_document(path)
+        .ok_or_else(|| format!("Document {} not found in cache", path.display()))?;
+    let global = match doc.local_registry.lookup_element(global_name) {
+        Ok(ElementType::Component(c)) if c.is_global() => c,
+        _ => return Err(format!("No global named {global_name} found").into()),
+    };
+    let root_element = global.root_element.borrow();
+
+    values
+        .iter()
+        .map(|(name, value)| {
+            let name = i_slint_compiler::parser::normalize_identifier(name);
+            let ty = root_element.lookup_property(&name).property_type;
+            let invalid = || format!("Invalid value {value} for the property {name} of type {ty}");
+            let property_override = match (&ty, value) {
+                (Type::Invalid, _) => {
+                    return Err(format!("{global_name} has no property named {name}").into())
+                }
+                (Type::Color | Type::Brush, serde_json::Value::String(color)) => {
+                    PropertyOverride::Color(
+                        i_slint_compiler::literals::parse_color_literal(color)
+                            .ok_or_else(invalid)?,
+                    )
+                }
+                (Type::Bool, serde_json::Value::Bool(b)) => PropertyOverride::Bool(*b),
+                (Type::String, serde_json::Value::String(s)) => PropertyOverride::String(s.clone()),
+                (
+                    Type::Float32
+                    | Type::Int32
+                    | Type::Duration
+                    | Type::PhysicalLength
+                    | Type::LogicalLength
+                    | Type::Angle
+                    | Type::Percent,
+                    serde_json::Value::Number(n),
+                ) => PropertyOverride::Number(n.as_f64().ok_or_else(invalid)?),
+                _ => return Err(invalid().into()),
+            };
+            Ok((name, property_override))
+        })
+        .collect()
+}
+
+/// Returns the directories of the workspace folders, or the root directory if the client
+/// does not support workspace folders
+pub fn workspace_roots(init_param: &InitializeParams) -> Vec<PathBuf> {
+    match &init_param.workspace_folders {
+        Some(folders) => folders.iter().filter_map(|f| uri_to_file(&f.uri)).collect(),
+        None => init_param.root_uri.as_ref().and_then(uri_to_file).into_iter().collect(),
+    }
+}
+
+pub async fn format_workspace_command(
+    params: &[serde_json::Value],
+    ctx: &Rc<Context>,
+) -> Result<serde_json::Value> {
+    let dry_run = {
+        if let Some(p) = params.first() {
+            serde_json::from_value::<bool>(p.clone())
+        } else {
+            Ok(true)
+        }
+    }?;
+
+    let document_cache = ctx.document_cache.borrow();
+    let edit = formatting::format_workspace(&document_cache, &document_cache.workspace_folders);
+    drop(document_cache);
+    let mut changed_files =
+        edit.changes.as_ref().map(|c| c.keys().cloned().collect::<Vec<_>>()).unwrap_or_default();
+    changed_files.sort();
+
+    if !dry_run && !changed_files.is_empty() {
+        apply_workspace_edit(ctx, "format workspace", edit.clone()).await?;
+    }
+
+    Ok(serde_json::to_value(formatting::FormatWorkspaceResponse { changed_files, edit })?)
+}
+
+/// Asks the client to apply the `edit`, failing if it wasn't applied
+async fn apply_workspace_edit(ctx: &Rc<Context>, label: &str, edit: WorkspaceEdit) -> Result<()> {
+    let response = ctx
+        .server_notifier
+        .send_request::<lsp_types::request::ApplyWorkspaceEdit>(
+            lsp_types::ApplyWorkspaceEditParams { label: Some(label.into()), edit },
+        )?
+        .await?;
+    if !response.applied {
+        return Err(response
+            .failure_reason
+            .unwrap_or("Operation failed, no specific reason given".into())
+            .into());
+    }
+    Ok(())
+}
+
 pub async fn set_binding_command(
     params: &[serde_json::Value],
     ctx: &Rc<Context>,
@@ -650,16 +1148,24 @@ pub(crate) async fn reload_document_impl(
     if let Some(ctx) = ctx {
         ctx.preview.set_contents(&path, &content);
     }
+    compile_document(path, content, version, document_cache).await
+}
+
+/// Loads the slint code in `content` as the document of `path` and returns the diagnostics
+/// of all the files involved
+async fn compile_document(
+    path: PathBuf,
+    content: String,
+    version: Option<i32>,
+    document_cache: &mut DocumentCache,
+) -> HashMap<Url, Vec<lsp_types::Diagnostic>> {
     let mut diag = BuildDiagnostics::default();
     document_cache.documents.load_file(&path, version, &path, content, false, &mut diag).await;
 
     // Always provide diagnostics for all files. Empty diagnostics clear any previous ones.
     let mut lsp_diags: HashMap<Url, Vec<lsp_types::Diagnostic>> = core::iter::once(&path)
         .chain(diag.all_loaded_files.iter())
-        .map(|path| {
-            let uri = Url::from_file_path(path).unwrap();
-            (uri, Default::default())
-        })
+        .filter_map(|path| Some((Url::from_file_path(path).ok()?, Default::default())))
         .collect();
 
     for d in diag.into_iter() {
@@ -671,6 +1177,11 @@ pub(crate) async fn reload_document_impl(
         lsp_diags.entry(uri).or_default().push(to_lsp_diag(&d));
     }
 
+    if let Ok(uri) = Url::from_file_path(&path) {
+        let unused = unused_declarations::unused_declaration_diagnostics(document_cache, &path);
+        lsp_diags.entry(uri).or_default().extend(unused);
+    }
+
     lsp_diags
 }
 
@@ -681,8 +1192,72 @@ pub async fn reload_document(
     version: Option<i32>,
     document_cache: &mut DocumentCache,
 ) -> Result<()> {
+    document_cache.open_document_texts.insert(uri.clone(), content.clone());
     let lsp_diags = reload_document_impl(Some(ctx), content, uri, version, document_cache).await;
+    publish_diagnostics(ctx, document_cache, lsp_diags)
+}
 
+/// Applies the changes of a `textDocument/didChange` notification, in order, to the text of
+/// the document and reloads it
+pub async fn change_document(
+    ctx: &Rc<Context>,
+    params: lsp_types::DidChangeTextDocumentParams,
+    document_cache: &mut DocumentCache,
+) -> Result<()> {
+    let uri = params.text_document.uri;
+    let mut text = document_cache
+        .open_document_texts
+        .get(&uri)
+        .cloned()
+        .ok_or_else(|| format!("Received changes for {uri}, which is not open"))?;
+    for change in params.content_changes {
+        apply_content_change(&mut text, change);
+    }
+    reload_document(ctx, text, uri, Some(params.text_document.version), document_cache).await
+}
+
+/// Applies one change to `text`. A change without a range replaces the whole text.
+fn apply_content_change(text: &mut String, change: lsp_types::TextDocumentContentChangeEvent) {
+    let Some(range) = change.range else {
+        *text = change.text;
+        return;
+    };
+    let start = position_to_offset(text, range.start);
+    let end = position_to_offset(text, range.end).max(start);
+    text.replace_range(start..end, &change.text);
+}
+
+/// Returns the byte offset of `position` in `text`, clamped to the end of the line. The server
+/// doesn't negotiate the position encoding, so the character of the position counts UTF-16 code
+/// units, as the LSP specification requires by default.
+fn position_to_offset(text: &str, position: Position) -> usize {
+    let line_start = match position.line {
+        0 => 0,
+        line => text.match_indices('\n').nth(line as usize - 1).map_or(text.len(), |(i, _)| i + 1),
+    };
+    let line_end = text[line_start..].find('\n').map_or(text.len(), |i| line_start + i);
+    let mut utf16_offset = 0;
+    text[line_start..line_end]
+        .char_indices()
+        .find(|(_, c)| {
+            let found = utf16_offset >= position.character as usize;
+            utf16_offset += c.len_utf16();
+            found
+        })
+        .map_or(line_end, |(i, _)| line_start + i)
+}
+
+/// Publishes the diagnostics of every file involved in a compilation, including the imported
+/// files that are not open in the editor
+fn publish_diagnostics(
+    ctx: &Context,
+    document_cache: &mut DocumentCache,
+    mut lsp_diags: HashMap<Url, Vec<lsp_types::Diagnostic>>,
+) -> Result<()> {
+    for diagnostics in lsp_diags.values_mut() {
+        apply_severity_overrides(&document_cache.severity_overrides, diagnostics);
+    }
+    document_cache.track_published_diagnostics(&mut lsp_diags);
     for (uri, diagnostics) in lsp_diags {
         ctx.server_notifier.send_notification(
             "textDocument/publishDiagnostics".into(),
@@ -692,6 +1267,40 @@ pub async fn reload_document(
     Ok(())
 }
 
+/// Changes the severity of the diagnostics matching one of the overrides, or removes them.
+///
+/// The compiler diagnostics have no codes, so the overrides match on a part of the message.
+/// This only changes what the editor shows, not what the compiler reports.
+fn apply_severity_overrides(
+    overrides: &[(String, Option<lsp_types::DiagnosticSeverity>)],
+    diagnostics: &mut Vec<lsp_types::Diagnostic>,
+) {
+    if overrides.is_empty() {
+        return;
+    }
+    diagnostics.retain_mut(|d| {
+        match overrides.iter().find(|(pattern, _)| d.message.contains(pattern.as_str())) {
+            Some((_, None)) => false,
+            Some((_, severity)) => {
+                d.severity = *severity;
+                true
+            }
+            None => true,
+        }
+    });
+}
+
+fn parse_severity(severity: &str) -> Option<Option<lsp_types::DiagnosticSeverity>> {
+    match severity {
+        "error" => Some(Some(lsp_types::DiagnosticSeverity::ERROR)),
+        "warning" => Some(Some(lsp_types::DiagnosticSeverity::WARNING)),
+        "information" => Some(Some(lsp_types::DiagnosticSeverity::INFORMATION)),
+        "hint" => Some(Some(lsp_types::DiagnosticSeverity::HINT)),
+        "off" => Some(None),
+        _ => None,
+    }
+}
+
 fn get_document_and_offset<'a>(
     document_cache: &'a mut DocumentCache,
     text_document_uri: &'a Url,
@@ -713,7 +1322,18 @@ pub fn element_at_position(
     text_document_uri: &Url,
     pos: &Position,
 ) -> Option<i_slint_compiler::object_tree::ElementRc> {
-    let (doc, offset) = get_document_and_offset(document_cache, text_document_uri, pos)?;
+    let (_, offset) = get_document_and_offset(document_cache, text_document_uri, pos)?;
+    element_at_offset(document_cache, text_document_uri, offset)
+}
+
+/// Returns the innermost element containing the byte `offset` of the document
+pub fn element_at_offset(
+    document_cache: &mut DocumentCache,
+    text_document_uri: &Url,
+    offset: u32,
+) -> Option<i_slint_compiler::object_tree::ElementRc> {
+    let path = uri_to_file(text_document_uri)?;
+    let doc = document_cache.documents.get_document(&path)?;
 
     for component in &doc.inner_components {
         let mut element = component.root_element.clone();
@@ -813,6 +1433,74 @@ fn get_code_actions(
         }
     }
 
+    let can_create_files = client_capabilities
+        .workspace
+        .as_ref()
+        .and_then(|w| w.workspace_edit.as_ref())
+        .and_then(|e| e.resource_operations.as_ref())
+        .map_or(false, |ops| ops.contains(&lsp_types::ResourceOperationKind::Create));
+    if let Some(component) = component.as_ref().filter(|_| can_create_files) {
+        if let Some(edit) = move_component::move_component_to_file(document_cache, component) {
+            result.push(CodeActionOrCommand::CodeAction(lsp_types::CodeAction {
+                title: "Move to its own file".into(),
+                kind: Some(lsp_types::CodeActionKind::REFACTOR),
+                edit: Some(edit),
+                ..Default::default()
+            }));
+        }
+    }
+
+    if let Some(qualified_name) = syntax_nodes::QualifiedName::new(node.clone())
+        .filter(|n| n.parent().map_or(false, |p| p.kind() == SyntaxKind::Element))
+    {
+        let type_name =
+            i_slint_compiler::object_tree::QualifiedTypeName::from_node(qualified_name).to_string();
+        let is_unknown = document_cache
+            .documents
+            .get_document(token.source_file.path())
+            .map_or(false, |doc| doc.local_registry.lookup_element(&type_name).is_err());
+        if is_unknown {
+            let components =
+                completion::importable_components(&token, document_cache, Default::default());
+            for component in components.unwrap_or_default() {
+                if component.name != type_name {
+                    continue;
+                }
+                result.push(CodeActionOrCommand::CodeAction(lsp_types::CodeAction {
+                    title: format!("Import {} from \"{}\"", component.name, component.file),
+                    kind: Some(lsp_types::CodeActionKind::QUICKFIX),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(
+                            std::iter::once((uri.clone(), vec![component.import_edit])).collect(),
+                        ),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }));
+            }
+        }
+    }
+
+    for (role, edit) in palette::palette_role_edits(document_cache, &token).unwrap_or_default() {
+        result.push(CodeActionOrCommand::CodeAction(lsp_types::CodeAction {
+            title: format!("Replace with `Palette.{role}`"),
+            kind: Some(lsp_types::CodeActionKind::QUICKFIX),
+            edit: Some(edit),
+            ..Default::default()
+        }));
+    }
+
+    if let Some((title, edit)) =
+        unused_declarations::remove_unused_declaration(document_cache, &token)
+    {
+        result.push(CodeActionOrCommand::CodeAction(lsp_types::CodeAction {
+            title,
+            kind: Some(lsp_types::CodeActionKind::QUICKFIX),
+            edit: Some(edit),
+            ..Default::default()
+        }));
+    }
+
     if token.kind() == SyntaxKind::StringLiteral && node.kind() == SyntaxKind::Expression {
         let r = map_range(&token.source_file, node.text_range());
         let edits = vec![
@@ -1003,9 +1691,37 @@ fn get_document_color(
         }
         token = match token.next_token() {
             Some(token) => token,
-            None => break Some(result),
+            None => break,
         }
     }
+
+    // Named colors, such as `Colors.red` or `red` where a color is expected
+    for node in root_node.descendants() {
+        if node.kind() != SyntaxKind::QualifiedName
+            || node.parent().map_or(true, |p| p.kind() != SyntaxKind::Expression)
+        {
+            continue;
+        }
+        let node = SyntaxNode { node, source_file: root_node.source_file.clone() };
+        let Some(LookupResult::Expression {
+            expression: Expression::Cast { from, to: Type::Color },
+            ..
+        }) = lookup_qualified_name(document_cache, &node)
+        else {
+            continue;
+        };
+        let (Expression::NumberLiteral(col, _), Some(range)) = (*from, map_node(&node)) else {
+            continue;
+        };
+        let col = col as u32;
+        let shift = |s: u32| -> f32 { ((col >> s) & 0xff) as f32 / 255. };
+        result.push(ColorInformation {
+            range,
+            color: Color { alpha: shift(24), red: shift(16), green: shift(8), blue: shift(0) },
+        });
+    }
+    result.sort_by(|a, b| a.range.start.cmp(&b.range.start));
+    Some(result)
 }
 
 fn get_document_symbols(
@@ -1124,6 +1840,30 @@ fn get_code_lenses(
     }
 }
 
+/// Returns the components of the document that can be previewed, in the order of the document
+fn get_component_list(
+    document_cache: &mut DocumentCache,
+    text_document: &lsp_types::TextDocumentIdentifier,
+) -> Option<Vec<crate::lsp_ext::ComponentListItem>> {
+    let filepath = uri_to_file(&text_document.uri)?;
+    let doc = document_cache.documents.get_document(&filepath)?;
+
+    Some(
+        doc.inner_components
+            .iter()
+            .filter(|c| !c.is_global())
+            .map(|c| crate::lsp_ext::ComponentListItem {
+                name: c.id.clone(),
+                exported: doc
+                    .exports
+                    .iter()
+                    .any(|(_, e)| e.as_ref().left().map_or(false, |e| Rc::ptr_eq(e, c))),
+                is_default: Rc::ptr_eq(c, &doc.root_component),
+            })
+            .collect(),
+    )
+}
+
 /// If the token is matching a Element ID, return the list of all element id in the same component
 fn find_element_id_for_highlight(
     token: &SyntaxToken,
@@ -1190,37 +1930,52 @@ fn recurse(
     None
 }
 
+/// Applies the settings from the `initializationOptions` and from the workspace configuration
+///
+/// The `initializationOptions` use the same keys as the `slint` section of the workspace
+/// configuration, which takes precedence. Open documents are recompiled if the settings
+/// affecting the compilation changed.
 pub async fn load_configuration(ctx: &Context) -> Result<()> {
-    if !ctx
-        .init_param
-        .capabilities
-        .workspace
-        .as_ref()
-        .and_then(|w| w.configuration)
-        .unwrap_or(false)
+    let mut r: Vec<serde_json::Value> =
+        ctx.init_param.initialization_options.iter().cloned().collect();
+    if ctx.init_param.capabilities.workspace.as_ref().and_then(|w| w.configuration).unwrap_or(false)
     {
+        r.extend(
+            ctx.server_notifier
+                .send_request::<lsp_types::request::WorkspaceConfiguration>(
+                    lsp_types::ConfigurationParams {
+                        items: vec![lsp_types::ConfigurationItem {
+                            scope_uri: None,
+                            section: Some("slint".into()),
+                        }],
+                    },
+                )?
+                .await?,
+        );
+    }
+    if r.is_empty() {
         return Ok(());
     }
 
-    let r = ctx
-        .server_notifier
-        .send_request::<lsp_types::request::WorkspaceConfiguration>(
-            lsp_types::ConfigurationParams {
-                items: vec![lsp_types::ConfigurationItem {
-                    scope_uri: None,
-                    section: Some("slint".into()),
-                }],
-            },
-        )?
-        .await?;
+    // Loading the widgets and their dependencies may take a while on the first run.
+    // Not being able to report the progress is no reason to not load the configuration.
+    let _ = ctx.progress.begin(&ctx.server_notifier, &ctx.init_param, "Loading workspace").await;
+    let result = apply_configuration(ctx, r).await;
+    ctx.progress.end();
+    result
+}
 
+/// Applies the configuration `values` to the document cache and the preview.
+async fn apply_configuration(ctx: &Context, values: Vec<serde_json::Value>) -> Result<()> {
     let document_cache = &mut ctx.document_cache.borrow_mut();
+    let previous_config = compilation_settings(&document_cache.documents.compiler_config);
     let mut hide_ui = None;
-    for v in r {
+    let mut font_dirs = Vec::new();
+    for v in values {
         if let Some(o) = v.as_object() {
             if let Some(ip) = o.get("includePaths").and_then(|v| v.as_array()) {
                 if !ip.is_empty() {
-                    document_cache.documents.compiler_config.include_paths =
+                    document_cache.include_paths =
                         ip.iter().filter_map(|x| x.as_str()).map(PathBuf::from).collect();
                 }
             }
@@ -1239,10 +1994,37 @@ pub async fn load_configuration(ctx: &Context) -> Result<()> {
                     document_cache.documents.compiler_config.style = Some(style.into());
                 }
             }
+            if let Some(cd) = o
+                .get("completionDetail")
+                .and_then(|v| completion::CompletionDetail::try_from(v.as_str()?).ok())
+            {
+                document_cache.completion_detail = cd;
+            }
+            if let Some(eg) = o.get("excludeGlobs").and_then(|v| v.as_array()) {
+                document_cache.exclude_globs =
+                    eg.iter().filter_map(|x| x.as_str()).map(String::from).collect();
+            }
+            if let Some(ds) = o.get("diagnosticSeverity").and_then(|v| v.as_object()) {
+                // Unknown severities are ignored
+                document_cache.severity_overrides = ds
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.clone(), parse_severity(v.as_str()?)?)))
+                    .collect();
+            }
             hide_ui = o.get("preview").and_then(|v| v.as_object()?.get("hide_ui")?.as_bool());
+            if let Some(fd) =
+                o.get("preview").and_then(|v| v.as_object()?.get("fontDirs")?.as_array().cloned())
+            {
+                font_dirs = fd.iter().filter_map(|x| x.as_str()).map(PathBuf::from).collect();
+            }
         }
     }
 
+    document_cache.update_include_paths();
+    if compilation_settings(&document_cache.documents.compiler_config) != previous_config {
+        recompile_documents(ctx, document_cache).await?;
+    }
+
     // Always load the widgets so we can auto-complete them
     let mut diag = BuildDiagnostics::default();
     document_cache.documents.import_component("std-widgets.slint", "StyleMetrics", &mut diag).await;
@@ -1253,11 +2035,82 @@ pub async fn load_configuration(ctx: &Context) -> Result<()> {
         style: cc.style.clone().unwrap_or_default(),
         include_paths: cc.include_paths.clone(),
         library_paths: cc.library_paths.clone(),
+        font_dirs,
     };
     ctx.preview.config_changed(document_cache.preview_config.clone());
     Ok(())
 }
 
+/// Updates the workspace folders after a `workspace/didChangeWorkspaceFolders` notification,
+/// and recompiles the documents if that changes the include paths.
+pub async fn change_workspace_folders(
+    ctx: &Context,
+    event: lsp_types::WorkspaceFoldersChangeEvent,
+    document_cache: &mut DocumentCache,
+) -> Result<()> {
+    let removed = event.removed.iter().filter_map(|f| uri_to_file(&f.uri)).collect::<Vec<_>>();
+    let mut workspace_folders = std::mem::take(&mut document_cache.workspace_folders);
+    workspace_folders.retain(|f| !removed.contains(f));
+    workspace_folders.extend(event.added.iter().filter_map(|f| uri_to_file(&f.uri)));
+
+    let previous_config = compilation_settings(&document_cache.documents.compiler_config);
+    document_cache.set_workspace_folders(workspace_folders);
+    if compilation_settings(&document_cache.documents.compiler_config) == previous_config {
+        return Ok(());
+    }
+    recompile_documents(ctx, document_cache).await?;
+
+    document_cache.preview_config.include_paths =
+        document_cache.documents.compiler_config.include_paths.clone();
+    ctx.preview.config_changed(document_cache.preview_config.clone());
+    Ok(())
+}
+
+/// The parts of the configuration that need a recompilation when they change
+fn compilation_settings(
+    config: &CompilerConfiguration,
+) -> (Vec<PathBuf>, HashMap<String, PathBuf>, Option<String>) {
+    (config.include_paths.clone(), config.library_paths.clone(), config.style.clone())
+}
+
+/// Starts over with a new type loader for the current compiler configuration and reloads all
+/// the documents that were loaded from files.
+async fn recompile_documents(ctx: &Context, document_cache: &mut DocumentCache) -> Result<()> {
+    let documents = document_cache
+        .documents
+        .all_file_documents()
+        // Builtin documents such as the styles are loaded again as needed
+        .filter(|(path, _)| Url::from_file_path(path).is_ok())
+        .filter_map(|(path, doc)| {
+            let node = doc.node.as_ref()?;
+            Some((path.clone(), node.text().to_string(), node.source_file.version()))
+        })
+        .collect::<Vec<_>>();
+
+    // The type loader validates the style, and only reports problems with it here
+    let mut diag = BuildDiagnostics::default();
+    document_cache.documents = TypeLoader::new(
+        TypeRegister::builtin(),
+        document_cache.documents.compiler_config.clone(),
+        &mut diag,
+    );
+    for d in diag.iter().filter(|d| d.level() == DiagnosticLevel::Error) {
+        ctx.server_notifier.send_notification(
+            "window/showMessage".into(),
+            lsp_types::ShowMessageParams {
+                typ: lsp_types::MessageType::ERROR,
+                message: d.message().into(),
+            },
+        )?;
+    }
+
+    for (path, content, version) in documents {
+        let lsp_diags = compile_document(path, content, version, document_cache).await;
+        publish_diagnostics(ctx, document_cache, lsp_diags)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1299,6 +2152,29 @@ fn test_text_document_color_no_color_set() {
         assert!(result.is_empty());
     }
 
+    #[test]
+    fn test_text_document_color_named_color() {
+        let (mut dc, url, _) = loaded_document_cache(
+            r#"component Main inherits Rectangle {
+    background: Colors.red;
+    Text { color: blue; text: "red"; }
+    property <int> red: 4;
+}"#
+            .into(),
+        );
+
+        let result = get_document_color(&mut dc, &lsp_types::TextDocumentIdentifier { uri: url })
+            .expect("Color Vec was returned");
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].range.start, Position::new(1, 16));
+        assert_eq!(result[0].range.end, Position::new(1, 26));
+        assert_eq!(result[0].color, Color { red: 1., green: 0., blue: 0., alpha: 1. });
+        assert_eq!(result[1].range.start, Position::new(2, 18));
+        assert_eq!(result[1].range.end, Position::new(2, 22));
+        assert_eq!(result[1].color, Color { red: 0., green: 0., blue: 1., alpha: 1. });
+    }
+
     #[test]
     fn test_text_document_color_rgba_color() {
         let (mut dc, url, _) = loaded_document_cache(
@@ -1352,6 +2228,32 @@ fn base_type_at_position(
         Some(format!("{}", &element.base_type))
     }
 
+    #[test]
+    fn test_element_at_offset_nested() {
+        let source = r#"
+export component Test {
+    outer := Rectangle {
+        background: blue;
+        inner := Text {
+            text: "hello";
+        }
+    }
+}"#;
+        let (mut dc, url, _) = loaded_document_cache(source.into());
+
+        let element_at = |dc: &mut DocumentCache, needle: &str| {
+            let offset = source.find(needle).unwrap() as u32;
+            let element = element_at_offset(dc, &url, offset)?;
+            let element = element.borrow();
+            Some((element.id.clone(), element.base_type.to_string()))
+        };
+
+        assert_eq!(element_at(&mut dc, "\"hello\""), Some(("inner".into(), "Text".into())));
+        assert_eq!(element_at(&mut dc, "blue"), Some(("outer".into(), "Rectangle".into())));
+        assert_eq!(element_at(&mut dc, "outer :=").unwrap().0, "root");
+        assert_eq!(element_at(&mut dc, "export"), None);
+    }
+
     #[test]
     fn test_element_at_position_no_element() {
         let (mut dc, url, _) = complex_document_cache();
@@ -1483,6 +2385,220 @@ enum {}
         }
     }
 
+    #[test]
+    fn test_workspace_include_paths() {
+        let mut dc = DocumentCache::new(CompilerConfiguration::new(
+            i_slint_compiler::generator::OutputFormat::Interpreter,
+        ));
+        let absolute = std::env::temp_dir().join("include");
+        dc.include_paths = vec![PathBuf::from("ui"), absolute.clone()];
+        dc.set_workspace_folders(vec![PathBuf::from("/a"), PathBuf::from("/b")]);
+        assert_eq!(
+            dc.documents.compiler_config.include_paths,
+            vec![
+                PathBuf::from("/a/ui"),
+                PathBuf::from("/b/ui"),
+                PathBuf::from("ui"),
+                absolute.clone()
+            ]
+        );
+        dc.set_workspace_folders(vec![]);
+        assert_eq!(dc.documents.compiler_config.include_paths, vec![PathBuf::from("ui"), absolute]);
+    }
+
+    #[test]
+    fn test_text_document_sync_kind() {
+        let sync_kind = |capabilities: &ClientCapabilities| {
+            server_initialize_result(capabilities).capabilities.text_document_sync
+        };
+        let mut capabilities = ClientCapabilities::default();
+        assert_eq!(
+            sync_kind(&capabilities),
+            Some(TextDocumentSyncCapability::Kind(lsp_types::TextDocumentSyncKind::INCREMENTAL))
+        );
+        capabilities.experimental = Some(serde_json::json!({"fullTextDocumentSync": true}));
+        assert_eq!(
+            sync_kind(&capabilities),
+            Some(TextDocumentSyncCapability::Kind(lsp_types::TextDocumentSyncKind::FULL))
+        );
+    }
+
+    #[test]
+    fn test_apply_content_changes() {
+        let change = |range: Option<(u32, u32, u32, u32)>, text: &str| {
+            lsp_types::TextDocumentContentChangeEvent {
+                range: range.map(|(sl, sc, el, ec)| {
+                    lsp_types::Range::new(Position::new(sl, sc), Position::new(el, ec))
+                }),
+                range_length: None,
+                text: text.into(),
+            }
+        };
+        let mut text = "component Foo {\n    width: 10px;\n}\n".to_string();
+
+        apply_content_change(&mut text, change(Some((0, 10, 0, 13)), "Bar"));
+        assert_eq!(text, "component Bar {\n    width: 10px;\n}\n");
+        // Changes apply in order, each to the result of the previous one
+        apply_content_change(&mut text, change(Some((1, 4, 1, 16)), ""));
+        apply_content_change(&mut text, change(Some((1, 4, 1, 4)), "height: 5px;\n    "));
+        assert_eq!(text, "component Bar {\n    height: 5px;\n    \n}\n");
+        // Positions past the end of a line or of the text are clamped
+        apply_content_change(&mut text, change(Some((2, 80, 2, 80)), "x: 1px;"));
+        apply_content_change(&mut text, change(Some((9, 0, 9, 0)), "// end"));
+        assert_eq!(text, "component Bar {\n    height: 5px;\n    x: 1px;\n}\n// end");
+        // Without a range, the text is replaced
+        apply_content_change(&mut text, change(None, "component Baz {}"));
+        assert_eq!(text, "component Baz {}");
+
+        // Characters are counted in UTF-16 code units
+        let mut text = "Text { text: \"h\u{e9}llo \u{1f600}!\"; }".to_string();
+        apply_content_change(&mut text, change(Some((0, 15, 0, 16)), "e"));
+        assert_eq!(text, "Text { text: \"hello \u{1f600}!\"; }");
+        apply_content_change(&mut text, change(Some((0, 20, 0, 22)), ":)"));
+        assert_eq!(text, "Text { text: \"hello :)!\"; }");
+    }
+
+    #[test]
+    fn test_track_published_diagnostics() {
+        let mut dc = DocumentCache::new(CompilerConfiguration::new(
+            i_slint_compiler::generator::OutputFormat::Interpreter,
+        ));
+        let main = Url::parse("file:///main.slint").unwrap();
+        let import = Url::parse("file:///import.slint").unwrap();
+        let error = lsp_types::Diagnostic { message: "error".into(), ..Default::default() };
+        let mut published = |diags: &[(&Url, bool)]| {
+            let mut lsp_diags = diags
+                .iter()
+                .map(|(uri, has_error)| {
+                    ((*uri).clone(), if *has_error { vec![error.clone()] } else { vec![] })
+                })
+                .collect();
+            dc.track_published_diagnostics(&mut lsp_diags);
+            let mut result =
+                lsp_diags.into_iter().map(|(uri, d)| (uri, d.len())).collect::<Vec<_>>();
+            result.sort();
+            result
+        };
+
+        // Files without errors are only published to clear their previous errors
+        assert_eq!(published(&[(&main, false), (&import, false)]), vec![]);
+        assert_eq!(published(&[(&main, false), (&import, true)]), vec![(import.clone(), 1)]);
+        assert_eq!(
+            published(&[(&main, true), (&import, true)]),
+            vec![(import.clone(), 1), (main.clone(), 1)]
+        );
+        assert_eq!(
+            published(&[(&main, false), (&import, true)]),
+            vec![(import.clone(), 1), (main.clone(), 0)]
+        );
+        assert_eq!(published(&[(&main, false), (&import, false)]), vec![(import.clone(), 0)]);
+        assert_eq!(published(&[(&main, false), (&import, false)]), vec![]);
+    }
+
+    #[test]
+    fn test_severity_overrides() {
+        let diag = |message: &str| {
+            lsp_types::Diagnostic::new(
+                Default::default(),
+                Some(lsp_types::DiagnosticSeverity::WARNING),
+                None,
+                None,
+                message.into(),
+                None,
+                None,
+            )
+        };
+        let mut diagnostics =
+            vec![diag("Unused property 'foo'"), diag("Unknown element 'Bar'"), diag("Other")];
+        let overrides = vec![
+            ("Unused property".to_string(), parse_severity("hint").unwrap()),
+            ("Unknown element".to_string(), parse_severity("off").unwrap()),
+        ];
+        assert_eq!(parse_severity("unknown"), None);
+        apply_severity_overrides(&overrides, &mut diagnostics);
+        let result =
+            diagnostics.into_iter().map(|d| (d.message, d.severity.unwrap())).collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![
+                ("Unused property 'foo'".to_string(), lsp_types::DiagnosticSeverity::HINT),
+                ("Other".to_string(), lsp_types::DiagnosticSeverity::WARNING),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_component_list() {
+        let (mut dc, uri, _) = loaded_document_cache(
+            r#"component Internal { }
+export global Glob { }
+export component Exported { }
+export component Main { Internal { } }
+            "#
+            .into(),
+        );
+        let result =
+            get_component_list(&mut dc, &lsp_types::TextDocumentIdentifier { uri }).unwrap();
+        let result =
+            result.into_iter().map(|c| (c.name, c.exported, c.is_default)).collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![
+                ("Internal".to_string(), false, false),
+                ("Exported".to_string(), true, false),
+                ("Main".to_string(), true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_code_action() {
+        let (mut dc, url, _) = loaded_document_cache(
+            r#"import { Button } from "std-widgets.slint";
+
+export component TestWindow inherits Window {
+    VerticalBox { }
+    Button { }
+}"#
+            .into(),
+        );
+        let capabilities = ClientCapabilities::default();
+
+        let vertical_box = Position::new(3, 4);
+        assert_eq!(
+            token_descr(&mut dc, &url, &vertical_box).and_then(|(token, _)| get_code_actions(
+                &mut dc,
+                token,
+                &capabilities
+            )),
+            Some(vec![CodeActionOrCommand::CodeAction(lsp_types::CodeAction {
+                title: "Import VerticalBox from \"std-widgets.slint\"".into(),
+                kind: Some(lsp_types::CodeActionKind::QUICKFIX),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(
+                        std::iter::once((
+                            url.clone(),
+                            vec![TextEdit::new(
+                                lsp_types::Range::new(Position::new(0, 15), Position::new(0, 15)),
+                                ", VerticalBox".into()
+                            )]
+                        ))
+                        .collect()
+                    ),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })])
+        );
+
+        // Button is already imported
+        assert_eq!(
+            token_descr(&mut dc, &url, &Position::new(4, 4))
+                .and_then(|(token, _)| get_code_actions(&mut dc, token, &capabilities)),
+            None
+        );
+    }
+
     #[test]
     fn test_code_actions() {
         let (mut dc, url, _) = loaded_document_cache(
@@ -1709,4 +2825,62 @@ fn test_code_actions() {
             ])
         );
     }
+
+    #[test]
+    fn test_palette_overrides() {
+        let (dc, url, _) = loaded_document_cache(
+            r#"export global Palette {
+    in-out property <color> background: white;
+    in-out property <bool> dark-mode;
+    in-out property <length> spacing: 4px;
+}
+export component Main inherits Window {
+    background: Palette.background;
+}
+"#
+            .into(),
+        );
+        let path = uri_to_file(&url).unwrap();
+        let values = |json: serde_json::Value| json.as_object().unwrap().clone();
+
+        assert_eq!(
+            palette_overrides(
+                &dc,
+                &path,
+                "Palette",
+                &values(
+                    serde_json::json!({ "background": "#202020", "dark_mode": true, "spacing": 8 })
+                )
+            )
+            .unwrap(),
+            vec![
+                ("background".to_string(), PropertyOverride::Color(0xff202020)),
+                ("dark-mode".to_string(), PropertyOverride::Bool(true)),
+                ("spacing".to_string(), PropertyOverride::Number(8.)),
+            ]
+        );
+
+        assert!(palette_overrides(
+            &dc,
+            &path,
+            "Palette",
+            &values(serde_json::json!({ "foreground": "#000" }))
+        )
+        .is_err());
+        assert!(palette_overrides(
+            &dc,
+            &path,
+            "Palette",
+            &values(serde_json::json!({ "background": "blue" }))
+        )
+        .is_err());
+        assert!(palette_overrides(
+            &dc,
+            &path,
+            "Palette",
+            &values(serde_json::json!({ "spacing": "8px" }))
+        )
+        .is_err());
+        assert!(palette_overrides(&dc, &path, "Main", &values(serde_json::json!({}))).is_err());
+    }
 }
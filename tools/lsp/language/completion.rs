@@ -4,18 +4,21 @@
 // cSpell: ignore rfind
 
 use super::DocumentCache;
-use crate::util::{lookup_current_element_type, map_position, with_lookup_ctx};
+use crate::util::{
+    lookup_current_element_type, lookup_qualified_name, map_position, map_token, with_lookup_ctx,
+};
 
 #[cfg(target_arch = "wasm32")]
 use crate::wasm_prelude::*;
 use i_slint_compiler::diagnostics::Spanned;
-use i_slint_compiler::expression_tree::Expression;
+use i_slint_compiler::expression_tree::{Expression, Unit};
 use i_slint_compiler::langtype::{ElementType, Type};
-use i_slint_compiler::lookup::{LookupCtx, LookupObject, LookupResult};
-use i_slint_compiler::parser::{syntax_nodes, SyntaxKind, SyntaxToken};
+use i_slint_compiler::lookup::{LookupCtx, LookupObject, LookupResult, ReturnTypeSpecificLookup};
+use i_slint_compiler::parser::{syntax_nodes, SyntaxKind, SyntaxNode, SyntaxToken};
 use lsp_types::{
-    CompletionClientCapabilities, CompletionItem, CompletionItemKind, InsertTextFormat, Position,
-    Range, TextEdit,
+    CompletionClientCapabilities, CompletionItem, CompletionItemKind, CompletionTextEdit,
+    Documentation, InsertTextFormat, MarkupContent, MarkupKind, Position, Range,
+    TextDocumentPositionParams, TextEdit,
 };
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
@@ -34,23 +37,25 @@ pub(crate) fn completion_at(
         .unwrap_or(false);
 
     if token.kind() == SyntaxKind::StringLiteral {
-        if matches!(node.kind(), SyntaxKind::ImportSpecifier | SyntaxKind::AtImageUrl) {
+        if node.kind() == SyntaxKind::ImportSpecifier {
+            return complete_import_path(
+                document_cache,
+                token.source_file()?.path(),
+                token.text(),
+                offset.checked_sub(token.text_range().start().into())?,
+            );
+        } else if node.kind() == SyntaxKind::AtImageUrl {
             return complete_path_in_string(
+                document_cache,
                 token.source_file()?.path(),
                 token.text(),
                 offset.checked_sub(token.text_range().start().into())?,
-            )
-            .map(|mut r| {
-                if node.kind() == SyntaxKind::ImportSpecifier && !token.text().contains('/') {
-                    let mut c =
-                        CompletionItem::new_simple("std-widgets.slint".into(), String::new());
-
-                    c.kind = Some(CompletionItemKind::FILE);
-                    r.push(c)
-                }
-                r
-            });
+            );
         }
+    } else if token.kind() == SyntaxKind::NumberLiteral
+        && offset == u32::from(token.text_range().end())
+    {
+        return complete_unit(document_cache, &token);
     } else if let Some(element) = syntax_nodes::Element::new(node.clone()) {
         if token.kind() == SyntaxKind::At
             || (token.kind() == SyntaxKind::Identifier
@@ -221,7 +226,11 @@ pub(crate) fn completion_at(
             );
         }
 
+        let assigned_type = assigned_type(document_cache, &token);
         return with_lookup_ctx(document_cache, node, |ctx| {
+            if let Some(ty) = assigned_type {
+                ctx.property_type = ty;
+            }
             resolve_expression_scope(ctx).map(Into::into)
         })?;
     } else if let Some(q) = syntax_nodes::QualifiedName::new(node.clone()) {
@@ -371,6 +380,73 @@ pub(crate) fn completion_at(
     None
 }
 
+/// How much of the completion items is sent with the completion response, as configured by the
+/// `slint.completionDetail` setting
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CompletionDetail {
+    /// Only send the label and the kind, the rest is sent on `completionItem/resolve`
+    Minimal,
+    #[default]
+    Full,
+}
+
+impl TryFrom<&str> for CompletionDetail {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "minimal" => Ok(Self::Minimal),
+            "full" => Ok(Self::Full),
+            _ => Err(format!("Invalid completion detail {value}, expected minimal or full")),
+        }
+    }
+}
+
+/// Removes the detail and the documentation from the items, and remembers where the completion
+/// was requested so that `resolve_completion_item` can fill them in again.
+pub(crate) fn defer_details(items: &mut [CompletionItem], position: &TextDocumentPositionParams) {
+    let data = serde_json::to_value(position).ok();
+    for item in items {
+        item.detail = None;
+        item.documentation = None;
+        item.data = data.clone();
+    }
+}
+
+/// Fills in the detail and the documentation of an item returned with
+/// `CompletionDetail::Minimal`, by completing again at the position the item was created for.
+pub(crate) fn resolve_completion_item(
+    document_cache: &mut DocumentCache,
+    mut item: CompletionItem,
+    client_caps: Option<&CompletionClientCapabilities>,
+) -> CompletionItem {
+    let Some(position) = item
+        .data
+        .take()
+        .and_then(|data| serde_json::from_value::<TextDocumentPositionParams>(data).ok())
+    else {
+        return item;
+    };
+    let resolved =
+        super::token_descr(document_cache, &position.text_document.uri, &position.position)
+            .and_then(|(token, offset)| completion_at(document_cache, token, offset, client_caps))
+            .and_then(|items| {
+                items.into_iter().find(|i| i.label == item.label && i.kind == item.kind)
+            });
+    if let Some(resolved) = resolved {
+        item.detail = resolved.detail;
+        item.documentation = resolved.documentation;
+    }
+    item
+}
+
+/// Returns the doc comment of the declaration as documentation of a completion item
+fn documentation(node: &SyntaxNode) -> Option<Documentation> {
+    super::hover::doc_comment(node).map(|value| {
+        Documentation::MarkupContent(MarkupContent { kind: MarkupKind::Markdown, value })
+    })
+}
+
 fn with_insert_text(
     mut c: CompletionItem,
     ins_text: &str,
@@ -413,6 +489,7 @@ fn resolve_element_scope(
                 pr.Type().map(|t| t.text().into()).unwrap_or_else(|| "property".to_owned()),
             );
             c.kind = Some(CompletionItemKind::PROPERTY);
+            c.documentation = documentation(&pr);
             c
         }))
         .chain(element.CallbackDeclaration().map(|cd| {
@@ -422,6 +499,7 @@ fn resolve_element_scope(
                 "callback".into(),
             );
             c.kind = Some(CompletionItemKind::METHOD);
+            c.documentation = documentation(&cd);
             c
         }))
         .collect::<Vec<_>>();
@@ -463,9 +541,39 @@ fn resolve_expression_scope(lookup_context: &LookupCtx) -> Option<Vec<Completion
         r.push(completion_item_from_expression(str, expr));
         None
     });
+
+    // List the values of the expected type first, such as the enum values or the colors
+    let mut expected_values = HashSet::new();
+    ReturnTypeSpecificLookup.for_each_entry(lookup_context, &mut |str, _| -> Option<()> {
+        expected_values.insert(str.to_string());
+        None
+    });
+    for c in r.iter_mut().filter(|c| expected_values.contains(&c.label)) {
+        c.sort_text = Some(format!("0{}", c.label));
+    }
     Some(r)
 }
 
+/// Returns the type of the left hand side if `token` is in the right hand side of an assignment
+fn assigned_type(document_cache: &DocumentCache, token: &SyntaxToken) -> Option<Type> {
+    let mut node = token.parent();
+    let assignment = loop {
+        match node.kind() {
+            SyntaxKind::SelfAssignment => break node,
+            SyntaxKind::CodeBlock | SyntaxKind::BindingExpression => return None,
+            _ => node = node.parent()?,
+        }
+    };
+    let lhs = assignment.child_node(SyntaxKind::Expression)?;
+    if token.text_range().start() < lhs.text_range().end() {
+        return None;
+    }
+    match lookup_qualified_name(document_cache, &lhs.child_node(SyntaxKind::QualifiedName)?)? {
+        LookupResult::Expression { expression, .. } => Some(expression.ty()),
+        _ => None,
+    }
+}
+
 fn completion_item_from_expression(str: &str, lookup_result: LookupResult) -> CompletionItem {
     match lookup_result {
         LookupResult::Expression { expression, .. } => {
@@ -525,7 +633,75 @@ fn resolve_type_scope(
     )
 }
 
-fn complete_path_in_string(base: &Path, text: &str, offset: u32) -> Option<Vec<CompletionItem>> {
+/// Complete the unit of the number literal in `token`, depending on the type of the property
+/// it is assigned to.
+fn complete_unit(
+    document_cache: &DocumentCache,
+    token: &SyntaxToken,
+) -> Option<Vec<CompletionItem>> {
+    // Properties for which a percentage is converted to a length relative to the parent
+    const RELATIVE_TO_PARENT_PROPERTIES: &[&str] =
+        &["width", "height", "preferred-width", "preferred-height"];
+
+    let (property_type, property_name) = with_lookup_ctx(document_cache, token.parent(), |ctx| {
+        (ctx.property_type.clone(), ctx.property_name.unwrap_or_default().to_string())
+    })?;
+    if property_type == Type::Invalid {
+        return None;
+    }
+
+    let text = token.text();
+    let number =
+        &text[..text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len())];
+    let range = map_token(token)?;
+
+    let units = [
+        Unit::Percent,
+        Unit::Phx,
+        Unit::Px,
+        Unit::Cm,
+        Unit::Mm,
+        Unit::In,
+        Unit::Pt,
+        Unit::Rem,
+        Unit::S,
+        Unit::Ms,
+        Unit::Deg,
+        Unit::Grad,
+        Unit::Turn,
+        Unit::Rad,
+    ];
+    Some(
+        units
+            .into_iter()
+            .filter(|unit| match unit {
+                Unit::Percent => {
+                    property_type == Type::Percent
+                        || (property_type == Type::LogicalLength
+                            && RELATIVE_TO_PARENT_PROPERTIES.contains(&property_name.as_str()))
+                }
+                _ => unit.ty() == property_type || unit.ty().can_convert(&property_type),
+            })
+            .map(|unit| {
+                let new_text = format!("{number}{unit}");
+                CompletionItem {
+                    label: unit.to_string(),
+                    kind: Some(CompletionItemKind::UNIT),
+                    filter_text: Some(new_text.clone()),
+                    text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(range, new_text))),
+                    ..Default::default()
+                }
+            })
+            .collect(),
+    )
+}
+
+fn complete_path_in_string(
+    document_cache: &DocumentCache,
+    base: &Path,
+    text: &str,
+    offset: u32,
+) -> Option<Vec<CompletionItem>> {
     if offset as usize > text.len() || offset == 0 {
         return None;
     }
@@ -541,6 +717,9 @@ fn complete_path_in_string(base: &Path, text: &str, offset: u32) -> Option<Vec<C
     Some(
         dir.filter_map(|x| {
             let entry = x.ok()?;
+            if document_cache.is_excluded(&entry.path()) {
+                return None;
+            }
             let mut c =
                 CompletionItem::new_simple(entry.file_name().into_string().ok()?, String::new());
             if entry.file_type().ok()?.is_dir() {
@@ -555,6 +734,80 @@ fn complete_path_in_string(base: &Path, text: &str, offset: u32) -> Option<Vec<C
     )
 }
 
+/// Completes the file name of an import with the `.slint` files and directories found relative to
+/// the importing file and to the include paths, or in the library the path starts with. At the
+/// start of the path, the libraries and `std-widgets.slint` are proposed as well.
+fn complete_import_path(
+    document_cache: &DocumentCache,
+    base: &Path,
+    text: &str,
+    offset: u32,
+) -> Option<Vec<CompletionItem>> {
+    if offset as usize > text.len() || offset == 0 {
+        return None;
+    }
+    let text = &text.strip_prefix('\"')?[..(offset - 1) as usize];
+    let config = &document_cache.documents.compiler_config;
+
+    let mut result = Vec::new();
+    let directories = if let Some(library_path) = text.strip_prefix('@') {
+        let (library, path) = library_path.split_once('/')?;
+        let directory = &path[..path.rfind('/').unwrap_or(0)];
+        vec![config.library_paths.get(library)?.join(directory)]
+    } else {
+        let directory = &text[..text.rfind('/').unwrap_or(0)];
+        if !text.contains('/') {
+            let mut c = CompletionItem::new_simple("std-widgets.slint".into(), String::new());
+            c.kind = Some(CompletionItemKind::FILE);
+            result.push(c);
+        }
+        std::iter::once(i_slint_compiler::typeloader::base_directory(base))
+            .chain(config.include_paths.iter().cloned())
+            .map(|include_path| include_path.join(directory))
+            .collect()
+    };
+
+    if !text.contains('/') {
+        result.extend(config.library_paths.iter().map(|(name, path)| {
+            let mut c = CompletionItem::new_simple(format!("@{name}"), String::new());
+            c.kind = Some(CompletionItemKind::MODULE);
+            if path.is_dir() {
+                c.insert_text = Some(format!("@{name}/"));
+            }
+            c
+        }));
+    }
+
+    let mut seen = HashSet::new();
+    for entry in directories.iter().filter_map(|d| std::fs::read_dir(d).ok()).flatten() {
+        let Ok(entry) = entry else { continue };
+        let path = entry.path();
+        let Ok(name) = entry.file_name().into_string() else { continue };
+        if name.starts_with('.') || path == base || document_cache.is_excluded(&path) {
+            continue;
+        }
+        let mut c = CompletionItem::new_simple(name, String::new());
+        if entry.file_type().map_or(false, |t| t.is_dir()) {
+            c.kind = Some(CompletionItemKind::FOLDER);
+            c.insert_text = Some(format!("{}/", c.label));
+        } else if path.extension().map_or(false, |e| e == "slint") {
+            // Files that were loaded already are known to export nothing, or something
+            if document_cache.documents.get_document(&path).map_or(false, |d| d.exports.is_empty())
+            {
+                continue;
+            }
+            c.kind = Some(CompletionItemKind::FILE);
+        } else {
+            continue;
+        }
+        if seen.insert(c.label.clone()) {
+            result.push(c);
+        }
+    }
+
+    Some(result)
+}
+
 /// Add the components that are available when adding import to the `result`
 ///
 /// `available_types`  are the component which are already available and need no
@@ -562,13 +815,103 @@ fn complete_path_in_string(base: &Path, text: &str, offset: u32) -> Option<Vec<C
 fn add_components_to_import(
     token: &SyntaxToken,
     document_cache: &mut DocumentCache,
-    mut available_types: HashSet<String>,
+    available_types: HashSet<String>,
     result: &mut Vec<CompletionItem>,
 ) -> Option<()> {
+    for component in importable_components(token, document_cache, available_types)? {
+        result.push(CompletionItem {
+            label: format!("{} (import from \"{}\")", component.name, component.file),
+            insert_text: if is_followed_by_brace(token) {
+                Some(component.name.clone())
+            } else {
+                Some(format!("{} {{$1}}", component.name))
+            },
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            filter_text: Some(component.name.clone()),
+            kind: Some(CompletionItemKind::CLASS),
+            detail: Some(format!("(import from \"{}\")", component.file)),
+            additional_text_edits: Some(vec![component.import_edit]),
+            ..Default::default()
+        });
+    }
+    Some(())
+}
+
+/// A component exported by another file
+pub struct ImportableComponent {
+    pub name: String,
+    /// The file to import from, relative to the current file
+    pub file: String,
+    /// The edit adding the component to the imports of the current file, extending the
+    /// existing import from the same file if there is one
+    pub import_edit: TextEdit,
+}
+
+/// Returns the components exported by the loaded files that can be imported in the file
+/// of `token`, except those in `available_types`.
+pub fn importable_components(
+    token: &SyntaxToken,
+    document_cache: &DocumentCache,
+    mut available_types: HashSet<String>,
+) -> Option<Vec<ImportableComponent>> {
     // Find out types that can be imported
     let current_file = token.source_file.path().to_owned();
     let current_uri = lsp_types::Url::from_file_path(&current_file).ok()?;
-    let current_doc = document_cache.documents.get_document(&current_file)?.node.as_ref()?;
+    let import_edit = import_edit_builder(token, document_cache)?;
+
+    let mut result = Vec::new();
+    for file in document_cache.documents.all_files() {
+        if document_cache.is_excluded(file) {
+            continue;
+        }
+        let Some(doc) = document_cache.documents.get_document(file) else { continue };
+        let file = if file.starts_with("builtin:/") {
+            match file.file_name() {
+                Some(file) if file == "std-widgets.slint" => "std-widgets.slint".into(),
+                _ => continue,
+            }
+        } else {
+            match lsp_types::Url::make_relative(
+                &current_uri,
+                &lsp_types::Url::from_file_path(file)
+                    .unwrap_or_else(|()| panic!("Cannot parse URL for file '{file:?}'")),
+            ) {
+                Some(file) => file,
+                None => continue,
+            }
+        };
+
+        for (exported_name, ty) in &*doc.exports {
+            if available_types.contains(&exported_name.name) {
+                continue;
+            }
+            if let Some(c) = ty.as_ref().left() {
+                if c.is_global() {
+                    continue;
+                }
+            } else {
+                continue;
+            }
+            available_types.insert(exported_name.name.clone());
+            result.push(ImportableComponent {
+                name: exported_name.name.clone(),
+                file: file.clone(),
+                import_edit: import_edit(&file, &exported_name.name),
+            });
+        }
+    }
+    Some(result)
+}
+
+/// Returns a function that creates the edit adding the type `name` exported by `file` to the
+/// imports of the file of `token`, extending the existing import from `file` if there is one.
+/// `file` is relative to the file of `token`, as it is written in the import statement.
+pub fn import_edit_builder(
+    token: &SyntaxToken,
+    document_cache: &DocumentCache,
+) -> Option<impl Fn(&str, &str) -> TextEdit> {
+    let current_doc =
+        document_cache.documents.get_document(token.source_file.path())?.node.as_ref()?;
     let mut import_locations = HashMap::new();
     let mut last = 0u32;
     for import in current_doc.ImportSpecifier() {
@@ -624,62 +967,17 @@ fn add_components_to_import(
         Position::new(map_position(&token.source_file, last.into()).line + 1, 0)
     };
 
-    for file in document_cache.documents.all_files() {
-        let Some(doc) = document_cache.documents.get_document(file) else { continue };
-        let file = if file.starts_with("builtin:/") {
-            match file.file_name() {
-                Some(file) if file == "std-widgets.slint" => "std-widgets.slint".into(),
-                _ => continue,
-            }
-        } else {
-            match lsp_types::Url::make_relative(
-                &current_uri,
-                &lsp_types::Url::from_file_path(file)
-                    .unwrap_or_else(|()| panic!("Cannot parse URL for file '{file:?}'")),
-            ) {
-                Some(file) => file,
-                None => continue,
-            }
-        };
-
-        for (exported_name, ty) in &*doc.exports {
-            if available_types.contains(&exported_name.name) {
-                continue;
-            }
-            if let Some(c) = ty.as_ref().left() {
-                if c.is_global() {
-                    continue;
-                }
-            } else {
-                continue;
-            }
-            available_types.insert(exported_name.name.clone());
-            let the_import = import_locations.get(&file).map_or_else(
-                || {
-                    TextEdit::new(
-                        Range::new(new_import_position, new_import_position),
-                        format!("import {{ {} }} from \"{}\";\n", exported_name.name, file),
-                    )
-                },
-                |pos| TextEdit::new(Range::new(*pos, *pos), format!(", {}", exported_name.name)),
-            );
-            result.push(CompletionItem {
-                label: format!("{} (import from \"{}\")", exported_name.name, file),
-                insert_text: if is_followed_by_brace(token) {
-                    Some(exported_name.name.clone())
-                } else {
-                    Some(format!("{} {{$1}}", exported_name.name))
-                },
-                insert_text_format: Some(InsertTextFormat::SNIPPET),
-                filter_text: Some(exported_name.name.clone()),
-                kind: Some(CompletionItemKind::CLASS),
-                detail: Some(format!("(import from \"{}\")", file)),
-                additional_text_edits: Some(vec![the_import]),
-                ..Default::default()
-            });
-        }
-    }
-    Some(())
+    Some(move |file: &str, name: &str| {
+        import_locations.get(file).map_or_else(
+            || {
+                TextEdit::new(
+                    Range::new(new_import_position, new_import_position),
+                    format!("import {{ {name} }} from \"{file}\";\n"),
+                )
+            },
+            |pos| TextEdit::new(Range::new(*pos, *pos), format!(", {name}")),
+        )
+    })
 }
 
 fn is_followed_by_brace(token: &SyntaxToken) -> bool {
@@ -770,6 +1068,41 @@ fn in_expression() {
         }
     }
 
+    #[test]
+    fn expected_type_values() {
+        let sort_text = |source: &str, label: &str| {
+            let res = get_completions(source).unwrap();
+            res.into_iter().find(|ci| ci.label == label).unwrap().sort_text
+        };
+
+        let source = r#"
+            component Foo {
+                Text { horizontal-alignment: 🔺 }
+            }
+        "#;
+        for value in ["left", "center", "right"] {
+            assert_eq!(sort_text(source, value), Some(format!("0{value}")));
+        }
+        assert_eq!(sort_text(source, "root"), None);
+
+        let source = r#"
+            component Foo {
+                Rectangle { background: 🔺; }
+            }
+        "#;
+        assert_eq!(sort_text(source, "red"), Some("0red".into()));
+
+        let source = r#"
+            component Foo {
+                t := Text { }
+                TouchArea {
+                    clicked => { t.horizontal-alignment = 🔺 }
+                }
+            }
+        "#;
+        assert_eq!(sort_text(source, "center"), Some("0center".into()));
+    }
+
     #[test]
     fn arguments_struct() {
         let source = r#"
@@ -874,6 +1207,48 @@ fn import_component() {
         res.iter().find(|ci| ci.label == "TextEdit").unwrap();
     }
 
+    #[test]
+    fn import_path() {
+        let dir =
+            std::env::temp_dir().join(format!("slint-lsp-import-path-{}", std::process::id()));
+        let include_dir = dir.join("include");
+        std::fs::create_dir_all(dir.join("widgets")).unwrap();
+        std::fs::create_dir_all(&include_dir).unwrap();
+        for file in ["main.slint", "button.slint", "notes.txt", "widgets/slider.slint"] {
+            std::fs::write(dir.join(file), "export component X {}").unwrap();
+        }
+        std::fs::write(include_dir.join("theme.slint"), "export global Theme {}").unwrap();
+
+        let mut dc = crate::language::test::empty_document_cache();
+        dc.documents.compiler_config.include_paths = vec![include_dir.clone()];
+        dc.documents.compiler_config.library_paths =
+            HashMap::from([("mylib".to_string(), dir.join("widgets"))]);
+
+        let labels = |text: &str| {
+            let mut labels = complete_import_path(
+                &dc,
+                &dir.join("main.slint"),
+                &format!("\"{text}\""),
+                text.len() as u32 + 1,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|c| c.label)
+            .collect::<Vec<_>>();
+            labels.sort();
+            labels
+        };
+
+        assert_eq!(
+            labels(""),
+            ["@mylib", "button.slint", "include", "std-widgets.slint", "theme.slint", "widgets"]
+        );
+        assert_eq!(labels("./widgets/"), ["slider.slint"]);
+        assert_eq!(labels("@mylib/"), ["slider.slint"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn animation_completion() {
         let source = r#"
@@ -1014,4 +1389,125 @@ fn import_completed_component() {
         assert_eq!(edit.range.end.character, 32);
         assert_eq!(edit.new_text, ", AboutSlint");
     }
+
+    #[test]
+    fn number_units() {
+        let source = r#"
+            component Foo inherits Rectangle {
+                width: 100🔺;
+                border-width: 4🔺;
+                property <duration> delay: 2🔺;
+                background: red;
+            }
+        "#;
+        let lines = source.split('🔺').collect::<Vec<_>>();
+        let with_cursor_at = |i: usize| {
+            let mut s = String::new();
+            for (j, l) in lines.iter().enumerate() {
+                s += l;
+                if j == i {
+                    s.push('🔺');
+                }
+            }
+            s
+        };
+
+        let res = get_completions(&with_cursor_at(0)).unwrap();
+        res.iter().find(|ci| ci.label == "px").unwrap();
+        res.iter().find(|ci| ci.label == "%").unwrap();
+        res.iter().find(|ci| ci.label == "phx").unwrap();
+        assert!(!res.iter().any(|ci| ci.label == "ms"));
+        assert!(!res.iter().any(|ci| ci.label == "deg"));
+        let px = res.iter().find(|ci| ci.label == "px").unwrap();
+        let Some(CompletionTextEdit::Edit(edit)) = &px.text_edit else { panic!("no edit") };
+        assert_eq!(edit.new_text, "100px");
+
+        let res = get_completions(&with_cursor_at(1)).unwrap();
+        res.iter().find(|ci| ci.label == "px").unwrap();
+        assert!(!res.iter().any(|ci| ci.label == "%"));
+
+        let res = get_completions(&with_cursor_at(2)).unwrap();
+        res.iter().find(|ci| ci.label == "ms").unwrap();
+        res.iter().find(|ci| ci.label == "s").unwrap();
+        assert!(!res.iter().any(|ci| ci.label == "px"));
+    }
+
+    #[test]
+    fn import_excluded_component() {
+        let mut dc = crate::language::test::empty_document_cache();
+        dc.exclude_globs = vec!["**/vendor/**".into()];
+
+        let load = |dc: &mut DocumentCache, path: &str, content: &str| {
+            let path = if cfg!(target_family = "windows") {
+                format!("c:/{path}")
+            } else {
+                path.to_string()
+            };
+            let url = lsp_types::Url::from_file_path(path).unwrap();
+            spin_on::spin_on(crate::language::reload_document_impl(
+                None,
+                content.into(),
+                url.clone(),
+                Some(42),
+                dc,
+            ));
+            url
+        };
+        load(&mut dc, "/foo/vendor/hidden.slint", "export component HiddenComponent {}");
+        load(&mut dc, "/foo/lib/shown.slint", "export component ShownComponent {}");
+
+        let source = "export component Test {\n    \n}\n";
+        let uri = load(&mut dc, "/foo/bar.slint", source);
+        let offset = source.find("\n    \n").unwrap() as u32 + 3;
+
+        let doc = dc.documents.get_document(&uri_to_file(&uri).unwrap()).unwrap();
+        let token = crate::language::token_at_offset(doc.node.as_ref().unwrap(), offset).unwrap();
+        let caps = CompletionClientCapabilities {
+            completion_item: Some(lsp_types::CompletionItemCapability {
+                snippet_support: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let res = completion_at(&mut dc, token, offset, Some(&caps)).unwrap();
+
+        assert!(res.iter().any(|ci| ci.label.starts_with("ShownComponent")));
+        assert!(!res.iter().any(|ci| ci.label.starts_with("HiddenComponent")));
+    }
+
+    #[test]
+    fn minimal_completion_detail() {
+        let source = r#"component Foo {
+    /// The greeting shown to the user
+    in property <string> greeting;
+
+}"#;
+        let (mut dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+        let position = Position::new(3, 0);
+        let (token, offset) = crate::language::token_descr(&mut dc, &uri, &position).unwrap();
+        let mut items = completion_at(&mut dc, token, offset, None).unwrap();
+
+        let greeting = |items: &[CompletionItem]| {
+            items.iter().find(|item| item.label == "greeting").unwrap().clone()
+        };
+        let documentation = Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: "The greeting shown to the user".into(),
+        }));
+        assert_eq!(greeting(&items).documentation, documentation);
+
+        let position = TextDocumentPositionParams::new(
+            lsp_types::TextDocumentIdentifier::new(uri.clone()),
+            position,
+        );
+        defer_details(&mut items, &position);
+        let item = greeting(&items);
+        assert_eq!(item.detail, None);
+        assert_eq!(item.documentation, None);
+
+        let item = resolve_completion_item(&mut dc, item, None);
+        assert_eq!(item.detail.as_deref(), Some("string"));
+        assert_eq!(item.documentation, documentation);
+        assert_eq!(item.data, None);
+    }
 }
@@ -0,0 +1,95 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use super::DocumentCache;
+use crate::util::map_position;
+
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_prelude::*;
+
+use i_slint_compiler::parser::{SyntaxKind, TextSize};
+use lsp_types::{FoldingRange, FoldingRangeKind};
+
+/// Returns the folding ranges of the blocks delimited by braces or brackets, such as element
+/// bodies, states and animations, and of the block comments.
+pub fn get_folding_ranges(
+    document_cache: &DocumentCache,
+    text_document: &lsp_types::TextDocumentIdentifier,
+) -> Option<Vec<FoldingRange>> {
+    let filepath = super::uri_to_file(&text_document.uri)?;
+    let doc = document_cache.documents.get_document(&filepath)?.node.as_ref()?;
+    let sf = &doc.source_file;
+    let line = |offset: TextSize| map_position(sf, offset).line;
+
+    let mut result = Vec::new();
+    let mut open_blocks = Vec::new();
+    for token in doc.descendants_with_tokens().filter_map(rowan::NodeOrToken::into_token) {
+        match token.kind() {
+            SyntaxKind::LBrace | SyntaxKind::LBracket => {
+                open_blocks.push(line(token.text_range().start()));
+            }
+            SyntaxKind::RBrace | SyntaxKind::RBracket => {
+                let Some(start_line) = open_blocks.pop() else { continue };
+                // Keep the line with the closing brace visible
+                let end_line = line(token.text_range().start()).saturating_sub(1);
+                if end_line > start_line {
+                    result.push(FoldingRange {
+                        start_line,
+                        end_line,
+                        kind: Some(FoldingRangeKind::Region),
+                        ..Default::default()
+                    });
+                }
+            }
+            SyntaxKind::Comment => {
+                let start_line = line(token.text_range().start());
+                let end_line = line(token.text_range().end());
+                if end_line > start_line {
+                    result.push(FoldingRange {
+                        start_line,
+                        end_line,
+                        kind: Some(FoldingRangeKind::Comment),
+                        ..Default::default()
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+    result.sort_by_key(|r| (r.start_line, r.end_line));
+    Some(result)
+}
+
+#[test]
+fn test_folding_ranges() {
+    let source = r#"/* A comment
+   on several lines */
+component Foo inherits Rectangle {
+    property <bool> active;
+    states [
+        active when active: {
+            background: red;
+        }
+    ]
+    animate background {
+        duration: 100ms;
+    }
+    Text { text: "single line"; }
+}"#;
+    let (dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+    let ranges = get_folding_ranges(&dc, &lsp_types::TextDocumentIdentifier { uri })
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.start_line, r.end_line, r.kind.unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ranges,
+        vec![
+            (0, 1, FoldingRangeKind::Comment),
+            (2, 12, FoldingRangeKind::Region),
+            (4, 7, FoldingRangeKind::Region),
+            (5, 6, FoldingRangeKind::Region),
+            (9, 10, FoldingRangeKind::Region),
+        ]
+    );
+}
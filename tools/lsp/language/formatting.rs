@@ -0,0 +1,221 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use super::DocumentCache;
+use crate::util::{map_range, map_token};
+
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_prelude::*;
+
+use i_slint_compiler::diagnostics::BuildDiagnostics;
+use i_slint_compiler::parser::{syntax_nodes, SyntaxToken, TextRange, TextSize};
+use lsp_types::{TextEdit, Url, WorkspaceEdit};
+use slint_fmt::writer::{FileWriter, TokenWriter};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatWorkspaceResponse {
+    pub changed_files: Vec<Url>,
+    pub edit: WorkspaceEdit,
+}
+
+/// Collects the changes done by the formatter as edits of the original tokens
+#[derive(Default)]
+struct TextEditWriter {
+    edits: Vec<TextEdit>,
+}
+
+impl TokenWriter for TextEditWriter {
+    fn no_change(&mut self, _token: SyntaxToken) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn with_new_content(&mut self, token: SyntaxToken, contents: &str) -> std::io::Result<()> {
+        if token.text() != contents {
+            if let Some(range) = map_token(&token) {
+                self.edits.push(TextEdit { range, new_text: contents.into() });
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_before(&mut self, token: SyntaxToken, contents: &str) -> std::io::Result<()> {
+        if !contents.is_empty() {
+            if let Some(range) = map_token(&token) {
+                let range = lsp_types::Range::new(range.start, range.start);
+                self.edits.push(TextEdit { range, new_text: contents.into() });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses `source` for formatting. Returns None if it has syntax errors, as the formatter
+/// could then mangle the incomplete parts.
+fn parse_document(source: String, path: &Path) -> Option<syntax_nodes::Document> {
+    let mut diag = BuildDiagnostics::default();
+    let syntax_node = i_slint_compiler::parser::parse(source, Some(path), None, &mut diag);
+    if diag.has_error() {
+        return None;
+    }
+    syntax_nodes::Document::new(syntax_node)
+}
+
+/// Returns the edits formatting the open document. If `range` is set, only the edits
+/// touching that range are returned.
+pub fn format_document(
+    document_cache: &DocumentCache,
+    text_document: &lsp_types::TextDocumentIdentifier,
+    range: Option<lsp_types::Range>,
+) -> Option<Vec<TextEdit>> {
+    let path = super::uri_to_file(&text_document.uri)?;
+    let source =
+        document_cache.documents.get_document(&path)?.node.as_ref()?.source_file.source()?;
+    let doc = parse_document(source.to_string(), &path)?;
+
+    let mut writer = TextEditWriter::default();
+    slint_fmt::fmt::format_document(doc, &mut writer).ok()?;
+
+    let mut edits = writer.edits;
+    if let Some(range) = range {
+        edits.retain(|edit| edit.range.start <= range.end && edit.range.end >= range.start);
+    }
+    Some(edits)
+}
+
+/// Formats all the `.slint` files found in the `roots` directories.
+///
+/// Files matching the exclude globs and files with syntax errors are left alone.
+pub fn format_workspace(document_cache: &DocumentCache, roots: &[PathBuf]) -> WorkspaceEdit {
+    let mut changes = HashMap::new();
+    for path in roots.iter().flat_map(|root| slint_files(document_cache, root)) {
+        let Some(edit) = format_file(document_cache, &path) else { continue };
+        let Ok(uri) = Url::from_file_path(&path) else { continue };
+        changes.insert(uri, vec![edit]);
+    }
+    WorkspaceEdit { changes: Some(changes), ..Default::default() }
+}
+
+/// Returns the edit replacing the content of the file with its formatted version, or None
+/// if the file cannot be formatted or is already formatted.
+fn format_file(document_cache: &DocumentCache, path: &Path) -> Option<TextEdit> {
+    // Prefer the content of the editor, which might not be saved yet
+    let source = document_cache
+        .documents
+        .get_document(path)
+        .and_then(|doc| Some(doc.node.as_ref()?.source_file.source()?.to_string()))
+        .or_else(|| std::fs::read_to_string(path).ok())?;
+
+    let doc = parse_document(source.clone(), path)?;
+    let range = map_range(&doc.source_file, TextRange::up_to(TextSize::of(source.as_str())));
+
+    let mut formatted = Vec::new();
+    slint_fmt::fmt::format_document(doc, &mut FileWriter { file: &mut formatted }).ok()?;
+    let formatted = String::from_utf8(formatted).ok()?;
+
+    (formatted != source).then(|| TextEdit { range, new_text: formatted })
+}
+
+/// Returns all the `.slint` files in `dir` and its sub-directories that are not excluded
+pub(super) fn slint_files(document_cache: &DocumentCache, dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut result = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if document_cache.is_excluded(&path) {
+            continue;
+        }
+        // Don't follow symlinks to directories to avoid cycles
+        if entry.file_type().map_or(false, |t| t.is_dir()) {
+            result.extend(slint_files(document_cache, &path));
+        } else if path.extension().map_or(false, |e| e == "slint") {
+            result.push(path);
+        }
+    }
+    result.sort();
+    result
+}
+
+#[test]
+fn test_format_workspace() {
+    let root = std::env::temp_dir().join(format!("slint-lsp-format-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    std::fs::create_dir_all(root.join("excluded")).unwrap();
+
+    std::fs::write(root.join("a.slint"), "component   A   {}").unwrap();
+    std::fs::write(root.join("formatted.slint"), "component A { }\n").unwrap();
+    std::fs::write(root.join("broken.slint"), "component   B  { Text { ").unwrap();
+    std::fs::write(root.join("sub/c.slint"), "export component  C  inherits  Text {  }").unwrap();
+    std::fs::write(root.join("excluded/d.slint"), "component   D   {}").unwrap();
+    std::fs::write(root.join("not-slint.txt"), "component   E   {}").unwrap();
+
+    let mut dc = crate::language::test::empty_document_cache();
+    dc.exclude_globs = vec!["excluded".into()];
+
+    let edit = format_workspace(&dc, &[root.clone()]);
+    let changes = edit.changes.unwrap();
+
+    let new_text = |path: &str| {
+        let uri = Url::from_file_path(root.join(path)).unwrap();
+        let edits = changes.get(&uri)?;
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start, lsp_types::Position::new(0, 0));
+        Some(edits[0].new_text.clone())
+    };
+
+    assert_eq!(changes.len(), 2);
+    assert_eq!(new_text("a.slint").unwrap(), "component A { }\n");
+    assert_eq!(new_text("sub/c.slint").unwrap(), "export component C inherits Text { }\n");
+    assert_eq!(new_text("broken.slint"), None);
+    assert_eq!(new_text("formatted.slint"), None);
+    assert_eq!(new_text("excluded/d.slint"), None);
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_format_document() {
+    let source = "component A {\n  // keep me\n  Text {  }\n    Rectangle {}\n}\n";
+    let (dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+    let text_document = lsp_types::TextDocumentIdentifier { uri };
+
+    let apply = |edits: Vec<TextEdit>| {
+        let mut result = source.to_string();
+        for edit in edits.iter().rev() {
+            let offset = |pos: lsp_types::Position| {
+                result.split_inclusive('\n').take(pos.line as usize).map(str::len).sum::<usize>()
+                    + pos.character as usize
+            };
+            let range = offset(edit.range.start)..offset(edit.range.end);
+            result.replace_range(range, &edit.new_text);
+        }
+        result
+    };
+
+    let mut expected = Vec::new();
+    let doc = parse_document(source.into(), Path::new("test.slint")).unwrap();
+    slint_fmt::fmt::format_document(doc, &mut FileWriter { file: &mut expected }).unwrap();
+    let expected = String::from_utf8(expected).unwrap();
+    assert!(expected.contains("// keep me"));
+
+    let edits = format_document(&dc, &text_document, None).unwrap();
+    assert_eq!(apply(edits), expected);
+
+    // Only touch the line of the Rectangle
+    let range =
+        lsp_types::Range::new(lsp_types::Position::new(3, 0), lsp_types::Position::new(3, 16));
+    let edits = format_document(&dc, &text_document, Some(range)).unwrap();
+    assert!(edits.iter().all(|e| e.range.start.line >= 2 && e.range.end.line <= 3));
+    assert!(apply(edits).starts_with("component A {\n  // keep me\n  Text {  }\n"));
+
+    let (dc, uri, _) =
+        crate::language::test::loaded_document_cache("component   B  { Text { ".into());
+    let text_document = lsp_types::TextDocumentIdentifier { uri };
+    assert_eq!(format_document(&dc, &text_document, None), None);
+}
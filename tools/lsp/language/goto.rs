@@ -18,6 +18,14 @@ pub fn goto_definition(
     document_cache: &mut DocumentCache,
     token: SyntaxToken,
 ) -> Option<GotoDefinitionResponse> {
+    goto_node(&find_definition_node(document_cache, token)?)
+}
+
+/// Returns the node where the item referenced by `token` is defined
+pub fn find_definition_node(
+    document_cache: &mut DocumentCache,
+    token: SyntaxToken,
+) -> Option<SyntaxNode> {
     let mut node = token.parent();
     loop {
         if let Some(n) = syntax_nodes::QualifiedName::new(node.clone()) {
@@ -27,8 +35,8 @@ pub fn goto_definition(
                     let qual = i_slint_compiler::object_tree::QualifiedTypeName::from_node(n);
                     let doc = document_cache.documents.get_document(node.source_file.path())?;
                     match doc.local_registry.lookup_qualified(&qual.members) {
-                        Type::Struct { node: Some(node), .. } => goto_node(node.parent().as_ref()?),
-                        Type::Enumeration(e) => goto_node(e.node.as_ref()?),
+                        Type::Struct { node: Some(node), .. } => node.parent(),
+                        Type::Enumeration(e) => e.node.clone().map(Into::into),
                         _ => None,
                     }
                 }
@@ -37,34 +45,15 @@ pub fn goto_definition(
                     let doc = document_cache.documents.get_document(node.source_file.path())?;
                     match doc.local_registry.lookup_element(&qual.to_string()) {
                         Ok(ElementType::Component(c)) => {
-                            goto_node(c.root_element.borrow().node.as_ref()?)
+                            c.root_element.borrow().node.clone().map(Into::into)
                         }
                         _ => None,
                     }
                 }
-                SyntaxKind::Expression => {
-                    if token.kind() != SyntaxKind::Identifier {
-                        return None;
-                    }
-                    let lr = with_lookup_ctx(document_cache, node, |ctx| {
-                        let mut it = n
-                            .children_with_tokens()
-                            .filter_map(|t| t.into_token())
-                            .filter(|t| t.kind() == SyntaxKind::Identifier);
-                        let mut cur_tok = it.next()?;
-                        let first_str =
-                            i_slint_compiler::parser::normalize_identifier(cur_tok.text());
-                        let global = i_slint_compiler::lookup::global_lookup();
-                        let mut expr_it = global.lookup(ctx, &first_str)?;
-                        while cur_tok.token != token.token {
-                            cur_tok = it.next()?;
-                            let str =
-                                i_slint_compiler::parser::normalize_identifier(cur_tok.text());
-                            expr_it = expr_it.lookup(ctx, &str)?;
-                        }
-                        Some(expr_it)
-                    })?;
-                    let gn = match lr? {
+                SyntaxKind::Expression
+                | SyntaxKind::StatePropertyChange
+                | SyntaxKind::PropertyAnimation => {
+                    let gn = match lookup_qualified_name_token(document_cache, n, &token)? {
                         LookupResult::Expression {
                             expression: Expression::ElementReference(e),
                             ..
@@ -99,7 +88,7 @@ pub fn goto_definition(
                         LookupResult::Enumeration(e) => e.node.clone()?.into(),
                         _ => return None,
                     };
-                    goto_node(&gn)
+                    Some(gn)
                 }
                 _ => None,
             };
@@ -107,20 +96,33 @@ pub fn goto_definition(
             let doc = document_cache.documents.get_document(node.source_file.path())?;
             let imp_name = i_slint_compiler::typeloader::ImportedName::from_node(n);
             return match doc.local_registry.lookup_element(&imp_name.internal_name) {
-                Ok(ElementType::Component(c)) => goto_node(c.root_element.borrow().node.as_ref()?),
+                Ok(ElementType::Component(c)) => {
+                    c.root_element.borrow().node.clone().map(Into::into)
+                }
                 _ => None,
             };
         } else if let Some(n) = syntax_nodes::ImportSpecifier::new(node.clone()) {
-            let import_file = node
-                .source_file
-                .path()
-                .parent()
-                .unwrap_or_else(|| Path::new("/"))
-                .join(n.child_text(SyntaxKind::StringLiteral)?.trim_matches('\"'));
-            let import_file = clean_path(&import_file);
+            let import = n.child_text(SyntaxKind::StringLiteral)?;
+            let import = import.trim_matches('\"');
+            // Resolve the include and library paths, as well as the builtin styles, like the
+            // compiler does. Files that were loaded with the `open_import_fallback` are not
+            // found on disk, so they are looked up relative to the importing file.
+            let import_file = document_cache
+                .documents
+                .resolve_import_path(Some(&node.clone().into()), import)
+                .map(|(path, _)| path)
+                .unwrap_or_else(|| {
+                    clean_path(
+                        &node
+                            .source_file
+                            .path()
+                            .parent()
+                            .unwrap_or_else(|| Path::new("/"))
+                            .join(import),
+                    )
+                });
             let doc = document_cache.documents.get_document(&import_file)?;
-            let doc_node = doc.node.clone()?;
-            return goto_node(&doc_node);
+            return doc.node.clone().map(Into::into);
         } else if syntax_nodes::BindingExpression::new(node.clone()).is_some() {
             // don't fallback to the Binding
             return None;
@@ -134,10 +136,9 @@ pub fn goto_definition(
                 (i_slint_compiler::parser::identifier_text(&p.DeclaredIdentifier())? == prop_name)
                     .then_some(p)
             }) {
-                return goto_node(&p);
+                return Some(p.into());
             }
-            let n = find_property_declaration_in_base(document_cache, element, prop_name)?;
-            return goto_node(&n);
+            return find_property_declaration_in_base(document_cache, element, prop_name);
         } else if let Some(n) = syntax_nodes::TwoWayBinding::new(node.clone()) {
             if token.kind() != SyntaxKind::Identifier {
                 return None;
@@ -151,10 +152,9 @@ pub fn goto_definition(
                 (i_slint_compiler::parser::identifier_text(&p.DeclaredIdentifier())? == prop_name)
                     .then_some(p)
             }) {
-                return goto_node(&p);
+                return Some(p.into());
             }
-            let n = find_property_declaration_in_base(document_cache, element, prop_name)?;
-            return goto_node(&n);
+            return find_property_declaration_in_base(document_cache, element, prop_name);
         } else if let Some(n) = syntax_nodes::CallbackConnection::new(node.clone()) {
             if token.kind() != SyntaxKind::Identifier {
                 return None;
@@ -168,15 +168,41 @@ pub fn goto_definition(
                 (i_slint_compiler::parser::identifier_text(&p.DeclaredIdentifier())? == prop_name)
                     .then_some(p)
             }) {
-                return goto_node(&p);
+                return Some(p.into());
             }
-            let n = find_property_declaration_in_base(document_cache, element, prop_name)?;
-            return goto_node(&n);
+            return find_property_declaration_in_base(document_cache, element, prop_name);
         }
         node = node.parent()?;
     }
 }
 
+/// Resolves the qualified name `n` of an expression up to the identifier `token`
+pub(super) fn lookup_qualified_name_token(
+    document_cache: &DocumentCache,
+    n: syntax_nodes::QualifiedName,
+    token: &SyntaxToken,
+) -> Option<LookupResult> {
+    if token.kind() != SyntaxKind::Identifier {
+        return None;
+    }
+    with_lookup_ctx(document_cache, n.clone().into(), |ctx| {
+        let mut it = n
+            .children_with_tokens()
+            .filter_map(|t| t.into_token())
+            .filter(|t| t.kind() == SyntaxKind::Identifier);
+        let mut cur_tok = it.next()?;
+        let first_str = i_slint_compiler::parser::normalize_identifier(cur_tok.text());
+        let global = i_slint_compiler::lookup::global_lookup();
+        let mut expr_it = global.lookup(ctx, &first_str)?;
+        while cur_tok.token != token.token {
+            cur_tok = it.next()?;
+            let str = i_slint_compiler::parser::normalize_identifier(cur_tok.text());
+            expr_it = expr_it.lookup(ctx, &str)?;
+        }
+        Some(expr_it)
+    })?
+}
+
 /// Try to lookup the property `prop_name` in the base of the given Element
 fn find_property_declaration_in_base(
     document_cache: &DocumentCache,
@@ -293,4 +319,12 @@ fn first_link(def: &GotoDefinitionResponse) -> &LocationLink {
     let token = crate::language::token_at_offset(&doc, offset).unwrap();
     assert_eq!(token.text(), "text");
     assert!(goto_definition(&mut dc, token).is_none());
+
+    // The builtin style is resolved, but has no location the editor could open
+    let offset = source.find("\"std-widgets.slint\"").unwrap() as u32;
+    let token = crate::language::token_at_offset(&doc, offset + 1).unwrap();
+    assert_eq!(token.text(), "\"std-widgets.slint\"");
+    let node = find_definition_node(&mut dc, token.clone()).unwrap();
+    assert!(node.source_file.path().starts_with("builtin:/"));
+    assert!(goto_definition(&mut dc, token).is_none());
 }
@@ -0,0 +1,425 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use super::DocumentCache;
+use crate::util::{lookup_current_element_type, map_token};
+
+use i_slint_compiler::expression_tree::Expression;
+use i_slint_compiler::langtype::{ElementType, Type};
+use i_slint_compiler::lookup::LookupResult;
+use i_slint_compiler::object_tree::{recurse_elem, ElementRc, PropertyVisibility};
+use i_slint_compiler::parser::{
+    identifier_text, normalize_identifier, syntax_nodes, SyntaxKind, SyntaxNode, SyntaxToken,
+};
+use i_slint_compiler::passes::apply_default_properties_from_style::PROPERTIES_WITH_STYLE_DEFAULT;
+
+use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind};
+
+pub fn get_tooltip(document_cache: &mut DocumentCache, token: SyntaxToken) -> Option<Hover> {
+    let node = token.parent();
+    let value = if node.kind() == SyntaxKind::DeclaredIdentifier {
+        // Hovering the declaration itself
+        declaration_tooltip(document_cache, &node.parent()?)?
+    } else if let Some(definition) =
+        super::goto::find_definition_node(document_cache, token.clone())
+    {
+        declaration_tooltip(document_cache, &definition)?
+    } else {
+        builtin_property_tooltip(document_cache, &token)?
+    };
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value }),
+        range: map_token(&token),
+    })
+}
+
+/// The tooltip of a declaration in a .slint file: the signature of properties, followed by
+/// the documentation comment
+fn declaration_tooltip(document_cache: &DocumentCache, definition: &SyntaxNode) -> Option<String> {
+    let documentation = doc_comment(definition);
+    let Some(declaration) = syntax_nodes::PropertyDeclaration::new(definition.clone()) else {
+        return documentation;
+    };
+
+    let mut value =
+        format!("```slint\n{}\n```", property_declaration_signature(document_cache, &declaration)?);
+    if let Some(documentation) = documentation {
+        value += "\n\n";
+        value += &documentation;
+    }
+    Some(value)
+}
+
+/// Returns the declaration as it would be written, with the type inferred by the compiler
+/// and the default value when it is a constant.
+fn property_declaration_signature(
+    document_cache: &DocumentCache,
+    declaration: &syntax_nodes::PropertyDeclaration,
+) -> Option<String> {
+    let name = identifier_text(&declaration.DeclaredIdentifier())?;
+    let element = declaration.parent().and_then(|e| find_element(document_cache, &e));
+
+    // The `in`, `out`, ... and `property` keywords
+    let mut signature = declaration
+        .children_with_tokens()
+        .map_while(|t| t.into_token())
+        .filter(|t| t.kind() == SyntaxKind::Identifier)
+        .map(|t| t.text().to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let ty = element
+        .as_ref()
+        .and_then(|e| e.borrow().property_declarations.get(&name).map(|d| d.property_type.clone()))
+        .filter(|ty| !matches!(ty, Type::Invalid | Type::InferredProperty))
+        .map(|ty| ty.to_string())
+        .or_else(|| declaration.Type().map(|t| t.text().to_string().trim().to_string()));
+    if let Some(ty) = ty {
+        signature += &format!(" <{ty}>");
+    }
+    signature += " ";
+    signature += declaration.DeclaredIdentifier().text().to_string().trim();
+
+    if let Some(expression) = declaration.BindingExpression().and_then(|b| b.Expression()) {
+        let is_constant = element.as_ref().map_or(false, |e| {
+            e.borrow().bindings.get(&name).map_or(false, |b| b.borrow().expression.is_constant())
+        });
+        if is_constant {
+            signature += &format!(": {}", expression.text().to_string().trim());
+        }
+    } else if let Some(twb) = declaration.TwoWayBinding() {
+        signature += &format!(" <=> {}", twb.Expression().text().to_string().trim());
+    }
+    Some(signature)
+}
+
+/// Returns the element of the compiled document for the Element `node`
+fn find_element(document_cache: &DocumentCache, node: &SyntaxNode) -> Option<ElementRc> {
+    let doc = document_cache.documents.get_document(node.source_file.path())?;
+    let mut result = None;
+    for component in &doc.inner_components {
+        recurse_elem(&component.root_element, &(), &mut |element: &ElementRc, _| {
+            if element.borrow().node.as_ref().map_or(false, |n| n.node == node.node) {
+                result = Some(element.clone());
+            }
+        });
+    }
+    result
+}
+
+/// The tooltip of a property or callback of a builtin element: its type, the documentation
+/// from builtins.slint, its default value, and whether it is deprecated.
+fn builtin_property_tooltip(document_cache: &DocumentCache, token: &SyntaxToken) -> Option<String> {
+    if token.kind() != SyntaxKind::Identifier {
+        return None;
+    }
+    let name = normalize_identifier(token.text());
+    let mut node = token.parent();
+    let element_type = loop {
+        if let Some(n) = syntax_nodes::QualifiedName::new(node.clone()) {
+            if !matches!(
+                n.parent()?.kind(),
+                SyntaxKind::Expression
+                    | SyntaxKind::StatePropertyChange
+                    | SyntaxKind::PropertyAnimation
+            ) {
+                return None;
+            }
+            match super::goto::lookup_qualified_name_token(document_cache, n, token)? {
+                LookupResult::Expression {
+                    expression:
+                        Expression::PropertyReference(nr) | Expression::CallbackReference(nr, _),
+                    ..
+                } => {
+                    let base_type = nr.element().borrow().base_type.clone();
+                    break base_type;
+                }
+                _ => return None,
+            }
+        } else if matches!(
+            node.kind(),
+            SyntaxKind::Binding | SyntaxKind::TwoWayBinding | SyntaxKind::CallbackConnection
+        ) {
+            if node.child_text(SyntaxKind::Identifier)? != token.text() {
+                return None;
+            }
+            let global_tr = document_cache.documents.global_type_registry.borrow();
+            let tr = document_cache
+                .documents
+                .get_document(node.source_file.path())
+                .map(|doc| &doc.local_registry)
+                .unwrap_or(&global_tr);
+            break lookup_current_element_type(node.parent()?, tr)?;
+        } else if node.kind() == SyntaxKind::BindingExpression {
+            return None;
+        }
+        node = node.parent()?;
+    };
+
+    // Properties declared in .slint files are handled by `declaration_tooltip`
+    let mut element_type = element_type;
+    let builtin = loop {
+        match element_type {
+            ElementType::Component(c) => {
+                if c.root_element.borrow().property_declarations.contains_key(&name) {
+                    return None;
+                }
+                element_type = c.root_element.borrow().base_type.clone();
+            }
+            ElementType::Builtin(b) => break b,
+            _ => return None,
+        }
+    };
+
+    let lookup = ElementType::Builtin(builtin.clone()).lookup_property(&name);
+    if !lookup.is_valid() {
+        return None;
+    }
+    let info = builtin.properties.get(lookup.resolved_name.as_ref());
+
+    let signature = match &lookup.property_type {
+        Type::Callback { args, return_type } => {
+            let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
+            let return_type = return_type.as_ref().map(|r| format!(" -> {r}")).unwrap_or_default();
+            format!("callback {name}({args}){return_type}")
+        }
+        Type::Function { .. } => format!("function {name}()"),
+        ty => {
+            let visibility = match lookup.property_visibility {
+                PropertyVisibility::Input => "in ",
+                PropertyVisibility::Output => "out ",
+                PropertyVisibility::InOut => "in-out ",
+                _ => "",
+            };
+            format!("{visibility}property <{ty}> {name}")
+        }
+    };
+    let mut value = format!("```slint\n{signature}\n```");
+
+    if lookup.resolved_name != name {
+        value += &format!("\n\n**Deprecated**: use `{}` instead.", lookup.resolved_name);
+    }
+    if let Some(documentation) = info.and_then(|i| i.documentation.as_ref()) {
+        value += "\n\n";
+        value += documentation;
+    }
+    if !matches!(lookup.property_type, Type::Callback { .. } | Type::Function { .. })
+        && !PROPERTIES_WITH_STYLE_DEFAULT
+            .contains(&(builtin.name.as_str(), lookup.resolved_name.as_ref()))
+    {
+        let default_value = info
+            .and_then(|i| i.default_value.clone())
+            .unwrap_or_else(|| Expression::default_value_for_type(&lookup.property_type));
+        if let Some(default_value) = literal_text(&default_value) {
+            value += &format!("\n\nDefault: `{default_value}`");
+        }
+    }
+    Some(value)
+}
+
+/// Returns the .slint syntax of the literal `expression`, or None if it is not a literal
+fn literal_text(expression: &Expression) -> Option<String> {
+    Some(match expression {
+        Expression::BoolLiteral(b) => b.to_string(),
+        Expression::NumberLiteral(n, unit) => format!("{n}{unit}"),
+        Expression::StringLiteral(s) => format!("{s:?}"),
+        Expression::EnumerationValue(v) => format!("{}.{v}", v.enumeration.name),
+        Expression::ImageReference {
+            resource_ref: i_slint_compiler::expression_tree::ImageReference::None,
+            ..
+        } => "@image-url(\"\")".into(),
+        Expression::Cast { from, to: Type::Color } => match from.as_ref() {
+            Expression::NumberLiteral(argb, _) => {
+                let argb = *argb as u32;
+                match argb >> 24 {
+                    0 => "transparent".into(),
+                    0xff => format!("#{:06x}", argb & 0xffffff),
+                    alpha => format!("#{:06x}{alpha:02x}", argb & 0xffffff),
+                }
+            }
+            _ => return None,
+        },
+        Expression::Cast { from, .. } => literal_text(from)?,
+        _ => return None,
+    })
+}
+
+/// Returns the text of the comments directly preceding the declaration in `node`,
+/// without the comment markers.
+pub(super) fn doc_comment(node: &SyntaxNode) -> Option<String> {
+    let mut node = node.clone();
+    // The comments of a component are in front of the `component` (or `export`) keyword
+    if node.kind() == SyntaxKind::Element {
+        if let Some(parent) = node.parent().filter(|p| p.kind() == SyntaxKind::Component) {
+            node = parent;
+        }
+    }
+    if let Some(parent) = node.parent().filter(|p| p.kind() == SyntaxKind::ExportsList) {
+        node = parent;
+    }
+
+    let mut lines = Vec::new();
+    let mut token = node.first_token()?.prev_token();
+    while let Some(t) = token {
+        match t.kind() {
+            SyntaxKind::Comment => {
+                let text = t.text();
+                let text = if let Some(text) = text.strip_prefix("//") {
+                    text.trim_start_matches(['/', '!'])
+                } else {
+                    text.strip_prefix("/*")?.strip_suffix("*/")?
+                };
+                lines.push(text.strip_prefix(' ').unwrap_or(text).trim_end().to_string());
+            }
+            // An empty line separates the documentation from other comments
+            SyntaxKind::Whitespace if t.text().matches('\n').count() <= 1 => {}
+            _ => break,
+        }
+        token = t.prev_token();
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    Some(lines.join("\n"))
+}
+
+#[test]
+fn test_hover_doc_comment() {
+    let source = r#"
+// Not documentation
+
+/// A fancy button
+/// with two lines of documentation
+component Abc {
+    //! The greeting shown to the user
+    in property <string> hello;
+
+    in property <int> undocumented;
+}
+export component Test {
+    abc := Abc {
+        hello: "foo";
+        undocumented: 42;
+    }
+}"#;
+
+    let (mut dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+    let doc = dc
+        .documents
+        .get_document(&crate::language::uri_to_file(&uri).unwrap())
+        .unwrap()
+        .node
+        .clone()
+        .unwrap();
+
+    let hover_text = |dc: &mut DocumentCache, offset: usize| {
+        let token = crate::language::token_at_offset(&doc, offset as u32).unwrap();
+        let hover = get_tooltip(dc, token)?;
+        let HoverContents::Markup(markup) = hover.contents else { panic!("not markup") };
+        assert_eq!(markup.kind, MarkupKind::Markdown);
+        Some(markup.value)
+    };
+
+    let hello = "```slint\nin property <string> hello\n```\n\nThe greeting shown to the user";
+
+    // Property in a binding
+    let offset = source.find("hello: \"foo\"").unwrap() + 1;
+    assert_eq!(hover_text(&mut dc, offset).unwrap(), hello);
+
+    // Property at its declaration
+    let offset = source.find("hello;").unwrap() + 1;
+    assert_eq!(hover_text(&mut dc, offset).unwrap(), hello);
+
+    // Component type
+    let offset = source.find("abc := Abc").unwrap() + 8;
+    assert_eq!(
+        hover_text(&mut dc, offset).unwrap(),
+        "A fancy button\nwith two lines of documentation"
+    );
+
+    // Not documented
+    let offset = source.find("undocumented: 42").unwrap() + 1;
+    assert_eq!(
+        hover_text(&mut dc, offset).unwrap(),
+        "```slint\nin property <int> undocumented\n```"
+    );
+}
+
+#[test]
+fn test_hover_property_signature() {
+    let source = r#"
+export component Test {
+    property <int> counter: 4 + 2;
+    property <length> position: ta.mouse-x;
+    property inferred <=> txt.text;
+    Rectangle {
+        background: red;
+        color: blue;
+    }
+    ta := TouchArea {
+        clicked => { debug(root.counter); }
+    }
+    txt := Text { color: ta.pressed ? red : blue; }
+}"#;
+
+    let (mut dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+    let doc = dc
+        .documents
+        .get_document(&crate::language::uri_to_file(&uri).unwrap())
+        .unwrap()
+        .node
+        .clone()
+        .unwrap();
+
+    let hover_text = |dc: &mut DocumentCache, needle: &str| {
+        let offset = source.find(needle).unwrap() + 1;
+        let token = crate::language::token_at_offset(&doc, offset as u32).unwrap();
+        let HoverContents::Markup(markup) = get_tooltip(dc, token)?.contents else {
+            panic!("not markup")
+        };
+        Some(markup.value)
+    };
+
+    // Constant default values are shown, other bindings are not
+    assert_eq!(
+        hover_text(&mut dc, "counter:").unwrap(),
+        "```slint\nproperty <int> counter: 4 + 2\n```"
+    );
+    assert_eq!(
+        hover_text(&mut dc, "position:").unwrap(),
+        "```slint\nproperty <length> position\n```"
+    );
+    assert_eq!(
+        hover_text(&mut dc, "inferred <=>").unwrap(),
+        "```slint\nproperty <string> inferred <=> txt.text\n```"
+    );
+
+    // Builtin properties
+    assert_eq!(
+        hover_text(&mut dc, "background: red").unwrap(),
+        "```slint\nin property <brush> background\n```\n\nThe brush used to fill the rectangle.\n\nDefault: `transparent`"
+    );
+    assert_eq!(
+        hover_text(&mut dc, "color: blue").unwrap(),
+        "```slint\nin property <brush> color\n```\n\n**Deprecated**: use `background` instead.\n\nThe brush used to fill the rectangle.\n\nDefault: `transparent`"
+    );
+    assert_eq!(
+        hover_text(&mut dc, "clicked =>").unwrap(),
+        "```slint\ncallback clicked()\n```\n\nInvoked when the touch area was clicked."
+    );
+    assert_eq!(
+        hover_text(&mut dc, "pressed ?").unwrap(),
+        "```slint\nout property <bool> pressed\n```\n\nTrue while the mouse or finger is pressed on the touch area.\n\nDefault: `false`"
+    );
+    // The default of the text color comes from the style
+    assert_eq!(
+        hover_text(&mut dc, "color: ta").unwrap(),
+        "```slint\nin property <brush> color\n```\n\nThe color of the text. Defaults to the text color of the style."
+    );
+
+    // Not a property
+    assert_eq!(hover_text(&mut dc, "debug("), None);
+}
@@ -0,0 +1,143 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use i_slint_compiler::expression_tree::Expression;
+use i_slint_compiler::langtype::Type;
+use i_slint_compiler::object_tree::{recurse_elem, ElementRc};
+use i_slint_compiler::parser::{syntax_nodes, SyntaxNode};
+use lsp_types::{InlayHint, InlayHintKind, InlayHintLabel};
+
+use super::DocumentCache;
+use crate::util::map_position;
+
+/// Returns hints with the types the compiler inferred for properties declared without a type,
+/// and for the model data of repeated elements, within the given range of the document.
+pub fn get_inlay_hints(
+    document_cache: &mut DocumentCache,
+    text_document: &lsp_types::TextDocumentIdentifier,
+    range: &lsp_types::Range,
+) -> Option<Vec<InlayHint>> {
+    let filepath = super::uri_to_file(&text_document.uri)?;
+    let doc = document_cache.documents.get_document(&filepath)?;
+
+    let mut result = vec![];
+    for component in &doc.inner_components {
+        recurse_elem(&component.root_element, &(), &mut |element: &ElementRc, _| {
+            collect_element_hints(element, &mut result);
+        });
+    }
+
+    result.retain(|hint| hint.position >= range.start && hint.position <= range.end);
+    result.sort_by(|a, b| a.position.cmp(&b.position));
+    Some(result)
+}
+
+fn collect_element_hints(element: &ElementRc, result: &mut Vec<InlayHint>) {
+    let elem = element.borrow();
+
+    for declaration in elem.property_declarations.values() {
+        let Some(node) = declaration.node.clone().and_then(syntax_nodes::PropertyDeclaration::new)
+        else {
+            continue;
+        };
+        if node.Type().is_some() {
+            continue;
+        }
+        let Some(ty) = displayable_type(&declaration.property_type) else { continue };
+        let identifier: SyntaxNode = node.DeclaredIdentifier().into();
+        result.push(type_hint(
+            &identifier,
+            identifier.text_range().start(),
+            format!("<{ty}>"),
+            true,
+        ));
+    }
+
+    if elem.repeated.as_ref().map_or(false, |r| !r.model_data_id.is_empty()) {
+        let model_data_identifier = elem
+            .node
+            .as_ref()
+            .and_then(|n| n.parent())
+            .and_then(|n| n.parent())
+            .and_then(syntax_nodes::RepeatedElement::new)
+            .and_then(|n| n.DeclaredIdentifier());
+        let ty =
+            Expression::RepeaterModelReference { element: std::rc::Rc::downgrade(element) }.ty();
+        if let (Some(identifier), Some(ty)) = (model_data_identifier, displayable_type(&ty)) {
+            let identifier: SyntaxNode = identifier.into();
+            result.push(type_hint(
+                &identifier,
+                identifier.text_range().end(),
+                format!(": {ty}"),
+                false,
+            ));
+        }
+    }
+}
+
+fn displayable_type(ty: &Type) -> Option<&Type> {
+    (!matches!(ty, Type::Invalid | Type::InferredProperty | Type::InferredCallback)).then_some(ty)
+}
+
+fn type_hint(
+    node: &SyntaxNode,
+    offset: i_slint_compiler::parser::TextSize,
+    label: String,
+    before: bool,
+) -> InlayHint {
+    InlayHint {
+        position: map_position(&node.source_file, offset),
+        label: InlayHintLabel::String(label),
+        kind: Some(InlayHintKind::TYPE),
+        text_edits: None,
+        tooltip: None,
+        padding_left: Some(!before),
+        padding_right: Some(before),
+        data: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::language::test::loaded_document_cache;
+
+    fn hints(source: &str) -> Vec<(u32, u32, String)> {
+        let (mut dc, url, _) = loaded_document_cache(source.into());
+        let range = lsp_types::Range::new(
+            lsp_types::Position::new(0, 0),
+            lsp_types::Position::new(u32::MAX, u32::MAX),
+        );
+        get_inlay_hints(&mut dc, &lsp_types::TextDocumentIdentifier { uri: url }, &range)
+            .unwrap()
+            .into_iter()
+            .map(|hint| {
+                let InlayHintLabel::String(label) = hint.label else { panic!("Unexpected label") };
+                (hint.position.line, hint.position.character, label)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_inlay_hints() {
+        let result = hints(
+            r#"component Main inherits Rectangle {
+    property <[string]> names;
+    property alias <=> label.text;
+    property <int> explicit;
+    for name in names: Text { text: name; }
+    for index in 3: Rectangle { }
+    label := Text { }
+}"#,
+        );
+        assert_eq!(
+            result,
+            vec![
+                (2, 13, "<string>".to_string()),
+                (4, 12, ": string".to_string()),
+                (5, 13, ": int".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,462 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use super::DocumentCache;
+use crate::util::map_range;
+
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_prelude::*;
+
+use i_slint_compiler::parser::{
+    identifier_text, normalize_identifier, syntax_nodes, SyntaxKind, SyntaxNode, TextRange,
+};
+use lsp_types::{
+    CreateFile, CreateFileOptions, DocumentChangeOperation, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, ResourceOp, TextDocumentEdit, TextEdit, Url,
+    WorkspaceEdit,
+};
+
+use std::collections::HashSet;
+
+/// A component, global, struct or enum declared at the top level of a document
+struct Declaration {
+    name: String,
+    /// The declaration, or the `export` list wrapping it
+    node: SyntaxNode,
+    /// The range of the node, including the comments directly preceding it
+    range: TextRange,
+    /// The range to delete when moving the declaration away
+    removal_range: TextRange,
+    /// The declaration is written as `export component ...`
+    export_keyword: bool,
+    /// The declaration is exported, either with the `export` keyword or in an export list
+    exported: bool,
+    /// The identifiers used in the declaration
+    uses: HashSet<String>,
+}
+
+/// An `import { ... } from "..."` statement
+struct Import {
+    node: SyntaxNode,
+    removal_range: TextRange,
+    /// The file name, including the quotes
+    file: Option<String>,
+    /// The local name and the source text of the imported identifiers
+    identifiers: Vec<(String, String)>,
+}
+
+/// Returns the edit moving the top level `component` into a new file named after it.
+///
+/// Non-exported declarations only used by the moved component are moved along with it.
+/// The declarations of the original file the component still depends on (like globals) are
+/// exported and imported from the new file, and the original file imports the component back
+/// if it is still used or exported there.
+pub fn move_component_to_file(
+    document_cache: &DocumentCache,
+    component: &syntax_nodes::Component,
+) -> Option<WorkspaceEdit> {
+    let component_name = identifier_text(&component.DeclaredIdentifier())?;
+    let parent = component.parent()?;
+    let document = if parent.kind() == SyntaxKind::ExportsList { parent.parent()? } else { parent };
+    if document.kind() != SyntaxKind::Document {
+        return None;
+    }
+
+    let source_file = &document.source_file;
+    let source = source_file.source()?;
+    let path = source_file.path();
+    let uri = Url::from_file_path(path).ok()?;
+    let file_name = path.file_name()?.to_string_lossy().to_string();
+    let new_file_name = format!("{}.slint", super::scaffold::file_stem(&component_name));
+    if new_file_name == file_name {
+        return None;
+    }
+    let new_uri = Url::from_file_path(path.with_file_name(&new_file_name)).ok()?;
+
+    let declarations = declarations(&document);
+    let imports = imports(&document);
+    let target = declarations.iter().position(|d| d.name == component_name)?;
+
+    // Move the private helpers that are only used by the moved declarations
+    let mut moved = vec![false; declarations.len()];
+    moved[target] = true;
+    loop {
+        let mut changed = false;
+        for (i, declaration) in declarations.iter().enumerate() {
+            if moved[i] || declaration.exported {
+                continue;
+            }
+            let mut users =
+                declarations.iter().enumerate().filter(|(_, d)| d.uses.contains(&declaration.name));
+            let used_by_moved = users.clone().any(|(j, _)| moved[j]);
+            if used_by_moved && !users.any(|(j, _)| !moved[j] && j != i) {
+                moved[i] = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let uses = |moved_ones: bool| {
+        declarations
+            .iter()
+            .zip(&moved)
+            .filter(|(_, m)| **m == moved_ones)
+            .flat_map(|(d, _)| d.uses.iter().cloned())
+            .collect::<HashSet<_>>()
+    };
+    let moved_uses = uses(true);
+    let remaining_uses = uses(false);
+
+    let text = |range: TextRange| &source[std::ops::Range::<usize>::from(range)];
+    let mut edits = Vec::new();
+
+    // The declarations staying in the original file that are used by the moved ones
+    let mut dependencies = Vec::new();
+    for (declaration, _) in declarations.iter().zip(&moved).filter(|(_, m)| !**m) {
+        if !moved_uses.contains(&declaration.name) {
+            continue;
+        }
+        if !declaration.exported {
+            let start = declaration.node.text_range().start();
+            edits.push((TextRange::empty(start), "export ".to_string()));
+        }
+        dependencies.push(declaration.name.as_str());
+    }
+
+    let mut new_source = String::new();
+    for import in &imports {
+        let Some(file) = &import.file else { continue };
+        let used = import
+            .identifiers
+            .iter()
+            .filter(|(name, _)| moved_uses.contains(name))
+            .map(|(_, text)| text.as_str())
+            .collect::<Vec<_>>();
+        if !used.is_empty() {
+            new_source += &format!("import {{ {} }} from {file};\n", used.join(", "));
+        }
+    }
+    if !dependencies.is_empty() {
+        new_source += &format!("import {{ {} }} from \"{file_name}\";\n", dependencies.join(", "));
+    }
+    if !new_source.is_empty() {
+        new_source.push('\n');
+    }
+    let mut moved_texts = Vec::new();
+    for (i, declaration) in declarations.iter().enumerate().filter(|(i, _)| moved[*i]) {
+        let mut moved_text = text(declaration.range).to_string();
+        if i == target && !declaration.export_keyword {
+            let offset = declaration.node.text_range().start() - declaration.range.start();
+            moved_text.insert_str(offset.into(), "export ");
+        }
+        moved_texts.push(moved_text);
+        edits.push((declaration.removal_range, String::new()));
+    }
+    new_source += &moved_texts.join("\n\n");
+    new_source.push('\n');
+
+    // Import the component back where it is still needed
+    let target = &declarations[target];
+    let mut new_imports = Vec::new();
+    if target.exported || remaining_uses.contains(&target.name) {
+        new_imports.push(format!("import {{ {component_name} }} from \"{new_file_name}\";"));
+    }
+    if target.export_keyword {
+        new_imports.push(format!("export {{ {component_name} }}"));
+    }
+
+    // Drop the imports that are only used by the moved declarations
+    for (i, import) in imports.iter().enumerate() {
+        let kept = import
+            .identifiers
+            .iter()
+            .filter(|(name, _)| !moved_uses.contains(name) || remaining_uses.contains(name))
+            .map(|(_, text)| text.as_str())
+            .collect::<Vec<_>>();
+        let is_last = i == imports.len() - 1;
+        let mut statements = Vec::new();
+        if kept.len() == import.identifiers.len() {
+            if !is_last || new_imports.is_empty() {
+                continue;
+            }
+            statements.push(import.node.text().to_string());
+        } else if !kept.is_empty() {
+            let file = import.file.as_deref().unwrap_or_default();
+            statements.push(format!("import {{ {} }} from {file};", kept.join(", ")));
+        }
+        if is_last {
+            statements.append(&mut new_imports);
+        }
+        if statements.is_empty() {
+            edits.push((import.removal_range, String::new()));
+        } else {
+            edits.push((import.node.text_range(), statements.join("\n")));
+        }
+    }
+    if !new_imports.is_empty() {
+        // There were no imports: add them in front of the first declaration
+        let start = declarations.iter().map(|d| d.range.start()).min()?;
+        let new_text = new_imports.join("\n") + "\n\n";
+        match edits.iter_mut().find(|(range, _)| range.start() == start) {
+            Some((_, text)) => text.insert_str(0, &new_text),
+            None => edits.push((TextRange::empty(start), new_text)),
+        }
+    }
+
+    edits.sort_by_key(|(range, _)| (range.start(), range.end()));
+    let edits = edits
+        .into_iter()
+        .map(|(range, new_text)| {
+            OneOf::Left(TextEdit::new(map_range(source_file, range), new_text))
+        })
+        .collect();
+
+    Some(WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Operations(vec![
+            DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                uri: new_uri.clone(),
+                options: Some(CreateFileOptions {
+                    overwrite: Some(false),
+                    ignore_if_exists: Some(false),
+                }),
+                annotation_id: None,
+            })),
+            DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    uri: new_uri,
+                    version: None,
+                },
+                edits: vec![OneOf::Left(TextEdit::new(Default::default(), new_source))],
+            }),
+            DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    version: document_cache.document_version(&uri),
+                    uri,
+                },
+                edits,
+            }),
+        ])),
+        ..Default::default()
+    })
+}
+
+fn declarations(document: &SyntaxNode) -> Vec<Declaration> {
+    let is_declaration = |node: &SyntaxNode| {
+        matches!(
+            node.kind(),
+            SyntaxKind::Component | SyntaxKind::StructDeclaration | SyntaxKind::EnumDeclaration
+        )
+    };
+
+    let mut result = Vec::new();
+    let mut exported_names = HashSet::new();
+    for child in document.children() {
+        let declaration = if is_declaration(&child) {
+            child.clone()
+        } else if child.kind() == SyntaxKind::ExportsList {
+            exported_names.extend(
+                child
+                    .children()
+                    .filter(|n| n.kind() == SyntaxKind::ExportSpecifier)
+                    .filter_map(|n| n.child_node(SyntaxKind::ExportIdentifier))
+                    .filter_map(|n| identifier_text(&n)),
+            );
+            let mut declarations = child.children().filter(is_declaration);
+            match (declarations.next(), declarations.next()) {
+                (Some(declaration), None) => declaration,
+                _ => continue,
+            }
+        } else {
+            continue;
+        };
+        let Some(name) = declaration
+            .child_node(SyntaxKind::DeclaredIdentifier)
+            .and_then(|n| identifier_text(&n))
+        else {
+            continue;
+        };
+        let uses = child
+            .descendants_with_tokens()
+            .filter_map(|e| e.into_token())
+            .filter(|t| t.kind() == SyntaxKind::Identifier)
+            .map(|t| normalize_identifier(t.text()))
+            .filter(|n| *n != name)
+            .collect();
+        let range = range_with_comments(&child);
+        let export_keyword = child.kind() == SyntaxKind::ExportsList;
+        result.push(Declaration {
+            name,
+            removal_range: removal_range(&child, range),
+            node: child,
+            range,
+            export_keyword,
+            exported: export_keyword,
+            uses,
+        });
+    }
+    for declaration in &mut result {
+        declaration.exported |= exported_names.contains(&declaration.name);
+    }
+    result
+}
+
+fn imports(document: &SyntaxNode) -> Vec<Import> {
+    document
+        .children()
+        .filter(|n| n.kind() == SyntaxKind::ImportSpecifier)
+        .map(|node| {
+            let identifiers = node
+                .child_node(SyntaxKind::ImportIdentifierList)
+                .into_iter()
+                .flat_map(|list| list.children())
+                .filter_map(|identifier| {
+                    let local_name = identifier
+                        .child_node(SyntaxKind::InternalName)
+                        .or_else(|| identifier.child_node(SyntaxKind::ExternalName))?;
+                    Some((
+                        identifier_text(&local_name)?,
+                        identifier.text().to_string().trim().to_string(),
+                    ))
+                })
+                .collect();
+            Import {
+                removal_range: removal_range(&node, node.text_range()),
+                file: node.child_text(SyntaxKind::StringLiteral),
+                identifiers,
+                node,
+            }
+        })
+        // `import "font.ttf";` only registers a font, leave it alone
+        .filter(|import| import.node.child_node(SyntaxKind::ImportIdentifierList).is_some())
+        .collect()
+}
+
+/// Extends the range of `node` to the comments directly preceding it
+fn range_with_comments(node: &SyntaxNode) -> TextRange {
+    let mut start = node.text_range().start();
+    let mut token = node.first_token().and_then(|t| t.prev_token());
+    while let Some(t) = token {
+        match t.kind() {
+            SyntaxKind::Comment => start = t.text_range().start(),
+            // An empty line separates the declaration from other comments
+            SyntaxKind::Whitespace if t.text().matches('\n').count() <= 1 => {}
+            _ => break,
+        }
+        token = t.prev_token();
+    }
+    TextRange::new(start, node.text_range().end())
+}
+
+/// Extends `range` to the whitespace following `node`
+fn removal_range(node: &SyntaxNode, range: TextRange) -> TextRange {
+    match node.last_token().and_then(|t| t.next_token()) {
+        Some(t) if t.kind() == SyntaxKind::Whitespace => {
+            TextRange::new(range.start(), t.text_range().end())
+        }
+        _ => range,
+    }
+}
+
+#[test]
+fn test_move_component_to_file() {
+    let source = r#"import { Button, CheckBox } from "std-widgets.slint";
+
+global Palette {
+    out property <color> accent: blue;
+}
+
+component Helper inherits Rectangle {
+    background: Palette.accent;
+}
+
+/// A card with a button
+export component Card inherits Rectangle {
+    Helper { }
+    Button { text: "Ok"; }
+}
+
+export component Main inherits Window {
+    Card { }
+    CheckBox { }
+    background: Palette.accent;
+}
+"#;
+    let (dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+    let doc = dc.documents.get_document(&super::uri_to_file(&uri).unwrap()).unwrap();
+    let component = doc
+        .node
+        .as_ref()
+        .unwrap()
+        .ExportsList()
+        .find_map(|e| e.Component())
+        .filter(|c| identifier_text(&c.DeclaredIdentifier()).as_deref() == Some("Card"))
+        .unwrap();
+
+    let edit = move_component_to_file(&dc, &component).unwrap();
+    let Some(DocumentChanges::Operations(operations)) = edit.document_changes else {
+        panic!("Expected document operations")
+    };
+    assert_eq!(operations.len(), 3);
+    let DocumentChangeOperation::Op(ResourceOp::Create(create)) = &operations[0] else {
+        panic!("Expected a file creation")
+    };
+    assert_eq!(create.uri, uri.join("card.slint").unwrap());
+
+    let DocumentChangeOperation::Edit(new_file) = &operations[1] else {
+        panic!("Expected an edit")
+    };
+    assert_eq!(new_file.text_document.uri, create.uri);
+    let OneOf::Left(new_file) = &new_file.edits[0] else { panic!("Expected a text edit") };
+    assert_eq!(
+        new_file.new_text,
+        r#"import { Button } from "std-widgets.slint";
+import { Palette } from "bar.slint";
+
+component Helper inherits Rectangle {
+    background: Palette.accent;
+}
+
+/// A card with a button
+export component Card inherits Rectangle {
+    Helper { }
+    Button { text: "Ok"; }
+}
+"#
+    );
+
+    let DocumentChangeOperation::Edit(original) = &operations[2] else {
+        panic!("Expected an edit")
+    };
+    assert_eq!(original.text_document.uri, uri);
+    // Apply the edits from the end so that the positions before stay valid
+    let mut result = source.to_string();
+    for edit in original.edits.iter().rev() {
+        let OneOf::Left(edit) = edit else { panic!("Expected a text edit") };
+        let offset = |position: lsp_types::Position| {
+            let line_start: usize =
+                source.split_inclusive('\n').take(position.line as usize).map(str::len).sum();
+            line_start + position.character as usize
+        };
+        result.replace_range(offset(edit.range.start)..offset(edit.range.end), &edit.new_text);
+    }
+    assert_eq!(
+        result,
+        r#"import { CheckBox } from "std-widgets.slint";
+import { Card } from "card.slint";
+export { Card }
+
+export global Palette {
+    out property <color> accent: blue;
+}
+
+export component Main inherits Window {
+    Card { }
+    CheckBox { }
+    background: Palette.accent;
+}
+"#
+    );
+}
@@ -0,0 +1,211 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+//! Replace color literals with the roles of the `Palette` of the style
+
+use super::DocumentCache;
+use crate::util::map_token;
+
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_prelude::*;
+
+use i_slint_compiler::expression_tree::Expression;
+use i_slint_compiler::langtype::{ElementType, Type};
+use i_slint_compiler::object_tree::Component;
+use i_slint_compiler::parser::{SyntaxKind, SyntaxToken};
+use lsp_types::{TextEdit, Url, WorkspaceEdit};
+
+use std::rc::Rc;
+
+/// The maximum distance between the color literal and the color of a role, in the RGBA space
+/// with 255 per channel, for the role to be proposed
+const MAX_COLOR_DISTANCE: f32 = 20.;
+
+/// Returns the `Palette` roles that have a color close to the color literal `token`, with the
+/// edit replacing the literal with the role, closest first.
+pub fn palette_role_edits(
+    document_cache: &DocumentCache,
+    token: &SyntaxToken,
+) -> Option<Vec<(String, WorkspaceEdit)>> {
+    if token.kind() != SyntaxKind::ColorLiteral {
+        return None;
+    }
+    let color = i_slint_compiler::literals::parse_color_literal(token.text())?;
+    let uri = Url::from_file_path(token.source_file.path()).ok()?;
+    let range = map_token(token)?;
+    let palette = std_widgets_palette(document_cache)?;
+
+    // `Palette` may not be imported, or refer to something else in this file
+    let doc = document_cache.documents.get_document(token.source_file.path())?;
+    let import_edit = match doc.local_registry.lookup_element("Palette") {
+        Ok(ElementType::Component(c)) if Rc::ptr_eq(&c, &palette) => None,
+        Ok(_) => return None,
+        Err(_) => Some(super::completion::import_edit_builder(token, document_cache)?(
+            "std-widgets.slint",
+            "Palette",
+        )),
+    };
+
+    let mut roles = palette_colors(&palette)
+        .into_iter()
+        .filter_map(|(role, colors)| {
+            let distance = colors
+                .into_iter()
+                .map(|c| color_distance(c, color))
+                .min_by(|a, b| a.total_cmp(b))?;
+            (distance <= MAX_COLOR_DISTANCE).then_some((distance, role))
+        })
+        .collect::<Vec<_>>();
+    roles.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    Some(
+        roles
+            .into_iter()
+            .map(|(_, role)| {
+                let edits = std::iter::once(TextEdit::new(range, format!("Palette.{role}")))
+                    .chain(import_edit.clone())
+                    .collect();
+                let edit = WorkspaceEdit {
+                    changes: Some(std::iter::once((uri.clone(), edits)).collect()),
+                    ..Default::default()
+                };
+                (role, edit)
+            })
+            .collect(),
+    )
+}
+
+/// The `Palette` global exported by the std-widgets.slint of the style
+fn std_widgets_palette(document_cache: &DocumentCache) -> Option<Rc<Component>> {
+    document_cache
+        .documents
+        .all_files()
+        .filter(|f| f.starts_with("builtin:/"))
+        .filter(|f| f.file_name().map_or(false, |n| n == "std-widgets.slint"))
+        .find_map(|f| document_cache.documents.get_document(f)?.exports.find("Palette")?.left())
+}
+
+/// Returns the colors the roles of the palette can have, as ARGB
+fn palette_colors(palette: &Component) -> Vec<(String, Vec<u32>)> {
+    let root = palette.root_element.borrow();
+    root.property_declarations
+        .iter()
+        .filter(|(_, d)| matches!(d.property_type, Type::Brush | Type::Color))
+        .filter_map(|(name, _)| {
+            let mut colors = vec![];
+            static_colors(&root.bindings.get(name)?.borrow().expression, 0, &mut colors);
+            (!colors.is_empty()).then(|| (name.clone(), colors))
+        })
+        .collect()
+}
+
+/// Collects the colors `expression` can evaluate to, following the references to other
+/// properties and both branches of conditions, such as the ones for the dark and light
+/// color schemes.
+fn static_colors(expression: &Expression, depth: usize, result: &mut Vec<u32>) {
+    // Guards against binding loops
+    if depth > 8 {
+        return;
+    }
+    match expression {
+        Expression::Cast { from, to: Type::Color } => {
+            if let Expression::NumberLiteral(argb, _) = from.as_ref() {
+                result.push(*argb as u32);
+            }
+        }
+        Expression::Cast { from, to: Type::Brush } => static_colors(from, depth, result),
+        Expression::Condition { true_expr, false_expr, .. } => {
+            static_colors(true_expr, depth, result);
+            static_colors(false_expr, depth, result);
+        }
+        Expression::CodeBlock(sub) if sub.len() == 1 => static_colors(&sub[0], depth, result),
+        Expression::PropertyReference(nr) => {
+            let element = nr.element();
+            let element = element.borrow();
+            if let Some(binding) = element.bindings.get(nr.name()) {
+                static_colors(&binding.borrow().expression, depth + 1, result);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The euclidean distance between two ARGB colors
+fn color_distance(a: u32, b: u32) -> f32 {
+    (0..4)
+        .map(|i| {
+            let shift = i * 8;
+            ((a >> shift) & 0xff) as f32 - ((b >> shift) & 0xff) as f32
+        })
+        .map(|d| d * d)
+        .sum::<f32>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::language::test::loaded_document_cache;
+
+    fn role_edits(source: &str, literal: &str) -> Vec<(String, Vec<TextEdit>)> {
+        let (dc, url, _) = loaded_document_cache(source.into());
+        let doc = dc
+            .documents
+            .get_document(&crate::language::uri_to_file(&url).unwrap())
+            .unwrap()
+            .node
+            .clone()
+            .unwrap();
+        let offset = source.rfind(literal).unwrap() as u32 + 1;
+        let token = crate::language::token_at_offset(&doc, offset).unwrap();
+        palette_role_edits(&dc, &token)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(role, mut edit)| (role, edit.changes.as_mut().unwrap().remove(&url).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_palette_role_edits() {
+        let source = r#"import { Palette } from "std-widgets.slint";
+export component Test {
+    Rectangle { background: #005fb9; }
+    Rectangle { background: #123456; }
+}"#;
+        // The fluent accent color is #005FB8 in the light color scheme
+        let edits = role_edits(source, "#005fb9");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].0, "accent-background");
+        assert_eq!(edits[0].1.len(), 1);
+        assert_eq!(edits[0].1[0].new_text, "Palette.accent-background");
+        assert_eq!(edits[0].1[0].range.start, lsp_types::Position::new(2, 28));
+        assert_eq!(edits[0].1[0].range.end, lsp_types::Position::new(2, 35));
+
+        // Too far from any role
+        assert!(role_edits(source, "#123456").is_empty());
+    }
+
+    #[test]
+    fn test_palette_role_edits_import() {
+        let source = r#"import { Button } from "std-widgets.slint";
+export component Test {
+    Rectangle { background: #60cdff; }
+}"#;
+        // The fluent accent color is #60CDFF in the dark color scheme
+        let edits = role_edits(source, "#60cdff");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].0, "accent-background");
+        assert_eq!(edits[0].1.len(), 2);
+        assert_eq!(edits[0].1[1].new_text, ", Palette");
+        assert_eq!(edits[0].1[1].range.start, lsp_types::Position::new(0, 15));
+
+        // A local `Palette` is not replaced
+        let source = r#"import { Button } from "std-widgets.slint";
+global Palette { out property <brush> accent: #60cdff; }
+export component Test {
+    Rectangle { background: #60cdff; }
+}"#;
+        assert!(role_edits(source, "#60cdff").is_empty());
+    }
+}
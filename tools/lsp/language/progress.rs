@@ -0,0 +1,105 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use crate::common::Result;
+use crate::ServerNotifier;
+
+use lsp_types::notification::Notification;
+use lsp_types::{
+    InitializeParams, NumberOrString, ProgressParams, ProgressParamsValue, WorkDoneProgress,
+    WorkDoneProgressBegin, WorkDoneProgressCreateParams, WorkDoneProgressEnd,
+    WorkDoneProgressReport,
+};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Reports the progress of a long running operation to the client with `$/progress`
+/// notifications.
+///
+/// The reporter is shared with the `open_import_fallback` of the compiler configuration,
+/// so that every file that gets loaded while an operation is in progress is reported.
+#[derive(Clone, Default)]
+pub struct ProgressReporter(Rc<RefCell<Option<(ServerNotifier, NumberOrString)>>>);
+
+impl ProgressReporter {
+    /// Asks the client to create a progress token and starts reporting with the given title.
+    /// An operation that is still in progress is ended first.
+    ///
+    /// Does nothing if the client does not support server initiated progress.
+    pub async fn begin(
+        &self,
+        server_notifier: &ServerNotifier,
+        init_param: &InitializeParams,
+        title: &str,
+    ) -> Result<()> {
+        if !init_param
+            .capabilities
+            .window
+            .as_ref()
+            .and_then(|w| w.work_done_progress)
+            .unwrap_or(false)
+        {
+            return Ok(());
+        }
+        self.end();
+
+        let token = NumberOrString::String("slint/progress".into());
+        server_notifier
+            .send_request::<lsp_types::request::WorkDoneProgressCreate>(
+                WorkDoneProgressCreateParams { token: token.clone() },
+            )?
+            .await?;
+
+        send_progress(
+            server_notifier,
+            &token,
+            WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: title.into(),
+                cancellable: Some(false),
+                message: None,
+                percentage: None,
+            }),
+        );
+        *self.0.borrow_mut() = Some((server_notifier.clone(), token));
+        Ok(())
+    }
+
+    /// Reports `message` if an operation is in progress
+    pub fn report(&self, message: String) {
+        if let Some((server_notifier, token)) = self.0.borrow().as_ref() {
+            send_progress(
+                server_notifier,
+                token,
+                WorkDoneProgress::Report(WorkDoneProgressReport {
+                    cancellable: Some(false),
+                    message: Some(message),
+                    percentage: None,
+                }),
+            );
+        }
+    }
+
+    /// Ends the operation in progress, if any
+    pub fn end(&self) {
+        if let Some((server_notifier, token)) = self.0.borrow_mut().take() {
+            send_progress(
+                &server_notifier,
+                &token,
+                WorkDoneProgress::End(WorkDoneProgressEnd { message: None }),
+            );
+        }
+    }
+}
+
+fn send_progress(
+    server_notifier: &ServerNotifier,
+    token: &NumberOrString,
+    value: WorkDoneProgress,
+) {
+    // Progress is only informative, so errors are not worth failing the operation for
+    let _ = server_notifier.send_notification(
+        lsp_types::notification::Progress::METHOD.into(),
+        ProgressParams { token: token.clone(), value: ProgressParamsValue::WorkDone(value) },
+    );
+}
@@ -9,8 +9,11 @@
 };
 
 use i_slint_compiler::diagnostics::{BuildDiagnostics, SourceFileVersion, Spanned};
+use i_slint_compiler::expression_tree::{Expression, Unit};
 use i_slint_compiler::langtype::{ElementType, Type};
-use i_slint_compiler::object_tree::{Element, ElementRc, PropertyDeclaration, PropertyVisibility};
+use i_slint_compiler::object_tree::{
+    Component, Element, ElementRc, PropertyDeclaration, PropertyVisibility,
+};
 use i_slint_compiler::parser::{syntax_nodes, Language, SyntaxKind};
 
 use std::collections::HashSet;
@@ -59,6 +62,22 @@ pub fn no_element_response(source_uri: String, source_version: i32) -> Self {
     }
 }
 
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum InitialValue {
+    /// The value is known at compile time, in `.slint` syntax
+    Constant(String),
+    /// The value is only known when running the component
+    Runtime,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+pub(crate) struct InitialPropertyValue {
+    name: String,
+    type_name: String,
+    value: InitialValue,
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub(crate) struct SetBindingResponse {
     diagnostics: Vec<lsp_types::Diagnostic>,
@@ -401,7 +420,7 @@ fn find_block_range(element: &ElementRc) -> Option<lsp_types::Range> {
     ))
 }
 
-fn get_element_information(element: &ElementRc) -> ElementInformation {
+pub(crate) fn get_element_information(element: &ElementRc) -> ElementInformation {
     let e = element.borrow();
 
     ElementInformation {
@@ -806,6 +825,89 @@ pub(crate) fn remove_binding(
     ))
 }
 
+/// Returns the initial value of all the public properties declared in the component, folding
+/// the binding expressions to a constant where possible.
+pub(crate) fn initial_property_values(component: &Component) -> Vec<InitialPropertyValue> {
+    let root = component.root_element.borrow();
+    root.property_declarations
+        .iter()
+        .filter(|(_, decl)| {
+            !matches!(decl.visibility, PropertyVisibility::Private)
+                && !matches!(
+                    decl.property_type,
+                    Type::Callback { .. } | Type::Function { .. } | Type::InferredProperty
+                )
+        })
+        .map(|(name, decl)| {
+            let value = match root.bindings.get(name).map(|b| b.borrow()) {
+                Some(binding) if !binding.two_way_bindings.is_empty() => None,
+                Some(binding) if !matches!(binding.expression, Expression::Invalid) => {
+                    constant_value(&binding.expression)
+                }
+                // Without a binding, the property has the default value of its type
+                _ => constant_value(&Expression::default_value_for_type(&decl.property_type)),
+            };
+            InitialPropertyValue {
+                name: name.clone(),
+                type_name: decl.property_type.to_string(),
+                value: value.map_or(InitialValue::Runtime, InitialValue::Constant),
+            }
+        })
+        .collect()
+}
+
+/// Returns the value of the expression in `.slint` syntax, if it is made of literals only.
+fn constant_value(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::StringLiteral(s) => Some(format!("\"{}\"", s.escape_default())),
+        Expression::NumberLiteral(n, unit) => Some(format!("{n}{unit}")),
+        Expression::BoolLiteral(b) => Some(b.to_string()),
+        Expression::EnumerationValue(v) => Some(format!("{}.{v}", v.enumeration.name)),
+        Expression::Cast { from, to: Type::Color } => match from.as_ref() {
+            Expression::NumberLiteral(argb, Unit::None) => {
+                let [a, r, g, b] = (*argb as u32).to_be_bytes();
+                Some(if a == 0xff {
+                    format!("#{r:02x}{g:02x}{b:02x}")
+                } else {
+                    format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+                })
+            }
+            _ => None,
+        },
+        Expression::Cast { from, to: Type::String } => match from.as_ref() {
+            Expression::NumberLiteral(n, Unit::None) => Some(format!("\"{n}\"")),
+            from => constant_value(from),
+        },
+        Expression::Cast { from, .. } => constant_value(from),
+        Expression::UnaryOp { sub, op: '-' } => match sub.as_ref() {
+            Expression::NumberLiteral(n, unit) => Some(format!("{}{unit}", -n)),
+            _ => None,
+        },
+        Expression::BinaryExpression { lhs, rhs, op } => match (lhs.as_ref(), rhs.as_ref()) {
+            (Expression::NumberLiteral(a, u1), Expression::NumberLiteral(b, u2))
+                if u1 == u2 && matches!(op, '+' | '-') =>
+            {
+                let n = if *op == '+' { a + b } else { a - b };
+                Some(format!("{n}{u1}"))
+            }
+            (Expression::NumberLiteral(a, unit), Expression::NumberLiteral(b, Unit::None))
+                if matches!(op, '*' | '/') =>
+            {
+                let n = if *op == '*' { a * b } else { a / b };
+                Some(format!("{n}{unit}"))
+            }
+            (Expression::StringLiteral(a), Expression::StringLiteral(b)) if *op == '+' => {
+                constant_value(&Expression::StringLiteral(format!("{a}{b}")))
+            }
+            _ => None,
+        },
+        Expression::CodeBlock(statements) if statements.len() == 1 => {
+            constant_value(&statements[0])
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1701,4 +1803,29 @@ fn test_set_binding_valid() {
 
         assert_eq!(result.diagnostics.len(), 0_usize);
     }
+
+    #[test]
+    fn test_initial_property_values() {
+        let (dc, url, _) = loaded_document_cache(
+            r#"export component Test {
+    in property <color> background-color: #fff;
+    out property <color> foreground-color: background-color;
+    in-out property <length> size: 10px * 2;
+    in property <string> label;
+    property <int> private-value: 42;
+}"#
+            .into(),
+        );
+        let doc = dc.documents.get_document(&language::uri_to_file(&url).unwrap()).unwrap();
+        let component = doc.inner_components.iter().find(|c| c.id == "Test").unwrap();
+
+        let values = initial_property_values(component);
+        let value = |name: &str| values.iter().find(|v| v.name == name).map(|v| v.value.clone());
+
+        assert_eq!(value("background-color"), Some(InitialValue::Constant("#ffffff".into())));
+        assert_eq!(value("foreground-color"), Some(InitialValue::Runtime));
+        assert_eq!(value("size"), Some(InitialValue::Constant("20px".into())));
+        assert_eq!(value("label"), Some(InitialValue::Constant("\"\"".into())));
+        assert_eq!(value("private-value"), None);
+    }
 }
@@ -0,0 +1,196 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use super::DocumentCache;
+use crate::util::map_token;
+
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_prelude::*;
+
+use i_slint_compiler::parser::{normalize_identifier, SyntaxKind, SyntaxNode, SyntaxToken};
+use lsp_types::{Location, Url};
+
+/// Returns the node declaring the property, callback, function or component referenced
+/// by `token`. For components, this is the root element of the component.
+pub fn find_declaration(
+    document_cache: &mut DocumentCache,
+    token: &SyntaxToken,
+) -> Option<SyntaxNode> {
+    if token.kind() != SyntaxKind::Identifier {
+        return None;
+    }
+    let parent = token.parent();
+    let declaration = if parent.kind() == SyntaxKind::DeclaredIdentifier {
+        let declaration = parent.parent()?;
+        if declaration.kind() == SyntaxKind::Component {
+            declaration.child_node(SyntaxKind::Element)?
+        } else {
+            declaration
+        }
+    } else {
+        super::goto::find_definition_node(document_cache, token.clone())?
+    };
+    match declaration.kind() {
+        SyntaxKind::PropertyDeclaration
+        | SyntaxKind::CallbackDeclaration
+        | SyntaxKind::Function => Some(declaration),
+        SyntaxKind::Element
+            if declaration.parent().map_or(false, |p| p.kind() == SyntaxKind::Component) =>
+        {
+            Some(declaration)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the identifier token naming the declaration returned by `find_declaration`
+pub fn declared_identifier(declaration: &SyntaxNode) -> Option<SyntaxToken> {
+    let node = if declaration.kind() == SyntaxKind::Element {
+        declaration.parent()?
+    } else {
+        declaration.clone()
+    };
+    node.child_node(SyntaxKind::DeclaredIdentifier)?.child_token(SyntaxKind::Identifier)
+}
+
+/// Returns all the identifiers in the loaded documents that refer to `declaration`,
+/// not including the declared identifier itself.
+pub fn find_references(
+    document_cache: &mut DocumentCache,
+    declaration: &SyntaxNode,
+) -> Vec<SyntaxToken> {
+    let Some(name) = declared_identifier(declaration).map(|t| normalize_identifier(t.text()))
+    else {
+        return Vec::new();
+    };
+    let documents = document_cache
+        .documents
+        .all_documents()
+        .filter_map(|doc| doc.node.clone())
+        .collect::<Vec<_>>();
+
+    let mut result = Vec::new();
+    for doc in documents {
+        for token in doc.descendants_with_tokens().filter_map(rowan::NodeOrToken::into_token) {
+            if token.kind() != SyntaxKind::Identifier
+                || normalize_identifier(token.text()) != name
+                || token.parent().map_or(true, |p| p.kind() == SyntaxKind::DeclaredIdentifier)
+            {
+                continue;
+            }
+            let token = SyntaxToken { token, source_file: doc.source_file.clone() };
+            if find_declaration(document_cache, &token)
+                .map_or(false, |d| same_node(&d, declaration))
+            {
+                result.push(token);
+            }
+        }
+    }
+    result
+}
+
+pub(super) fn same_node(a: &SyntaxNode, b: &SyntaxNode) -> bool {
+    a.kind() == b.kind()
+        && a.text_range() == b.text_range()
+        && a.source_file.path() == b.source_file.path()
+}
+
+fn token_location(token: &SyntaxToken) -> Option<Location> {
+    Some(Location::new(Url::from_file_path(token.source_file.path()).ok()?, map_token(token)?))
+}
+
+/// Returns the locations of all the uses of the element id, property, callback, function or
+/// component at `token`.
+pub fn find_all_references(
+    document_cache: &mut DocumentCache,
+    token: &SyntaxToken,
+    include_declaration: bool,
+) -> Option<Vec<Location>> {
+    let parent = token.parent();
+    if let Some(ranges) = super::find_element_id_for_highlight(token, &parent) {
+        let uri = Url::from_file_path(token.source_file.path()).ok()?;
+        let root = parent.node.ancestors().last()?;
+        return Some(
+            ranges
+                .into_iter()
+                .filter(|r| {
+                    include_declaration
+                        || root
+                            .covering_element(*r)
+                            .parent()
+                            .map_or(true, |p| p.kind() != SyntaxKind::SubElement)
+                })
+                .map(|r| Location::new(uri.clone(), crate::util::map_range(&parent.source_file, r)))
+                .collect(),
+        );
+    }
+
+    let declaration = find_declaration(document_cache, token)?;
+    let mut tokens = find_references(document_cache, &declaration);
+    if include_declaration {
+        tokens.insert(0, declared_identifier(&declaration)?);
+    }
+    Some(tokens.iter().filter_map(token_location).collect())
+}
+
+#[test]
+fn test_find_all_references() {
+    let source = r#"component Abc {
+    in property <string> hello;
+    callback clicked();
+    function foo() { clicked(); }
+}
+export component Test {
+    in-out property <string> hello;
+    abc := Abc {
+        hello: "foo";
+        clicked => { root.hello = "x"; }
+    }
+    Abc { hello: abc.hello; }
+    states [
+        pressed when hello == "": { abc.hello: "y"; }
+    ]
+}"#;
+
+    let (mut dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+    let doc = dc
+        .documents
+        .get_document(&crate::language::uri_to_file(&uri).unwrap())
+        .unwrap()
+        .node
+        .clone()
+        .unwrap();
+    let mut references = |needle: &str, delta: usize, include_declaration: bool| {
+        let offset = (source.find(needle).unwrap() + delta) as u32;
+        let token = crate::language::token_at_offset(&doc, offset).unwrap();
+        let mut lines = find_all_references(&mut dc, &token, include_declaration)
+            .unwrap()
+            .into_iter()
+            .map(|l| {
+                assert_eq!(l.uri, uri);
+                l.range.start.line
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines
+    };
+
+    // The property of Abc, from its declaration and from a use
+    assert_eq!(references("hello;", 0, true), vec![1, 8, 11, 11, 13]);
+    assert_eq!(references("hello;", 0, false), vec![8, 11, 11, 13]);
+    assert_eq!(references("abc.hello", 4, true), vec![1, 8, 11, 11, 13]);
+
+    // The property of Test
+    assert_eq!(references("root.hello", 5, true), vec![6, 9, 13]);
+
+    // The callback
+    assert_eq!(references("clicked =>", 0, false), vec![3, 9]);
+
+    // The element id, including the use in the states
+    assert_eq!(references("abc :=", 0, true), vec![7, 11, 13]);
+    assert_eq!(references("abc :=", 0, false), vec![11, 13]);
+
+    // The component
+    assert_eq!(references("component Abc", 10, true), vec![0, 7, 11]);
+    assert_eq!(references("Abc {\n        hello", 0, false), vec![7, 11]);
+}
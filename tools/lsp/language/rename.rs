@@ -0,0 +1,179 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use super::references::{declared_identifier, find_references};
+use super::DocumentCache;
+use crate::util::{lookup_current_element_type, map_token};
+
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_prelude::*;
+
+use i_slint_compiler::langtype::Type;
+use i_slint_compiler::parser::{
+    identifier_text, normalize_identifier, SyntaxKind, SyntaxNode, SyntaxToken,
+};
+use lsp_types::{TextEdit, Url, WorkspaceEdit};
+
+use std::collections::HashMap;
+
+/// Returns the declaration of the property, callback or function referenced by `token`
+pub fn find_declaration(
+    document_cache: &mut DocumentCache,
+    token: &SyntaxToken,
+) -> Option<SyntaxNode> {
+    super::references::find_declaration(document_cache, token)
+        .filter(|d| d.kind() != SyntaxKind::Element)
+}
+
+/// Returns the edits renaming the property, callback or function referenced by `token`
+/// to `new_name` in all the loaded documents.
+pub fn rename_declaration(
+    document_cache: &mut DocumentCache,
+    token: &SyntaxToken,
+    new_name: &str,
+) -> Result<WorkspaceEdit, String> {
+    let declaration = find_declaration(document_cache, token)
+        .ok_or_else(|| "This symbol cannot be renamed.".to_string())?;
+    let declared_identifier = declared_identifier(&declaration)
+        .ok_or_else(|| "This symbol cannot be renamed.".to_string())?;
+    if Url::from_file_path(declaration.source_file.path()).is_err() {
+        return Err(format!(
+            "'{}' is not declared in a file that can be edited",
+            declared_identifier.text()
+        ));
+    }
+
+    if !is_valid_identifier(new_name) {
+        return Err(format!("'{new_name}' is not a valid identifier"));
+    }
+    let name = normalize_identifier(declared_identifier.text());
+    if normalize_identifier(new_name) == name {
+        return Ok(WorkspaceEdit::default());
+    }
+    check_collision(document_cache, &declaration, &normalize_identifier(new_name))?;
+
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+    let references = find_references(document_cache, &declaration);
+    for token in std::iter::once(&declared_identifier).chain(references.iter()) {
+        let (Ok(uri), Some(range)) =
+            (Url::from_file_path(token.source_file.path()), map_token(token))
+        else {
+            continue;
+        };
+        changes.entry(uri).or_default().push(TextEdit { range, new_text: new_name.into() });
+    }
+
+    Ok(WorkspaceEdit { changes: Some(changes), ..Default::default() })
+}
+
+fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().map_or(false, |c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Returns an error if the element declaring `declaration` already has a member called `new_name`
+fn check_collision(
+    document_cache: &DocumentCache,
+    declaration: &SyntaxNode,
+    new_name: &str,
+) -> Result<(), String> {
+    let Some(element) = declaration.parent().filter(|n| n.kind() == SyntaxKind::Element) else {
+        return Ok(());
+    };
+
+    if element
+        .children()
+        .filter(|n| {
+            matches!(
+                n.kind(),
+                SyntaxKind::PropertyDeclaration
+                    | SyntaxKind::CallbackDeclaration
+                    | SyntaxKind::Function
+            )
+        })
+        .any(|d| {
+            d.child_node(SyntaxKind::DeclaredIdentifier)
+                .and_then(|i| identifier_text(&i))
+                .map_or(false, |n| n == new_name)
+        })
+    {
+        return Err(format!("'{new_name}' is already declared in this element"));
+    }
+
+    let global_tr = document_cache.documents.global_type_registry.borrow();
+    let tr = document_cache
+        .documents
+        .get_document(element.source_file.path())
+        .map(|doc| &doc.local_registry)
+        .unwrap_or(&global_tr);
+    if let Some(base) = lookup_current_element_type(element, tr) {
+        if base.lookup_property(new_name).property_type != Type::Invalid {
+            return Err(format!("'{new_name}' is already a member of the base element"));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_rename_declaration() {
+    let source = r#"component Abc {
+    in property <string> hello;
+    callback clicked();
+    property <int> world;
+    function foo() { clicked(); }
+}
+export component Test {
+    in-out property <string> hello;
+    abc := Abc {
+        hello: "foo";
+        clicked => { root.hello = "x"; }
+    }
+    Text { text: abc.hello + root.hello; }
+}"#;
+
+    let (mut dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+    let doc = dc
+        .documents
+        .get_document(&crate::language::uri_to_file(&uri).unwrap())
+        .unwrap()
+        .node
+        .clone()
+        .unwrap();
+    let token_at = |needle: &str, delta: usize| {
+        let offset = (source.find(needle).unwrap() + delta) as u32;
+        crate::language::token_at_offset(&doc, offset).unwrap()
+    };
+    let edited_lines = |edit: WorkspaceEdit| {
+        let mut changes = edit.changes.unwrap();
+        assert_eq!(changes.len(), 1);
+        let mut lines =
+            changes.remove(&uri).unwrap().iter().map(|e| e.range.start.line).collect::<Vec<_>>();
+        lines.sort();
+        lines
+    };
+
+    // Only the property of Abc is renamed, not the one of Test
+    let token = token_at("abc.hello", 4);
+    assert_eq!(token.text(), "hello");
+    let edit = rename_declaration(&mut dc, &token, "greeting").unwrap();
+    assert_eq!(edited_lines(edit), vec![1, 9, 12]);
+
+    let token = token_at("clicked =>", 0);
+    let edit = rename_declaration(&mut dc, &token, "activated").unwrap();
+    assert_eq!(edited_lines(edit), vec![2, 4, 10]);
+
+    let token = token_at("root.hello;", 5);
+    let edit = rename_declaration(&mut dc, &token, "greeting").unwrap();
+    assert_eq!(edited_lines(edit), vec![7, 10, 12]);
+
+    // Collisions and invalid names
+    let token = token_at("hello;", 0);
+    assert!(rename_declaration(&mut dc, &token, "world").is_err());
+    assert!(rename_declaration(&mut dc, &token, "foo").is_err());
+    assert!(rename_declaration(&mut dc, &token, "1abc").is_err());
+
+    // Element ids are not declarations
+    let token = token_at("abc := ", 0);
+    assert!(find_declaration(&mut dc, &token).is_none());
+}
@@ -0,0 +1,298 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use super::{uri_to_file, DocumentCache};
+use crate::common::Result;
+
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_prelude::*;
+
+use i_slint_compiler::langtype::Type;
+use i_slint_compiler::parser::{syntax_nodes, SyntaxKind, SyntaxToken, TextRange};
+use lsp_types::{
+    CreateFile, CreateFileOptions, DocumentChangeOperation, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, Position, ResourceOp, TextDocumentEdit, TextEdit, Url,
+    WorkspaceEdit,
+};
+
+/// Generates a new file with a component that shows the fields of the exported struct
+/// `struct_name` of the document at `uri`, and returns the edit creating it.
+pub fn scaffold_component(
+    document_cache: &DocumentCache,
+    uri: &Url,
+    struct_name: &str,
+) -> Result<WorkspaceEdit> {
+    let path = uri_to_file(uri).ok_or("Failed to convert the document uri to a path")?;
+    let doc = document_cache
+        .documents
+        .get_document(&path)
+        .ok_or_else(|| format!("Document with uri {uri} not found in cache"))?;
+    let ty = doc
+        .exports
+        .find(struct_name)
+        .and_then(|e| e.right())
+        .ok_or_else(|| format!("No exported struct named {struct_name} found"))?;
+    let Type::Struct { fields, node, .. } = &ty else {
+        return Err(format!("{struct_name} is not a struct").into());
+    };
+
+    // Keep the order of the declaration when possible
+    let field_names = match node {
+        Some(node) => node
+            .ObjectTypeMember()
+            .filter_map(|member| i_slint_compiler::parser::identifier_text(&member))
+            .collect::<Vec<_>>(),
+        None => fields.keys().cloned().collect(),
+    };
+
+    let mut uses_checkbox = false;
+    let mut elements = String::new();
+    for name in field_names {
+        let Some(field_ty) = fields.get(&name) else { continue };
+        let element = match field_ty {
+            Type::Bool => {
+                uses_checkbox = true;
+                format!("CheckBox {{\n            text: \"{name}\";\n            checked: root.model.{name};\n        }}")
+            }
+            Type::Image => format!("Image {{\n            source: root.model.{name};\n        }}"),
+            Type::Color | Type::Brush => {
+                format!("Rectangle {{\n            background: root.model.{name};\n        }}")
+            }
+            ty if ty.can_convert(&Type::String) => {
+                format!("Text {{\n            text: root.model.{name};\n        }}")
+            }
+            ty => format!("// {name}: no element to show a value of type {ty}"),
+        };
+        if !elements.is_empty() {
+            elements += "\n\n";
+        }
+        elements += "        ";
+        elements += &element;
+    }
+
+    let component_name = format!("{struct_name}View");
+    let file_name = path.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
+
+    let mut source = String::new();
+    if uses_checkbox {
+        source += "import { CheckBox } from \"std-widgets.slint\";\n";
+    }
+    source += &format!(
+        "import {{ {struct_name} }} from \"{file_name}\";\n\nexport component {component_name} {{\n    in property <{struct_name}> model;\n\n    VerticalLayout {{\n{elements}\n    }}\n}}\n"
+    );
+
+    let new_uri =
+        Url::from_file_path(path.with_file_name(format!("{}.slint", file_stem(&component_name))))
+            .map_err(|_| "Failed to create the uri of the new file")?;
+
+    Ok(WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Operations(vec![
+            DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                uri: new_uri.clone(),
+                options: Some(CreateFileOptions {
+                    overwrite: Some(false),
+                    ignore_if_exists: Some(false),
+                }),
+                annotation_id: None,
+            })),
+            DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    uri: new_uri,
+                    version: None,
+                },
+                edits: vec![OneOf::Left(TextEdit::new(Default::default(), source))],
+            }),
+        ])),
+        ..Default::default()
+    })
+}
+
+/// Returns the edit wrapping the element at `position` in a `for item[index] in model : ...`
+/// repeater. Bindings with a literal value are rebound to the field of the same name of the
+/// model's struct, if there is one.
+pub fn scaffold_repeater(
+    document_cache: &mut DocumentCache,
+    uri: &Url,
+    position: &Position,
+    model_name: &str,
+) -> Result<WorkspaceEdit> {
+    let element = super::element_at_position(document_cache, uri, position)
+        .ok_or("No element found at the given position")?;
+    let element = element.borrow();
+    let node = element.node.as_ref().ok_or("The element has no source")?;
+    // Only direct children of another element can be repeated
+    let sub_element = node
+        .parent()
+        .filter(|n| n.kind() == SyntaxKind::SubElement)
+        .filter(|n| n.parent().is_some_and(|p| p.kind() == SyntaxKind::Element))
+        .ok_or("Only child elements can be repeated")?;
+
+    let model_type = element
+        .enclosing_component
+        .upgrade()
+        .map(|c| c.root_element.borrow().lookup_property(model_name).property_type)
+        .unwrap_or(Type::Invalid);
+    let fields = match &model_type {
+        Type::Array(item) => match item.as_ref() {
+            Type::Struct { fields, .. } => Some(fields),
+            _ => None,
+        },
+        _ => None,
+    };
+    let model = if model_type == Type::Invalid {
+        model_name.to_string()
+    } else {
+        format!("root.{model_name}")
+    };
+
+    let mut replacements = Vec::new();
+    for binding in node.Binding() {
+        let Some(name) = i_slint_compiler::parser::identifier_text(&binding) else { continue };
+        let Some(field_ty) = fields.and_then(|fields| fields.get(&name)) else { continue };
+        if !field_ty.can_convert(&element.lookup_property(&name).property_type) {
+            continue;
+        }
+        let Some(literal) = binding.BindingExpression().Expression().and_then(literal_token) else {
+            continue;
+        };
+        replacements.push((literal.text_range(), format!("item.{name}")));
+    }
+
+    let start = sub_element.text_range().start();
+    let mut text = sub_element.text().to_string();
+    // Replace from the end, so that the ranges before stay valid
+    for (range, replacement) in replacements.into_iter().rev() {
+        let range = TextRange::new(range.start() - start, range.end() - start);
+        text.replace_range(std::ops::Range::<usize>::from(range), &replacement);
+    }
+
+    let range = crate::util::map_node(&sub_element).ok_or("Failed to map node")?;
+    let edit = TextEdit::new(range, format!("for item[index] in {model} : {text}"));
+    Ok(WorkspaceEdit {
+        changes: Some(std::iter::once((uri.clone(), vec![edit])).collect()),
+        ..Default::default()
+    })
+}
+
+/// Returns the token of the expression if it is a plain string, number, color or bool literal
+fn literal_token(expression: syntax_nodes::Expression) -> Option<SyntaxToken> {
+    if let Some(name) = expression.QualifiedName() {
+        let is_bool = matches!(name.text().to_string().as_str(), "true" | "false");
+        return if is_bool { name.first_token() } else { None };
+    }
+    if expression.children().next().is_some() {
+        return None;
+    }
+    let token = expression.first_token()?;
+    matches!(
+        token.kind(),
+        SyntaxKind::StringLiteral | SyntaxKind::NumberLiteral | SyntaxKind::ColorLiteral
+    )
+    .then_some(token)
+}
+
+/// Converts a component name like `PersonView` into a file name like `person_view`
+pub(super) fn file_stem(component_name: &str) -> String {
+    let mut result = String::new();
+    for (i, c) in component_name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            result.push('_');
+        }
+        result.extend(c.to_lowercase());
+    }
+    result
+}
+
+#[test]
+fn test_scaffold_component() {
+    let source = r#"
+export struct Person {
+    name: string,
+    active: bool,
+    age: int,
+}
+"#;
+    let (dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+
+    let edit = scaffold_component(&dc, &uri, "Person").unwrap();
+    let Some(DocumentChanges::Operations(operations)) = edit.document_changes else {
+        panic!("Expected document operations")
+    };
+    assert_eq!(operations.len(), 2);
+    let DocumentChangeOperation::Op(ResourceOp::Create(create)) = &operations[0] else {
+        panic!("Expected a file creation")
+    };
+    assert_eq!(create.uri, uri.join("person_view.slint").unwrap());
+    let DocumentChangeOperation::Edit(edit) = &operations[1] else { panic!("Expected an edit") };
+    assert_eq!(edit.text_document.uri, create.uri);
+    let OneOf::Left(text_edit) = &edit.edits[0] else { panic!("Expected a text edit") };
+
+    assert_eq!(
+        text_edit.new_text,
+        r#"import { CheckBox } from "std-widgets.slint";
+import { Person } from "bar.slint";
+
+export component PersonView {
+    in property <Person> model;
+
+    VerticalLayout {
+        Text {
+            text: root.model.name;
+        }
+
+        CheckBox {
+            text: "active";
+            checked: root.model.active;
+        }
+
+        Text {
+            text: root.model.age;
+        }
+    }
+}
+"#
+    );
+
+    assert!(scaffold_component(&dc, &uri, "Unknown").is_err());
+}
+
+#[test]
+fn test_scaffold_repeater() {
+    let source = r#"
+struct Entry { text: string, font-size: length }
+export component Test {
+    in property <[Entry]> entries;
+    VerticalLayout {
+        label := Text {
+            text: "Hello";
+            font-size: 12px;
+            color: blue;
+        }
+    }
+}
+"#;
+    let (mut dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+
+    let offset = source.find("Text {").unwrap();
+    let line = source[..offset].matches('\n').count() as u32;
+    let character = (offset - source[..offset].rfind('\n').unwrap() - 1) as u32;
+    let position = Position::new(line, character);
+
+    let edit = scaffold_repeater(&mut dc, &uri, &position, "entries").unwrap();
+    let edits = edit.changes.unwrap().remove(&uri).unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].range.start, Position::new(line, character - "label := ".len() as u32));
+    assert_eq!(
+        edits[0].new_text,
+        r#"for item[index] in root.entries : label := Text {
+            text: item.text;
+            font-size: item.font-size;
+            color: blue;
+        }"#
+    );
+
+    // The root element cannot be repeated
+    let offset = source.find("in property").unwrap();
+    let line = source[..offset].matches('\n').count() as u32;
+    assert!(scaffold_repeater(&mut dc, &uri, &Position::new(line, 4), "entries").is_err());
+}
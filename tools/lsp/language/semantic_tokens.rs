@@ -183,3 +183,108 @@ pub fn get_semantic_tokens(
     }
     Some(SemanticTokensResult::Tokens(SemanticTokens { result_id: None, data }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::language::test::loaded_document_cache;
+
+    /// Returns the text, type and modifiers of the tokens of `source`
+    fn tokens(source: &str) -> Vec<(String, SemanticTokenType, Vec<SemanticTokenModifier>)> {
+        let (mut dc, url, _) = loaded_document_cache(source.into());
+        let Some(SemanticTokensResult::Tokens(tokens)) =
+            get_semantic_tokens(&mut dc, &lsp_types::TextDocumentIdentifier { uri: url })
+        else {
+            panic!("No semantic tokens");
+        };
+        let lines = source.lines().collect::<Vec<_>>();
+        let (mut line, mut start) = (0, 0);
+        tokens
+            .data
+            .into_iter()
+            .map(|token| {
+                line += token.delta_line;
+                start = if token.delta_line == 0 {
+                    start + token.delta_start
+                } else {
+                    token.delta_start
+                };
+                let text = lines[line as usize][start as usize..(start + token.length) as usize]
+                    .to_string();
+                let modifiers = LEGEND_MODS
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| token.token_modifiers_bitset & (1 << index) != 0)
+                    .map(|(_, modifier)| modifier.clone())
+                    .collect();
+                (text, LEGEND_TYPES[token.token_type as usize].clone(), modifiers)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_semantic_tokens() {
+        let result = tokens(
+            r#"enum Mode { on, off }
+/* The main window */
+component Foo inherits Rectangle {
+    in-out property <int> count: 42;
+    callback clicked;
+    property <string> label;
+    txt := Text {
+        text <=> root.label;
+        color: #ff0000;
+    }
+}
+"#,
+        );
+
+        let definition = || vec![SemanticTokenModifier::DEFINITION];
+        for expected in [
+            ("enum", SemanticTokenType::KEYWORD, vec![]),
+            ("Mode", SemanticTokenType::ENUM, definition()),
+            ("on", SemanticTokenType::ENUM_MEMBER, definition()),
+            ("off", SemanticTokenType::ENUM_MEMBER, definition()),
+            ("/* The main window */", SemanticTokenType::COMMENT, vec![]),
+            ("component", SemanticTokenType::KEYWORD, vec![]),
+            ("Foo", SemanticTokenType::TYPE, definition()),
+            ("inherits", SemanticTokenType::KEYWORD, vec![]),
+            ("Rectangle", SemanticTokenType::TYPE, vec![]),
+            ("property", SemanticTokenType::KEYWORD, vec![]),
+            ("count", SemanticTokenType::PROPERTY, definition()),
+            ("42", SemanticTokenType::NUMBER, vec![]),
+            ("callback", SemanticTokenType::KEYWORD, vec![]),
+            ("clicked", SemanticTokenType::FUNCTION, definition()),
+            ("txt", SemanticTokenType::VARIABLE, definition()),
+            ("Text", SemanticTokenType::TYPE, vec![]),
+            // Two-way binding
+            ("text", SemanticTokenType::PROPERTY, vec![]),
+            ("color", SemanticTokenType::PROPERTY, vec![]),
+            ("#ff0000", SemanticTokenType::NUMBER, vec![]),
+        ] {
+            let expected = (expected.0.to_string(), expected.1, expected.2);
+            assert!(result.contains(&expected), "{expected:?} not in {result:?}");
+        }
+        // Names in expressions are not looked up yet
+        assert!(!result.iter().any(|(text, _, _)| text == "root"));
+    }
+
+    #[test]
+    fn test_semantic_tokens_legend() {
+        let Some(lsp_types::SemanticTokensServerCapabilities::SemanticTokensOptions(options)) =
+            crate::language::server_initialize_result(&Default::default())
+                .capabilities
+                .semantic_tokens_provider
+        else {
+            panic!("Semantic tokens are not advertised");
+        };
+        assert_eq!(options.legend.token_types, LEGEND_TYPES);
+        assert_eq!(options.legend.token_modifiers, LEGEND_MODS);
+        assert_eq!(options.full, Some(lsp_types::SemanticTokensFullOptions::Bool(true)));
+        // The indices of the legend are sent as the token types and modifiers
+        assert_eq!(LEGEND_TYPES[super::PROPERTY as usize], SemanticTokenType::PROPERTY);
+        assert_eq!(LEGEND_TYPES[super::ENUM_MEMBER as usize], SemanticTokenType::ENUM_MEMBER);
+        assert_eq!(LEGEND_MODS[super::DECLARATION as usize], SemanticTokenModifier::DECLARATION);
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use super::DocumentCache;
+use crate::util::lookup_qualified_name;
+
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_prelude::*;
+
+use i_slint_compiler::langtype::Type;
+use i_slint_compiler::lookup::LookupResult;
+use i_slint_compiler::parser::{identifier_text, SyntaxKind, SyntaxNode, SyntaxToken};
+use lsp_types::{ParameterInformation, ParameterLabel, SignatureHelp, SignatureInformation};
+
+/// Returns the signature of the callback or function called at `offset`, with the parameter
+/// the cursor is on as the active parameter.
+pub fn get_signature_help(
+    document_cache: &mut DocumentCache,
+    token: SyntaxToken,
+    offset: u32,
+) -> Option<SignatureHelp> {
+    let offset = offset.into();
+    let mut call = token.parent();
+    loop {
+        if call.kind() == SyntaxKind::FunctionCallExpression {
+            let lparen = call.child_token(SyntaxKind::LParent)?;
+            let after_lparen = lparen.text_range().end() <= offset;
+            let before_rparen = call
+                .child_token(SyntaxKind::RParent)
+                .map_or(true, |r| offset <= r.text_range().start());
+            if after_lparen && before_rparen {
+                break;
+            }
+        }
+        call = call.parent()?;
+    }
+
+    let qualified_name =
+        call.child_node(SyntaxKind::Expression)?.child_node(SyntaxKind::QualifiedName)?;
+    let identifiers = qualified_name
+        .children_with_tokens()
+        .filter_map(|t| t.into_token())
+        .filter(|t| t.kind() == SyntaxKind::Identifier)
+        .collect::<Vec<_>>();
+    let name = identifiers.last()?.text().to_string();
+
+    let ty = match lookup_qualified_name(document_cache, &qualified_name)? {
+        LookupResult::Expression { expression, .. } => expression.ty(),
+        _ => return None,
+    };
+    let (args, return_type) = match ty {
+        Type::Callback { args, return_type } => (args, return_type.map(|t| *t)),
+        Type::Function { args, return_type } => (args, Some(*return_type)),
+        _ => return None,
+    };
+
+    // Only functions declared in .slint have named arguments
+    let argument_names =
+        super::goto::find_definition_node(document_cache, identifiers.last()?.clone())
+            .filter(|n| n.kind() == SyntaxKind::Function)
+            .map(|n| argument_names(&n))
+            .unwrap_or_default();
+
+    let mut label = format!("{name}(");
+    let mut parameters = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            label += ", ";
+        }
+        let parameter = match argument_names.get(i) {
+            Some(arg_name) => format!("{arg_name}: {arg}"),
+            None => arg.to_string(),
+        };
+        let start = label.encode_utf16().count() as u32;
+        label += &parameter;
+        let end = label.encode_utf16().count() as u32;
+        parameters.push(ParameterInformation {
+            label: ParameterLabel::LabelOffsets([start, end]),
+            documentation: None,
+        });
+    }
+    label += ")";
+    if let Some(return_type) = return_type.filter(|t| *t != Type::Void) {
+        label += &format!(" -> {return_type}");
+    }
+
+    let active_parameter = call
+        .children_with_tokens()
+        .filter_map(|t| t.into_token())
+        .filter(|t| t.kind() == SyntaxKind::Comma && t.text_range().start() < offset)
+        .count() as u32;
+
+    Some(SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label,
+            documentation: None,
+            parameters: Some(parameters),
+            active_parameter: None,
+        }],
+        active_signature: Some(0),
+        active_parameter: Some(active_parameter),
+    })
+}
+
+fn argument_names(function: &SyntaxNode) -> Vec<String> {
+    function
+        .children()
+        .filter(|n| n.kind() == SyntaxKind::ArgumentDeclaration)
+        .filter_map(|n| identifier_text(&n.child_node(SyntaxKind::DeclaredIdentifier)?))
+        .collect()
+}
+
+#[test]
+fn test_signature_help() {
+    let source = r#"component Foo inherits Rectangle {
+    callback activated(int, string);
+    function compute(factor: float, name: string) -> int { return 0; }
+    ta := TouchArea { }
+    init => {
+        activated(1, "x");
+        compute(2.0, "y");
+        ta.clicked();
+    }
+}"#;
+    let (mut dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+    let doc = dc
+        .documents
+        .get_document(&crate::language::uri_to_file(&uri).unwrap())
+        .unwrap()
+        .node
+        .clone()
+        .unwrap();
+
+    let mut help = |needle: &str| {
+        let offset = (source.find(needle).unwrap() + needle.len()) as u32;
+        let token = crate::language::token_at_offset(&doc, offset).unwrap();
+        let help = get_signature_help(&mut dc, token, offset)?;
+        let signature = help.signatures.into_iter().next().unwrap();
+        let parameters = signature
+            .parameters
+            .unwrap()
+            .into_iter()
+            .map(|p| {
+                let ParameterLabel::LabelOffsets([start, end]) = p.label else { unreachable!() };
+                signature.label[start as usize..end as usize].to_string()
+            })
+            .collect::<Vec<_>>();
+        Some((signature.label, parameters, help.active_parameter.unwrap()))
+    };
+
+    assert_eq!(
+        help("activated(1, "),
+        Some((
+            "activated(int, string)".to_string(),
+            vec!["int".to_string(), "string".to_string()],
+            1
+        ))
+    );
+    assert_eq!(
+        help("compute("),
+        Some((
+            "compute(factor: float, name: string) -> int".to_string(),
+            vec!["factor: float".to_string(), "name: string".to_string()],
+            0
+        ))
+    );
+    assert_eq!(help("ta.clicked("), Some(("clicked()".to_string(), vec![], 0)));
+    assert_eq!(help("init => {"), None);
+}
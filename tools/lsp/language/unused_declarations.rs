@@ -0,0 +1,256 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+//! Find the properties and callbacks that are declared but never used
+
+use super::references::{declared_identifier, find_declaration, same_node};
+use super::DocumentCache;
+use crate::util::{map_node, map_range};
+
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_prelude::*;
+
+use i_slint_compiler::parser::{
+    identifier_text, normalize_identifier, syntax_nodes, SyntaxKind, SyntaxNode, SyntaxToken,
+    TextRange, TextSize,
+};
+use lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag, TextEdit, Url, WorkspaceEdit};
+
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Returns the property and callback declarations of the document at `path` that are never
+/// referenced.
+///
+/// Only the declarations that can't be used from other files are considered: the private
+/// properties, and the callbacks of components that are not exported. Declarations that are
+/// aliases with `<=>` are never reported.
+fn unused_declarations(document_cache: &mut DocumentCache, path: &Path) -> Vec<SyntaxNode> {
+    let Some(doc) = document_cache.documents.get_document(path) else { return Vec::new() };
+    let Some(doc_node) = doc.node.clone() else { return Vec::new() };
+    let exported = doc
+        .exports
+        .iter()
+        .filter_map(|(_, e)| e.as_ref().left().map(|c| c.id.clone()))
+        .collect::<HashSet<_>>();
+
+    let mut candidates = doc_node
+        .descendants()
+        .map(|node| SyntaxNode { node, source_file: doc_node.source_file.clone() })
+        .filter(|n| can_be_unused(n, &exported))
+        .collect::<Vec<_>>();
+    let names = candidates
+        .iter()
+        .filter_map(|d| declared_identifier(d).map(|t| normalize_identifier(t.text())))
+        .collect::<HashSet<_>>();
+
+    // Nothing can reference the declarations from other files, so only this document is searched
+    for token in doc_node.descendants_with_tokens().filter_map(rowan::NodeOrToken::into_token) {
+        if candidates.is_empty() {
+            break;
+        }
+        if token.kind() != SyntaxKind::Identifier
+            || !names.contains(&normalize_identifier(token.text()))
+            || token.parent().map_or(true, |p| p.kind() == SyntaxKind::DeclaredIdentifier)
+        {
+            continue;
+        }
+        let token = SyntaxToken { token, source_file: doc_node.source_file.clone() };
+        if let Some(declaration) = find_declaration(document_cache, &token) {
+            candidates.retain(|c| !same_node(c, &declaration));
+        }
+    }
+    candidates
+}
+
+/// Returns true for the declarations that `unused_declarations` looks at
+fn can_be_unused(node: &SyntaxNode, exported: &HashSet<String>) -> bool {
+    if node.child_node(SyntaxKind::TwoWayBinding).is_some() {
+        return false;
+    }
+    match node.kind() {
+        // `in`, `out`, and `in-out` properties are part of the interface of the component
+        SyntaxKind::PropertyDeclaration => node
+            .children_with_tokens()
+            .map_while(|t| t.into_token())
+            .filter(|t| t.kind() == SyntaxKind::Identifier)
+            .all(|t| matches!(t.text(), "property" | "private")),
+        SyntaxKind::CallbackDeclaration => std::iter::successors(node.parent(), |n| n.parent())
+            .find_map(syntax_nodes::Component::new)
+            .and_then(|c| identifier_text(&c.DeclaredIdentifier()))
+            .map_or(false, |name| !exported.contains(&name)),
+        _ => false,
+    }
+}
+
+/// Returns the hints for the unused declarations of the document at `path`
+pub fn unused_declaration_diagnostics(
+    document_cache: &mut DocumentCache,
+    path: &Path,
+) -> Vec<Diagnostic> {
+    unused_declarations(document_cache, path)
+        .into_iter()
+        .filter_map(|declaration| {
+            let name = declared_identifier(&declaration)?;
+            let what = match declaration.kind() {
+                SyntaxKind::CallbackDeclaration => "Callback",
+                _ => "Property",
+            };
+            Some(Diagnostic {
+                range: map_node(&declaration)?,
+                severity: Some(DiagnosticSeverity::HINT),
+                message: format!("{what} '{}' is never used", name.text()),
+                tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Returns the title and the edit removing the unused declaration named by `token`, if it is
+/// one of the declarations reported by `unused_declaration_diagnostics`
+pub fn remove_unused_declaration(
+    document_cache: &mut DocumentCache,
+    token: &SyntaxToken,
+) -> Option<(String, WorkspaceEdit)> {
+    let declaration = token.parent().parent()?;
+    if token.parent().kind() != SyntaxKind::DeclaredIdentifier
+        || !matches!(
+            declaration.kind(),
+            SyntaxKind::PropertyDeclaration | SyntaxKind::CallbackDeclaration
+        )
+    {
+        return None;
+    }
+    let path = token.source_file.path().to_owned();
+    if !unused_declarations(document_cache, &path).iter().any(|d| same_node(d, &declaration)) {
+        return None;
+    }
+
+    let what = match declaration.kind() {
+        SyntaxKind::CallbackDeclaration => "callback",
+        _ => "property",
+    };
+    let name = identifier_text(&token.parent())?;
+    let uri = Url::from_file_path(&path).ok()?;
+    let edit = TextEdit::new(map_range(&token.source_file, removal_range(&declaration)), "".into());
+    Some((
+        format!("Remove unused {what} '{name}'"),
+        WorkspaceEdit {
+            changes: Some(std::iter::once((uri, vec![edit])).collect()),
+            ..Default::default()
+        },
+    ))
+}
+
+/// The range of the declaration, including the comments directly preceding it, its
+/// indentation, and the end of its line
+fn removal_range(declaration: &SyntaxNode) -> TextRange {
+    let text = declaration.node.ancestors().last().map(|root| root.text().to_string());
+    let text = text.unwrap_or_default();
+
+    let mut start = usize::from(declaration.text_range().start());
+    let mut token = declaration.first_token().and_then(|t| t.prev_token());
+    while let Some(t) = token {
+        match t.kind() {
+            SyntaxKind::Comment => start = t.text_range().start().into(),
+            SyntaxKind::Whitespace if t.text().matches('\n').count() <= 1 => {}
+            _ => break,
+        }
+        token = t.prev_token();
+    }
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    if text[line_start..start].trim().is_empty() {
+        start = line_start;
+    }
+
+    let mut end = usize::from(declaration.text_range().end());
+    let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i + 1);
+    if text[end..line_end].trim().is_empty() {
+        end = line_end;
+    }
+    TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::language::test::loaded_document_cache;
+
+    #[test]
+    fn test_unused_declarations() {
+        let source = r#"component Abc {
+    callback used-outside();
+    callback unused-callback();
+    in property <int> input;
+    property <int> used: 42;
+    property <int> unused-property: used;
+    property <string> alias <=> txt.text;
+    txt := Text { }
+}
+export component Test {
+    callback exported-callback();
+    property <int> unused-in-test;
+    Abc { used-outside => { } }
+}"#;
+        let (mut dc, url, _) = loaded_document_cache(source.into());
+        let path = crate::language::uri_to_file(&url).unwrap();
+        let diagnostics = unused_declaration_diagnostics(&mut dc, &path);
+        let mut result = diagnostics
+            .iter()
+            .map(|d| {
+                assert_eq!(d.severity, Some(DiagnosticSeverity::HINT));
+                assert_eq!(d.tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+                (d.range.start.line, d.message.as_str())
+            })
+            .collect::<Vec<_>>();
+        result.sort();
+        assert_eq!(
+            result,
+            vec![
+                (2, "Callback 'unused-callback' is never used"),
+                (5, "Property 'unused-property' is never used"),
+                (11, "Property 'unused-in-test' is never used"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remove_unused_declaration() {
+        let source = r#"export component Test {
+    property <int> used: 42;
+    // The documentation
+    property <int> unused: used;
+    Text { text: used; }
+}"#;
+        let (mut dc, url, _) = loaded_document_cache(source.into());
+        let doc = dc
+            .documents
+            .get_document(&crate::language::uri_to_file(&url).unwrap())
+            .unwrap()
+            .node
+            .clone()
+            .unwrap();
+        let token_at = |needle: &str| {
+            let offset = source.find(needle).unwrap() as u32 + 1;
+            crate::language::token_at_offset(&doc, offset).unwrap()
+        };
+
+        let (title, edit) = remove_unused_declaration(&mut dc, &token_at("unused:")).unwrap();
+        assert_eq!(title, "Remove unused property 'unused'");
+        let edits = edit.changes.unwrap().remove(&url).unwrap();
+        assert_eq!(
+            edits,
+            vec![TextEdit::new(
+                lsp_types::Range::new(
+                    lsp_types::Position::new(2, 0),
+                    lsp_types::Position::new(4, 0)
+                ),
+                String::new()
+            )]
+        );
+
+        assert!(remove_unused_declaration(&mut dc, &token_at("used: 42")).is_none());
+    }
+}
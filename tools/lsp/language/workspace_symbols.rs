@@ -0,0 +1,256 @@
+// Copyright © SixtyFPS GmbH <info@slint.dev>
+// SPDX-License-Identifier: GPL-3.0-only OR LicenseRef-Slint-Royalty-free-1.1 OR LicenseRef-Slint-commercial
+
+use super::DocumentCache;
+use crate::util::map_node;
+
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_prelude::*;
+
+use i_slint_compiler::parser::{identifier_text, SyntaxKind, SyntaxNode};
+use lsp_types::{Location, OneOf, SymbolKind, Url, WorkspaceSymbol};
+use std::collections::HashSet;
+
+/// Returns the components, properties, callbacks, functions and named elements of all the
+/// loaded documents, and of the other `.slint` files of the workspace folders, whose name
+/// matches `query`.
+pub fn get_workspace_symbols(document_cache: &DocumentCache, query: &str) -> Vec<WorkspaceSymbol> {
+    let mut result = Vec::new();
+    for (path, doc) in document_cache.documents.all_file_documents() {
+        if document_cache.is_excluded(path) {
+            continue;
+        }
+        // Builtin documents such as the styles don't have a location the editor can open
+        let (Ok(uri), Some(node)) = (Url::from_file_path(path), doc.node.as_ref()) else {
+            continue;
+        };
+        collect_symbols(node, &uri, None, query, &mut result);
+    }
+
+    let other_files = document_cache
+        .workspace_folders
+        .iter()
+        .flat_map(|folder| super::formatting::slint_files(document_cache, folder))
+        .filter(|path| document_cache.documents.get_document(path).is_none())
+        .collect::<HashSet<_>>();
+    let mut parsed_files = document_cache.parsed_workspace_files.borrow_mut();
+    // Forget the files that were removed, excluded or loaded since the last request
+    parsed_files.retain(|path, _| other_files.contains(path));
+    for path in other_files {
+        let (Ok(uri), Ok(modified)) =
+            (Url::from_file_path(&path), std::fs::metadata(&path).and_then(|m| m.modified()))
+        else {
+            continue;
+        };
+        let node = match parsed_files.get(&path) {
+            Some((parsed_modified, node)) if *parsed_modified == modified => node.clone(),
+            _ => {
+                let Ok(source) = std::fs::read_to_string(&path) else { continue };
+                // The symbols are only looked up in the syntax tree, so there is no need to
+                // compile
+                let node = i_slint_compiler::parser::parse(
+                    source,
+                    Some(&path),
+                    None,
+                    &mut i_slint_compiler::diagnostics::BuildDiagnostics::default(),
+                );
+                parsed_files.insert(path.clone(), (modified, node.clone()));
+                node
+            }
+        };
+        collect_symbols(&node, &uri, None, query, &mut result);
+    }
+    result
+        .sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.container_name.cmp(&b.container_name)));
+    result
+}
+
+fn collect_symbols(
+    node: &SyntaxNode,
+    uri: &Url,
+    container_name: Option<&str>,
+    query: &str,
+    result: &mut Vec<WorkspaceSymbol>,
+) {
+    for child in node.children() {
+        let symbol = match child.kind() {
+            SyntaxKind::Component => {
+                let name = child
+                    .child_node(SyntaxKind::DeclaredIdentifier)
+                    .and_then(|n| identifier_text(&n));
+                let kind =
+                    if child.child_text(SyntaxKind::Identifier).map_or(false, |x| x == "global") {
+                        SymbolKind::OBJECT
+                    } else {
+                        SymbolKind::CLASS
+                    };
+                if let Some(name) = name {
+                    add_symbol(&child, &name, kind, uri, container_name, query, result);
+                    collect_symbols(&child, uri, Some(&name), query, result);
+                }
+                continue;
+            }
+            SyntaxKind::PropertyDeclaration => Some(SymbolKind::PROPERTY),
+            SyntaxKind::CallbackDeclaration => Some(SymbolKind::EVENT),
+            SyntaxKind::Function => Some(SymbolKind::METHOD),
+            _ => None,
+        };
+        if let Some(kind) = symbol {
+            if let Some(name) =
+                child.child_node(SyntaxKind::DeclaredIdentifier).and_then(|n| identifier_text(&n))
+            {
+                add_symbol(&child, &name, kind, uri, container_name, query, result);
+            }
+        } else if child.kind() == SyntaxKind::SubElement {
+            if let Some(id) = identifier_text(&child) {
+                add_symbol(&child, &id, SymbolKind::VARIABLE, uri, container_name, query, result);
+            }
+        }
+        collect_symbols(&child, uri, container_name, query, result);
+    }
+}
+
+fn add_symbol(
+    node: &SyntaxNode,
+    name: &str,
+    kind: SymbolKind,
+    uri: &Url,
+    container_name: Option<&str>,
+    query: &str,
+    result: &mut Vec<WorkspaceSymbol>,
+) {
+    if !fuzzy_match(query, name) {
+        return;
+    }
+    let Some(range) = map_node(node) else { return };
+    result.push(WorkspaceSymbol {
+        name: name.into(),
+        kind,
+        tags: None,
+        container_name: container_name.map(Into::into),
+        location: OneOf::Left(Location::new(uri.clone(), range)),
+        data: None,
+    });
+}
+
+/// Returns true if all the characters of `query` appear in `name` in the same order,
+/// ignoring the case. So "Btn" matches "PushButton".
+fn fuzzy_match(query: &str, name: &str) -> bool {
+    let mut name = name.chars().flat_map(char::to_lowercase);
+    query.chars().flat_map(char::to_lowercase).all(|q| name.any(|n| n == q))
+}
+
+#[test]
+fn test_fuzzy_match() {
+    assert!(fuzzy_match("", "PushButton"));
+    assert!(fuzzy_match("Btn", "PushButton"));
+    assert!(fuzzy_match("pushbutton", "PushButton"));
+    assert!(!fuzzy_match("ntb", "PushButton"));
+    assert!(!fuzzy_match("Buttons", "PushButton"));
+}
+
+#[test]
+fn test_workspace_symbols() {
+    let source = r#"export global Settings {
+    in-out property <bool> dark;
+}
+component PushButton inherits Rectangle {
+    callback clicked();
+    function toggle() { }
+    label := Text { }
+}
+export component Main {
+    button := PushButton { }
+}"#;
+    let (dc, uri, _) = crate::language::test::loaded_document_cache(source.into());
+
+    let symbols = |query: &str| {
+        get_workspace_symbols(&dc, query)
+            .into_iter()
+            .map(|s| {
+                let OneOf::Left(location) = s.location else { panic!("Unexpected location") };
+                assert_eq!(location.uri, uri);
+                (s.name, s.kind, s.container_name, location.range.start.line)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        symbols("Btn"),
+        vec![
+            ("PushButton".to_string(), SymbolKind::CLASS, None, 3),
+            ("button".to_string(), SymbolKind::VARIABLE, Some("Main".to_string()), 9),
+        ]
+    );
+    assert_eq!(
+        symbols("dark"),
+        vec![("dark".to_string(), SymbolKind::PROPERTY, Some("Settings".to_string()), 1)]
+    );
+    assert_eq!(symbols("Settings")[0].1, SymbolKind::OBJECT);
+    assert_eq!(symbols("clicked")[0].1, SymbolKind::EVENT);
+    assert_eq!(symbols("toggle")[0].1, SymbolKind::METHOD);
+    assert_eq!(symbols("").len(), 8);
+}
+
+#[test]
+fn test_workspace_symbols_of_unloaded_files() {
+    let root = std::env::temp_dir().join(format!("slint-lsp-symbols-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("a.slint"), "component FromDisk {}").unwrap();
+    std::fs::write(root.join("b.slint"), "component AlsoFromDisk {}").unwrap();
+
+    let mut dc = crate::language::test::empty_document_cache();
+    dc.set_workspace_folders(vec![root.clone()]);
+    let names = |dc: &DocumentCache| {
+        get_workspace_symbols(dc, "FromDisk").into_iter().map(|s| s.name).collect::<Vec<_>>()
+    };
+    let parsed = |dc: &DocumentCache, path: &str| {
+        dc.parsed_workspace_files.borrow().get(&root.join(path)).map(|(_, node)| node.node.clone())
+    };
+
+    assert_eq!(names(&dc), vec!["AlsoFromDisk", "FromDisk"]);
+    let parsed_a = parsed(&dc, "a.slint").unwrap();
+    assert!(parsed(&dc, "b.slint").is_some());
+
+    // The next requests reuse the syntax trees, and forget the removed files
+    std::fs::remove_file(root.join("b.slint")).unwrap();
+    assert_eq!(names(&dc), vec!["FromDisk"]);
+    assert_eq!(parsed(&dc, "a.slint"), Some(parsed_a));
+    assert!(parsed(&dc, "b.slint").is_none());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_workspace_symbols_exclude_globs() {
+    let root = std::env::temp_dir().join(format!("slint-lsp-exclude-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("vendor/widgets")).unwrap();
+    std::fs::write(root.join("app.slint"), "component AppButton {}").unwrap();
+    std::fs::write(root.join("vendor/widgets/button.slint"), "component VendorButton {}").unwrap();
+
+    let mut dc = crate::language::test::empty_document_cache();
+    dc.set_workspace_folders(vec![root.clone()]);
+    dc.exclude_globs = vec!["vendor".into()];
+    // A document of the excluded directory that got loaded, for example because it was opened
+    let loaded = Url::from_file_path(root.join("vendor/generated.slint")).unwrap();
+    spin_on::spin_on(crate::language::reload_document_impl(
+        None,
+        "component GeneratedButton {}".into(),
+        loaded,
+        Some(1),
+        &mut dc,
+    ));
+
+    let names =
+        get_workspace_symbols(&dc, "Button").into_iter().map(|s| s.name).collect::<Vec<_>>();
+    assert_eq!(names, vec!["AppButton"]);
+
+    dc.exclude_globs.clear();
+    let names =
+        get_workspace_symbols(&dc, "Button").into_iter().map(|s| s.name).collect::<Vec<_>>();
+    assert_eq!(names, vec!["AppButton", "GeneratedButton", "VendorButton"]);
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
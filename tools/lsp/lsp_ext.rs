@@ -4,6 +4,7 @@
 //! Extensions to the LSP
 
 use lsp_types::notification::Notification;
+use lsp_types::request::Request;
 use serde::{Deserialize, Serialize};
 
 /// Taken from rust-analyzer
@@ -28,3 +29,79 @@ pub enum Health {
     Warning,
     Error,
 }
+
+/// Sent by the preview when evaluating the previewed component failed at runtime,
+/// for example because an image could not be loaded
+pub enum PreviewRuntimeErrorNotification {}
+
+impl Notification for PreviewRuntimeErrorNotification {
+    type Params = PreviewRuntimeErrorParams;
+    const METHOD: &'static str = "slint/previewRuntimeError";
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewRuntimeErrorParams {
+    pub message: String,
+    pub source_location: Option<lsp_types::Location>,
+}
+
+/// Lists the components of a document that can be previewed
+pub enum ComponentListRequest {}
+
+impl Request for ComponentListRequest {
+    type Params = lsp_types::TextDocumentIdentifier;
+    type Result = Vec<ComponentListItem>;
+    const METHOD: &'static str = "slint/componentList";
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentListItem {
+    pub name: String,
+    /// Whether the component is exported from the document
+    pub exported: bool,
+    /// Whether this is the component that gets previewed when no component name is given
+    pub is_default: bool,
+}
+
+/// Sent when an element is selected in the preview, with the elements containing the position
+/// that was clicked, so that the editor can show them as a breadcrumb
+pub enum PreviewElementPathNotification {}
+
+impl Notification for PreviewElementPathNotification {
+    type Params = PreviewElementPathParams;
+    const METHOD: &'static str = "slint/previewElementPath";
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewElementPathParams {
+    /// From the root element of the previewed component to the innermost element
+    pub elements: Vec<PreviewElementPathItem>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewElementPathItem {
+    /// The component name for the root element, otherwise the type with the id, if any
+    pub label: String,
+    pub location: lsp_types::Location,
+}
+
+/// Sent by the preview with the properties of the previewed component that can be changed live,
+/// when asked with the `slint/queryLiveProperties` command or after `slint/setLiveProperty`
+pub enum PreviewLivePropertiesNotification {}
+
+impl Notification for PreviewLivePropertiesNotification {
+    type Params = PreviewLivePropertiesParams;
+    const METHOD: &'static str = "slint/previewLiveProperties";
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewLivePropertiesParams {
+    pub properties: Vec<crate::common::LiveProperty>,
+    /// Why the value passed to `slint/setLiveProperty` could not be set
+    pub error: Option<String>,
+}
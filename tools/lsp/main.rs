@@ -14,13 +14,18 @@
 pub mod util;
 
 use common::{PreviewApi, Result};
+use language::progress::ProgressReporter;
 use language::*;
 
 use i_slint_compiler::CompilerConfiguration;
 use lsp_types::notification::{
-    DidChangeConfiguration, DidChangeTextDocument, DidOpenTextDocument, Notification,
+    Cancel, DidChangeConfiguration, DidChangeTextDocument, DidChangeWorkspaceFolders,
+    DidCloseTextDocument, DidOpenTextDocument, Notification,
+};
+use lsp_types::{
+    CancelParams, DidChangeTextDocumentParams, DidChangeWorkspaceFoldersParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, InitializeParams, NumberOrString,
 };
-use lsp_types::{DidChangeTextDocumentParams, DidOpenTextDocumentParams, InitializeParams};
 
 use clap::Parser;
 use lsp_server::{Connection, ErrorCode, IoThreads, Message, RequestId, Response};
@@ -33,11 +38,59 @@
 use std::sync::{atomic, Arc, Mutex};
 use std::task::{Poll, Waker};
 
+/// How often the LSP checks that the external previewer is still running
+#[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+const PREVIEW_HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+/// How many heartbeats the external previewer may leave unanswered before it is considered gone
+#[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+const MAX_MISSED_HEARTBEATS: u32 = 2;
+/// How often the main loop polls the pending futures when no message comes in, so that the
+/// requests sent to the client can time out
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// How long to wait for the editor to show a document before giving up
+#[cfg(feature = "preview-engine")]
+const SHOW_DOCUMENT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
 struct Previewer {
     #[allow(unused)]
     server_notifier: ServerNotifier,
     use_external_previewer: RefCell<bool>,
     to_show: RefCell<Option<common::PreviewComponent>>,
+    /// Whether the external previewer sent a message since we switched to it. Only then is
+    /// it checked with heartbeats: it may not be opened yet.
+    #[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+    external_previewer_connected: std::cell::Cell<bool>,
+    #[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+    missed_heartbeats: std::cell::Cell<u32>,
+}
+
+#[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+impl Previewer {
+    /// Called for every message received from the external previewer
+    fn external_previewer_alive(&self) {
+        self.external_previewer_connected.set(true);
+        self.missed_heartbeats.set(0);
+    }
+
+    /// Sends a heartbeat to the external previewer. Returns true if the external previewer
+    /// did not answer the previous ones and is considered gone.
+    fn check_external_previewer(&self) -> bool {
+        if !*self.use_external_previewer.borrow() || !self.external_previewer_connected.get() {
+            return false;
+        }
+        let missed = self.missed_heartbeats.get() + 1;
+        if missed > MAX_MISSED_HEARTBEATS {
+            self.external_previewer_connected.set(false);
+            self.missed_heartbeats.set(0);
+            return true;
+        }
+        self.missed_heartbeats.set(missed);
+        let _ = self.server_notifier.send_notification(
+            "slint/lsp_to_preview".to_string(),
+            crate::common::LspToPreviewMessage::Heartbeat,
+        );
+        false
+    }
 }
 
 impl PreviewApi for Previewer {
@@ -46,6 +99,8 @@ fn set_use_external_previewer(&self, _use_external: bool) {
         #[cfg(all(feature = "preview-builtin", feature = "preview-external"))]
         {
             self.use_external_previewer.replace(_use_external);
+            self.external_previewer_connected.set(false);
+            self.missed_heartbeats.set(0);
 
             if _use_external {
                 preview::close_ui();
@@ -124,6 +179,79 @@ fn highlight(&self, _path: Option<std::path::PathBuf>, _offset: u32) -> Result<(
         }
     }
 
+    fn layout_tree(&self) -> Result<common::LayoutTreeNode> {
+        if *self.use_external_previewer.borrow() {
+            return Err("The layout tree is not available with an external previewer".into());
+        }
+        #[cfg(feature = "preview-builtin")]
+        return Ok(preview::layout_tree()?);
+        #[cfg(not(feature = "preview-builtin"))]
+        return Err("The preview is not available".into());
+    }
+
+    fn set_preview_clock(&self, _clock: Option<common::PreviewClock>) -> Result<()> {
+        if *self.use_external_previewer.borrow() {
+            #[cfg(feature = "preview-external")]
+            self.server_notifier.send_notification(
+                "slint/lsp_to_preview".to_string(),
+                crate::common::LspToPreviewMessage::SetPreviewClock { clock: _clock },
+            )?;
+        } else {
+            #[cfg(feature = "preview-builtin")]
+            preview::set_preview_clock(_clock);
+        }
+        Ok(())
+    }
+
+    fn set_palette_overrides(
+        &self,
+        _global: String,
+        _overrides: Vec<(String, common::PropertyOverride)>,
+    ) -> Result<()> {
+        if *self.use_external_previewer.borrow() {
+            #[cfg(feature = "preview-external")]
+            self.server_notifier.send_notification(
+                "slint/lsp_to_preview".to_string(),
+                crate::common::LspToPreviewMessage::SetPaletteOverrides {
+                    global: _global,
+                    overrides: _overrides,
+                },
+            )?;
+        } else {
+            #[cfg(feature = "preview-builtin")]
+            preview::set_palette_overrides(_global, _overrides);
+        }
+        Ok(())
+    }
+
+    fn query_live_properties(&self) -> Result<()> {
+        if *self.use_external_previewer.borrow() {
+            #[cfg(feature = "preview-external")]
+            self.server_notifier.send_notification(
+                "slint/lsp_to_preview".to_string(),
+                crate::common::LspToPreviewMessage::QueryLiveProperties,
+            )?;
+        } else {
+            #[cfg(feature = "preview-builtin")]
+            preview::query_live_properties();
+        }
+        Ok(())
+    }
+
+    fn set_live_property(&self, _name: String, _value: serde_json::Value) -> Result<()> {
+        if *self.use_external_previewer.borrow() {
+            #[cfg(feature = "preview-external")]
+            self.server_notifier.send_notification(
+                "slint/lsp_to_preview".to_string(),
+                crate::common::LspToPreviewMessage::SetLiveProperty { name: _name, value: _value },
+            )?;
+        } else {
+            #[cfg(feature = "preview-builtin")]
+            preview::set_live_property(_name, _value);
+        }
+        Ok(())
+    }
+
     fn current_component(&self) -> Option<crate::common::PreviewComponent> {
         self.to_show.borrow().clone()
     }
@@ -140,6 +268,12 @@ pub struct Cli {
     )]
     include_paths: Vec<std::path::PathBuf>,
 
+    /// The argument should be in the format `<library>=<path>` specifying the
+    /// name of the library and the path to the library directory or a .slint
+    /// entry-point file.
+    #[arg(short = 'L', name = "library path", number_of_values = 1, action)]
+    library_paths: Vec<String>,
+
     /// The style name for the preview ('native' or 'fluent')
     #[arg(long, name = "style name", default_value_t, action)]
     style: String,
@@ -152,9 +286,49 @@ pub struct Cli {
     #[arg(long, action)]
     fullscreen: bool,
 
+    /// Open the preview window with the given logical size, such as `800x480`, instead of the
+    /// preferred size of the component
+    #[arg(long, value_name = "WIDTHxHEIGHT", conflicts_with = "fullscreen", value_parser = parse_window_size)]
+    window_size: Option<(f32, f32)>,
+
+    /// The scale factor of the preview window, between 0.5 and 4. It replaces the one of the
+    /// monitor, or the one derived from the DPI of the display with the linuxkms backend.
+    /// Not supported by the Qt backend.
+    #[arg(long, value_parser = parse_scale_factor)]
+    scale_factor: Option<f32>,
+
     /// Hide the preview toolbar
     #[arg(long, action)]
     no_toolbar: bool,
+
+    /// Do not start the language server, but preview the last component of the .slint code
+    /// read from stdin. Imports are resolved relative to the current directory.
+    #[arg(long, action)]
+    load_from_stdin: bool,
+
+    /// Print the display outputs of the linuxkms backend, for use with SLINT_DRM_OUTPUT, and exit
+    #[arg(long, action)]
+    list_outputs: bool,
+
+    /// Print the display modes of the connected outputs of the linuxkms backend, for use with
+    /// SLINT_DRM_MODE, and exit
+    #[arg(long, action)]
+    list_modes: bool,
+}
+
+fn parse_window_size(size: &str) -> std::result::Result<(f32, f32), String> {
+    size.split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|(width, height): &(f32, f32)| *width > 0. && *height > 0.)
+        .ok_or_else(|| format!("'{size}' is not of the form WIDTHxHEIGHT"))
+}
+
+fn parse_scale_factor(factor: &str) -> std::result::Result<f32, String> {
+    factor
+        .parse::<f32>()
+        .ok()
+        .filter(|factor| (0.5..=4.).contains(factor))
+        .ok_or_else(|| format!("'{factor}' is not a number between 0.5 and 4"))
 }
 
 enum OutgoingRequest {
@@ -179,33 +353,77 @@ pub fn send_notification(&self, method: String, params: impl serde::Serialize) -
     pub fn send_request<T: lsp_types::request::Request>(
         &self,
         request: T::Params,
+    ) -> Result<impl Future<Output = Result<T::Result>>> {
+        self.send_request_impl::<T>(request, None)
+    }
+
+    /// Like `send_request`, but the future resolves with an error if the client did not answer
+    /// within `timeout`. Use this for requests the client may ignore. The timeout is checked when
+    /// the main loop polls the future, at least every `POLL_INTERVAL`.
+    pub fn send_request_with_timeout<T: lsp_types::request::Request>(
+        &self,
+        request: T::Params,
+        timeout: std::time::Duration,
+    ) -> Result<impl Future<Output = Result<T::Result>>> {
+        self.send_request_impl::<T>(request, Some(std::time::Instant::now() + timeout))
+    }
+
+    fn send_request_impl<T: lsp_types::request::Request>(
+        &self,
+        request: T::Params,
+        deadline: Option<std::time::Instant>,
     ) -> Result<impl Future<Output = Result<T::Result>>> {
         static REQ_ID: atomic::AtomicI32 = atomic::AtomicI32::new(0);
         let id = RequestId::from(REQ_ID.fetch_add(1, atomic::Ordering::Relaxed));
         let msg =
             Message::Request(lsp_server::Request::new(id.clone(), T::METHOD.to_string(), request));
+        self.1.lock().unwrap().insert(id.clone(), OutgoingRequest::Start);
+        let pending = PendingRequest { id, queue: self.1.clone(), deadline };
         self.0.send(msg)?;
-        let queue = self.1.clone();
-        queue.lock().unwrap().insert(id.clone(), OutgoingRequest::Start);
-        Ok(std::future::poll_fn(move |ctx| {
-            let mut queue = queue.lock().unwrap();
-            match queue.remove(&id).unwrap() {
-                OutgoingRequest::Pending(_) | OutgoingRequest::Start => {
-                    queue.insert(id.clone(), OutgoingRequest::Pending(ctx.waker().clone()));
-                    Poll::Pending
-                }
-                OutgoingRequest::Done(d) => {
-                    if let Some(err) = d.error {
-                        Poll::Ready(Err(err.message.into()))
-                    } else {
-                        Poll::Ready(
-                            serde_json::from_value(d.result.unwrap_or_default())
-                                .map_err(|e| format!("cannot deserialize response: {e:?}").into()),
-                        )
-                    }
-                }
+        Ok(async move {
+            let response = pending.await?;
+            if let Some(err) = response.error {
+                Err(err.message.into())
+            } else {
+                serde_json::from_value(response.result.unwrap_or_default())
+                    .map_err(|e| format!("cannot deserialize response: {e:?}").into())
             }
-        }))
+        })
+    }
+}
+
+/// Waits for the response to a request sent to the client.
+///
+/// The request is removed from the queue when this is dropped, so that requests whose future
+/// was dropped, or that timed out, do not stay in the queue forever.
+struct PendingRequest {
+    id: RequestId,
+    queue: OutgoingRequestQueue,
+    /// Nothing wakes the task when the deadline passes, the main loop polls its futures
+    /// regularly instead
+    deadline: Option<std::time::Instant>,
+}
+
+impl Future for PendingRequest {
+    type Output = Result<lsp_server::Response>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut queue = this.queue.lock().unwrap();
+        if let Some(OutgoingRequest::Done(response)) = queue.remove(&this.id) {
+            return Poll::Ready(Ok(response));
+        }
+        if this.deadline.map_or(false, |deadline| std::time::Instant::now() >= deadline) {
+            return Poll::Ready(Err(format!("Request {} timed out", this.id).into()));
+        }
+        queue.insert(this.id.clone(), OutgoingRequest::Pending(ctx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+impl Drop for PendingRequest {
+    fn drop(&mut self) {
+        self.queue.lock().unwrap().remove(&self.id);
     }
 }
 
@@ -235,7 +453,10 @@ async fn handle_request(&self, request: lsp_server::Request, ctx: &Rc<Context>)
 }
 
 fn main() {
-    let args: Cli = Cli::parse();
+    let mut args: Cli = Cli::parse();
+    if args.list_outputs || args.list_modes {
+        std::process::exit(list_drm_outputs(args.list_modes));
+    }
     if !args.backend.is_empty() {
         std::env::set_var("SLINT_BACKEND", &args.backend);
     }
@@ -243,20 +464,37 @@ fn main() {
         // TODO: Have an API to set the Window fullscreen #3283
         std::env::set_var("SLINT_FULLSCREEN", "1");
     }
+    if let Some(scale_factor) = args.scale_factor {
+        // The winit and linuxkms backends use this instead of the scale factor of the screen
+        std::env::set_var("SLINT_SCALE_FACTOR", scale_factor.to_string());
+    }
+    let backend = std::env::var("SLINT_BACKEND").unwrap_or_default();
+    let only_linuxkms = cfg!(not(any(
+        feature = "backend-qt",
+        feature = "backend-winit",
+        feature = "backend-winit-x11",
+        feature = "backend-winit-wayland"
+    )));
+    if args.window_size.is_some() && (backend.starts_with("linuxkms") || only_linuxkms) {
+        eprintln!("Warning: --window-size is ignored by the linuxkms backend, which uses the size of the display mode");
+        args.window_size = None;
+    }
+
+    if args.load_from_stdin {
+        #[cfg(feature = "preview-builtin")]
+        std::process::exit(run_stdin_preview(args));
+        #[cfg(not(feature = "preview-builtin"))]
+        {
+            eprintln!("--load-from-stdin needs the builtin preview");
+            std::process::exit(1);
+        }
+    }
 
     #[cfg(feature = "preview-engine")]
     {
         let cli_args = args.clone();
-        let lsp_thread = std::thread::Builder::new()
-            .name("LanguageServer".into())
-            .spawn(move || {
-                /// Make sure we quit the event loop even if we panic
-                struct QuitEventLoop;
-                impl Drop for QuitEventLoop {
-                    fn drop(&mut self) {
-                        preview::quit_ui_event_loop();
-                    }
-                }
+        let lsp_thread =
+            std::thread::Builder::new().name("LanguageServer".into()).spawn(move || {
                 let quit_ui_loop = QuitEventLoop;
 
                 let threads = match run_lsp_server(args) {
@@ -268,27 +506,182 @@ fn drop(&mut self) {
                 };
 
                 drop(quit_ui_loop);
-                threads.join().unwrap();
-            })
-            .unwrap();
+                join_io_threads(threads);
+            });
+        let lsp_thread = match lsp_thread {
+            Ok(lsp_thread) => lsp_thread,
+            Err(error) => {
+                eprintln!("Error starting the LSP server thread: {error}");
+                std::process::exit(1);
+            }
+        };
 
         preview::start_ui_event_loop(cli_args);
-        lsp_thread.join().unwrap();
+        if lsp_thread.join().is_err() {
+            // The panic message was already printed
+            std::process::exit(1);
+        }
     }
     #[cfg(not(feature = "preview-engine"))]
     match run_lsp_server(args) {
-        Ok(threads) => threads.join().unwrap(),
+        Ok(threads) => join_io_threads(threads),
         Err(error) => {
             eprintln!("Error running LSP server: {}", error);
         }
     }
 }
 
+/// Makes sure the UI event loop quits when the thread running next to it ends, even if it panics
+#[cfg(feature = "preview-engine")]
+struct QuitEventLoop;
+
+#[cfg(feature = "preview-engine")]
+impl Drop for QuitEventLoop {
+    fn drop(&mut self) {
+        preview::quit_ui_event_loop();
+    }
+}
+
+/// Previews the component read from stdin until its window is closed, and returns the exit
+/// code of the process
+#[cfg(feature = "preview-builtin")]
+fn run_stdin_preview(args: Cli) -> i32 {
+    let cli_args = args.clone();
+    let preview_thread = std::thread::Builder::new().name("StdinPreview".into()).spawn(move || {
+        let _quit_ui_loop = QuitEventLoop;
+        // The error is not `Send`
+        preview_from_stdin(args).map_err(|error| error.to_string())
+    });
+    let preview_thread = match preview_thread {
+        Ok(preview_thread) => preview_thread,
+        Err(error) => {
+            eprintln!("Error starting the preview thread: {error}");
+            return 1;
+        }
+    };
+
+    preview::start_ui_event_loop(cli_args);
+    match preview_thread.join() {
+        Ok(Ok(())) => 0,
+        Ok(Err(error)) => {
+            eprintln!("Error previewing from stdin: {error}");
+            1
+        }
+        // The panic message was already printed
+        Err(_) => 1,
+    }
+}
+
+fn join_io_threads(threads: IoThreads) {
+    if let Err(error) = threads.join() {
+        eprintln!("Error communicating with the client: {}", error);
+    }
+}
+
+/// Prints the outputs, or their modes, and returns the exit code of the process
+#[cfg(any(feature = "backend-linuxkms", feature = "backend-linuxkms-noseat"))]
+fn list_drm_outputs(list_modes: bool) -> i32 {
+    match i_slint_backend_linuxkms::list_connectors() {
+        Ok(connectors) => {
+            for connector in connectors {
+                if !list_modes {
+                    let state = if connector.connected { "connected" } else { "disconnected" };
+                    println!("{} ({state})", connector.name);
+                    continue;
+                }
+                for mode in connector.modes {
+                    println!(
+                        "{}: {}x{}@{}{}",
+                        connector.name,
+                        mode.size.width,
+                        mode.size.height,
+                        mode.refresh_rate,
+                        if mode.preferred { " (preferred)" } else { "" }
+                    );
+                }
+            }
+            0
+        }
+        Err(error) => {
+            eprintln!("Error listing the outputs: {}", error);
+            1
+        }
+    }
+}
+
+#[cfg(not(any(feature = "backend-linuxkms", feature = "backend-linuxkms-noseat")))]
+fn list_drm_outputs(_list_modes: bool) -> i32 {
+    eprintln!("Listing the outputs needs the linuxkms backend");
+    1
+}
+
+/// Shows the code read from stdin in the preview until its window is closed, and prints the
+/// diagnostics to stderr.
+#[cfg(feature = "preview-builtin")]
+fn preview_from_stdin(args: Cli) -> Result<()> {
+    let mut source = String::new();
+    std::io::Read::read_to_string(&mut std::io::stdin(), &mut source)?;
+
+    // A virtual document in the current directory, so that relative imports are found there
+    let path = std::env::current_dir()?.join("stdin.slint");
+    preview::set_contents(&path, source);
+    preview::config_changed(common::PreviewConfig {
+        hide_ui: None,
+        style: args.style.clone(),
+        include_paths: args.include_paths,
+        library_paths: args
+            .library_paths
+            .iter()
+            .filter_map(|entry| entry.split_once('=').map(|(k, v)| (k.into(), v.into())))
+            .collect(),
+        font_dirs: Vec::new(),
+    });
+
+    // The preview keeps the only sender while its window is open, so the loop below
+    // ends when the window gets closed.
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    preview::open_ui(&ServerNotifier(sender, OutgoingRequestQueue::default()));
+    preview::load_preview(common::PreviewComponent { path, component: None, style: args.style });
+
+    for message in receiver {
+        let Message::Notification(notification) = message else { continue };
+        if notification.method != "textDocument/publishDiagnostics" {
+            continue;
+        }
+        let params: lsp_types::PublishDiagnosticsParams =
+            serde_json::from_value(notification.params)?;
+        for d in params.diagnostics {
+            eprintln!(
+                "{}:{}:{}: {}",
+                params.uri.path(),
+                d.range.start.line + 1,
+                d.range.start.character + 1,
+                d.message
+            );
+        }
+    }
+    Ok(())
+}
+
 fn run_lsp_server(args: Cli) -> Result<IoThreads> {
     let (connection, io_threads) = Connection::stdio();
     let (id, params) = connection.initialize_start()?;
 
-    let init_param: InitializeParams = serde_json::from_value(params).unwrap();
+    let init_param: InitializeParams = match serde_json::from_value(params) {
+        Ok(init_param) => init_param,
+        Err(error) => {
+            let message = format!("Invalid initialize parameters: {error}");
+            connection.sender.send(Message::Response(Response::new_err(
+                id,
+                ErrorCode::InvalidParams as i32,
+                message.clone(),
+            )))?;
+            // Let the IO threads send the response, they stop when the client goes away
+            drop(connection);
+            io_threads.join()?;
+            return Err(message.into());
+        }
+    };
     let initialize_result =
         serde_json::to_value(language::server_initialize_result(&init_param.capabilities))?;
     connection.initialize_finish(id, initialize_result)?;
@@ -316,16 +709,30 @@ fn main_loop(connection: Connection, init_param: InitializeParams, cli_args: Cli
         #[cfg(all(feature = "preview-builtin", feature = "preview-external"))]
         use_external_previewer: RefCell::new(false), // prefer internal
         to_show: RefCell::new(None),
+        #[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+        external_previewer_connected: Default::default(),
+        #[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+        missed_heartbeats: Default::default(),
     });
+    #[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+    let previewer = preview.clone();
     let mut compiler_config =
         CompilerConfiguration::new(i_slint_compiler::generator::OutputFormat::Interpreter);
 
     compiler_config.style =
         Some(if cli_args.style.is_empty() { "native".into() } else { cli_args.style });
     compiler_config.include_paths = cli_args.include_paths;
+    compiler_config.library_paths = cli_args
+        .library_paths
+        .iter()
+        .filter_map(|entry| entry.split_once('=').map(|(k, v)| (k.into(), v.into())))
+        .collect();
+    let progress = ProgressReporter::default();
     let preview_notifier = preview.clone();
+    let import_progress = progress.clone();
     compiler_config.open_import_fallback = Some(Rc::new(move |path| {
         let preview_notifier = preview_notifier.clone();
+        import_progress.report(path.clone());
         Box::pin(async move {
             let contents = std::fs::read_to_string(&path);
             if let Ok(contents) = &contents {
@@ -335,21 +742,27 @@ fn main_loop(connection: Connection, init_param: InitializeParams, cli_args: Cli
         })
     }));
 
+    let mut document_cache = DocumentCache::new(compiler_config);
+    document_cache.set_workspace_folders(workspace_roots(&init_param));
     let ctx = Rc::new(Context {
-        document_cache: RefCell::new(DocumentCache::new(compiler_config)),
+        document_cache: RefCell::new(document_cache),
         server_notifier,
         init_param,
         preview,
+        progress,
     });
 
     let mut futures = Vec::<Pin<Box<dyn Future<Output = Result<()>>>>>::new();
+    // The futures handling the requests, by id so they can be dropped when a request is cancelled
+    let mut requests = HashMap::<RequestId, Pin<Box<dyn Future<Output = Result<()>>>>>::new();
     let mut first_future = Box::pin(load_configuration(&ctx));
 
     // We are waiting in this loop for two kind of futures:
     //  - The compiler future should always be ready immediately because we do not set a callback to load files
     //  - the future from `send_request` are blocked waiting for a response from the client.
     //    Responses are sent on the `connection.receiver` which will wake the loop, so there
-    //    is no need to do anything in the Waker.
+    //    is no need to do anything in the Waker. The futures are also polled every
+    //    `POLL_INTERVAL`, for the requests that time out.
     struct DummyWaker;
     impl std::task::Wake for DummyWaker {
         fn wake(self: Arc<Self>) {}
@@ -360,52 +773,114 @@ fn wake(self: Arc<Self>) {}
         Poll::Pending => futures.push(first_future),
     };
 
-    for msg in &connection.receiver {
-        match msg {
-            Message::Request(req) => {
-                // ignore errors when shutdown
-                if connection.handle_shutdown(&req).unwrap_or(false) {
-                    return Ok(());
+    #[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+    let heartbeat = crossbeam_channel::tick(PREVIEW_HEARTBEAT_INTERVAL);
+    #[cfg(not(all(feature = "preview-external", feature = "preview-builtin")))]
+    let heartbeat = crossbeam_channel::never::<std::time::Instant>();
+
+    loop {
+        let msg = crossbeam_channel::select! {
+            recv(connection.receiver) -> msg => match msg {
+                Ok(msg) => Some(msg),
+                Err(_) => break,
+            },
+            recv(heartbeat) -> _ => {
+                #[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+                if previewer.check_external_previewer() {
+                    external_previewer_disconnected(&ctx);
                 }
-                futures.push(Box::pin(rh.handle_request(req, &ctx)));
+                None
             }
-            Message::Response(resp) => {
-                if let Some(q) = request_queue.lock().unwrap().get_mut(&resp.id) {
-                    match q {
-                        OutgoingRequest::Done(_) => {
-                            return Err("Response to unknown request".into())
-                        }
-                        OutgoingRequest::Start => { /* nothing to do */ }
-                        OutgoingRequest::Pending(x) => x.wake_by_ref(),
+            default(POLL_INTERVAL) => None,
+        };
+        if let Some(msg) = msg {
+            match msg {
+                Message::Request(req) => {
+                    // ignore errors when shutdown
+                    if connection.handle_shutdown(&req).unwrap_or(false) {
+                        return Ok(());
+                    }
+                    requests.insert(req.id.clone(), Box::pin(rh.handle_request(req, &ctx)));
+                }
+                Message::Response(resp) => handle_response(&request_queue, resp),
+                Message::Notification(notification) if notification.method == Cancel::METHOD => {
+                    let params: CancelParams = serde_json::from_value(notification.params)?;
+                    let id = match params.id {
+                        NumberOrString::Number(id) => RequestId::from(id),
+                        NumberOrString::String(id) => RequestId::from(id),
                     };
-                    *q = OutgoingRequest::Done(resp)
-                } else {
-                    return Err("Response to unknown request".into());
+                    // Requests that are already answered are not in the map anymore
+                    if requests.remove(&id).is_some() {
+                        connection.sender.send(Message::Response(Response::new_err(
+                            id,
+                            ErrorCode::RequestCanceled as i32,
+                            "Request cancelled".into(),
+                        )))?;
+                    }
+                }
+                Message::Notification(notification) => {
+                    #[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+                    if notification.method == "slint/preview_to_lsp" {
+                        previewer.external_previewer_alive();
+                    }
+                    futures.push(Box::pin(handle_notification(notification, &ctx)))
                 }
-            }
-            Message::Notification(notification) => {
-                futures.push(Box::pin(handle_notification(notification, &ctx)))
             }
         }
 
         let mut result = Ok(());
-        futures.retain_mut(|f| {
-            if result.is_err() {
-                return true;
-            }
-            match f.as_mut().poll(&mut std::task::Context::from_waker(&waker)) {
-                Poll::Ready(x) => {
-                    result = x;
-                    false
-                }
-                Poll::Pending => true,
-            }
-        });
+        futures.retain_mut(|f| result.is_err() || is_pending(f, &waker, &mut result));
+        requests.retain(|_, f| result.is_err() || is_pending(f, &waker, &mut result));
         result?;
     }
     Ok(())
 }
 
+/// Hands the response over to the `PendingRequest` waiting for it
+fn handle_response(queue: &OutgoingRequestQueue, response: Response) {
+    let mut queue = queue.lock().unwrap();
+    // If the request is not in the queue, it timed out or its future was dropped, and nobody
+    // waits for the response anymore
+    let Some(q) = queue.get_mut(&response.id) else { return };
+    match q {
+        // Only the first response to a request is used
+        OutgoingRequest::Done(_) => return,
+        OutgoingRequest::Start => { /* nothing to do */ }
+        OutgoingRequest::Pending(x) => x.wake_by_ref(),
+    };
+    *q = OutgoingRequest::Done(response)
+}
+
+/// The external previewer stopped answering, probably because it crashed: show the preview in
+/// the builtin previewer instead. The editor owns the external previewer, so it is up to the
+/// editor to start it again, which switches back to it.
+#[cfg(all(feature = "preview-external", feature = "preview-builtin"))]
+fn external_previewer_disconnected(ctx: &Rc<Context>) {
+    preview::send_status_notification(
+        &ctx.server_notifier,
+        "Preview disconnected, using the builtin preview",
+        lsp_ext::Health::Warning,
+    );
+    ctx.preview.set_use_external_previewer(false);
+    language::request_state(ctx);
+}
+
+/// Polls the future and returns true if it is not ready yet. Otherwise, its output is
+/// stored in `result`.
+fn is_pending(
+    future: &mut Pin<Box<dyn Future<Output = Result<()>> + '_>>,
+    waker: &Waker,
+    result: &mut Result<()>,
+) -> bool {
+    match future.as_mut().poll(&mut std::task::Context::from_waker(waker)) {
+        Poll::Ready(x) => {
+            *result = x;
+            false
+        }
+        Poll::Pending => true,
+    }
+}
+
 async fn handle_notification(req: lsp_server::Notification, ctx: &Rc<Context>) -> Result<()> {
     match &*req.method {
         DidOpenTextDocument::METHOD => {
@@ -420,19 +895,21 @@ async fn handle_notification(req: lsp_server::Notification, ctx: &Rc<Context>) -
             .await?;
         }
         DidChangeTextDocument::METHOD => {
-            let mut params: DidChangeTextDocumentParams = serde_json::from_value(req.params)?;
-            reload_document(
-                ctx,
-                params.content_changes.pop().unwrap().text,
-                params.text_document.uri,
-                Some(params.text_document.version),
-                &mut ctx.document_cache.borrow_mut(),
-            )
-            .await?;
+            let params: DidChangeTextDocumentParams = serde_json::from_value(req.params)?;
+            change_document(ctx, params, &mut ctx.document_cache.borrow_mut()).await?;
+        }
+        DidCloseTextDocument::METHOD => {
+            let params: DidCloseTextDocumentParams = serde_json::from_value(req.params)?;
+            ctx.document_cache.borrow_mut().open_document_texts.remove(&params.text_document.uri);
         }
         DidChangeConfiguration::METHOD => {
             load_configuration(ctx).await?;
         }
+        DidChangeWorkspaceFolders::METHOD => {
+            let params: DidChangeWorkspaceFoldersParams = serde_json::from_value(req.params)?;
+            change_workspace_folders(ctx, params.event, &mut ctx.document_cache.borrow_mut())
+                .await?;
+        }
 
         #[cfg(any(feature = "preview-builtin", feature = "preview-external"))]
         "slint/showPreview" => {
@@ -467,6 +944,18 @@ async fn handle_notification(req: lsp_server::Notification, ctx: &Rc<Context>) -
                 M::RequestState { .. } => {
                     crate::language::request_state(ctx);
                 }
+                M::RuntimeError { params } => {
+                    crate::preview::send_runtime_error_notification(&ctx.server_notifier, params);
+                }
+                M::ElementPath { params } => {
+                    crate::preview::send_element_path_notification(&ctx.server_notifier, params);
+                }
+                M::LiveProperties { params } => {
+                    crate::preview::send_live_properties_notification(&ctx.server_notifier, params);
+                }
+                M::Heartbeat => {
+                    // Handled in the main loop
+                }
             }
         }
         _ => (),
@@ -484,9 +973,107 @@ pub async fn send_show_document_to_editor(
     else {
         return;
     };
-    let Ok(fut) = sender.send_request::<lsp_types::request::ShowDocument>(params) else {
+    let Ok(fut) = sender.send_request_with_timeout::<lsp_types::request::ShowDocument>(
+        params,
+        SHOW_DOCUMENT_TIMEOUT,
+    ) else {
         return;
     };
 
     let _ = fut.await;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll<F: Future>(future: &mut Pin<Box<F>>) -> Poll<F::Output> {
+        struct DummyWaker;
+        impl std::task::Wake for DummyWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+        let waker = Arc::new(DummyWaker).into();
+        future.as_mut().poll(&mut std::task::Context::from_waker(&waker))
+    }
+
+    #[test]
+    fn test_outgoing_request_queue_cleanup() {
+        use lsp_types::request::ShowDocument;
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let queue = OutgoingRequestQueue::default();
+        let server_notifier = ServerNotifier(sender, queue.clone());
+        let params = lsp_types::ShowDocumentParams {
+            uri: lsp_types::Url::parse("file:///test.slint").unwrap(),
+            external: None,
+            take_focus: None,
+            selection: None,
+        };
+        let sent_id = || match receiver.try_recv().unwrap() {
+            Message::Request(request) => request.id,
+            _ => panic!("Expected a request"),
+        };
+        let result = lsp_types::ShowDocumentResult { success: true };
+
+        // Successful response, the duplicate is ignored
+        let mut future =
+            Box::pin(server_notifier.send_request::<ShowDocument>(params.clone()).unwrap());
+        let id = sent_id();
+        assert!(poll(&mut future).is_pending());
+        assert_eq!(queue.lock().unwrap().len(), 1);
+        handle_response(&queue, Response::new_ok(id.clone(), &result));
+        handle_response(&queue, Response::new_ok(id, &result));
+        assert!(matches!(poll(&mut future), Poll::Ready(Ok(r)) if r.success));
+        assert!(queue.lock().unwrap().is_empty());
+
+        // Error response, before the future was polled
+        let mut future =
+            Box::pin(server_notifier.send_request::<ShowDocument>(params.clone()).unwrap());
+        handle_response(&queue, Response::new_err(sent_id(), 1, "error".into()));
+        assert!(matches!(poll(&mut future), Poll::Ready(Err(_))));
+        assert!(queue.lock().unwrap().is_empty());
+
+        // Response that cannot be deserialized
+        let mut future =
+            Box::pin(server_notifier.send_request::<ShowDocument>(params.clone()).unwrap());
+        let id = sent_id();
+        assert!(poll(&mut future).is_pending());
+        handle_response(&queue, Response::new_ok(id, "not a result"));
+        assert!(matches!(poll(&mut future), Poll::Ready(Err(_))));
+        assert!(queue.lock().unwrap().is_empty());
+
+        // Timeout, the late response is ignored
+        let mut future = Box::pin(
+            server_notifier
+                .send_request_with_timeout::<ShowDocument>(
+                    params.clone(),
+                    std::time::Duration::ZERO,
+                )
+                .unwrap(),
+        );
+        let id = sent_id();
+        assert!(matches!(poll(&mut future), Poll::Ready(Err(_))));
+        assert!(queue.lock().unwrap().is_empty());
+        handle_response(&queue, Response::new_ok(id, &result));
+        assert!(queue.lock().unwrap().is_empty());
+
+        // The deadline is checked when the main loop polls the future again
+        let timeout = std::time::Duration::from_millis(10);
+        let mut future = Box::pin(
+            server_notifier
+                .send_request_with_timeout::<ShowDocument>(params.clone(), timeout)
+                .unwrap(),
+        );
+        sent_id();
+        assert!(poll(&mut future).is_pending());
+        std::thread::sleep(timeout);
+        assert!(matches!(poll(&mut future), Poll::Ready(Err(_))));
+        assert!(queue.lock().unwrap().is_empty());
+
+        // Dropped future
+        let mut future = Box::pin(server_notifier.send_request::<ShowDocument>(params).unwrap());
+        assert!(poll(&mut future).is_pending());
+        drop(future);
+        assert!(queue.lock().unwrap().is_empty());
+    }
+}
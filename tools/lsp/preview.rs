@@ -9,7 +9,7 @@
 };
 
 use crate::{
-    common::{PreviewComponent, PreviewConfig},
+    common::{LiveProperty, PreviewClock, PreviewComponent, PreviewConfig, PropertyOverride},
     lsp_ext::Health,
 };
 use i_slint_compiler::{diagnostics::SourceFile, object_tree::ElementRc};
@@ -194,6 +194,63 @@ fn root_element(component_instance: &ComponentInstance) -> ElementRc {
     }
 }
 
+/// Returns the label of the element for the element path: the component name for the root
+/// element of a component, the type and the id otherwise.
+fn element_label(element: &ElementRc) -> String {
+    use i_slint_compiler::parser::SyntaxKind;
+
+    let e = element.borrow();
+    let Some(node) = &e.node else {
+        return e.id.clone();
+    };
+    let parent = node.parent();
+    if let Some(component) = parent.as_ref().filter(|p| p.kind() == SyntaxKind::Component) {
+        if let Some(name) = component
+            .child_node(SyntaxKind::DeclaredIdentifier)
+            .and_then(|n| i_slint_compiler::parser::identifier_text(&n))
+        {
+            return name;
+        }
+    }
+    let type_name =
+        node.QualifiedName().map(|q| q.text().to_string().trim().to_string()).unwrap_or_default();
+    match parent
+        .filter(|p| p.kind() == SyntaxKind::SubElement)
+        .and_then(|p| p.child_text(SyntaxKind::Identifier))
+    {
+        Some(id) => format!("{id} := {type_name}"),
+        None => type_name,
+    }
+}
+
+/// Sends the path from the root element to the innermost element at the given position
+/// to the LSP. Runs in the UI thread.
+fn send_element_path_at(x: f32, y: f32, component_instance: &ComponentInstance) {
+    let click_position = LogicalPoint::from_lengths(LogicalLength::new(x), LogicalLength::new(y));
+
+    let mut path = vec![root_element(component_instance)];
+    while let Some(child) = path.last().unwrap().borrow().children.iter().find_map(|c| {
+        let c = self_or_embedded_component_root(c);
+        let position = component_instance.element_position(&c)?;
+        position.contains(click_position).then_some(c)
+    }) {
+        path.push(child);
+    }
+
+    let elements = path
+        .iter()
+        .filter_map(|e| {
+            let (file, range) = lsp_element_position(e);
+            let uri = lsp_types::Url::from_file_path(file).ok()?;
+            Some(crate::lsp_ext::PreviewElementPathItem {
+                label: element_label(e),
+                location: lsp_types::Location::new(uri, range),
+            })
+        })
+        .collect();
+    notify_element_path(crate::lsp_ext::PreviewElementPathParams { elements });
+}
+
 // triggered from the UI, running in UI thread
 pub fn select_element_at(x: f32, y: f32) {
     let Some(component_instance) = component_instance() else {
@@ -202,6 +259,7 @@ pub fn select_element_at(x: f32, y: f32) {
     let root_element = root_element(&component_instance);
 
     select_element_at_impl(x, y, &component_instance, &root_element);
+    send_element_path_at(x, y, &component_instance);
 }
 
 // triggered from the UI, running in UI thread
@@ -221,6 +279,7 @@ pub fn select_element_into(x: f32, y: f32) {
     if let Some(se) = select_element_at_impl(x, y, &component_instance, &root_element) {
         select_element_at_impl(x, y, &component_instance, &se);
     }
+    send_element_path_at(x, y, &component_instance);
 }
 
 fn change_style() {
@@ -350,6 +409,7 @@ async fn reload_preview_impl(
     let component = PreviewComponent { style: String::new(), ..preview_component };
 
     start_parsing();
+    set_runtime_error_handler();
 
     let mut builder = slint_interpreter::ComponentCompiler::default();
 
@@ -391,20 +451,69 @@ async fn reload_preview_impl(
     };
 }
 
+thread_local! {
+    /// The values of the properties of the previewed component right after it was created
+    static INITIAL_PROPERTY_VALUES: std::cell::RefCell<HashMap<String, slint_interpreter::Value>> =
+        Default::default();
+}
+
+fn property_values(
+    instance: &ComponentInstance,
+) -> impl Iterator<Item = (String, slint_interpreter::ValueType, slint_interpreter::Value)> + '_ {
+    instance.definition().properties().collect::<Vec<_>>().into_iter().filter_map(|(name, ty)| {
+        let value = instance.get_property(&name).ok()?;
+        Some((name, ty, value))
+    })
+}
+
+/// Returns the properties of `instance` that changed since it was created, for example
+/// because the user interacted with the preview
+fn changed_properties(
+    instance: &ComponentInstance,
+) -> Vec<(String, slint_interpreter::ValueType, slint_interpreter::Value)> {
+    INITIAL_PROPERTY_VALUES.with(|initial| {
+        let initial = initial.borrow();
+        property_values(instance)
+            .filter(|(name, _, value)| initial.get(name) != Some(value))
+            .collect()
+    })
+}
+
 /// This sets up the preview area to show the ComponentInstance
 ///
+/// When reloading the same component, the properties of the `previous` instance that changed
+/// since it was created are set on the new instance, if it still has them with the same type.
+///
 /// This must be run in the UI thread.
 pub fn set_preview_factory(
     ui: &ui::PreviewUi,
     compiled: ComponentDefinition,
+    previous: Option<ComponentInstance>,
     callback: Box<dyn Fn(ComponentInstance)>,
 ) {
     // Ensure that the popup is closed as it is related to the old factory
     i_slint_core::window::WindowInner::from_pub(ui.window()).close_popup();
 
+    let changed = previous
+        .as_ref()
+        .filter(|previous| previous.definition().name() == compiled.name())
+        .map(changed_properties)
+        .unwrap_or_default();
+
     let factory = slint::ComponentFactory::new(move |ctx: FactoryContext| {
         let instance = compiled.create_embedded(ctx).unwrap();
 
+        INITIAL_PROPERTY_VALUES.with(|initial| {
+            *initial.borrow_mut() =
+                property_values(&instance).map(|(name, _, value)| (name, value)).collect();
+        });
+        for (name, ty, value) in &changed {
+            if compiled.properties().any(|(n, t)| n == *name && t == *ty) {
+                // Output properties can't be set, they are computed again
+                let _ = instance.set_property(name, value.clone());
+            }
+        }
+
         if let Some((path, offset)) =
             CONTENT_CACHE.get().and_then(|c| c.lock().unwrap().highlight.clone())
         {
@@ -435,6 +544,147 @@ pub fn highlight(path: &Option<PathBuf>, offset: u32) {
     }
 }
 
+/// Overrides the time seen by `animation-tick()` and the animations of the preview
+pub fn set_preview_clock(clock: Option<PreviewClock>) {
+    run_in_ui_thread(move || async move {
+        i_slint_core::animations::CURRENT_ANIMATION_DRIVER.with(|driver| {
+            driver.set_simulated_time(
+                clock.map(|c| (i_slint_core::animations::Instant(c.time), c.speed)),
+            )
+        });
+        i_slint_core::animations::update_animations();
+    });
+}
+
+/// Overrides properties of the global `global` of the previewed component, for example to see
+/// it with another palette. The overrides are gone when the preview is reloaded.
+pub fn set_palette_overrides(global: String, overrides: Vec<(String, PropertyOverride)>) {
+    run_in_ui_thread(move || async move {
+        let Some(component_instance) = component_instance() else { return };
+        for (name, value) in overrides {
+            let value = match value {
+                PropertyOverride::Number(n) => slint_interpreter::Value::Number(n),
+                PropertyOverride::Bool(b) => slint_interpreter::Value::Bool(b),
+                PropertyOverride::String(s) => slint_interpreter::Value::String(s.into()),
+                PropertyOverride::Color(argb) => slint_interpreter::Value::Brush(
+                    slint::Brush::SolidColor(slint::Color::from_argb_encoded(argb)),
+                ),
+            };
+            // The LSP checked the values against the source, but the preview may be outdated
+            component_instance.set_global_property(&global, &name, value).ok();
+        }
+    });
+}
+
+/// Returns the type name of the property `name` of the previewed component if it can be changed
+/// live: output properties and properties bound to an expression that is not constant are
+/// computed by the component.
+fn live_property_type(instance: &ComponentInstance, name: &str) -> Option<String> {
+    let component = instance.definition().root_component();
+    let root = component.root_element.borrow();
+    let declaration = root.property_declarations.get(name)?;
+    if declaration.visibility == i_slint_compiler::object_tree::PropertyVisibility::Output {
+        return None;
+    }
+    if root.bindings.get(name).map_or(false, |b| !b.borrow().expression.is_constant()) {
+        return None;
+    }
+    Some(declaration.property_type.to_string())
+}
+
+fn live_property_value(value: &slint_interpreter::Value) -> Option<serde_json::Value> {
+    Some(match value {
+        slint_interpreter::Value::Number(n) => serde_json::Number::from_f64(*n)?.into(),
+        slint_interpreter::Value::Bool(b) => (*b).into(),
+        slint_interpreter::Value::String(s) => s.as_str().into(),
+        slint_interpreter::Value::Brush(slint::Brush::SolidColor(c)) => {
+            format!("#{:02x}{:02x}{:02x}{:02x}", c.red(), c.green(), c.blue(), c.alpha()).into()
+        }
+        _ => return None,
+    })
+}
+
+fn live_properties(instance: &ComponentInstance) -> Vec<LiveProperty> {
+    property_values(instance)
+        .filter_map(|(name, _, value)| {
+            Some(LiveProperty {
+                type_name: live_property_type(instance, &name)?,
+                value: live_property_value(&value)?,
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Converts `value` to the type of the property `name` and sets it on `instance`
+fn set_live_property_impl(
+    instance: &ComponentInstance,
+    name: &str,
+    value: &serde_json::Value,
+) -> Result<(), String> {
+    let (_, ty) = instance
+        .definition()
+        .properties()
+        .find(|(n, _)| n == name)
+        .ok_or_else(|| format!("The previewed component has no property named {name}"))?;
+    let type_name = live_property_type(instance, name)
+        .ok_or_else(|| format!("The property {name} is read-only"))?;
+
+    let value = match (ty, value) {
+        (slint_interpreter::ValueType::Number, serde_json::Value::Number(n)) => {
+            n.as_f64().map(slint_interpreter::Value::Number)
+        }
+        (slint_interpreter::ValueType::Bool, serde_json::Value::Bool(b)) => {
+            Some(slint_interpreter::Value::Bool(*b))
+        }
+        (slint_interpreter::ValueType::String, serde_json::Value::String(s)) => {
+            Some(slint_interpreter::Value::String(s.into()))
+        }
+        (slint_interpreter::ValueType::Brush, serde_json::Value::String(color)) => {
+            i_slint_compiler::literals::parse_color_literal(color).map(|argb| {
+                slint_interpreter::Value::Brush(slint::Brush::SolidColor(
+                    slint::Color::from_argb_encoded(argb),
+                ))
+            })
+        }
+        _ => None,
+    }
+    .ok_or_else(|| format!("Invalid value {value} for the property {name} of type {type_name}"))?;
+
+    instance.set_property(name, value).map_err(|e| format!("Cannot set {name}: {e}"))
+}
+
+/// Sends the properties of the previewed component that can be changed live to the LSP
+pub fn query_live_properties() {
+    run_in_ui_thread(move || async move {
+        let properties = component_instance().map(|i| live_properties(&i)).unwrap_or_default();
+        notify_live_properties(crate::lsp_ext::PreviewLivePropertiesParams {
+            properties,
+            error: None,
+        });
+    });
+}
+
+/// Sets the property `name` of the previewed component, without recompiling, and sends the
+/// updated properties to the LSP. Like the changes made by interacting with the preview,
+/// the value is kept when the preview is reloaded.
+pub fn set_live_property(name: String, value: serde_json::Value) {
+    run_in_ui_thread(move || async move {
+        let Some(instance) = component_instance() else {
+            notify_live_properties(crate::lsp_ext::PreviewLivePropertiesParams {
+                properties: vec![],
+                error: Some("No component is previewed".into()),
+            });
+            return;
+        };
+        let error = set_live_property_impl(&instance, &name, &value).err();
+        notify_live_properties(crate::lsp_ext::PreviewLivePropertiesParams {
+            properties: live_properties(&instance),
+            error,
+        });
+    });
+}
+
 pub fn show_document_request_from_element_callback(
     file: &str,
     range: lsp_types::Range,
@@ -495,6 +745,57 @@ pub fn send_status_notification(sender: &crate::ServerNotifier, message: &str, h
         .unwrap_or_else(|e| eprintln!("Error sending notification: {:?}", e));
 }
 
+pub fn send_element_path_notification(
+    sender: &crate::ServerNotifier,
+    params: crate::lsp_ext::PreviewElementPathParams,
+) {
+    sender
+        .send_notification(crate::lsp_ext::PreviewElementPathNotification::METHOD.into(), params)
+        .unwrap_or_else(|e| eprintln!("Error sending notification: {:?}", e));
+}
+
+pub fn send_live_properties_notification(
+    sender: &crate::ServerNotifier,
+    params: crate::lsp_ext::PreviewLivePropertiesParams,
+) {
+    sender
+        .send_notification(crate::lsp_ext::PreviewLivePropertiesNotification::METHOD.into(), params)
+        .unwrap_or_else(|e| eprintln!("Error sending notification: {:?}", e));
+}
+
+pub fn send_runtime_error_notification(
+    sender: &crate::ServerNotifier,
+    params: crate::lsp_ext::PreviewRuntimeErrorParams,
+) {
+    sender
+        .send_notification(crate::lsp_ext::PreviewRuntimeErrorNotification::METHOD.into(), params)
+        .unwrap_or_else(|e| eprintln!("Error sending notification: {:?}", e));
+}
+
+/// Forwards the errors happening while evaluating the bindings of the previewed component
+/// to the LSP.
+///
+/// Must be called in the UI thread.
+fn set_runtime_error_handler() {
+    slint_interpreter::set_runtime_error_handler(Some(Box::new(|message, source_location| {
+        notify_runtime_error(runtime_error_params(message, source_location));
+    })));
+}
+
+/// Maps an error reported by the interpreter to the notification sent to the editor
+fn runtime_error_params(
+    message: &str,
+    source_location: Option<&i_slint_compiler::diagnostics::SourceLocation>,
+) -> crate::lsp_ext::PreviewRuntimeErrorParams {
+    let source_location = source_location.and_then(|location| {
+        let sf = location.source_file.as_ref().filter(|_| location.span.is_valid())?;
+        let uri = lsp_types::Url::from_file_path(sf.path()).ok()?;
+        let position = crate::util::map_position(sf, (location.span.offset as u32).into());
+        Some(lsp_types::Location { uri, range: lsp_types::Range::new(position, position) })
+    });
+    crate::lsp_ext::PreviewRuntimeErrorParams { message: message.into(), source_location }
+}
+
 pub fn reset_selections(ui: &ui::PreviewUi) {
     let model = Rc::new(slint::VecModel::from(Vec::new()));
     ui.set_selections(slint::ModelRc::from(model));
@@ -546,3 +847,46 @@ pub fn set_selections(
     let model = Rc::new(slint::VecModel::from(values));
     ui.set_selections(slint::ModelRc::from(model));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_missing_image_reports_runtime_error() {
+        i_slint_backend_testing::init();
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        slint_interpreter::set_runtime_error_handler(Some(Box::new({
+            let errors = errors.clone();
+            move |message, source_location| {
+                errors.borrow_mut().push(runtime_error_params(message, source_location))
+            }
+        })));
+
+        let path = std::env::temp_dir().join("runtime-error.slint");
+        let mut compiler = slint_interpreter::ComponentCompiler::default();
+        let definition = spin_on::spin_on(
+            compiler.build_from_source(
+                r#"export component Test {
+    out property <image> missing: @image-url("does-not-exist.png");
+}"#
+                .into(),
+                path.clone(),
+            ),
+        )
+        .unwrap();
+        let instance = definition.create().unwrap();
+        instance.get_property("missing").unwrap();
+        slint_interpreter::set_runtime_error_handler(None);
+
+        let errors = errors.borrow();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("does-not-exist.png"), "{}", errors[0].message);
+        let location = errors[0].source_location.as_ref().unwrap();
+        assert_eq!(location.uri, lsp_types::Url::from_file_path(&path).unwrap());
+        // The binding of the property
+        assert_eq!(location.range.start.line, 1);
+        assert!(location.range.start.character >= 4);
+    }
+}
@@ -3,6 +3,7 @@
 
 // cSpell: ignore condvar
 
+use crate::common::{LayoutConstraints, LayoutGeometry, LayoutTreeNode};
 use crate::lsp_ext::Health;
 use crate::ServerNotifier;
 
@@ -13,8 +14,9 @@
 
 use once_cell::sync::Lazy;
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::future::Future;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::{Rc, Weak};
 use std::sync::{Condvar, Mutex};
 
@@ -167,7 +169,14 @@ fn open_ui_impl(preview_state: &mut PreviewState) {
     };
 
     // TODO: Handle Error!
-    let ui = preview_state.ui.get_or_insert_with(|| super::ui::create_ui(default_style).unwrap());
+    let window_size = CLI_ARGS.with(|args| args.get().and_then(|a| a.window_size));
+    let ui = preview_state.ui.get_or_insert_with(|| {
+        let ui = super::ui::create_ui(default_style).unwrap();
+        if let Some((width, height)) = window_size {
+            ui.window().set_size(slint::LogicalSize::new(width, height));
+        }
+        ui
+    });
     ui.set_show_preview_ui(show_preview_ui);
     ui.window().on_close_requested(|| {
         let mut cache = super::CONTENT_CACHE.get_or_init(Default::default).lock().unwrap();
@@ -213,6 +222,7 @@ struct PreviewState {
     ui: Option<super::ui::PreviewUi>,
     handle: Rc<RefCell<Option<ComponentInstance>>>,
     selected_element: Option<ElementWeak>,
+    registered_font_dirs: HashSet<PathBuf>,
 }
 
 thread_local! {static PREVIEW_STATE: std::cell::RefCell<PreviewState> = Default::default();}
@@ -332,6 +342,30 @@ pub fn send_status(message: &str, health: Health) {
     crate::preview::send_status_notification(&sender, message, health)
 }
 
+pub fn notify_runtime_error(params: crate::lsp_ext::PreviewRuntimeErrorParams) {
+    let Some(sender) = SERVER_NOTIFIER.get_or_init(Default::default).lock().unwrap().clone() else {
+        return;
+    };
+
+    crate::preview::send_runtime_error_notification(&sender, params)
+}
+
+pub fn notify_element_path(params: crate::lsp_ext::PreviewElementPathParams) {
+    let Some(sender) = SERVER_NOTIFIER.get_or_init(Default::default).lock().unwrap().clone() else {
+        return;
+    };
+
+    crate::preview::send_element_path_notification(&sender, params)
+}
+
+pub fn notify_live_properties(params: crate::lsp_ext::PreviewLivePropertiesParams) {
+    let Some(sender) = SERVER_NOTIFIER.get_or_init(Default::default).lock().unwrap().clone() else {
+        return;
+    };
+
+    crate::preview::send_live_properties_notification(&sender, params)
+}
+
 pub fn ask_editor_to_show_document(file: String, selection: lsp_types::Range) {
     let Some(sender) = SERVER_NOTIFIER.get_or_init(Default::default).lock().unwrap().clone() else {
         return;
@@ -348,13 +382,16 @@ pub fn update_preview_area(compiled: ComponentDefinition) {
         let mut preview_state = preview_state.borrow_mut();
 
         open_ui_impl(&mut preview_state);
+        register_font_dirs(&mut preview_state);
 
         let shared_handle = preview_state.handle.clone();
+        let previous = shared_handle.borrow().as_ref().map(|h| h.clone_strong());
 
         let ui = preview_state.ui.as_ref().unwrap();
         super::set_preview_factory(
             ui,
             compiled,
+            previous,
             Box::new(move |instance| {
                 shared_handle.replace(Some(instance));
             }),
@@ -365,6 +402,113 @@ pub fn update_preview_area(compiled: ComponentDefinition) {
     });
 }
 
+/// Registers the fonts of the `font_dirs` of the preview configuration with the renderer
+/// of the preview window. Fonts stay registered until the preview is restarted.
+fn register_font_dirs(preview_state: &mut PreviewState) {
+    let font_dirs =
+        super::CONTENT_CACHE.get_or_init(Default::default).lock().unwrap().config.font_dirs.clone();
+    let Some(ui) = preview_state.ui.as_ref() else {
+        return;
+    };
+    let window_adapter = i_slint_core::window::WindowInner::from_pub(ui.window()).window_adapter();
+    register_fonts(&font_dirs, &mut preview_state.registered_font_dirs, |font| {
+        window_adapter.renderer().register_font_from_path(font)
+    });
+}
+
+/// Registers the fonts in the `font_dirs` with `register_font`, skipping the directories in
+/// `registered_font_dirs` and adding the others to it.
+fn register_fonts(
+    font_dirs: &[PathBuf],
+    registered_font_dirs: &mut HashSet<PathBuf>,
+    register_font: impl Fn(&Path) -> Result<(), Box<dyn std::error::Error>>,
+) {
+    for dir in font_dirs {
+        if !registered_font_dirs.insert(dir.clone()) {
+            continue;
+        }
+        for font in font_files(dir) {
+            if let Err(err) = register_font(&font) {
+                i_slint_core::debug_log!("Error loading font {}: {err}", font.display());
+            }
+        }
+    }
+}
+
+/// Returns all the font files in `dir` and its sub-directories
+fn font_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .flat_map(|path| {
+            if path.is_dir() {
+                font_files(&path)
+            } else if path.extension().and_then(|e| e.to_str()).map_or(false, |e| {
+                ["ttf", "otf", "ttc", "otc"].contains(&e.to_ascii_lowercase().as_str())
+            }) {
+                vec![path]
+            } else {
+                Vec::new()
+            }
+        })
+        .collect()
+}
+
+/// Returns the layout tree of the previewed component. Blocks until the UI thread has computed it.
+pub fn layout_tree() -> Result<LayoutTreeNode, String> {
+    let ui_is_visible =
+        super::CONTENT_CACHE.get().map_or(false, |cache| cache.lock().unwrap().ui_is_visible);
+    if !ui_is_visible {
+        return Err("The preview is not open".into());
+    }
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    run_in_ui_thread(move || async move {
+        let result = component_instance()
+            .ok_or_else(|| "The layout of the preview has not been computed yet".to_string())
+            .map(|component_instance| {
+                let root_element = super::root_element(&component_instance);
+                layout_tree_node(&component_instance, &root_element)
+            });
+        sender.send(result).ok();
+    });
+    receiver.recv().map_err(|_| "The preview did not respond".to_string())?
+}
+
+fn layout_tree_node(component_instance: &ComponentInstance, element: &ElementRc) -> LayoutTreeNode {
+    let element = super::self_or_embedded_component_root(element);
+    let (file, range) = super::lsp_element_position(&element);
+    let geometry = component_instance.element_position(&element).map(|rect| LayoutGeometry {
+        x: rect.origin.x,
+        y: rect.origin.y,
+        width: rect.size.width,
+        height: rect.size.height,
+    });
+    let to_constraints = |info: i_slint_core::layout::LayoutInfo| LayoutConstraints {
+        min: info.min,
+        max: info.max,
+        preferred: info.preferred,
+        stretch: info.stretch,
+    };
+    let (horizontal_constraints, vertical_constraints) = component_instance
+        .element_layout_info(&element)
+        .map_or((None, None), |(h, v)| (Some(to_constraints(h)), Some(to_constraints(v))));
+
+    let e = element.borrow();
+    LayoutTreeNode {
+        id: e.id.clone(),
+        type_name: e.base_type.to_string(),
+        file,
+        range,
+        geometry,
+        horizontal_constraints,
+        vertical_constraints,
+        children: e.children.iter().map(|c| layout_tree_node(component_instance, c)).collect(),
+    }
+}
+
 /// Highlight the element pointed at the offset in the path.
 /// When path is None, remove the highlight.
 pub fn update_highlight(path: PathBuf, offset: u32) {
@@ -381,3 +525,89 @@ pub fn update_highlight(path: PathBuf, offset: u32) {
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_node<'a>(node: &'a LayoutTreeNode, id: &str) -> Option<&'a LayoutTreeNode> {
+        if node.id == id {
+            return Some(node);
+        }
+        node.children.iter().find_map(|child| find_node(child, id))
+    }
+
+    #[test]
+    fn test_register_fonts() {
+        let font_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../examples/slide_puzzle/plaster-font")
+            .canonicalize()
+            .unwrap();
+        let mut registered_font_dirs = HashSet::new();
+        let registered_fonts = RefCell::new(Vec::new());
+        let register_font = |font: &Path| {
+            registered_fonts.borrow_mut().push(font.to_path_buf());
+            i_slint_common::sharedfontdb::register_font_from_path(font)
+        };
+
+        register_fonts(&[font_dir.clone()], &mut registered_font_dirs, register_font);
+        assert_eq!(*registered_fonts.borrow(), vec![font_dir.join("Plaster-Regular.ttf")]);
+        // The font is found by its family name
+        assert!(i_slint_common::sharedfontdb::FONT_DB
+            .with(|db| db.borrow().query_with_family(Default::default(), Some("Plaster")))
+            .is_some());
+
+        // Directories are registered once
+        register_fonts(&[font_dir], &mut registered_font_dirs, register_font);
+        assert_eq!(registered_fonts.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_layout_tree() {
+        i_slint_backend_testing::init();
+        let mut compiler = slint_interpreter::ComponentCompiler::default();
+        let definition = spin_on::spin_on(
+            compiler.build_from_source(
+                r#"
+export component Test inherits Window {
+    width: 200px;
+    height: 100px;
+    layout := HorizontalLayout {
+        padding: 0px;
+        spacing: 0px;
+        fixed := Rectangle { width: 50px; }
+        stretched := Rectangle { min-height: 10px; max-width: 300px; }
+    }
+}"#
+                .into(),
+                "test.slint".into(),
+            ),
+        )
+        .unwrap();
+        let instance = definition.create().unwrap();
+
+        let tree = layout_tree_node(&instance, &super::super::root_element(&instance));
+        assert_eq!(tree.file, "test.slint");
+        assert_eq!(tree.geometry, Some(LayoutGeometry { x: 0., y: 0., width: 200., height: 100. }));
+
+        let layout = find_node(&tree, "layout").unwrap();
+        assert_eq!(
+            layout.geometry,
+            Some(LayoutGeometry { x: 0., y: 0., width: 200., height: 100. })
+        );
+
+        let fixed = find_node(layout, "fixed").unwrap();
+        assert_eq!(fixed.geometry, Some(LayoutGeometry { x: 0., y: 0., width: 50., height: 100. }));
+        let horizontal = fixed.horizontal_constraints.unwrap();
+        assert_eq!((horizontal.min, horizontal.max, horizontal.preferred), (50., 50., 50.));
+
+        let stretched = find_node(layout, "stretched").unwrap();
+        assert_eq!(
+            stretched.geometry,
+            Some(LayoutGeometry { x: 50., y: 0., width: 150., height: 100. })
+        );
+        assert_eq!(stretched.horizontal_constraints.unwrap().max, 300.);
+        assert_eq!(stretched.vertical_constraints.unwrap().min, 10.);
+        assert!(stretched.children.is_empty());
+    }
+}
@@ -172,6 +172,26 @@ pub fn process_lsp_to_preview_message(&self, value: JsValue) -> Result<(), JsVal
                 super::highlight(&path.map(PathBuf::from), offset);
                 Ok(())
             }
+            M::SetPreviewClock { clock } => {
+                super::set_preview_clock(clock);
+                Ok(())
+            }
+            M::SetPaletteOverrides { global, overrides } => {
+                super::set_palette_overrides(global, overrides);
+                Ok(())
+            }
+            M::Heartbeat => {
+                send_message_to_lsp(crate::common::PreviewToLspMessage::Heartbeat);
+                Ok(())
+            }
+            M::QueryLiveProperties => {
+                super::query_live_properties();
+                Ok(())
+            }
+            M::SetLiveProperty { name, value } => {
+                super::set_live_property(name, value);
+                Ok(())
+            }
         }
     }
 }
@@ -333,6 +353,18 @@ pub fn send_status(message: &str, health: Health) {
     });
 }
 
+pub fn notify_runtime_error(params: crate::lsp_ext::PreviewRuntimeErrorParams) {
+    send_message_to_lsp(crate::common::PreviewToLspMessage::RuntimeError { params });
+}
+
+pub fn notify_element_path(params: crate::lsp_ext::PreviewElementPathParams) {
+    send_message_to_lsp(crate::common::PreviewToLspMessage::ElementPath { params });
+}
+
+pub fn notify_live_properties(params: crate::lsp_ext::PreviewLivePropertiesParams) {
+    send_message_to_lsp(crate::common::PreviewToLspMessage::LiveProperties { params });
+}
+
 pub fn notify_diagnostics(diagnostics: &[slint_interpreter::Diagnostic]) -> Option<()> {
     set_diagnostics(diagnostics);
     let diags = crate::preview::convert_diagnostics(diagnostics);
@@ -352,11 +384,13 @@ pub fn update_preview_area(compiled: slint_interpreter::ComponentDefinition) {
         let preview_state = preview_state.borrow_mut();
 
         let shared_handle = preview_state.handle.clone();
+        let previous = shared_handle.borrow().as_ref().map(|h| h.clone_strong());
 
         let ui = preview_state.ui.as_ref().unwrap();
         super::set_preview_factory(
             ui,
             compiled,
+            previous,
             Box::new(move |instance| {
                 shared_handle.replace(Some(instance));
             }),
@@ -5,7 +5,7 @@
 
 use i_slint_compiler::diagnostics::{DiagnosticLevel, SourceFile, Spanned};
 use i_slint_compiler::langtype::{ElementType, Type};
-use i_slint_compiler::lookup::LookupCtx;
+use i_slint_compiler::lookup::{LookupCtx, LookupObject, LookupResult};
 use i_slint_compiler::object_tree;
 use i_slint_compiler::parser::{syntax_nodes, SyntaxKind, SyntaxNode, SyntaxToken};
 use i_slint_compiler::parser::{TextRange, TextSize};
@@ -115,6 +115,26 @@ pub fn with_lookup_ctx<R>(
     with_property_lookup_ctx::<R>(document_cache, &expr_context_info, f)
 }
 
+/// Resolve a `QualifiedName` within an expression, such as `Colors.red` or `foo.bar`
+pub fn lookup_qualified_name(
+    document_cache: &DocumentCache,
+    qualified_name: &SyntaxNode,
+) -> Option<LookupResult> {
+    let mut identifiers = qualified_name
+        .children_with_tokens()
+        .filter_map(|t| t.into_token())
+        .filter(|t| t.kind() == SyntaxKind::Identifier);
+    with_lookup_ctx(document_cache, qualified_name.clone(), |ctx| {
+        let normalize = |t: SyntaxToken| i_slint_compiler::parser::normalize_identifier(t.text());
+        let global = i_slint_compiler::lookup::global_lookup();
+        let mut result = global.lookup(ctx, &normalize(identifiers.next()?))?;
+        for identifier in identifiers {
+            result = result.lookup(ctx, &normalize(identifier))?;
+        }
+        Some(result)
+    })?
+}
+
 /// Run the function with the LookupCtx associated with the token
 pub fn with_property_lookup_ctx<R>(
     document_cache: &DocumentCache,
@@ -276,3 +296,44 @@ fn to_lsp_diag_level(level: DiagnosticLevel) -> lsp_types::DiagnosticSeverity {
         _ => lsp_types::DiagnosticSeverity::INFORMATION,
     }
 }
+
+/// Match a `/`-separated `path` against a glob `pattern`.
+///
+/// `*` and `?` match within one path component, `**` matches any number of
+/// components. Patterns that are not absolute may match at any directory level.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[u8], path: &[u8]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            [b'*', b'*'] => true,
+            [b'*', b'*', rest @ ..] => {
+                let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+                (0..=path.len())
+                    .filter(|i| *i == 0 || path[i - 1] == b'/')
+                    .any(|i| matches(rest, &path[i..]))
+            }
+            [b'*', rest @ ..] => {
+                for i in 0..=path.len() {
+                    if matches(rest, &path[i..]) {
+                        return true;
+                    }
+                    if path.get(i) == Some(&b'/') {
+                        break;
+                    }
+                }
+                false
+            }
+            [b'?', rest @ ..] => {
+                matches!(path.first(), Some(c) if *c != b'/') && matches(rest, &path[1..])
+            }
+            [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+        }
+    }
+
+    let path = path.as_bytes();
+    if pattern.starts_with('/') || pattern.starts_with("**") {
+        matches(pattern.as_bytes(), path)
+    } else {
+        matches(format!("**/{pattern}").as_bytes(), path)
+    }
+}
@@ -13,6 +13,7 @@
 use common::{PreviewApi, Result};
 use i_slint_compiler::CompilerConfiguration;
 use js_sys::Function;
+use language::progress::ProgressReporter;
 pub use language::{Context, DocumentCache, RequestHandler};
 use serde::Serialize;
 use std::cell::RefCell;
@@ -100,6 +101,46 @@ fn highlight(&self, path: Option<std::path::PathBuf>, offset: u32) -> Result<()>
         )
     }
 
+    fn layout_tree(&self) -> Result<common::LayoutTreeNode> {
+        Err("The layout tree is not available with an external previewer".into())
+    }
+
+    fn set_preview_clock(&self, clock: Option<common::PreviewClock>) -> Result<()> {
+        #[cfg(feature = "preview-external")]
+        self.server_notifier.send_notification(
+            "slint/lsp_to_preview".to_string(),
+            crate::common::LspToPreviewMessage::SetPreviewClock { clock },
+        )
+    }
+
+    fn set_palette_overrides(
+        &self,
+        global: String,
+        overrides: Vec<(String, common::PropertyOverride)>,
+    ) -> Result<()> {
+        #[cfg(feature = "preview-external")]
+        self.server_notifier.send_notification(
+            "slint/lsp_to_preview".to_string(),
+            crate::common::LspToPreviewMessage::SetPaletteOverrides { global, overrides },
+        )
+    }
+
+    fn query_live_properties(&self) -> Result<()> {
+        #[cfg(feature = "preview-external")]
+        self.server_notifier.send_notification(
+            "slint/lsp_to_preview".to_string(),
+            crate::common::LspToPreviewMessage::QueryLiveProperties,
+        )
+    }
+
+    fn set_live_property(&self, name: String, value: serde_json::Value) -> Result<()> {
+        #[cfg(feature = "preview-external")]
+        self.server_notifier.send_notification(
+            "slint/lsp_to_preview".to_string(),
+            crate::common::LspToPreviewMessage::SetLiveProperty { name, value },
+        )
+    }
+
     fn current_component(&self) -> Option<crate::common::PreviewComponent> {
         self.to_show.borrow().clone()
     }
@@ -250,10 +291,13 @@ pub fn create(
     let mut compiler_config =
         CompilerConfiguration::new(i_slint_compiler::generator::OutputFormat::Interpreter);
 
+    let progress = ProgressReporter::default();
     let preview_notifier = preview.clone();
+    let import_progress = progress.clone();
     compiler_config.open_import_fallback = Some(Rc::new(move |path| {
         let load_file = Function::from(load_file.clone());
         let preview_notifier = preview_notifier.clone();
+        import_progress.report(path.clone());
         Box::pin(async move {
             let contents = self::load_file(path.clone(), &load_file).await;
             if let Ok(contents) = &contents {
@@ -262,14 +306,16 @@ pub fn create(
             Some(contents)
         })
     }));
-    let document_cache = RefCell::new(DocumentCache::new(compiler_config));
+    let mut document_cache = DocumentCache::new(compiler_config);
+    document_cache.set_workspace_folders(language::workspace_roots(&init_param));
+    let document_cache = RefCell::new(document_cache);
     let reentry_guard = Rc::new(RefCell::new(ReentryGuard::default()));
 
     let mut rh = RequestHandler::default();
     language::register_request_handlers(&mut rh);
 
     Ok(SlintServer {
-        ctx: Rc::new(Context { document_cache, init_param, server_notifier, preview }),
+        ctx: Rc::new(Context { document_cache, init_param, server_notifier, preview, progress }),
         reentry_guard,
         rh: Rc::new(rh),
     })
@@ -312,6 +358,21 @@ pub async fn process_preview_to_lsp_message(
             M::RequestState { .. } => {
                 crate::language::request_state(&self.ctx);
             }
+            M::RuntimeError { params } => {
+                crate::preview::send_runtime_error_notification(&self.ctx.server_notifier, params);
+            }
+            M::ElementPath { params } => {
+                crate::preview::send_element_path_notification(&self.ctx.server_notifier, params);
+            }
+            M::LiveProperties { params } => {
+                crate::preview::send_live_properties_notification(
+                    &self.ctx.server_notifier,
+                    params,
+                );
+            }
+            M::Heartbeat => {
+                // The WASM LSP does not check on the preview
+            }
         }
         Ok(())
     }
@@ -34,6 +34,11 @@ slint_init().then(() => {
     }
 
     connection.onInitialize((params: InitializeParams): InitializeResult => {
+        // Only the full text of the changed documents is passed on to the server
+        params.capabilities.experimental = {
+            ...(params.capabilities.experimental ?? {}),
+            fullTextDocumentSync: true,
+        };
         the_lsp = slint_lsp.create(
             params,
             send_notification,